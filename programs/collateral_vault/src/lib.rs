@@ -1,19 +1,113 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_option::COption,
+    sysvar::instructions::{self, load_current_index_checked, load_instruction_at_checked},
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address, AssociatedToken},
+    metadata::{
+        self, mpl_token_metadata::types::DataV2, CreateMasterEditionV3,
+        CreateMetadataAccountsV3, Metadata, UpdateMetadataAccountsV2,
+    },
     token::{self, Mint, Token, TokenAccount, Transfer},
+    token_2022::spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    },
+    token_interface::{self, TokenInterface, TransferChecked},
 };
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 declare_id!("CoVau1t111111111111111111111111111111111111");
 
+/// Maximum approved withdrawal token accounts per position
+pub const MAX_WITHDRAWAL_DESTINATIONS: usize = 4;
+
+/// Yield adapter instruction deploying idle collateral (args: amount u64)
+pub const ADAPTER_DEPOSIT_IX: &str = "global:adapter_deposit";
+
+/// Yield adapter instruction returning a position's whole deployment
+pub const ADAPTER_WITHDRAW_ALL_IX: &str = "global:adapter_withdraw_all";
+
+/// Delay between requesting and executing an unfreeze (~1 day)
+pub const UNFREEZE_TIMELOCK_SLOTS: u64 = 216_000;
+
+/// Maximum operator (hot) keys per position
+pub const MAX_OPERATORS: usize = 4;
+
+/// Maximum sessions released by one release_batch
+pub const MAX_BATCH_RELEASES: usize = 16;
+
+/// Maximum recipients of one slash_and_pay
+pub const MAX_SLASH_RECIPIENTS: usize = 4;
+
+/// Delay before a change to an existing withdrawal allowlist applies (~2 days)
+pub const WITHDRAWAL_ALLOWLIST_TIMELOCK_SLOTS: u64 = 432_000;
+
+/// Maximum age of the price used for stablecoin peg checks
+pub const PEG_PRICE_MAX_AGE_SECONDS: u64 = 60;
+
+/// Renderer serving position NFT JSON (attributes come from the URI path and query)
+pub const POSITION_METADATA_BASE_URI: &str = "https://positions.origin-os.xyz";
+
+/// Symbol shown by wallets for position NFTs
+pub const POSITION_NFT_SYMBOL: &str = "OPOS";
+
+/// Maximum secondary collateral legs per position
+pub const MAX_COLLATERAL_LEGS: usize = 4;
+
+/// Maximum age of the price used to value a collateral leg
+pub const LEG_PRICE_MAX_AGE_SECONDS: u64 = 60;
+
+/// Slots a leg valuation keeps counting toward reservations (~1 minute)
+pub const LEG_VALUE_MAX_AGE_SLOTS: u64 = 150;
+
+/// Maximum age of the price used to value a position in get_position_summary
+pub const SUMMARY_PRICE_MAX_AGE_SECONDS: u64 = 60;
+
+/// Maximum age of the price used for minimum collateral health checks
+pub const HEALTH_PRICE_MAX_AGE_SECONDS: u64 = 60;
+
 /// Collateral Vault Program (IMMUTABLE)
 /// 
 /// Custody provider collateral, track free vs reserved, pay claims.
 /// 
+/// Collateral for each (mode, mint) is pooled in a ModeVault. Positions own
+/// shares of the vault; a position's total is its shares valued at the vault
+/// exchange rate, so vault-wide yield or losses move the rate rather than
+/// every position.
+/// 
 /// INVARIANTS:
-/// - reserved <= total
+/// - reserve/release/slash_and_pay only run under a top-level instruction
+///   from a registry-allowlisted caller program
+/// - reserved <= total + leg value at reservation time
+/// - reserved == sum of the position's open ReservationRecords; release and
+///   slash never exceed the session's record
 /// - withdrawals cannot reduce total below reserved
-/// - claim payouts only come from reserved
+/// - claim payouts only come from reserved, primary collateral first and
+///   then legs in priority order
+/// - sum of position shares == vault total_shares
+/// - once a withdrawal allowlist is set, withdrawals only go to listed accounts
+/// - a depegged stablecoin vault blocks deposits and reservations, never withdrawals
+/// - sponsored_shares <= shares; sponsors never hold withdrawal rights
+/// - a frozen position cannot withdraw, deploy or reserve; slash_and_pay
+///   for existing reservations always works
+/// - deployed (yield) collateral is never reserved, withdrawn or slashed
+///   until recalled: reserved + deployed <= total + leg value
+/// - position PDAs are seeded by seed_provider (the creating key, or the
+///   split_seed keypair of a split), which never changes; the provider
+///   field moves only via propose/accept_position_transfer
+/// - split_position and merge_positions move shares between positions,
+///   never vault totals
+/// - deposits keep position total <= per_provider_cap and vault
+///   total_assets <= global_cap
+/// - withdrawals wait out the mode's unbonding period, and unbonding
+///   collateral cannot be reserved
+/// - deposits credit what reached the vault, net of any Token-2022 transfer
+///   fee; fees on outgoing transfers are borne by the recipient
 #[program]
 pub mod collateral_vault {
     use super::*;
@@ -27,38 +121,110 @@ pub mod collateral_vault {
         let mint_key = ctx.accounts.collateral_mint.key();
         let nft_mint_key = ctx.accounts.position_nft_mint.key();
         let position_bump = ctx.bumps.position;
+        let mode_vault_bump = ctx.bumps.mode_vault;
+        let per_provider_cap = ctx.accounts.mode.per_provider_cap;
+        let global_cap = ctx.accounts.mode.global_cap;
+        
+        assert_allowed_mint_extensions(
+            &ctx.accounts.collateral_mint.to_account_info(),
+            ctx.accounts.mode.allowed_mint_extensions,
+        )?;
+        
+        // Transfer collateral to vault; shares are minted for what arrived
+        let amount = transfer_in(
+            ctx.accounts.collateral_token_program.to_account_info(),
+            ctx.accounts.provider_token_account.to_account_info(),
+            &mut ctx.accounts.vault_token_account,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.provider.to_account_info(),
+            amount,
+        )?;
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        if mode_vault.mint == Pubkey::default() {
+            mode_vault.mode_id = mode_id;
+            mode_vault.mint = mint_key;
+            mode_vault.total_assets = 0;
+            mode_vault.total_shares = 0;
+            mode_vault.bump = mode_vault_bump;
+            mode_vault.peg_halted = false;
+        }
+        require!(!mode_vault.peg_halted, ErrorCode::CollateralDepegged);
         
         let position = &mut ctx.accounts.position;
         let is_new = position.total == 0 && position.provider == Pubkey::default();
         
         if is_new {
             position.provider = provider_key;
+            position.seed_provider = provider_key;
+            position.pending_provider = Pubkey::default();
             position.mode_id = mode_id;
             position.mint = mint_key;
             position.total = 0;
             position.reserved = 0;
             position.position_nft_mint = nft_mint_key;
             position.bump = position_bump;
+            position.shares = 0;
+            position.withdrawal_allowlist = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+            position.withdrawal_allowlist_count = 0;
+            position.pending_withdrawal_allowlist = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+            position.pending_withdrawal_allowlist_count = 0;
+            position.withdrawal_allowlist_effective_slot = 0;
+            position.sponsored_shares = 0;
+            position.pending_withdrawal = 0;
+            position.withdrawal_unlock_slot = 0;
+            position.operators = [Pubkey::default(); MAX_OPERATORS];
+            position.operator_count = 0;
+            position.deployed = 0;
+            position.yield_adapter = Pubkey::default();
+            position.frozen = false;
+            position.unfreeze_unlock_slot = 0;
+            position.legs = [CollateralLeg::default(); MAX_COLLATERAL_LEGS];
+            position.leg_count = 0;
+            position.leg_value_decimals = ctx.accounts.collateral_mint.decimals;
+            position.delegated_value = 0;
+            position.delegated_valued_slot = 0;
+            position.open_reservations = 0;
+            position.undercollateralized = false;
+            position.exiting = false;
+            position.exit_requested_slot = 0;
+            position.sessions_served = 0;
+            position.total_slashed = 0;
+            position.total_released = 0;
+            position.last_slash_slot = 0;
         }
+        require!(position.mint == mint_key, ErrorCode::MintMismatch);
+        // A transferred position only takes deposits from its new owner
+        require!(position.provider == provider_key, ErrorCode::WrongProvider);
         
-        // Update total first (we'll do NFT mint and transfer after releasing mutable borrow)
-        position.total = position.total.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+        // Mint shares at the current exchange rate (we'll do NFT mint and transfer after releasing mutable borrow)
+        let new_shares = mode_vault.shares_for_assets(amount)?;
+        require!(new_shares > 0, ErrorCode::ZeroShares);
+        
+        position.shares = position.shares.checked_add(new_shares).ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_shares = mode_vault.total_shares
+            .checked_add(new_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_assets = mode_vault.total_assets
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        check_deposit_caps(mode_vault, position, per_provider_cap, global_cap)?;
         let new_total = position.total;
         
         // Release mutable borrow by dropping position reference
         let _ = position;
-        
-        // Transfer collateral to vault
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.provider_token_account.to_account_info(),
-            to: ctx.accounts.vault_token_account.to_account_info(),
-            authority: ctx.accounts.provider.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        let _ = mode_vault;
         
         // Mint position NFT on first deposit
         if is_new {
+            assert_position_nft_mint(
+                &ctx.accounts.position_nft_mint.to_account_info(),
+                ctx.accounts.mode.soulbound_position_nft,
+            )?;
+            
             let mode_id_bytes = mode_id.to_le_bytes();
             let seeds: &[&[u8]] = &[
                 b"pos",
@@ -68,75 +234,394 @@ pub mod collateral_vault {
             ];
             let signer_seeds = &[seeds];
             
-            let mint_accounts = token::MintTo {
-                mint: ctx.accounts.position_nft_mint.to_account_info(),
-                to: ctx.accounts.provider_nft_account.to_account_info(),
-                authority: ctx.accounts.position.to_account_info(),
+            let nft_accounts = PositionNftAccounts {
+                position: ctx.accounts.position.to_account_info(),
+                nft_mint: ctx.accounts.position_nft_mint.to_account_info(),
+                nft_account: ctx.accounts.provider_nft_account.to_account_info(),
+                nft_metadata: ctx.accounts.nft_metadata.as_ref()
+                    .ok_or(ErrorCode::PositionMetadataAccountsRequired)?
+                    .to_account_info(),
+                nft_master_edition: ctx.accounts.nft_master_edition.as_ref()
+                    .ok_or(ErrorCode::PositionMetadataAccountsRequired)?
+                    .to_account_info(),
+                token_metadata_program: ctx.accounts.token_metadata_program.as_ref()
+                    .ok_or(ErrorCode::PositionMetadataAccountsRequired)?
+                    .to_account_info(),
+                payer: ctx.accounts.provider.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
             };
-            let mint_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                mint_accounts,
-                signer_seeds,
-            );
-            token::mint_to(mint_ctx, 1)?;
+            mint_position_nft(nft_accounts, position_metadata(&ctx.accounts.position), signer_seeds)?;
+        }
+        
+        emit_cpi!(CollateralDeposited {
+            provider: provider_key,
+            mode_id,
+            amount,
+            new_total,
+        });
+        
+        Ok(())
+    }
+
+    /// Sync the position NFT's metadata with current total and reserved (permissionless)
+    ///
+    /// reserve/release/slash run as CPIs without the metadata accounts, so
+    /// wallets and indexers call this after those change the position.
+    pub fn refresh_position_metadata(ctx: Context<RefreshPositionMetadata>) -> Result<()> {
+        let position_info = ctx.accounts.position.to_account_info();
+        let position = &mut ctx.accounts.position;
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        
+        let data = position_metadata(position);
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        let position_bump = position.bump;
+        let seed_provider = position.seed_provider;
+        let total = position.total;
+        let reserved = position.reserved;
+        
+        let _ = position;
+        
+        let mode_id_bytes = mode_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"pos",
+            seed_provider.as_ref(),
+            &mode_id_bytes,
+            &[position_bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        let cpi_accounts = UpdateMetadataAccountsV2 {
+            metadata: ctx.accounts.nft_metadata.to_account_info(),
+            update_authority: position_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_metadata_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        metadata::update_metadata_accounts_v2(cpi_ctx, None, Some(data), None, None)?;
+        
+        emit_cpi!(PositionMetadataRefreshed {
+            provider: provider_key,
+            mode_id,
+            total,
+            reserved,
+        });
+        
+        Ok(())
+    }
+
+    /// Quote a position's capacity (view; result via return data)
+    ///
+    /// Free collateral is computed as in reserve, so it is exactly what a
+    /// new session could reserve now. usd_value prices the primary
+    /// collateral plus fresh leg value with the supplied Pyth feed, in
+    /// PEG_DECIMALS.
+    pub fn get_position_summary(
+        ctx: Context<GetPositionSummary>,
+        feed_id: [u8; 32],
+    ) -> Result<PositionSummary> {
+        let clock = Clock::get()?;
+        let position = &ctx.accounts.position;
+        
+        let total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let leg_value = position.fresh_leg_value(clock.slot)?;
+        let capacity = total.checked_add(leg_value).ok_or(ErrorCode::Overflow)?;
+        let free = capacity
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.pending_withdrawal)
+            .saturating_sub(position.deployed);
+        
+        let price = pyth_helpers::load_price(
+            &ctx.accounts.price_update,
+            &feed_id,
+            SUMMARY_PRICE_MAX_AGE_SECONDS,
+        )?;
+        let usd_value = pyth_helpers::token_amount_to_usd(
+            capacity,
+            position.leg_value_decimals,
+            &price,
+            pyth_helpers::PEG_DECIMALS,
+        )?;
+        
+        Ok(PositionSummary {
+            total,
+            leg_value,
+            reserved: position.reserved,
+            pending_withdrawal: position.pending_withdrawal,
+            deployed: position.deployed,
+            free,
+            usd_value,
+            open_reservations: position.open_reservations,
+        })
+    }
+
+    /// Top up an existing provider position with a sponsor's collateral
+    ///
+    /// Shares are credited to the position, so the sponsor gets no withdrawal
+    /// rights. The sponsor's ledger and the position's sponsored_shares keep
+    /// sponsored capital attributable separately from the provider's own.
+    pub fn sponsor_deposit(ctx: Context<SponsorDeposit>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        
+        // Capture values BEFORE mutable borrow
+        let position_key = ctx.accounts.position.key();
+        let sponsor_key = ctx.accounts.sponsor.key();
+        let ledger_bump = ctx.bumps.sponsor_ledger;
+        let per_provider_cap = ctx.accounts.mode.per_provider_cap;
+        let global_cap = ctx.accounts.mode.global_cap;
+        
+        assert_allowed_mint_extensions(
+            &ctx.accounts.collateral_mint.to_account_info(),
+            ctx.accounts.mode.allowed_mint_extensions,
+        )?;
+        
+        // Transfer collateral to vault; shares are minted for what arrived
+        let amount = transfer_in(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.sponsor_token_account.to_account_info(),
+            &mut ctx.accounts.vault_token_account,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.sponsor.to_account_info(),
+            amount,
+        )?;
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        require!(!mode_vault.peg_halted, ErrorCode::CollateralDepegged);
+        
+        let position = &mut ctx.accounts.position;
+        
+        let new_shares = mode_vault.shares_for_assets(amount)?;
+        require!(new_shares > 0, ErrorCode::ZeroShares);
+        
+        position.shares = position.shares.checked_add(new_shares).ok_or(ErrorCode::Overflow)?;
+        position.sponsored_shares = position.sponsored_shares
+            .checked_add(new_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_shares = mode_vault.total_shares
+            .checked_add(new_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_assets = mode_vault.total_assets
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        check_deposit_caps(mode_vault, position, per_provider_cap, global_cap)?;
+        let new_total = position.total;
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        
+        let _ = position;
+        let _ = mode_vault;
+        
+        let ledger = &mut ctx.accounts.sponsor_ledger;
+        if ledger.sponsor == Pubkey::default() {
+            ledger.position = position_key;
+            ledger.sponsor = sponsor_key;
+            ledger.deposited = 0;
+            ledger.shares = 0;
+            ledger.bump = ledger_bump;
         }
+        ledger.deposited = ledger.deposited.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+        ledger.shares = ledger.shares.checked_add(new_shares).ok_or(ErrorCode::Overflow)?;
         
-        emit!(CollateralDeposited {
+        let _ = ledger;
+        
+        emit_cpi!(CollateralSponsored {
             provider: provider_key,
+            sponsor: sponsor_key,
             mode_id,
             amount,
+            shares: new_shares,
             new_total,
         });
         
         Ok(())
     }
 
-    /// Withdraw free (unreserved) collateral
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    /// Start a full provider exit; owner key only
+    ///
+    /// The position takes no new reservations from now on. Existing sessions
+    /// run to completion, and once reserved reaches zero (ProviderExitReady)
+    /// execute_withdraw pays out all free collateral without a request or
+    /// unbonding period, since no claim can reach the position any more.
+    /// Marketplaces watch ProviderExitRequested to stop routing sessions.
+    pub fn request_full_exit(ctx: Context<RequestFullExit>) -> Result<()> {
+        let clock = Clock::get()?;
+        let position_key = ctx.accounts.position.key();
+        let position = &mut ctx.accounts.position;
+        require!(!position.exiting, ErrorCode::PositionExiting);
+        
+        position.exiting = true;
+        position.exit_requested_slot = clock.slot;
+        
+        emit_cpi!(ProviderExitRequested {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            position: position_key,
+            reserved: position.reserved,
+            open_reservations: position.open_reservations,
+        });
+        if position.reserved == 0 {
+            emit_cpi!(ProviderExitReady {
+                provider: position.provider,
+                mode_id: position.mode_id,
+                position: position_key,
+            });
+        }
+        
+        Ok(())
+    }
+
+    /// Start unbonding free (unreserved) collateral for withdrawal
+    ///
+    /// The amount stops backing new reservations immediately and becomes
+    /// withdrawable via execute_withdraw after the mode's unbonding_slots.
+    /// A new request replaces any pending one and restarts the cooldown.
+    pub fn request_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RequestWithdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        
+        let clock = Clock::get()?;
+        let unbonding_slots = ctx.accounts.mode.unbonding_slots;
+        let min_free_usd = ctx.accounts.mode.min_free_collateral_usd;
+        let position = &mut ctx.accounts.position;
+        
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let free = position.total
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.deployed);
+        require!(amount <= free, ErrorCode::InsufficientFreeCollateral);
+        
+        // While sessions are live, what stays free must keep its USD floor
+        if min_free_usd > 0 && position.reserved > 0 {
+            let price = load_health_price(
+                &ctx.accounts.mode,
+                ctx.accounts.price_update.as_ref(),
+                ctx.remaining_accounts,
+            )?;
+            let remaining_free = position.total
+                .checked_add(position.fresh_leg_value(clock.slot)?)
+                .ok_or(ErrorCode::Overflow)?
+                .saturating_sub(position.reserved)
+                .saturating_sub(position.deployed)
+                .saturating_sub(amount);
+            pyth_helpers::assert_min_usd_value(
+                remaining_free,
+                position.leg_value_decimals,
+                &price,
+                min_free_usd,
+            )?;
+        }
+        
+        let unlock_slot = clock.slot
+            .checked_add(unbonding_slots)
+            .ok_or(ErrorCode::Overflow)?;
+        position.pending_withdrawal = amount;
+        position.withdrawal_unlock_slot = unlock_slot;
+        
+        emit_cpi!(WithdrawalRequested {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            amount,
+            unlock_slot,
+        });
+        
+        Ok(())
+    }
+
+    /// Withdraw collateral whose unbonding period has passed
+    ///
+    /// Pays out the pending request in full; it must still be free, so a
+    /// position slashed during unbonding has to request a smaller amount.
+    /// An exiting position with nothing reserved instead withdraws all free
+    /// collateral, with no request or unbonding needed.
+    /// Any Token-2022 transfer fee comes out of what the provider receives.
+    pub fn execute_withdraw(ctx: Context<Withdraw>) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        
+        // A full exit with nothing left reserved takes all free collateral, unbonding-free
+        let exit_ready = ctx.accounts.position.exiting && ctx.accounts.position.reserved == 0;
+        if !exit_ready {
+            require!(ctx.accounts.position.pending_withdrawal > 0, ErrorCode::NoPendingWithdrawal);
+            require!(
+                clock.slot >= ctx.accounts.position.withdrawal_unlock_slot,
+                ErrorCode::WithdrawalUnbonding
+            );
+        }
         
         // Capture values BEFORE mutable borrow
-        let position_info = ctx.accounts.position.to_account_info();
+        let mode_vault_info = ctx.accounts.mode_vault.to_account_info();
         let vault_info = ctx.accounts.vault_token_account.to_account_info();
         let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
+        let mint_info = ctx.accounts.collateral_mint.to_account_info();
+        let decimals = ctx.accounts.collateral_mint.decimals;
         let token_program_info = ctx.accounts.token_program.to_account_info();
         
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        let destination = ctx.accounts.provider_token_account.key();
         let position = &mut ctx.accounts.position;
         
-        let free = position.total.saturating_sub(position.reserved);
+        // Restrict destination once an allowlist is registered
+        let allowlist_count = position.withdrawal_allowlist_count as usize;
+        if allowlist_count > 0 {
+            require!(
+                position.withdrawal_allowlist[..allowlist_count].contains(&destination),
+                ErrorCode::DestinationNotAllowlisted
+            );
+        }
+        
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        let free = position.total
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.deployed);
+        let amount = if exit_ready { free } else { position.pending_withdrawal };
+        require!(amount > 0, ErrorCode::NoPendingWithdrawal);
         require!(amount <= free, ErrorCode::InsufficientFreeCollateral);
+        position.pending_withdrawal = 0;
         
         // Build signer seeds
-        let mode_id_bytes = position.mode_id.to_le_bytes();
+        let mode_id_bytes = mode_vault.mode_id.to_le_bytes();
+        let mint_key = mode_vault.mint;
+        let vault_bump = mode_vault.bump;
         let provider_key = position.provider;
-        let bump = position.bump;
         
-        // Update state
-        position.total = position.total.checked_sub(amount).ok_or(ErrorCode::Underflow)?;
+        // Update state (burn shares rounding up so the vault never loses value)
+        burn_shares_for_assets(mode_vault, position, amount)?;
         let new_total = position.total;
         let mode_id = position.mode_id;
         
         // Drop mutable borrow
         let _ = position;
+        let _ = mode_vault;
         
         // Transfer
         let seeds: &[&[u8]] = &[
-            b"pos",
-            provider_key.as_ref(),
+            b"mode_vault",
             &mode_id_bytes,
-            &[bump],
+            mint_key.as_ref(),
+            &[vault_bump],
         ];
         let signer_seeds = &[seeds];
         
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from: vault_info,
+            mint: mint_info,
             to: provider_token_info,
-            authority: position_info,
+            authority: mode_vault_info,
         };
         let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, amount)?;
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
         
-        emit!(CollateralWithdrawn {
+        emit_cpi!(CollateralWithdrawn {
             provider: provider_key,
             mode_id,
             amount,
@@ -146,235 +631,3074 @@ pub mod collateral_vault {
         Ok(())
     }
 
-    /// Reserve collateral for a session (CPI from session_escrow)
-    pub fn reserve(ctx: Context<Reserve>, session: Pubkey, amount_r: u64) -> Result<()> {
+    /// Propose the set of token accounts withdrawals may be sent to
+    ///
+    /// The first allowlist only restricts withdrawals and applies immediately.
+    /// Changes to an existing allowlist wait WITHDRAWAL_ALLOWLIST_TIMELOCK_SLOTS
+    /// and must be applied with apply_withdrawal_allowlist.
+    pub fn propose_withdrawal_allowlist(
+        ctx: Context<UpdateWithdrawalAllowlist>,
+        destinations: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            !destinations.is_empty() && destinations.len() <= MAX_WITHDRAWAL_DESTINATIONS,
+            ErrorCode::InvalidWithdrawalAllowlist
+        );
+        
+        let clock = Clock::get()?;
         let position = &mut ctx.accounts.position;
         
-        let free = position.total.saturating_sub(position.reserved);
-        require!(amount_r <= free, ErrorCode::InsufficientFreeCollateral);
+        let mut list = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+        list[..destinations.len()].copy_from_slice(&destinations);
+        let count = destinations.len() as u8;
         
-        position.reserved = position.reserved.checked_add(amount_r).ok_or(ErrorCode::Overflow)?;
-        require!(position.reserved <= position.total, ErrorCode::ReservedExceedsTotal);
+        let effective_slot = if position.withdrawal_allowlist_count == 0 {
+            position.withdrawal_allowlist = list;
+            position.withdrawal_allowlist_count = count;
+            position.pending_withdrawal_allowlist_count = 0;
+            clock.slot
+        } else {
+            position.pending_withdrawal_allowlist = list;
+            position.pending_withdrawal_allowlist_count = count;
+            position.withdrawal_allowlist_effective_slot = clock.slot
+                .checked_add(WITHDRAWAL_ALLOWLIST_TIMELOCK_SLOTS)
+                .ok_or(ErrorCode::Overflow)?;
+            position.withdrawal_allowlist_effective_slot
+        };
         
-        let provider = position.provider;
-        let new_reserved = position.reserved;
+        emit_cpi!(WithdrawalAllowlistProposed {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            destinations,
+            effective_slot,
+        });
         
-        emit!(CollateralReserved {
-            provider,
-            session,
-            amount: amount_r,
-            new_reserved,
+        Ok(())
+    }
+    
+    /// Offer the position to a new owner key (step 1 of 2)
+    ///
+    /// The position PDA keeps its original seeds, so live collateral and
+    /// reservations stay in place. Proposing Pubkey::default() cancels.
+    pub fn propose_position_transfer(
+        ctx: Context<ProposePositionTransfer>,
+        new_provider: Pubkey,
+    ) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let position = &mut ctx.accounts.position;
+        position.pending_provider = new_provider;
+        
+        emit_cpi!(PositionTransferProposed {
+            position: position_key,
+            provider: position.provider,
+            new_provider,
         });
         
         Ok(())
     }
 
-    /// Release reserved collateral (session completed successfully)
-    pub fn release(ctx: Context<Release>, session: Pubkey, amount_r: u64) -> Result<()> {
+    /// Accept a proposed transfer while holding the position NFT (step 2 of 2)
+    ///
+    /// The withdrawal allowlist carries over, so a new owner has to wait out
+    /// its timelock before redirecting withdrawals.
+    pub fn accept_position_transfer(ctx: Context<AcceptPositionTransfer>) -> Result<()> {
+        let position_key = ctx.accounts.position.key();
+        let new_provider = ctx.accounts.new_provider.key();
         let position = &mut ctx.accounts.position;
+        require!(
+            position.pending_provider != Pubkey::default() && position.pending_provider == new_provider,
+            ErrorCode::PositionTransferNotPending
+        );
         
-        require!(amount_r <= position.reserved, ErrorCode::ReleaseExceedsReserved);
+        let old_provider = position.provider;
+        position.provider = new_provider;
+        position.pending_provider = Pubkey::default();
+        // Hot keys belong to the previous owner
+        position.operators = [Pubkey::default(); MAX_OPERATORS];
+        position.operator_count = 0;
         
-        position.reserved = position.reserved.checked_sub(amount_r).ok_or(ErrorCode::Underflow)?;
+        emit_cpi!(PositionTransferred {
+            position: position_key,
+            old_provider,
+            new_provider,
+        });
         
-        let provider = position.provider;
-        let new_reserved = position.reserved;
+        Ok(())
+    }
+
+    /// Replace the position's operator (hot) keys; owner key only
+    ///
+    /// Operators may authorize reservations and day-to-day session actions
+    /// for the position. Withdrawals, allowlists, transfers and operator
+    /// changes stay with the owner.
+    pub fn set_operators(ctx: Context<SetOperators>, operators: Vec<Pubkey>) -> Result<()> {
+        require!(operators.len() <= MAX_OPERATORS, ErrorCode::InvalidOperators);
+        for (i, operator) in operators.iter().enumerate() {
+            require!(*operator != Pubkey::default(), ErrorCode::InvalidOperators);
+            require!(!operators[..i].contains(operator), ErrorCode::InvalidOperators);
+        }
         
-        emit!(CollateralReleased {
-            provider,
-            session,
-            amount: amount_r,
-            new_reserved,
+        let position = &mut ctx.accounts.position;
+        position.operators = [Pubkey::default(); MAX_OPERATORS];
+        position.operators[..operators.len()].copy_from_slice(&operators);
+        position.operator_count = operators.len() as u8;
+        
+        emit_cpi!(OperatorsSet {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            operators,
         });
         
         Ok(())
     }
 
-    /// Slash collateral and pay to user (claim payout)
-    pub fn slash_and_pay(
-        ctx: Context<SlashAndPay>,
-        session: Pubkey,
-        payout_amount: u64,
-    ) -> Result<()> {
+    /// Move free collateral into a new position for the same owner and mode
+    ///
+    /// The new position is seeded by the `split_seed` keypair and gets its
+    /// own NFT. It starts with the source's withdrawal allowlist and
+    /// operators, and no reservations, legs or deployment. Sponsored shares
+    /// stay with the source (the provider's own shares move first).
+    pub fn split_position(ctx: Context<SplitPosition>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        
         // Capture values BEFORE mutable borrow
-        let position_info = ctx.accounts.position.to_account_info();
-        let vault_info = ctx.accounts.vault_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
-        let user_owner = ctx.accounts.user_token_account.owner;
+        let source_key = ctx.accounts.position.key();
+        let new_position_key = ctx.accounts.new_position.key();
+        let split_seed = ctx.accounts.split_seed.key();
+        let nft_mint_key = ctx.accounts.new_position_nft_mint.key();
+        let new_position_bump = ctx.bumps.new_position;
+        
+        assert_position_nft_mint(
+            &ctx.accounts.new_position_nft_mint.to_account_info(),
+            ctx.accounts.mode.soulbound_position_nft,
+        )?;
         
+        let mode_vault = &ctx.accounts.mode_vault;
         let position = &mut ctx.accounts.position;
         
-        require!(payout_amount <= position.reserved, ErrorCode::PayoutExceedsReserved);
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        let committed = position.reserved
+            .saturating_add(position.pending_withdrawal)
+            .saturating_add(position.deployed);
+        require!(
+            amount <= position.total.saturating_sub(committed),
+            ErrorCode::InsufficientFreeCollateral
+        );
         
-        // Capture for signer seeds
-        let mode_id_bytes = position.mode_id.to_le_bytes();
-        let provider_key = position.provider;
-        let bump = position.bump;
+        // Round up so the new position holds at least `amount`
+        let moved_shares = mode_vault.shares_for_assets_round_up(amount)?;
+        require!(moved_shares <= position.shares, ErrorCode::InsufficientFreeCollateral);
+        position.shares = position.shares.checked_sub(moved_shares).ok_or(ErrorCode::Underflow)?;
+        position.sponsored_shares = position.sponsored_shares.min(position.shares);
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        require!(position.total >= committed, ErrorCode::InsufficientFreeCollateral);
         
-        // Update state
-        position.reserved = position.reserved.checked_sub(payout_amount).ok_or(ErrorCode::Underflow)?;
-        position.total = position.total.checked_sub(payout_amount).ok_or(ErrorCode::Underflow)?;
-        let new_total = position.total;
-        let new_reserved = position.reserved;
+        let mut split = ProviderPosition::clone(position);
+        split.seed_provider = split_seed;
+        split.pending_provider = Pubkey::default();
+        split.position_nft_mint = nft_mint_key;
+        split.bump = new_position_bump;
+        split.shares = moved_shares;
+        split.sponsored_shares = 0;
+        split.total = mode_vault.assets_for_shares(moved_shares)?;
+        split.reserved = 0;
+        split.pending_withdrawal = 0;
+        split.withdrawal_unlock_slot = 0;
+        split.deployed = 0;
+        split.yield_adapter = Pubkey::default();
+        split.unfreeze_unlock_slot = 0;
+        split.legs = [CollateralLeg::default(); MAX_COLLATERAL_LEGS];
+        split.leg_count = 0;
+        split.delegated_value = 0;
+        split.delegated_valued_slot = 0;
+        split.open_reservations = 0;
+        split.sessions_served = 0;
+        split.total_slashed = 0;
+        split.total_released = 0;
+        split.last_slash_slot = 0;
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        let source_total = position.total;
+        let new_total = split.total;
         
-        // Drop mutable borrow
         let _ = position;
         
-        // Transfer
+        ctx.accounts.new_position.set_inner(split);
+        
+        let mode_id_bytes = mode_id.to_le_bytes();
         let seeds: &[&[u8]] = &[
             b"pos",
-            provider_key.as_ref(),
+            split_seed.as_ref(),
             &mode_id_bytes,
-            &[bump],
+            &[new_position_bump],
         ];
         let signer_seeds = &[seeds];
         
-        let cpi_accounts = Transfer {
-            from: vault_info,
-            to: user_token_info,
-            authority: position_info,
+        let nft_accounts = PositionNftAccounts {
+            position: ctx.accounts.new_position.to_account_info(),
+            nft_mint: ctx.accounts.new_position_nft_mint.to_account_info(),
+            nft_account: ctx.accounts.provider_nft_account.to_account_info(),
+            nft_metadata: ctx.accounts.nft_metadata.to_account_info(),
+            nft_master_edition: ctx.accounts.nft_master_edition.to_account_info(),
+            token_metadata_program: ctx.accounts.token_metadata_program.to_account_info(),
+            payer: ctx.accounts.provider.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
         };
-        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, payout_amount)?;
+        mint_position_nft(nft_accounts, position_metadata(&ctx.accounts.new_position), signer_seeds)?;
         
-        emit!(CollateralSlashed {
+        emit_cpi!(PositionSplit {
             provider: provider_key,
-            session,
-            payout_amount,
-            user: user_owner,
+            mode_id,
+            source: source_key,
+            new_position: new_position_key,
+            amount,
+            shares: moved_shares,
+            source_total,
             new_total,
-            new_reserved,
         });
         
         Ok(())
     }
-}
 
-// ============================================================================
-// Accounts
-// ============================================================================
+    /// Fold a source position into a target of the same owner, mode and mint
+    ///
+    /// The source must be idle (nothing reserved, unbonding, deployed or in
+    /// legs) and its withdrawal allowlist must match the target's, so a merge
+    /// never loosens where collateral can go. Its shares, including sponsored
+    /// shares, move to the target; its NFT is burned and its account closed.
+    pub fn merge_positions(ctx: Context<MergePositions>) -> Result<()> {
+        let source = &ctx.accounts.source_position;
+        require!(!source.frozen && !ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        require!(
+            source.reserved == 0
+                && source.pending_withdrawal == 0
+                && source.deployed == 0
+                && source.legs[..source.leg_count as usize].iter().all(|leg| leg.amount == 0),
+            ErrorCode::PositionNotIdle
+        );
+        require!(
+            source.pending_provider == Pubkey::default(),
+            ErrorCode::PositionNotIdle
+        );
+        let source_allowlist = &source.withdrawal_allowlist[..source.withdrawal_allowlist_count as usize];
+        if !source_allowlist.is_empty() {
+            let target = &ctx.accounts.position;
+            require!(
+                target.withdrawal_allowlist[..target.withdrawal_allowlist_count as usize] == *source_allowlist,
+                ErrorCode::AllowlistMismatch
+            );
+        }
+        
+        // Capture values BEFORE mutable borrow
+        let source_key = source.key();
+        let moved_shares = source.shares;
+        let moved_sponsored = source.sponsored_shares;
+        let source_sessions_served = source.sessions_served;
+        let source_total_slashed = source.total_slashed;
+        let source_total_released = source.total_released;
+        let source_last_slash_slot = source.last_slash_slot;
+        
+        let mode_vault = &ctx.accounts.mode_vault;
+        let position = &mut ctx.accounts.position;
+        position.shares = position.shares.checked_add(moved_shares).ok_or(ErrorCode::Overflow)?;
+        position.sponsored_shares = position.sponsored_shares
+            .checked_add(moved_sponsored)
+            .ok_or(ErrorCode::Overflow)?;
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        // The merged position keeps both track records
+        position.sessions_served = position.sessions_served.saturating_add(source_sessions_served);
+        position.total_slashed = position.total_slashed.saturating_add(source_total_slashed);
+        position.total_released = position.total_released.saturating_add(source_total_released);
+        position.last_slash_slot = position.last_slash_slot.max(source_last_slash_slot);
+        let position_key = position.key();
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        let new_total = position.total;
+        
+        let _ = position;
+        
+        ctx.accounts.source_position.shares = 0;
+        ctx.accounts.source_position.sponsored_shares = 0;
+        ctx.accounts.source_position.total = 0;
+        
+        // Burn the source position's NFT
+        let burn_accounts = token_interface::Burn {
+            mint: ctx.accounts.source_nft_mint.to_account_info(),
+            from: ctx.accounts.provider_source_nft_account.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let burn_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), burn_accounts);
+        token_interface::burn(burn_ctx, 1)?;
+        
+        emit_cpi!(PositionsMerged {
+            provider: provider_key,
+            mode_id,
+            position: position_key,
+            source: source_key,
+            shares: moved_shares,
+            new_total,
+        });
+        
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(mode_id: u32)]
-pub struct Deposit<'info> {
-    #[account(
-        init_if_needed,
-        payer = provider,
-        space = 8 + ProviderPosition::INIT_SPACE,
-        seeds = [b"pos", provider.key().as_ref(), &mode_id.to_le_bytes()],
-        bump
-    )]
-    pub position: Account<'info, ProviderPosition>,
-    
-    #[account(
-        init_if_needed,
-        payer = provider,
-        associated_token::mint = collateral_mint,
-        associated_token::authority = position
-    )]
-    pub vault_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub provider_token_account: Account<'info, TokenAccount>,
-    
-    pub collateral_mint: Account<'info, Mint>,
-    
-    /// Position NFT mint (created externally, authority = position PDA)
-    #[account(mut)]
-    pub position_nft_mint: Account<'info, Mint>,
-    
-    /// Provider's NFT token account
-    #[account(
-        init_if_needed,
-        payer = provider,
-        associated_token::mint = position_nft_mint,
-        associated_token::authority = provider
-    )]
-    pub provider_nft_account: Account<'info, TokenAccount>,
-    
+    /// Move free collateral to the owner's position in another mode (same mint)
+    ///
+    /// Atomic alternative to request_withdraw, unbonding and a fresh deposit:
+    /// only free collateral moves, so every reservation on the source stays
+    /// covered. Shares are burned in the source mode vault and minted in the
+    /// target's for what arrives (net of any Token-2022 transfer fee), under
+    /// the target mode's extension allowlist and deposit caps. The same
+    /// guards as request_withdraw and merge_positions apply: a source with a
+    /// withdrawal allowlist only migrates to a target with the identical
+    /// list, and while the source has reservations what stays free must keep
+    /// the source mode's USD floor.
+    pub fn migrate_collateral<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MigrateCollateral<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(
+            !ctx.accounts.source_position.frozen && !ctx.accounts.position.frozen,
+            ErrorCode::PositionFrozen
+        );
+        require!(!ctx.accounts.mode_vault.peg_halted, ErrorCode::CollateralDepegged);
+        
+        // Migration must not route around the source's withdrawal allowlist
+        let source = &ctx.accounts.source_position;
+        let source_allowlist = &source.withdrawal_allowlist[..source.withdrawal_allowlist_count as usize];
+        if !source_allowlist.is_empty() {
+            let target = &ctx.accounts.position;
+            require!(
+                target.withdrawal_allowlist[..target.withdrawal_allowlist_count as usize] == *source_allowlist,
+                ErrorCode::AllowlistMismatch
+            );
+        }
+        
+        assert_allowed_mint_extensions(
+            &ctx.accounts.collateral_mint.to_account_info(),
+            ctx.accounts.mode.allowed_mint_extensions,
+        )?;
+        
+        // Capture values BEFORE mutable borrow
+        let source_vault_info = ctx.accounts.source_mode_vault.to_account_info();
+        let source_vault_token_info = ctx.accounts.source_vault_token_account.to_account_info();
+        let vault_token_info = ctx.accounts.vault_token_account.to_account_info();
+        let mint_info = ctx.accounts.collateral_mint.to_account_info();
+        let decimals = ctx.accounts.collateral_mint.decimals;
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let per_provider_cap = ctx.accounts.mode.per_provider_cap;
+        let global_cap = ctx.accounts.mode.global_cap;
+        let balance_before = ctx.accounts.vault_token_account.amount;
+        let min_free_usd = ctx.accounts.source_mode.min_free_collateral_usd;
+        let clock = Clock::get()?;
+        
+        let source_mode_vault = &mut ctx.accounts.source_mode_vault;
+        let source = &mut ctx.accounts.source_position;
+        
+        source.total = source_mode_vault.assets_for_shares(source.shares)?;
+        let committed = source.reserved
+            .checked_add(source.pending_withdrawal)
+            .and_then(|committed| committed.checked_add(source.deployed))
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            amount <= source.total.saturating_sub(committed),
+            ErrorCode::InsufficientFreeCollateral
+        );
+        
+        // While sessions are live, what stays free must keep its USD floor
+        if min_free_usd > 0 && source.reserved > 0 {
+            let price = load_health_price(
+                &ctx.accounts.source_mode,
+                ctx.accounts.price_update.as_ref(),
+                ctx.remaining_accounts,
+            )?;
+            let remaining_free = source.total
+                .checked_add(source.fresh_leg_value(clock.slot)?)
+                .ok_or(ErrorCode::Overflow)?
+                .saturating_sub(committed)
+                .saturating_sub(amount);
+            pyth_helpers::assert_min_usd_value(
+                remaining_free,
+                source.leg_value_decimals,
+                &price,
+                min_free_usd,
+            )?;
+        }
+        
+        // Burn rounding up so the source vault never loses value
+        burn_shares_for_assets(source_mode_vault, source, amount)?;
+        let source_mode_id = source.mode_id;
+        let source_total = source.total;
+        let mode_id_bytes = source_mode_vault.mode_id.to_le_bytes();
+        let mint_key = source_mode_vault.mint;
+        let source_vault_bump = source_mode_vault.bump;
+        
+        let _ = source;
+        let _ = source_mode_vault;
+        
+        let seeds: &[&[u8]] = &[
+            b"mode_vault",
+            &mode_id_bytes,
+            mint_key.as_ref(),
+            &[source_vault_bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        let cpi_accounts = TransferChecked {
+            from: source_vault_token_info,
+            mint: mint_info,
+            to: vault_token_info,
+            authority: source_vault_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+        
+        ctx.accounts.vault_token_account.reload()?;
+        let received = ctx.accounts.vault_token_account.amount
+            .checked_sub(balance_before)
+            .ok_or(ErrorCode::Underflow)?;
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        let position = &mut ctx.accounts.position;
+        
+        let new_shares = mode_vault.shares_for_assets(received)?;
+        require!(new_shares > 0, ErrorCode::ZeroShares);
+        
+        position.shares = position.shares.checked_add(new_shares).ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_shares = mode_vault.total_shares
+            .checked_add(new_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        mode_vault.total_assets = mode_vault.total_assets
+            .checked_add(received)
+            .ok_or(ErrorCode::Overflow)?;
+        
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        check_deposit_caps(mode_vault, position, per_provider_cap, global_cap)?;
+        
+        emit_cpi!(CollateralMigrated {
+            provider: position.provider,
+            from_mode_id: source_mode_id,
+            to_mode_id: position.mode_id,
+            amount,
+            received,
+            source_total,
+            new_total: position.total,
+        });
+        
+        Ok(())
+    }
+
+    /// Apply a pending withdrawal allowlist after its timelock
+    pub fn apply_withdrawal_allowlist(ctx: Context<UpdateWithdrawalAllowlist>) -> Result<()> {
+        let clock = Clock::get()?;
+        let position = &mut ctx.accounts.position;
+        
+        require!(
+            position.pending_withdrawal_allowlist_count > 0,
+            ErrorCode::NoPendingWithdrawalAllowlist
+        );
+        require!(
+            clock.slot >= position.withdrawal_allowlist_effective_slot,
+            ErrorCode::WithdrawalAllowlistTimelocked
+        );
+        
+        position.withdrawal_allowlist = position.pending_withdrawal_allowlist;
+        position.withdrawal_allowlist_count = position.pending_withdrawal_allowlist_count;
+        position.pending_withdrawal_allowlist = [Pubkey::default(); MAX_WITHDRAWAL_DESTINATIONS];
+        position.pending_withdrawal_allowlist_count = 0;
+        
+        emit_cpi!(WithdrawalAllowlistApplied {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            count: position.withdrawal_allowlist_count,
+        });
+        
+        Ok(())
+    }
+
+    /// Re-check a stablecoin vault against its peg (permissionless)
+    ///
+    /// Uses the mode's registry peg config. While the price is outside the
+    /// bound, new deposits and reservations are halted; existing funds stay
+    /// withdrawable.
+    pub fn refresh_peg_status(ctx: Context<RefreshPegStatus>) -> Result<()> {
+        let mode = &ctx.accounts.mode;
+        require!(mode.peg_max_dev_bps > 0, ErrorCode::NotStablecoinMode);
+        
+        let price = pyth_helpers::load_price(
+            &ctx.accounts.price_update,
+            &mode.peg_feed_id,
+            PEG_PRICE_MAX_AGE_SECONDS,
+        )?;
+        let deviation_bps = pyth_helpers::peg_deviation_bps(&price, pyth_helpers::PEG_TARGET_ONE_USD)?;
+        let halted = deviation_bps > mode.peg_max_dev_bps as u64;
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        mode_vault.peg_halted = halted;
+        
+        emit_cpi!(PegStatusRefreshed {
+            mode_id: mode_vault.mode_id,
+            mint: mode_vault.mint,
+            deviation_bps,
+            halted,
+        });
+        
+        Ok(())
+    }
+
+    /// Re-evaluate a position against the mode's USD health floor (permissionless)
+    ///
+    /// Values free collateral (as computed by reserve) at the conservative
+    /// Pyth price of the mode's health feed, or for LP-token modes at the
+    /// pool's fair value net of the LP haircut (see load_health_price). Below
+    /// min_free_collateral_usd the position is flagged and reserve rejects
+    /// it; calling this again once the price or the position recovers clears
+    /// the flag.
+    pub fn flag_undercollateralized<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FlagUndercollateralized<'info>>,
+    ) -> Result<()> {
+        let mode = &ctx.accounts.mode;
+        require!(mode.min_free_collateral_usd > 0, ErrorCode::HealthCheckDisabled);
+        
+        let clock = Clock::get()?;
+        let min_free_usd = mode.min_free_collateral_usd;
+        let price = load_health_price(
+            mode,
+            ctx.accounts.price_update.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+        
+        let position = &mut ctx.accounts.position;
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let free = position.total
+            .checked_add(position.fresh_leg_value(clock.slot)?)
+            .ok_or(ErrorCode::Overflow)?
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.pending_withdrawal)
+            .saturating_sub(position.deployed);
+        let free_usd = pyth_helpers::conservative_usd_value(
+            free,
+            position.leg_value_decimals,
+            &price,
+            pyth_helpers::PEG_DECIMALS,
+        )?;
+        position.undercollateralized = free_usd < min_free_usd;
+        
+        emit_cpi!(PositionHealthFlagged {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            free,
+            free_usd,
+            min_free_usd,
+            undercollateralized: position.undercollateralized,
+        });
+        
+        Ok(())
+    }
+
+    /// Post secondary collateral in a registry-approved mint
+    ///
+    /// Stablecoin modes only, since legs are valued in USD and counted 1:1
+    /// against the mode mint. Legs are kept sorted by registry priority,
+    /// which is the order slash_and_pay draws on them.
+    pub fn deposit_leg(ctx: Context<DepositLeg>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(ctx.accounts.mode.peg_max_dev_bps > 0, ErrorCode::NotStablecoinMode);
+        
+        // Capture values BEFORE mutable borrow
+        let clock = Clock::get()?;
+        let leg_mint = ctx.accounts.leg_mint.key();
+        let leg_decimals = ctx.accounts.leg_mint.decimals;
+        let feed_id = ctx.accounts.leg_config.feed_id;
+        let haircut_bps = ctx.accounts.leg_config.haircut_bps;
+        let priority = ctx.accounts.leg_config.priority;
+        let price = pyth_helpers::load_price(
+            &ctx.accounts.price_update,
+            &feed_id,
+            LEG_PRICE_MAX_AGE_SECONDS,
+        )?;
+        
+        let position = &mut ctx.accounts.position;
+        let index = match position.leg_index(&leg_mint) {
+            Some(index) => index,
+            None => {
+                let count = position.leg_count as usize;
+                require!(count < MAX_COLLATERAL_LEGS, ErrorCode::TooManyCollateralLegs);
+                let index = position.legs[..count]
+                    .iter()
+                    .position(|leg| leg.priority > priority)
+                    .unwrap_or(count);
+                position.legs.copy_within(index..count, index + 1);
+                position.legs[index] = CollateralLeg {
+                    mint: leg_mint,
+                    priority,
+                    decimals: leg_decimals,
+                    ..CollateralLeg::default()
+                };
+                position.leg_count += 1;
+                index
+            }
+        };
+        
+        let value_decimals = position.leg_value_decimals;
+        let leg = &mut position.legs[index];
+        leg.feed_id = feed_id;
+        leg.haircut_bps = haircut_bps;
+        leg.amount = leg.amount.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+        value_leg(leg, &price, value_decimals, clock.slot)?;
+        let leg_amount = leg.amount;
+        let leg_value = leg.value;
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        
+        let _ = position;
+        
+        // Transfer collateral to the position's leg vault
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.provider_token_account.to_account_info(),
+            to: ctx.accounts.leg_vault.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+        
+        emit_cpi!(CollateralLegDeposited {
+            provider: provider_key,
+            mode_id,
+            mint: leg_mint,
+            amount,
+            leg_amount,
+            leg_value,
+        });
+        
+        Ok(())
+    }
+
+    /// Revalue a collateral leg at the current oracle price (permissionless)
+    ///
+    /// Also picks up registry haircut changes. Leg value only counts toward
+    /// reservations for LEG_VALUE_MAX_AGE_SLOTS after a valuation.
+    pub fn refresh_leg_value(ctx: Context<RefreshLegValue>) -> Result<()> {
+        let clock = Clock::get()?;
+        let leg_config = &ctx.accounts.leg_config;
+        let price = pyth_helpers::load_price(
+            &ctx.accounts.price_update,
+            &leg_config.feed_id,
+            LEG_PRICE_MAX_AGE_SECONDS,
+        )?;
+        
+        let position = &mut ctx.accounts.position;
+        let index = position.leg_index(&leg_config.mint).ok_or(ErrorCode::UnknownCollateralLeg)?;
+        let value_decimals = position.leg_value_decimals;
+        let leg = &mut position.legs[index];
+        leg.feed_id = leg_config.feed_id;
+        leg.haircut_bps = leg_config.haircut_bps;
+        value_leg(leg, &price, value_decimals, clock.slot)?;
+        let leg_value = leg.value;
+        
+        emit_cpi!(LegValueRefreshed {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            mint: leg_config.mint,
+            leg_value,
+        });
+        
+        Ok(())
+    }
+
+    /// Value the delegated native stake backing a position (permissionless)
+    ///
+    /// Stablecoin modes only, like legs. Counts the delegators' slashable
+    /// share (naked_staking's delegator_slash_share_bps of the backing) at
+    /// the lower edge of the oracle confidence interval, and like leg value
+    /// only toward reservations for LEG_VALUE_MAX_AGE_SLOTS.
+    pub fn refresh_delegated_value(ctx: Context<RefreshDelegatedValue>) -> Result<()> {
+        require!(ctx.accounts.mode.peg_max_dev_bps > 0, ErrorCode::NotStablecoinMode);
+        
+        let clock = Clock::get()?;
+        let price_update = &ctx.accounts.price_update;
+        let price = pyth_helpers::load_price(
+            price_update,
+            &price_update.price_message.feed_id,
+            LEG_PRICE_MAX_AGE_SECONDS,
+        )?;
+        let slashable = (ctx.accounts.backing.total_delegated as u128)
+            .checked_mul(ctx.accounts.stake_pool.delegator_slash_share_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            / 10_000;
+        let slashable = u64::try_from(slashable).map_err(|_| ErrorCode::Overflow)?;
+        
+        let position = &mut ctx.accounts.position;
+        position.delegated_value = pyth_helpers::conservative_usd_value(
+            slashable,
+            ctx.accounts.native_mint.decimals,
+            &price,
+            position.leg_value_decimals,
+        )?;
+        position.delegated_valued_slot = clock.slot;
+        
+        emit_cpi!(DelegatedValueRefreshed {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            backing: ctx.accounts.backing.key(),
+            delegated_value: position.delegated_value,
+        });
+        
+        Ok(())
+    }
+
+    /// Withdraw secondary collateral
+    ///
+    /// Legs only add reservation capacity, so they skip unbonding; the
+    /// remaining primary collateral plus fresh leg value must still cover
+    /// reserved and unbonding amounts.
+    pub fn withdraw_leg(ctx: Context<WithdrawLeg>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        
+        // Capture values BEFORE mutable borrow
+        let clock = Clock::get()?;
+        let leg_vault_info = ctx.accounts.leg_vault.to_account_info();
+        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let position_info = ctx.accounts.position.to_account_info();
+        let destination = ctx.accounts.provider_token_account.key();
+        let leg_mint = ctx.accounts.leg_mint.key();
+        
+        let position = &mut ctx.accounts.position;
+        
+        // Restrict destination once an allowlist is registered
+        let allowlist_count = position.withdrawal_allowlist_count as usize;
+        if allowlist_count > 0 {
+            require!(
+                position.withdrawal_allowlist[..allowlist_count].contains(&destination),
+                ErrorCode::DestinationNotAllowlisted
+            );
+        }
+        
+        let index = position.leg_index(&leg_mint).ok_or(ErrorCode::UnknownCollateralLeg)?;
+        let value_decimals = position.leg_value_decimals;
+        let leg = &mut position.legs[index];
+        require!(amount <= leg.amount, ErrorCode::InsufficientFreeCollateral);
+        let price = pyth_helpers::load_price(
+            &ctx.accounts.price_update,
+            &leg.feed_id,
+            LEG_PRICE_MAX_AGE_SECONDS,
+        )?;
+        leg.amount = leg.amount.checked_sub(amount).ok_or(ErrorCode::Underflow)?;
+        value_leg(leg, &price, value_decimals, clock.slot)?;
+        let leg_amount = leg.amount;
+        let leg_value = leg.value;
+        
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let capacity = position.total
+            .checked_add(position.fresh_leg_value(clock.slot)?)
+            .ok_or(ErrorCode::Overflow)?;
+        let committed = position.reserved
+            .checked_add(position.pending_withdrawal)
+            .and_then(|committed| committed.checked_add(position.deployed))
+            .ok_or(ErrorCode::Overflow)?;
+        require!(committed <= capacity, ErrorCode::InsufficientFreeCollateral);
+        
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        let position_bump = position.bump;
+        let seed_provider = position.seed_provider;
+        
+        let _ = position;
+        
+        // Transfer (leg vault is owned by the position PDA)
+        let mode_id_bytes = mode_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"pos",
+            seed_provider.as_ref(),
+            &mode_id_bytes,
+            &[position_bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        let cpi_accounts = Transfer {
+            from: leg_vault_info,
+            to: provider_token_info,
+            authority: position_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+        token::transfer(cpi_ctx, amount)?;
+        
+        emit_cpi!(CollateralLegWithdrawn {
+            provider: provider_key,
+            mode_id,
+            mint: leg_mint,
+            amount,
+            leg_amount,
+            leg_value,
+        });
+        
+        Ok(())
+    }
+
+    /// Deploy idle (free) collateral to the mode's yield adapter; owner key only
+    ///
+    /// Deployed collateral keeps its shares but cannot be reserved, withdrawn
+    /// or slashed until recall_collateral brings it back. Remaining accounts
+    /// are passed through to the adapter.
+    pub fn deploy_collateral<'info>(
+        ctx: Context<'_, '_, 'info, 'info, YieldAdapterCall<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.position.provider,
+            ErrorCode::WrongProvider
+        );
+        let adapter = ctx.accounts.adapter_program.key();
+        require!(
+            adapter != Pubkey::default() && adapter == ctx.accounts.mode.yield_adapter,
+            ErrorCode::YieldAdapterNotAllowed
+        );
+        
+        let position = &mut ctx.accounts.position;
+        require!(
+            position.deployed == 0 || position.yield_adapter == adapter,
+            ErrorCode::YieldAdapterNotAllowed
+        );
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let free = position.total
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.pending_withdrawal)
+            .saturating_sub(position.deployed);
+        require!(amount <= free, ErrorCode::InsufficientFreeCollateral);
+        
+        position.deployed = position.deployed.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+        position.yield_adapter = adapter;
+        let deployed = position.deployed;
+        let provider_key = position.provider;
+        let mode_id = position.mode_id;
+        
+        let _ = position;
+        
+        invoke_yield_adapter(&ctx, ADAPTER_DEPOSIT_IX, &amount.to_le_bytes(), amount)?;
+        
+        emit_cpi!(CollateralDeployed {
+            provider: provider_key,
+            mode_id,
+            amount,
+            deployed,
+        });
+        
+        Ok(())
+    }
+
+    /// Recall a position's whole yield deployment (owner or operator)
+    ///
+    /// Yield is credited to this position as new shares; a shortfall burns
+    /// this position's shares. Call before reserving deployed collateral.
+    pub fn recall_collateral<'info>(
+        ctx: Context<'_, '_, 'info, 'info, YieldAdapterCall<'info>>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.position.can_operate(&ctx.accounts.authority.key()),
+            ErrorCode::WrongProvider
+        );
+        let principal = ctx.accounts.position.deployed;
+        require!(principal > 0, ErrorCode::NothingDeployed);
+        // The adapter used at deploy time, even if the registry has since moved on
+        require!(
+            ctx.accounts.adapter_program.key() == ctx.accounts.position.yield_adapter,
+            ErrorCode::YieldAdapterNotAllowed
+        );
+        
+        let balance_before = ctx.accounts.vault_token_account.amount;
+        invoke_yield_adapter(&ctx, ADAPTER_WITHDRAW_ALL_IX, &[], 0)?;
+        ctx.accounts.vault_token_account.reload()?;
+        let received = ctx.accounts.vault_token_account.amount
+            .checked_sub(balance_before)
+            .ok_or(ErrorCode::Underflow)?;
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        let position = &mut ctx.accounts.position;
+        position.deployed = 0;
+        
+        if received >= principal {
+            let profit = received - principal;
+            let new_shares = mode_vault.shares_for_assets(profit)?;
+            position.shares = position.shares.checked_add(new_shares).ok_or(ErrorCode::Overflow)?;
+            mode_vault.total_shares = mode_vault.total_shares
+                .checked_add(new_shares)
+                .ok_or(ErrorCode::Overflow)?;
+            mode_vault.total_assets = mode_vault.total_assets
+                .checked_add(profit)
+                .ok_or(ErrorCode::Overflow)?;
+            position.total = mode_vault.assets_for_shares(position.shares)?;
+        } else {
+            burn_shares_for_assets(mode_vault, position, principal - received)?;
+        }
+        
+        emit_cpi!(CollateralRecalled {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            principal,
+            received,
+            new_total: position.total,
+        });
+        
+        Ok(())
+    }
+
+    /// Freeze a position suspected of fraud (registry admin)
+    ///
+    /// Blocks withdrawals, yield deployment and new reservations. Claims on
+    /// existing reservations still pay out. Freezing again cancels any
+    /// pending unfreeze.
+    pub fn freeze_position(ctx: Context<AdminPosition>) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        position.frozen = true;
+        position.unfreeze_unlock_slot = 0;
+        
+        emit_cpi!(PositionFrozen {
+            provider: position.provider,
+            mode_id: position.mode_id,
+        });
+        
+        Ok(())
+    }
+
+    /// Start the unfreeze timelock (registry admin)
+    pub fn request_unfreeze(ctx: Context<AdminPosition>) -> Result<()> {
+        let clock = Clock::get()?;
+        let position = &mut ctx.accounts.position;
+        require!(position.frozen, ErrorCode::PositionNotFrozen);
+        
+        let unlock_slot = clock.slot
+            .checked_add(UNFREEZE_TIMELOCK_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+        position.unfreeze_unlock_slot = unlock_slot;
+        
+        emit_cpi!(PositionUnfreezeRequested {
+            provider: position.provider,
+            mode_id: position.mode_id,
+            unlock_slot,
+        });
+        
+        Ok(())
+    }
+
+    /// Lift a freeze once its unfreeze timelock has passed (registry admin)
+    pub fn unfreeze_position(ctx: Context<AdminPosition>) -> Result<()> {
+        let clock = Clock::get()?;
+        let position = &mut ctx.accounts.position;
+        require!(position.frozen, ErrorCode::PositionNotFrozen);
+        require!(
+            position.unfreeze_unlock_slot != 0 && clock.slot >= position.unfreeze_unlock_slot,
+            ErrorCode::UnfreezeTimelocked
+        );
+        
+        position.frozen = false;
+        position.unfreeze_unlock_slot = 0;
+        
+        emit_cpi!(PositionUnfrozen {
+            provider: position.provider,
+            mode_id: position.mode_id,
+        });
+        
+        Ok(())
+    }
+
+    /// Reserve collateral for a session (CPI from session_escrow)
+    pub fn reserve(ctx: Context<Reserve>, session: Pubkey, amount_r: u64) -> Result<()> {
+        assert_authorized_caller(&ctx.accounts.vault_callers, &ctx.accounts.instructions_sysvar)?;
+        require!(!ctx.accounts.mode_vault.peg_halted, ErrorCode::CollateralDepegged);
+        require!(!ctx.accounts.position.frozen, ErrorCode::PositionFrozen);
+        require!(!ctx.accounts.position.undercollateralized, ErrorCode::PositionUndercollateralized);
+        require!(!ctx.accounts.position.exiting, ErrorCode::PositionExiting);
+        
+        let clock = Clock::get()?;
+        let position = &mut ctx.accounts.position;
+        
+        // Freshly valued legs add capacity; unbonding and deployed collateral
+        // cannot back new sessions (recall_collateral first)
+        position.total = ctx.accounts.mode_vault.assets_for_shares(position.shares)?;
+        let capacity = position.total
+            .checked_add(position.fresh_leg_value(clock.slot)?)
+            .ok_or(ErrorCode::Overflow)?;
+        let free = capacity
+            .saturating_sub(position.reserved)
+            .saturating_sub(position.pending_withdrawal)
+            .saturating_sub(position.deployed);
+        require!(amount_r <= free, ErrorCode::InsufficientFreeCollateral);
+        
+        position.reserved = position.reserved.checked_add(amount_r).ok_or(ErrorCode::Overflow)?;
+        require!(position.reserved <= capacity, ErrorCode::ReservedExceedsTotal);
+        
+        // First reservation for this session creates its record
+        if ctx.accounts.reservation.position == Pubkey::default() {
+            position.open_reservations = position.open_reservations.checked_add(1).ok_or(ErrorCode::Overflow)?;
+            position.sessions_served = position.sessions_served.saturating_add(1);
+        }
+        
+        let position_key = position.key();
+        let provider = position.provider;
+        let new_reserved = position.reserved;
+        
+        let _ = position;
+        
+        let payer_key = ctx.accounts.provider.key();
+        let reservation_bump = ctx.bumps.reservation;
+        let reservation = &mut ctx.accounts.reservation;
+        if reservation.position == Pubkey::default() {
+            reservation.position = position_key;
+            reservation.session = session;
+            reservation.amount = 0;
+            reservation.payer = payer_key;
+            reservation.bump = reservation_bump;
+        }
+        reservation.amount = reservation.amount.checked_add(amount_r).ok_or(ErrorCode::Overflow)?;
+        
+        emit_cpi!(CollateralReserved {
+            provider,
+            session,
+            amount: amount_r,
+            new_reserved,
+        });
+        
+        Ok(())
+    }
+
+    /// Release reserved collateral (session completed successfully)
+    ///
+    /// Capped by the session's reservation record, which closes (rent back
+    /// to its payer) once nothing is left reserved for the session.
+    pub fn release(ctx: Context<Release>, session: Pubkey, amount_r: u64) -> Result<()> {
+        require_keys_eq!(ctx.accounts.session_authority.key(), session, ErrorCode::SessionAuthorityMismatch);
+        assert_session_caller(&ctx.accounts.vault_callers, &ctx.accounts.session_authority)?;
+        
+        let reservation = &mut ctx.accounts.reservation;
+        require!(amount_r <= reservation.amount, ErrorCode::ReleaseExceedsReserved);
+        reservation.amount -= amount_r;
+        let reservation_closed = reservation.amount == 0;
+        
+        let position = &mut ctx.accounts.position;
+        
+        require!(amount_r <= position.reserved, ErrorCode::ReleaseExceedsReserved);
+        
+        position.reserved = position.reserved.checked_sub(amount_r).ok_or(ErrorCode::Underflow)?;
+        position.total_released = position.total_released.saturating_add(amount_r);
+        if reservation_closed {
+            position.open_reservations = position.open_reservations.saturating_sub(1);
+        }
+        
+        let provider = position.provider;
+        let mode_id = position.mode_id;
+        let new_reserved = position.reserved;
+        let exit_ready = position.exiting && new_reserved == 0 && amount_r > 0;
+        
+        if reservation_closed {
+            ctx.accounts.reservation.close(ctx.accounts.rent_receiver.to_account_info())?;
+        }
+        
+        emit_cpi!(CollateralReleased {
+            provider,
+            session,
+            amount: amount_r,
+            new_reserved,
+        });
+        if exit_ready {
+            emit_cpi!(ProviderExitReady {
+                provider,
+                mode_id,
+                position: ctx.accounts.position.key(),
+            });
+        }
+        
+        Ok(())
+    }
+
+    /// Release reservations of many sessions on one position in one call
+    ///
+    /// Same rules as release, for caller programs settling several sessions
+    /// at once (e.g. at the end of an epoch). remaining_accounts holds, per
+    /// entry and in order, the session's ReservationRecord, its rent
+    /// receiver (the record's payer) and the session account itself, which
+    /// must sign and be owned by an allowlisted caller program.
+    pub fn release_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseBatch<'info>>,
+        releases: Vec<ReleaseEntry>,
+    ) -> Result<()> {
+        require!(
+            !releases.is_empty() && releases.len() <= MAX_BATCH_RELEASES,
+            ErrorCode::InvalidReleaseBatch
+        );
+        require!(
+            ctx.remaining_accounts.len() == releases.len() * 3,
+            ErrorCode::InvalidReleaseBatch
+        );
+        
+        let position_key = ctx.accounts.position.key();
+        let provider = ctx.accounts.position.provider;
+        let mode_id = ctx.accounts.position.mode_id;
+        let mut new_reserved = ctx.accounts.position.reserved;
+        let mut released: u64 = 0;
+        let mut closed: u32 = 0;
+        
+        for (entry, accounts) in releases.iter().zip(ctx.remaining_accounts.chunks(3)) {
+            let rent_receiver = &accounts[1];
+            let session_authority = &accounts[2];
+            require!(session_authority.is_signer, ErrorCode::SessionAuthorityMismatch);
+            require_keys_eq!(session_authority.key(), entry.session, ErrorCode::SessionAuthorityMismatch);
+            assert_session_caller(&ctx.accounts.vault_callers, session_authority)?;
+            let mut reservation = Account::<ReservationRecord>::try_from(&accounts[0])?;
+            require!(
+                reservation.position == position_key && reservation.session == entry.session,
+                ErrorCode::InvalidReleaseBatch
+            );
+            require_keys_eq!(rent_receiver.key(), reservation.payer, ErrorCode::InvalidRentReceiver);
+            require!(entry.amount <= reservation.amount, ErrorCode::ReleaseExceedsReserved);
+            require!(entry.amount <= new_reserved, ErrorCode::ReleaseExceedsReserved);
+            
+            reservation.amount -= entry.amount;
+            new_reserved -= entry.amount;
+            released = released.checked_add(entry.amount).ok_or(ErrorCode::Overflow)?;
+            if reservation.amount == 0 {
+                reservation.close(rent_receiver.clone())?;
+                closed += 1;
+            } else {
+                reservation.exit(&crate::ID)?;
+            }
+            
+            emit_cpi!(CollateralReleased {
+                provider,
+                session: entry.session,
+                amount: entry.amount,
+                new_reserved,
+            });
+        }
+        
+        let position = &mut ctx.accounts.position;
+        position.reserved = new_reserved;
+        position.open_reservations = position.open_reservations.saturating_sub(closed);
+        position.total_released = position.total_released.saturating_add(released);
+        let exit_ready = position.exiting && new_reserved == 0 && released > 0;
+        
+        let _ = position;
+        
+        if exit_ready {
+            emit_cpi!(ProviderExitReady {
+                provider,
+                mode_id,
+                position: position_key,
+            });
+        }
+        
+        Ok(())
+    }
+
+    /// Slash collateral and pay to user (claim payout)
+    ///
+    /// Primary collateral pays first. Any shortfall is paid from the
+    /// position's legs in priority order, in leg tokens, to the user's token
+    /// accounts for those mints; see slash_legs for the remaining accounts.
+    /// Any Token-2022 transfer fee comes out of what the user receives. The
+    /// payout is capped by the session's reservation record, which closes
+    /// once nothing is left reserved for the session.
+    ///
+    /// With the optional insurance fund accounts, a payout above the
+    /// reservation record is allowed: whatever the reservation and the
+    /// position's collateral cannot cover is topped up from the protocol
+    /// insurance fund, up to the fund's delegation to the insurance
+    /// authority PDA.
+    ///
+    /// `recipient_amounts` splits the payout across up to
+    /// MAX_SLASH_RECIPIENTS token accounts in one call (e.g. user, insurance
+    /// fund and referrer); see slash_recipients. Empty pays it all to the
+    /// user.
+    pub fn slash_and_pay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SlashAndPay<'info>>,
+        session: Pubkey,
+        payout_amount: u64,
+        recipient_amounts: Vec<u64>,
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.session_authority.key(), session, ErrorCode::SessionAuthorityMismatch);
+        assert_session_caller(&ctx.accounts.vault_callers, &ctx.accounts.session_authority)?;
+        
+        let (recipients, leg_accounts) = slash_recipients(
+            &ctx.accounts.user_token_account,
+            ctx.remaining_accounts,
+            &recipient_amounts,
+            payout_amount,
+            &ctx.accounts.collateral_mint.key(),
+        )?;
+        
+        // Capture values BEFORE mutable borrow
+        let mode_vault_info = ctx.accounts.mode_vault.to_account_info();
+        let vault_info = ctx.accounts.vault_token_account.to_account_info();
+        let mint_info = ctx.accounts.collateral_mint.to_account_info();
+        let decimals = ctx.accounts.collateral_mint.decimals;
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let position_info = ctx.accounts.position.to_account_info();
+        let user_owner = ctx.accounts.user_token_account.owner;
+        let backstop = ctx.accounts.insurance_fund_token_account.is_some();
+        
+        // Without a backstop the payout must fit the reservation
+        let reservation = &mut ctx.accounts.reservation;
+        require!(
+            backstop || payout_amount <= reservation.amount,
+            ErrorCode::PayoutExceedsReserved
+        );
+        let from_reservation = payout_amount.min(reservation.amount);
+        reservation.amount -= from_reservation;
+        let reservation_closed = reservation.amount == 0;
+        
+        let mode_vault = &mut ctx.accounts.mode_vault;
+        let position = &mut ctx.accounts.position;
+        
+        require!(from_reservation <= position.reserved, ErrorCode::PayoutExceedsReserved);
+        
+        // Capture for signer seeds
+        let mode_id_bytes = mode_vault.mode_id.to_le_bytes();
+        let mint_key = mode_vault.mint;
+        let vault_bump = mode_vault.bump;
+        let provider_key = position.provider;
+        let position_bump = position.bump;
+        let seed_provider = position.seed_provider;
+        
+        // Update state (slash is charged to this position's shares only)
+        position.reserved = position.reserved.checked_sub(from_reservation).ok_or(ErrorCode::Underflow)?;
+        if reservation_closed {
+            position.open_reservations = position.open_reservations.saturating_sub(1);
+        }
+        position.total = mode_vault.assets_for_shares(position.shares)?;
+        let from_primary = from_reservation.min(position.total.saturating_sub(position.deployed));
+        burn_shares_for_assets(mode_vault, position, from_primary)?;
+        
+        // Recipients are paid in order: the reservation covers the earliest
+        // amounts, each from primary collateral first, then from legs and
+        // then from delegated native stake
+        let position_seeds: &[&[u8]] = &[
+            b"pos",
+            seed_provider.as_ref(),
+            &mode_id_bytes,
+            &[position_bump],
+        ];
+        let mut covered_left = from_reservation;
+        let mut primary_left = from_primary;
+        let mut uncovered: u64 = 0;
+        let mut payments = Vec::with_capacity(recipients.len());    // (from primary, unpaid)
+        for (recipient, amount) in recipients.iter() {
+            let covered = (*amount).min(covered_left);
+            covered_left -= covered;
+            let primary = covered.min(primary_left);
+            primary_left -= primary;
+            
+            let recipient_uncovered = if covered > primary {
+                let after_legs = slash_legs(
+                    position,
+                    &position_info,
+                    leg_accounts,
+                    recipient.owner,
+                    &token_program_info,
+                    covered - primary,
+                    &[position_seeds],
+                )?;
+                slash_delegated_backing(
+                    position,
+                    &position_info,
+                    leg_accounts,
+                    recipient.owner,
+                    &token_program_info,
+                    after_legs,
+                    &[position_seeds],
+                )?
+            } else {
+                0
+            };
+            uncovered = uncovered.checked_add(recipient_uncovered).ok_or(ErrorCode::Overflow)?;
+            let unpaid = (amount - covered)
+                .checked_add(recipient_uncovered)
+                .ok_or(ErrorCode::Overflow)?;
+            payments.push((primary, unpaid));
+        }
+        let shortfall = from_reservation - from_primary;
+        let slashed = from_reservation - uncovered;
+        if slashed > 0 {
+            position.total_slashed = position.total_slashed.saturating_add(slashed);
+            position.last_slash_slot = Clock::get()?.slot;
+        }
+        let new_total = position.total;
+        let new_reserved = position.reserved;
+        let exit_ready = position.exiting && new_reserved == 0 && from_reservation > 0;
+        let mode_id = position.mode_id;
+        
+        // Drop mutable borrow
+        let _ = position;
+        let _ = mode_vault;
+        
+        // Transfer
+        let seeds: &[&[u8]] = &[
+            b"mode_vault",
+            &mode_id_bytes,
+            mint_key.as_ref(),
+            &[vault_bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        let mut unpaid_total: u64 = 0;
+        let mut insurance_paid: u64 = 0;
+        for ((recipient, _), (primary, unpaid)) in recipients.iter().zip(payments) {
+            if primary > 0 {
+                let cpi_accounts = TransferChecked {
+                    from: vault_info.clone(),
+                    mint: mint_info.clone(),
+                    to: recipient.to_account_info(),
+                    authority: mode_vault_info.clone(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, signer_seeds);
+                token_interface::transfer_checked(cpi_ctx, primary, decimals)?;
+            }
+            if unpaid == 0 {
+                continue;
+            }
+            unpaid_total = unpaid_total.checked_add(unpaid).ok_or(ErrorCode::Overflow)?;
+            
+            // Insurance backstop for what neither the reservation nor collateral
+            // covered; the fund does not backstop a payout to itself
+            match ctx.accounts.insurance_fund_token_account.as_deref_mut() {
+                Some(fund) if fund.key() != recipient.key() => {
+                    let authority = ctx
+                        .accounts
+                        .insurance_authority
+                        .as_ref()
+                        .ok_or(ErrorCode::InsuranceAuthorityRequired)?;
+                    let paid = draw_insurance_backstop(
+                        fund,
+                        authority,
+                        &ctx.accounts.collateral_mint,
+                        recipient,
+                        &token_program_info,
+                        unpaid,
+                    )?;
+                    insurance_paid = insurance_paid.checked_add(paid).ok_or(ErrorCode::Overflow)?;
+                    fund.reload()?;
+                }
+                _ => {}
+            }
+        }
+        
+        if reservation_closed {
+            ctx.accounts.reservation.close(ctx.accounts.rent_receiver.to_account_info())?;
+        }
+        
+        emit_cpi!(CollateralSlashed {
+            provider: provider_key,
+            session,
+            payout_amount,
+            user: user_owner,
+            new_total,
+            new_reserved,
+            leg_payout: shortfall - uncovered,
+            uncovered,
+        });
+        
+        if unpaid_total > 0 {
+            emit_cpi!(SlashShortfall {
+                provider: provider_key,
+                session,
+                shortfall: unpaid_total,
+                insurance_paid,
+            });
+        }
+        if exit_ready {
+            emit_cpi!(ProviderExitReady {
+                provider: provider_key,
+                mode_id,
+                position: position_info.key(),
+            });
+        }
+        
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Enforce the registry's per-provider and global collateral caps after a
+/// deposit. The mode vault's total_assets is the mode-wide deposited total.
+fn check_deposit_caps(
+    mode_vault: &ModeVault,
+    position: &ProviderPosition,
+    per_provider_cap: u64,
+    global_cap: u64,
+) -> Result<()> {
+    require!(position.total <= per_provider_cap, ErrorCode::ProviderCapExceeded);
+    require!(mode_vault.total_assets <= global_cap, ErrorCode::GlobalCapExceeded);
+    Ok(())
+}
+
+/// Call the mode's yield adapter as the position
+///
+/// Adapter accounts: [position (signer), vault_token_account, mode_vault,
+/// token_program, ...remaining accounts]. The adapter keys its
+/// lending-protocol account by the position. The pooled mode vault never
+/// signs: to let the adapter pull `delegate_amount` from the vault token
+/// account, the mode vault approves the position as delegate for exactly
+/// that amount around the call and revokes it afterwards.
+fn invoke_yield_adapter<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, YieldAdapterCall<'info>>,
+    name: &str,
+    args: &[u8],
+    delegate_amount: u64,
+) -> Result<()> {
+    let position = &ctx.accounts.position;
+    let mode_vault = &ctx.accounts.mode_vault;
+    
+    let mut data = hash(name.as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    
+    let mut metas = vec![
+        AccountMeta::new_readonly(position.key(), true),
+        AccountMeta::new(ctx.accounts.vault_token_account.key(), false),
+        AccountMeta::new_readonly(mode_vault.key(), false),
+        AccountMeta::new_readonly(ctx.accounts.token_program.key(), false),
+    ];
+    let mut infos = vec![
+        position.to_account_info(),
+        ctx.accounts.vault_token_account.to_account_info(),
+        mode_vault.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    ];
+    for info in ctx.remaining_accounts {
+        metas.push(AccountMeta {
+            pubkey: info.key(),
+            is_signer: info.is_signer,
+            is_writable: info.is_writable,
+        });
+        infos.push(info.clone());
+    }
+    infos.push(ctx.accounts.adapter_program.to_account_info());
+    
+    let mode_id_bytes = position.mode_id.to_le_bytes();
+    let position_seeds: &[&[u8]] = &[
+        b"pos",
+        position.seed_provider.as_ref(),
+        &mode_id_bytes,
+        &[position.bump],
+    ];
+    let vault_seeds: &[&[u8]] = &[
+        b"mode_vault",
+        &mode_id_bytes,
+        mode_vault.mint.as_ref(),
+        &[mode_vault.bump],
+    ];
+    
+    if delegate_amount > 0 {
+        let cpi_accounts = token_interface::Approve {
+            to: ctx.accounts.vault_token_account.to_account_info(),
+            delegate: position.to_account_info(),
+            authority: mode_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            &[vault_seeds],
+        );
+        token_interface::approve(cpi_ctx, delegate_amount)?;
+    }
+    
+    let ix = Instruction {
+        program_id: ctx.accounts.adapter_program.key(),
+        accounts: metas,
+        data,
+    };
+    invoke_signed(&ix, &infos, &[position_seeds])?;
+    
+    if delegate_amount > 0 {
+        let cpi_accounts = token_interface::Revoke {
+            source: ctx.accounts.vault_token_account.to_account_info(),
+            authority: mode_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            &[vault_seeds],
+        );
+        token_interface::revoke(cpi_ctx)?;
+    }
+    
+    Ok(())
+}
+
+/// Position NFT metadata: the URI encodes provider, mode, seed key and
+/// balances for the renderer (the mode determines the collateral mint)
+fn position_metadata(position: &ProviderPosition) -> DataV2 {
+    DataV2 {
+        name: format!("Origin OS Position #{}", position.mode_id),
+        symbol: POSITION_NFT_SYMBOL.to_string(),
+        uri: format!(
+            "{}/{}/{}?seed={}&total={}&reserved={}",
+            POSITION_METADATA_BASE_URI,
+            position.provider,
+            position.mode_id,
+            position.seed_provider,
+            position.total,
+            position.reserved,
+        ),
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    }
+}
+
+/// Accounts for minting a position NFT with its metadata and master edition
+struct PositionNftAccounts<'info> {
+    position: AccountInfo<'info>,       // Mint and update authority (signs via seeds)
+    nft_mint: AccountInfo<'info>,
+    nft_account: AccountInfo<'info>,    // Owner's token account for the NFT
+    nft_metadata: AccountInfo<'info>,
+    nft_master_edition: AccountInfo<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+}
+
+/// Mint a new position's NFT and create its wallet-visible metadata; the
+/// master edition caps supply at this one token
+fn mint_position_nft(
+    accounts: PositionNftAccounts,
+    data: DataV2,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mint_accounts = token_interface::MintTo {
+        mint: accounts.nft_mint.clone(),
+        to: accounts.nft_account,
+        authority: accounts.position.clone(),
+    };
+    let mint_ctx = CpiContext::new_with_signer(
+        accounts.token_program.clone(),
+        mint_accounts,
+        signer_seeds,
+    );
+    token_interface::mint_to(mint_ctx, 1)?;
+    
+    let metadata_accounts = CreateMetadataAccountsV3 {
+        metadata: accounts.nft_metadata.clone(),
+        mint: accounts.nft_mint.clone(),
+        mint_authority: accounts.position.clone(),
+        payer: accounts.payer.clone(),
+        update_authority: accounts.position.clone(),
+        system_program: accounts.system_program.clone(),
+        rent: accounts.rent.clone(),
+    };
+    let metadata_ctx = CpiContext::new_with_signer(
+        accounts.token_metadata_program.clone(),
+        metadata_accounts,
+        signer_seeds,
+    );
+    metadata::create_metadata_accounts_v3(metadata_ctx, data, true, true, None)?;
+    
+    let edition_accounts = CreateMasterEditionV3 {
+        edition: accounts.nft_master_edition,
+        mint: accounts.nft_mint,
+        update_authority: accounts.position.clone(),
+        mint_authority: accounts.position,
+        payer: accounts.payer,
+        metadata: accounts.nft_metadata,
+        token_program: accounts.token_program,
+        system_program: accounts.system_program,
+        rent: accounts.rent,
+    };
+    let edition_ctx = CpiContext::new_with_signer(
+        accounts.token_metadata_program,
+        edition_accounts,
+        signer_seeds,
+    );
+    metadata::create_master_edition_v3(edition_ctx, Some(0))?;
+    
+    Ok(())
+}
+
+/// Value a leg's balance in mode mint units at `price`, net of its haircut
+fn value_leg(
+    leg: &mut CollateralLeg,
+    price: &pyth_helpers::PriceData,
+    value_decimals: u8,
+    slot: u64,
+) -> Result<()> {
+    let usd = pyth_helpers::token_amount_to_usd(leg.amount, leg.decimals, price, value_decimals)?;
+    leg.value = pyth_helpers::apply_haircut(usd, leg.haircut_bps)?;
+    leg.valued_slot = slot;
+    Ok(())
+}
+
+/// Pay a slash shortfall from a position's legs in priority order
+///
+/// For every leg with a balance, `accounts` must contain the leg vault (the
+/// position's ATA for the leg mint), a token account of the leg mint owned
+/// by `recipient`, and a Pyth update for the leg's feed, in any order. Leg
+/// mints are SPL Token mints, so when the primary collateral is Token-2022
+/// `accounts` must also contain the SPL Token program. Leg tokens are taken
+/// at the spot price, without haircut. Returns the part of `shortfall` the
+/// legs could not cover.
+fn slash_legs<'info>(
+    position: &mut ProviderPosition,
+    position_info: &AccountInfo<'info>,
+    accounts: &'info [AccountInfo<'info>],
+    recipient: Pubkey,
+    token_program: &AccountInfo<'info>,
+    shortfall: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64> {
+    let clock = Clock::get()?;
+    let position_key = position_info.key();
+    let value_decimals = position.leg_value_decimals;
+    let leg_count = position.leg_count as usize;
+    let mut remaining = shortfall;
+    
+    for leg in position.legs[..leg_count].iter_mut() {
+        if remaining == 0 {
+            break;
+        }
+        if leg.amount == 0 {
+            continue;
+        }
+        let token_program = if token_program.key() == token::ID {
+            token_program
+        } else {
+            accounts
+                .iter()
+                .find(|info| info.key() == token::ID)
+                .ok_or(ErrorCode::LegAccountsRequired)?
+        };
+        
+        let vault_key = get_associated_token_address(&position_key, &leg.mint);
+        let vault = accounts
+            .iter()
+            .find(|info| info.key() == vault_key)
+            .ok_or(ErrorCode::LegAccountsRequired)?;
+        let to = accounts
+            .iter()
+            .find(|info| is_token_account_of(info, &leg.mint, &recipient))
+            .ok_or(ErrorCode::LegAccountsRequired)?;
+        let price = accounts
+            .iter()
+            .find_map(|info| leg_price(info, &leg.feed_id))
+            .ok_or(ErrorCode::LegAccountsRequired)?;
+        
+        let tokens = pyth_helpers::usd_to_token_amount(remaining, value_decimals, &price, leg.decimals)?
+            .min(leg.amount);
+        let covered = if tokens == leg.amount {
+            pyth_helpers::token_amount_to_usd(tokens, leg.decimals, &price, value_decimals)?
+                .min(remaining)
+        } else {
+            remaining
+        };
+        
+        if tokens > 0 {
+            let cpi_accounts = Transfer {
+                from: vault.clone(),
+                to: to.clone(),
+                authority: position_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+            token::transfer(cpi_ctx, tokens)?;
+        }
+        
+        leg.amount = leg.amount.checked_sub(tokens).ok_or(ErrorCode::Underflow)?;
+        value_leg(leg, &price, value_decimals, clock.slot)?;
+        remaining = remaining.checked_sub(covered).ok_or(ErrorCode::Underflow)?;
+    }
+    
+    Ok(remaining)
+}
+
+/// Pay a slash shortfall from delegated native stake backing the position
+///
+/// Runs after the legs. While the position carries delegated value,
+/// `accounts` must contain the naked_staking program, its pool, vault and
+/// native mint, the position's ProviderBacking, the pool's Pyth update and a
+/// native token account owned by `recipient`, in any order. Delegated tokens
+/// are taken at the spot price, up to the pool's delegator slash share.
+/// Returns the part of `shortfall` the delegators could not cover.
+fn slash_delegated_backing<'info>(
+    position: &mut ProviderPosition,
+    position_info: &AccountInfo<'info>,
+    accounts: &'info [AccountInfo<'info>],
+    recipient: Pubkey,
+    token_program: &AccountInfo<'info>,
+    shortfall: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64> {
+    if shortfall == 0 || position.delegated_value == 0 {
+        return Ok(shortfall);
+    }
+    
+    let find = |key: Pubkey| {
+        accounts
+            .iter()
+            .find(|info| info.key() == key)
+            .ok_or(error!(ErrorCode::DelegationAccountsRequired))
+    };
+    let program = find(naked_staking::ID)?;
+    let (pool_key, _) = Pubkey::find_program_address(&[b"native_pool"], &naked_staking::ID);
+    let pool_info = find(pool_key)?;
+    let pool = Account::<naked_staking::NativeStakePool>::try_from(pool_info)?;
+    let (backing_key, _) = Pubkey::find_program_address(
+        &[b"backing", pool_key.as_ref(), position.seed_provider.as_ref()],
+        &naked_staking::ID,
+    );
+    let backing_info = find(backing_key)?;
+    let backing = Account::<naked_staking::ProviderBacking>::try_from(backing_info)?;
+    require_keys_eq!(backing.position, position_info.key(), ErrorCode::InvalidProviderBacking);
+    let vault = find(pool.vault)?;
+    let native_mint = Account::<Mint>::try_from(find(pool.native_mint)?)?;
+    let price_update = Account::<PriceUpdateV2>::try_from(find(pool.pyth_feed)?)?;
+    let price = pyth_helpers::load_price(
+        &price_update,
+        &price_update.price_message.feed_id,
+        LEG_PRICE_MAX_AGE_SECONDS,
+    )?;
+    let to = accounts
+        .iter()
+        .find(|info| is_token_account_of(info, &pool.native_mint, &recipient))
+        .ok_or(ErrorCode::DelegationAccountsRequired)?;
+    let token_program = if token_program.key() == token::ID {
+        token_program
+    } else {
+        find(token::ID)?
+    };
+    
+    let slashable = (backing.total_delegated as u128)
+        .checked_mul(pool.delegator_slash_share_bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10_000;
+    let value_decimals = position.leg_value_decimals;
+    let needed = pyth_helpers::usd_to_token_amount(shortfall, value_decimals, &price, native_mint.decimals)?;
+    let tokens = needed.min(slashable as u64);
+    if tokens == 0 {
+        position.delegated_value = 0;
+        return Ok(shortfall);
+    }
+    
+    let cpi_accounts = naked_staking::cpi::accounts::SlashDelegatedStake {
+        pool: pool_info.clone(),
+        backing: backing_info.clone(),
+        position: position_info.clone(),
+        vault: vault.clone(),
+        slash_recipient: to.clone(),
+        token_program: token_program.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(program.clone(), cpi_accounts, signer_seeds);
+    let loss = naked_staking::cpi::slash_delegated_stake(cpi_ctx, tokens)?.get();
+    
+    let covered = if loss < needed {
+        pyth_helpers::token_amount_to_usd(loss, native_mint.decimals, &price, value_decimals)?
+            .min(shortfall)
+    } else {
+        shortfall
+    };
+    position.delegated_value = position.delegated_value.saturating_sub(covered);
+    
+    Ok(shortfall - covered)
+}
+
+/// Require the transaction's top-level instruction to belong to a
+/// registry-allowlisted caller program (see mode_registry::set_vault_callers)
+///
+/// A signature from the provider or a session PDA alone is not enough: any
+/// program that obtains one could otherwise reserve, release or slash.
+fn assert_authorized_caller(
+    vault_callers: &mode_registry::VaultCallers,
+    instructions_sysvar: &AccountInfo,
+) -> Result<()> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    let top_level = load_instruction_at_checked(current as usize, instructions_sysvar)?;
+    require!(
+        vault_callers.contains(&top_level.program_id),
+        ErrorCode::UnauthorizedCaller
+    );
+    Ok(())
+}
+
+/// Require a session signer to be an account of a registry-allowlisted
+/// caller program
+///
+/// Release and slash are signed by the session PDA itself, so its owner
+/// identifies the escrow program regardless of how the call was routed.
+fn assert_session_caller(
+    vault_callers: &mode_registry::VaultCallers,
+    session_authority: &AccountInfo,
+) -> Result<()> {
+    require!(
+        vault_callers.contains(session_authority.owner),
+        ErrorCode::UnauthorizedCaller
+    );
+    Ok(())
+}
+
+/// Check a new position's NFT mint against the mode's soulbound setting
+///
+/// Soulbound modes require a Token-2022 mint with the NonTransferable
+/// extension so the position cannot change hands; other modes keep the
+/// classic SPL Token NFT.
+fn assert_position_nft_mint(nft_mint: &AccountInfo, soulbound: bool) -> Result<()> {
+    if !soulbound {
+        require_keys_eq!(*nft_mint.owner, token::ID, ErrorCode::InvalidPositionNftMint);
+        return Ok(());
+    }
+    require_keys_eq!(*nft_mint.owner, spl_token_2022::ID, ErrorCode::PositionNftNotSoulbound);
+    
+    let data = nft_mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .map_err(|_| ErrorCode::PositionNftNotSoulbound)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| ErrorCode::PositionNftNotSoulbound)?;
+    require!(
+        extensions.contains(&ExtensionType::NonTransferable),
+        ErrorCode::PositionNftNotSoulbound
+    );
+    
+    Ok(())
+}
+
+/// Reject collateral mints carrying Token-2022 extensions the mode has not allowlisted
+///
+/// Classic SPL Token mints always pass. `allowed` is the registry's
+/// allowed_mint_extensions (bit n = ExtensionType n); e.g. a permanent
+/// delegate could drain the vault and a transfer hook could block claims.
+fn assert_allowed_mint_extensions(mint: &AccountInfo, allowed: u64) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .map_err(|_| ErrorCode::UnsupportedMintExtension)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| ErrorCode::UnsupportedMintExtension)?;
+    
+    for extension in extensions {
+        let bit = extension as u16;
+        require!(
+            bit < 64 && allowed & (1u64 << bit) != 0,
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    
+    Ok(())
+}
+
+/// Transfer `amount` of collateral into a vault token account and return
+/// what arrived, net of any Token-2022 transfer fee
+fn transfer_in<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    to: &mut InterfaceAccount<'info, token_interface::TokenAccount>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = to.amount;
+    
+    let cpi_accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to: to.to_account_info(),
+        authority,
+    };
+    let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
+    
+    to.reload()?;
+    let received = to.amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::Underflow)?;
+    Ok(received)
+}
+
+/// Whether `info` is an SPL token account of `mint` owned by `owner`
+fn is_token_account_of(info: &AccountInfo, mint: &Pubkey, owner: &Pubkey) -> bool {
+    if info.owner != &token::ID {
+        return false;
+    }
+    let Ok(data) = info.try_borrow_data() else {
+        return false;
+    };
+    matches!(
+        TokenAccount::try_deserialize(&mut &data[..]),
+        Ok(account) if account.mint == *mint && account.owner == *owner
+    )
+}
+
+/// Fresh price for `feed_id` if `info` is a matching Pyth price update
+fn leg_price<'info>(
+    info: &'info AccountInfo<'info>,
+    feed_id: &[u8; 32],
+) -> Option<pyth_helpers::PriceData> {
+    let price_update = Account::<PriceUpdateV2>::try_from(info).ok()?;
+    pyth_helpers::load_price(&price_update, feed_id, LEG_PRICE_MAX_AGE_SECONDS).ok()
+}
+
+/// Price the USD health floor values `mode` collateral at
+///
+/// Plain modes use `price_update` for the mode's health feed. LP-token modes
+/// are priced per LP token at the pool's fair value, net of the mode's LP
+/// haircut: `accounts` must then contain the mode's LpPoolConfig, both
+/// reserve token accounts, the LP mint and a Pyth update for each reserve's
+/// feed, in any order.
+fn load_health_price<'info>(
+    mode: &mode_registry::Mode,
+    price_update: Option<&Account<'info, PriceUpdateV2>>,
+    accounts: &'info [AccountInfo<'info>],
+) -> Result<pyth_helpers::PriceData> {
+    if !mode.is_lp_collateral {
+        let price_update = price_update.ok_or(ErrorCode::PriceUpdateRequired)?;
+        return pyth_helpers::load_price(price_update, &mode.health_feed_id, HEALTH_PRICE_MAX_AGE_SECONDS);
+    }
+    
+    let find = |key: Pubkey| {
+        accounts
+            .iter()
+            .find(|info| info.key() == key)
+            .ok_or(error!(ErrorCode::LpPoolAccountsRequired))
+    };
+    let feed_price = |feed_id: &[u8; 32]| {
+        accounts
+            .iter()
+            .find_map(|info| {
+                let price_update = Account::<PriceUpdateV2>::try_from(info).ok()?;
+                pyth_helpers::load_price(&price_update, feed_id, HEALTH_PRICE_MAX_AGE_SECONDS).ok()
+            })
+            .ok_or(error!(ErrorCode::LpPoolAccountsRequired))
+    };
+    
+    let (lp_pool_key, _) = Pubkey::find_program_address(
+        &[b"lp_pool", &mode.mode_id.to_le_bytes()],
+        &mode_registry::ID,
+    );
+    let lp_pool = Account::<mode_registry::LpPoolConfig>::try_from(find(lp_pool_key)?)?;
+    let reserve_a = Account::<TokenAccount>::try_from(find(lp_pool.reserve_a)?)?;
+    let reserve_b = Account::<TokenAccount>::try_from(find(lp_pool.reserve_b)?)?;
+    let lp_mint = Account::<Mint>::try_from(find(mode.mint)?)?;
+    let price_a = feed_price(&lp_pool.feed_id_a)?;
+    let price_b = feed_price(&lp_pool.feed_id_b)?;
+    
+    let fair_value = pyth_helpers::lp_pool_fair_value_usd(
+        reserve_a.amount,
+        lp_pool.decimals_a,
+        &price_a,
+        reserve_b.amount,
+        lp_pool.decimals_b,
+        &price_b,
+        pyth_helpers::PEG_DECIMALS,
+    )?;
+    let price = pyth_helpers::lp_token_price(
+        fair_value,
+        lp_mint.supply,
+        lp_mint.decimals,
+        pyth_helpers::PEG_DECIMALS,
+        price_a.publish_time.min(price_b.publish_time),
+    )?;
+    let kept = pyth_helpers::apply_haircut(price.price.unsigned_abs(), mode.lp_haircut_bps)?;
+    
+    Ok(pyth_helpers::PriceData {
+        price: kept as i64,
+        ..price
+    })
+}
+
+/// Pay up to `amount` of a slash shortfall from the protocol insurance fund
+///
+/// The fund's owner opts in by approving the ["insurance_authority"] PDA as
+/// delegate on the fund's token account; the delegated amount is the
+/// backstop budget. Returns the amount paid, capped by the fund's balance
+/// and remaining delegation.
+fn draw_insurance_backstop<'info>(
+    fund: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    authority: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    to: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let (authority_key, authority_bump) =
+        Pubkey::find_program_address(&[b"insurance_authority"], &crate::ID);
+    require_keys_eq!(authority.key(), authority_key, ErrorCode::InvalidInsuranceAuthority);
+    require!(fund.mint == mint.key(), ErrorCode::MintMismatch);
+    
+    let delegated = match fund.delegate {
+        COption::Some(delegate) if delegate == authority_key => fund.delegated_amount,
+        _ => 0,
+    };
+    let paid = amount.min(fund.amount).min(delegated);
+    if paid == 0 {
+        return Ok(0);
+    }
+    
+    let seeds: &[&[u8]] = &[b"insurance_authority", &[authority_bump]];
+    let cpi_accounts = TransferChecked {
+        from: fund.to_account_info(),
+        mint: mint.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, &[seeds]);
+    token_interface::transfer_checked(cpi_ctx, paid, mint.decimals)?;
+    
+    Ok(paid)
+}
+
+/// Pair each slash_and_pay recipient with its amount
+///
+/// With no `amounts` the whole payout goes to `user_token_account`.
+/// Otherwise the amounts (at most MAX_SLASH_RECIPIENTS, summing to
+/// `payout_amount`) go to `user_token_account` and then to the first
+/// `amounts.len() - 1` remaining accounts, which must be writable token
+/// accounts of `mint`. Also returns the remaining accounts left for
+/// slash_legs.
+#[allow(clippy::type_complexity)]
+fn slash_recipients<'info>(
+    user_token_account: &InterfaceAccount<'info, token_interface::TokenAccount>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    amounts: &[u64],
+    payout_amount: u64,
+    mint: &Pubkey,
+) -> Result<(
+    Vec<(InterfaceAccount<'info, token_interface::TokenAccount>, u64)>,
+    &'info [AccountInfo<'info>],
+)> {
+    if amounts.is_empty() {
+        return Ok((vec![(user_token_account.clone(), payout_amount)], remaining_accounts));
+    }
+    require!(
+        amounts.len() <= MAX_SLASH_RECIPIENTS && remaining_accounts.len() >= amounts.len() - 1,
+        ErrorCode::InvalidSlashRecipients
+    );
+    
+    let mut total: u64 = 0;
+    for amount in amounts {
+        total = total.checked_add(*amount).ok_or(ErrorCode::Overflow)?;
+    }
+    require!(total == payout_amount, ErrorCode::InvalidSlashRecipients);
+    
+    let (extra, leg_accounts) = remaining_accounts.split_at(amounts.len() - 1);
+    let mut recipients = vec![(user_token_account.clone(), amounts[0])];
+    for (info, amount) in extra.iter().zip(&amounts[1..]) {
+        require!(info.is_writable, ErrorCode::InvalidSlashRecipients);
+        let account = InterfaceAccount::<token_interface::TokenAccount>::try_from(info)?;
+        require!(account.mint == *mint, ErrorCode::MintMismatch);
+        recipients.push((account, *amount));
+    }
+    
+    Ok((recipients, leg_accounts))
+}
+
+/// Burn the shares backing `assets` from a position, rounding up in favor of
+/// the vault, and resync the position's total.
+fn burn_shares_for_assets(
+    mode_vault: &mut ModeVault,
+    position: &mut ProviderPosition,
+    assets: u64,
+) -> Result<()> {
+    let burned = mode_vault.shares_for_assets_round_up(assets)?;
+    require!(burned <= position.shares, ErrorCode::InsufficientFreeCollateral);
+    
+    position.shares = position.shares.checked_sub(burned).ok_or(ErrorCode::Underflow)?;
+    // Provider's own shares are consumed first
+    position.sponsored_shares = position.sponsored_shares.min(position.shares);
+    mode_vault.total_shares = mode_vault.total_shares
+        .checked_sub(burned)
+        .ok_or(ErrorCode::Underflow)?;
+    mode_vault.total_assets = mode_vault.total_assets
+        .checked_sub(assets)
+        .ok_or(ErrorCode::Underflow)?;
+    
+    position.total = mode_vault.assets_for_shares(position.shares)?;
+    
+    Ok(())
+}
+
+// ============================================================================
+// Accounts
+// ============================================================================
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(mode_id: u32)]
+pub struct Deposit<'info> {
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ProviderPosition::INIT_SPACE,
+        seeds = [b"pos", provider.key().as_ref(), &mode_id.to_le_bytes()],
+        bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ModeVault::INIT_SPACE,
+        seeds = [b"mode_vault".as_ref(), &mode_id.to_le_bytes(), collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode supplying the deposit caps
+    #[account(
+        seeds = [b"mode", &mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == collateral_mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = mode_vault,
+        associated_token::token_program = collateral_token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    #[account(mut)]
+    pub provider_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// Collateral mint (SPL Token or Token-2022)
+    pub collateral_mint: InterfaceAccount<'info, token_interface::Mint>,
+    
+    /// Position NFT mint (created externally, authority = position PDA;
+    /// Token-2022 NonTransferable when the mode is soulbound)
+    #[account(mut)]
+    pub position_nft_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    
+    /// Provider's NFT token account
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = provider,
+        associated_token::token_program = token_program
+    )]
+    pub provider_nft_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// CHECK: Position NFT metadata PDA, created by the token metadata program (first deposit only)
+    #[account(mut)]
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Position NFT master edition PDA, created by the token metadata program (first deposit only)
+    #[account(mut)]
+    pub nft_master_edition: Option<UncheckedAccount<'info>>,
+    
+    pub token_metadata_program: Option<Program<'info, Metadata>>,
+    
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    
+    /// Token program owning the position NFT mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program owning the collateral mint
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SponsorDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode supplying the deposit caps
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    #[account(
+        mut,
+        associated_token::mint = position.mint,
+        associated_token::authority = mode_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = sponsor,
+        space = 8 + SponsorLedger::INIT_SPACE,
+        seeds = [b"sponsor", position.key().as_ref(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub sponsor_ledger: Account<'info, SponsorLedger>,
+    
+    #[account(
+        mut,
+        constraint = sponsor_token_account.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub sponsor_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    #[account(address = position.mint @ ErrorCode::MintMismatch)]
+    pub collateral_mint: InterfaceAccount<'info, token_interface::Mint>,
+    
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    #[account(
+        mut,
+        associated_token::mint = position.mint,
+        associated_token::authority = mode_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    #[account(mut)]
+    pub provider_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    #[account(address = position.mint @ ErrorCode::MintMismatch)]
+    pub collateral_mint: InterfaceAccount<'info, token_interface::Mint>,
+    
+    pub provider: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestFullExit<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    pub provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode supplying the unbonding period and USD health floor
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    /// Price of the mode's health feed; required while the floor applies
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+    
+    pub provider: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetPositionSummary<'info> {
+    #[account(
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Pyth price for the position's mint (feed_id argument)
+    pub price_update: Account<'info, PriceUpdateV2>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefreshPositionMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// CHECK: Position NFT metadata PDA (checked by seeds; updated by the token metadata program)
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), position.position_nft_mint.as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    pub token_metadata_program: Program<'info, Metadata>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositLeg<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    /// Registry mode (legs require a stablecoin mode)
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    /// Registry approval for the leg mint
+    #[account(
+        seeds = [b"leg_cfg", &position.mode_id.to_le_bytes(), leg_mint.key().as_ref()],
+        bump = leg_config.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub leg_config: Account<'info, mode_registry::CollateralLegConfig>,
+    
+    pub leg_mint: Account<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = leg_mint,
+        associated_token::authority = position
+    )]
+    pub leg_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = provider_token_account.mint == leg_mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    
+    pub price_update: Account<'info, PriceUpdateV2>,
+    
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefreshLegValue<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    /// Registry approval supplying the feed and haircut
+    #[account(
+        seeds = [b"leg_cfg", &position.mode_id.to_le_bytes(), leg_config.mint.as_ref()],
+        bump = leg_config.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub leg_config: Account<'info, mode_registry::CollateralLegConfig>,
+    
+    pub price_update: Account<'info, PriceUpdateV2>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefreshDelegatedValue<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    /// Registry mode (delegated backing requires a stablecoin mode)
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    #[account(
+        seeds = [b"native_pool"],
+        bump = stake_pool.bump,
+        seeds::program = naked_staking::ID
+    )]
+    pub stake_pool: Account<'info, naked_staking::NativeStakePool>,
+    
+    #[account(
+        seeds = [b"backing", stake_pool.key().as_ref(), position.seed_provider.as_ref()],
+        bump = backing.bump,
+        seeds::program = naked_staking::ID,
+        constraint = backing.position == position.key() @ ErrorCode::InvalidProviderBacking
+    )]
+    pub backing: Account<'info, naked_staking::ProviderBacking>,
+    
+    #[account(address = stake_pool.native_mint)]
+    pub native_mint: Account<'info, Mint>,
+    
+    #[account(address = stake_pool.pyth_feed)]
+    pub price_update: Account<'info, PriceUpdateV2>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawLeg<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    pub leg_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = leg_mint,
+        associated_token::authority = position
+    )]
+    pub leg_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = provider_token_account.mint == leg_mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    
+    pub price_update: Account<'info, PriceUpdateV2>,
+    
+    pub provider: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposePositionTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    pub provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptPositionTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    /// New owner's position NFT account
+    #[account(
+        constraint = new_provider_nft_account.mint == position.position_nft_mint @ ErrorCode::PositionNftNotHeld,
+        constraint = new_provider_nft_account.owner == new_provider.key() @ ErrorCode::PositionNftNotHeld,
+        constraint = new_provider_nft_account.amount == 1 @ ErrorCode::PositionNftNotHeld
+    )]
+    pub new_provider_nft_account: Account<'info, TokenAccount>,
+    
+    pub new_provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetOperators<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    pub provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct YieldAdapterCall<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    #[account(
+        mut,
+        associated_token::mint = position.mint,
+        associated_token::authority = mode_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    /// Registry mode naming the allowlisted yield adapter
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    /// CHECK: Yield adapter program; checked against the registry (deploy)
+    /// or the position's deployment (recall)
+    #[account(executable)]
+    pub adapter_program: UncheckedAccount<'info>,
+    
+    /// Position owner (deploy) or owner/operator (recall)
+    pub authority: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AdminPosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    /// Registry naming the admin
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, mode_registry::Registry>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SplitPosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Box<Account<'info, ProviderPosition>>,
+    
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + ProviderPosition::INIT_SPACE,
+        seeds = [b"pos", split_seed.key().as_ref(), &position.mode_id.to_le_bytes()],
+        bump
+    )]
+    pub new_position: Box<Account<'info, ProviderPosition>>,
+    
+    /// Fresh keypair seeding the new position PDA (signs so no one can
+    /// occupy another key's position address)
+    pub split_seed: Signer<'info>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode (soulbound NFT setting)
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+    
+    /// New position's NFT mint (created externally, authority = new position PDA;
+    /// Token-2022 NonTransferable when the mode is soulbound)
+    #[account(mut)]
+    pub new_position_nft_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    
+    /// Provider's token account for the new NFT
+    #[account(
+        init_if_needed,
+        payer = provider,
+        associated_token::mint = new_position_nft_mint,
+        associated_token::authority = provider,
+        associated_token::token_program = token_program
+    )]
+    pub provider_nft_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// CHECK: New NFT's metadata PDA, created by the token metadata program
+    #[account(mut)]
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: New NFT's master edition PDA, created by the token metadata program
+    #[account(mut)]
+    pub nft_master_edition: UncheckedAccount<'info>,
+    
+    pub token_metadata_program: Program<'info, Metadata>,
+    
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MergePositions<'info> {
+    /// Target position (kept)
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Box<Account<'info, ProviderPosition>>,
+    
+    /// Source position (closed to the provider)
+    #[account(
+        mut,
+        close = provider,
+        seeds = [b"pos", source_position.seed_provider.as_ref(), &source_position.mode_id.to_le_bytes()],
+        bump = source_position.bump,
+        has_one = provider @ ErrorCode::WrongProvider,
+        constraint = source_position.key() != position.key() @ ErrorCode::InvalidPosition,
+        constraint = source_position.mode_id == position.mode_id @ ErrorCode::InvalidPosition,
+        constraint = source_position.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub source_position: Box<Account<'info, ProviderPosition>>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    #[account(mut, address = source_position.position_nft_mint @ ErrorCode::PositionNftNotHeld)]
+    pub source_nft_mint: InterfaceAccount<'info, token_interface::Mint>,
+    
+    /// Provider's token account holding the source position's NFT
+    #[account(
+        mut,
+        constraint = provider_source_nft_account.mint == source_nft_mint.key() @ ErrorCode::PositionNftNotHeld,
+        constraint = provider_source_nft_account.owner == provider.key() @ ErrorCode::PositionNftNotHeld,
+        constraint = provider_source_nft_account.amount == 1 @ ErrorCode::PositionNftNotHeld
+    )]
+    pub provider_source_nft_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
     #[account(mut)]
     pub provider: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateCollateral<'info> {
+    /// Position giving up free collateral
+    #[account(
+        mut,
+        seeds = [b"pos", source_position.seed_provider.as_ref(), &source_position.mode_id.to_le_bytes()],
+        bump = source_position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub source_position: Box<Account<'info, ProviderPosition>>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &source_position.mode_id.to_le_bytes(), source_position.mint.as_ref()],
+        bump = source_mode_vault.bump
+    )]
+    pub source_mode_vault: Box<Account<'info, ModeVault>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = source_mode_vault,
+        associated_token::token_program = token_program
+    )]
+    pub source_vault_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// Position receiving the collateral, in another mode
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider,
+        constraint = position.mode_id != source_position.mode_id @ ErrorCode::InvalidPosition,
+        constraint = position.mint == source_position.mint @ ErrorCode::MintMismatch
+    )]
+    pub position: Box<Account<'info, ProviderPosition>>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Box<Account<'info, ModeVault>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = mode_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// Target registry mode supplying the deposit caps and extension allowlist
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+    
+    /// Source registry mode supplying the USD health floor
+    #[account(
+        seeds = [b"mode", &source_position.mode_id.to_le_bytes()],
+        bump = source_mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub source_mode: Box<Account<'info, mode_registry::Mode>>,
+    
+    /// Price of the source mode's health feed; required while the floor applies
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+    
+    #[account(address = source_position.mint @ ErrorCode::MintMismatch)]
+    pub collateral_mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
+    
+    pub provider: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateWithdrawalAllowlist<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    pub provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefreshPegStatus<'info> {
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &mode_vault.mode_id.to_le_bytes(), mode_vault.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode supplying the peg feed and bound
+    #[account(
+        seeds = [b"mode", &mode_vault.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == mode_vault.mint @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    pub price_update: Account<'info, PriceUpdateV2>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FlagUndercollateralized<'info> {
+    #[account(
+        mut,
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, ProviderPosition>,
+    
+    #[account(
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
+    /// Registry mode supplying the health feed and floor
+    #[account(
+        seeds = [b"mode", &position.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID,
+        constraint = mode.mint == position.mint @ ErrorCode::MintMismatch
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+    
+    /// Health feed update; LP-token modes are priced from remaining_accounts
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+#[instruction(session: Pubkey)]
+pub struct Reserve<'info> {
     #[account(
         mut,
-        seeds = [b"pos", provider.key().as_ref(), &position.mode_id.to_le_bytes()],
-        bump = position.bump,
-        has_one = provider @ ErrorCode::WrongProvider
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
+        bump = position.bump
     )]
     pub position: Account<'info, ProviderPosition>,
     
     #[account(
-        mut,
-        associated_token::mint = position.mint,
-        associated_token::authority = position
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub mode_vault: Account<'info, ModeVault>,
     
-    #[account(mut)]
-    pub provider_token_account: Account<'info, TokenAccount>,
+    /// The session's reservation record (created on its first reservation)
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ReservationRecord::INIT_SPACE,
+        seeds = [b"reservation", position.key().as_ref(), session.as_ref()],
+        bump
+    )]
+    pub reservation: Account<'info, ReservationRecord>,
     
+    /// Provider or one of its operators must sign to authorize reservation
+    /// (and pays the reservation record's rent)
+    #[account(mut, constraint = position.can_operate(&provider.key()) @ ErrorCode::WrongProvider)]
     pub provider: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    /// Registry allowlist of programs that may drive reserve/release/slash
+    #[account(
+        seeds = [b"vault_callers"],
+        bump = vault_callers.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub vault_callers: Account<'info, mode_registry::VaultCallers>,
+    
+    /// CHECK: Instructions sysvar (identifies the top-level caller)
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Reserve<'info> {
+#[instruction(session: Pubkey)]
+pub struct Release<'info> {
     #[account(
         mut,
-        seeds = [b"pos", position.provider.as_ref(), &position.mode_id.to_le_bytes()],
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Account<'info, ProviderPosition>,
     
-    /// Provider must sign to authorize reservation
-    pub provider: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"reservation", position.key().as_ref(), session.as_ref()],
+        bump = reservation.bump
+    )]
+    pub reservation: Account<'info, ReservationRecord>,
+    
+    /// CHECK: Payer of the reservation record; receives its rent when it closes
+    #[account(mut, address = reservation.payer @ ErrorCode::InvalidRentReceiver)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    
+    /// Session escrow authority (CPI signer); the session account itself
+    pub session_authority: Signer<'info>,
+    
+    /// Registry allowlist of programs that may drive reserve/release/slash
+    #[account(
+        seeds = [b"vault_callers"],
+        bump = vault_callers.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub vault_callers: Account<'info, mode_registry::VaultCallers>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Release<'info> {
+pub struct ReleaseBatch<'info> {
     #[account(
         mut,
-        seeds = [b"pos", position.provider.as_ref(), &position.mode_id.to_le_bytes()],
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Account<'info, ProviderPosition>,
     
-    /// Session escrow authority (CPI signer)
-    pub session_authority: Signer<'info>,
+    /// Registry allowlist of programs that may drive reserve/release/slash
+    #[account(
+        seeds = [b"vault_callers"],
+        bump = vault_callers.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub vault_callers: Account<'info, mode_registry::VaultCallers>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(session: Pubkey)]
 pub struct SlashAndPay<'info> {
     #[account(
         mut,
-        seeds = [b"pos", position.provider.as_ref(), &position.mode_id.to_le_bytes()],
+        seeds = [b"pos", position.seed_provider.as_ref(), &position.mode_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Account<'info, ProviderPosition>,
     
+    #[account(
+        mut,
+        seeds = [b"reservation", position.key().as_ref(), session.as_ref()],
+        bump = reservation.bump
+    )]
+    pub reservation: Account<'info, ReservationRecord>,
+    
+    /// CHECK: Payer of the reservation record; receives its rent when it closes
+    #[account(mut, address = reservation.payer @ ErrorCode::InvalidRentReceiver)]
+    pub rent_receiver: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"mode_vault".as_ref(), &position.mode_id.to_le_bytes(), position.mint.as_ref()],
+        bump = mode_vault.bump
+    )]
+    pub mode_vault: Account<'info, ModeVault>,
+    
     #[account(
         mut,
         associated_token::mint = position.mint,
-        associated_token::authority = position
+        associated_token::authority = mode_vault,
+        associated_token::token_program = token_program
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub vault_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
     
     #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub user_token_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    
+    #[account(address = position.mint @ ErrorCode::MintMismatch)]
+    pub collateral_mint: InterfaceAccount<'info, token_interface::Mint>,
     
-    /// Session escrow authority (CPI signer)
+    /// Session escrow authority (CPI signer); the session account itself
     pub session_authority: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    /// Registry allowlist of programs that may drive reserve/release/slash
+    #[account(
+        seeds = [b"vault_callers"],
+        bump = vault_callers.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub vault_callers: Account<'info, mode_registry::VaultCallers>,
+    
+    /// Protocol insurance fund token account backstopping the payout (optional)
+    #[account(mut)]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    
+    /// CHECK: ["insurance_authority"] PDA, the fund's delegate; checked in draw_insurance_backstop
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 // ============================================================================
@@ -391,6 +3715,199 @@ pub struct ProviderPosition {
     pub reserved: u64,
     pub position_nft_mint: Pubkey,
     pub bump: u8,
+    pub shares: u128,               // Shares of the (mode_id, mint) ModeVault
+    
+    // Withdrawal destinations (count 0 = unrestricted)
+    pub withdrawal_allowlist: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+    pub withdrawal_allowlist_count: u8,
+    pub pending_withdrawal_allowlist: [Pubkey; MAX_WITHDRAWAL_DESTINATIONS],
+    pub pending_withdrawal_allowlist_count: u8,
+    pub withdrawal_allowlist_effective_slot: u64,
+    
+    pub sponsored_shares: u128,     // Portion of shares funded by sponsors
+    
+    // Unbonding withdrawal (request_withdraw -> execute_withdraw)
+    pub pending_withdrawal: u64,    // Amount requested; excluded from new reservations
+    pub withdrawal_unlock_slot: u64,
+    
+    // Registry admin freeze (unfreeze is timelocked)
+    pub frozen: bool,
+    pub unfreeze_unlock_slot: u64,  // 0 = no unfreeze requested
+    
+    // Collateral deployed to the mode's yield adapter (not liquid until recalled)
+    pub deployed: u64,
+    pub yield_adapter: Pubkey,      // Adapter holding the deployment
+    
+    // Hot keys allowed to reserve and run sessions (never withdraw)
+    pub operators: [Pubkey; MAX_OPERATORS],
+    pub operator_count: u8,
+    
+    // Ownership transfer (PDA seeds keep the original provider key)
+    pub seed_provider: Pubkey,
+    pub pending_provider: Pubkey,   // Proposed new owner (default = none)
+    
+    // Secondary collateral, sorted by slash priority
+    pub legs: [CollateralLeg; MAX_COLLATERAL_LEGS],
+    pub leg_count: u8,
+    pub leg_value_decimals: u8,     // Mode mint decimals; leg values are in mode mint units
+    
+    // Delegated native stake (naked_staking ProviderBacking bound to this position)
+    pub delegated_value: u64,       // Slashable delegated stake, in mode mint units
+    pub delegated_valued_slot: u64,
+    
+    // Live ReservationRecord PDAs (one per session)
+    pub open_reservations: u32,
+    
+    // Free collateral below the mode's USD floor (flag_undercollateralized)
+    pub undercollateralized: bool,  // Blocks new reservations
+    
+    // Full provider exit (request_full_exit)
+    pub exiting: bool,              // No new reservations; withdrawable once reserved is 0
+    pub exit_requested_slot: u64,
+    
+    // Lifetime track record (reserve/release/slash)
+    pub sessions_served: u64,       // Sessions that reserved against this position
+    pub total_slashed: u64,         // Collateral paid out in claims (mode mint units)
+    pub total_released: u64,        // Collateral released back after sessions
+    pub last_slash_slot: u64,       // 0 = never slashed
+}
+
+impl ProviderPosition {
+    /// Whether `key` is the owner or one of its operators
+    pub fn can_operate(&self, key: &Pubkey) -> bool {
+        *key == self.provider || self.operators[..self.operator_count as usize].contains(key)
+    }
+    
+    /// Index of the leg holding `mint`
+    pub fn leg_index(&self, mint: &Pubkey) -> Option<usize> {
+        self.legs[..self.leg_count as usize]
+            .iter()
+            .position(|leg| leg.mint == *mint)
+    }
+    
+    /// Value of legs and delegated stake priced within
+    /// LEG_VALUE_MAX_AGE_SLOTS of `slot`
+    pub fn fresh_leg_value(&self, slot: u64) -> Result<u64> {
+        let mut value: u64 = 0;
+        for leg in &self.legs[..self.leg_count as usize] {
+            if slot <= leg.valued_slot.saturating_add(LEG_VALUE_MAX_AGE_SLOTS) {
+                value = value.checked_add(leg.value).ok_or(ErrorCode::Overflow)?;
+            }
+        }
+        if slot <= self.delegated_valued_slot.saturating_add(LEG_VALUE_MAX_AGE_SLOTS) {
+            value = value.checked_add(self.delegated_value).ok_or(ErrorCode::Overflow)?;
+        }
+        Ok(value)
+    }
+}
+
+/// Collateral reserved from one position for one session
+#[account]
+#[derive(InitSpace)]
+pub struct ReservationRecord {
+    pub position: Pubkey,
+    pub session: Pubkey,
+    pub amount: u64,        // Still reserved; the record closes at 0
+    pub payer: Pubkey,      // Paid the rent; gets it back on close
+    pub bump: u8,
+}
+
+/// One session's release in release_batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ReleaseEntry {
+    pub session: Pubkey,
+    pub amount: u64,
+}
+
+/// Capacity quote returned by get_position_summary (mode mint units unless noted)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PositionSummary {
+    pub total: u64,                 // Primary collateral at the current share price
+    pub leg_value: u64,             // Freshly valued secondary collateral
+    pub reserved: u64,
+    pub pending_withdrawal: u64,
+    pub deployed: u64,
+    pub free: u64,                  // Available to reserve now
+    pub usd_value: u64,             // total + leg_value in USD (PEG_DECIMALS)
+    pub open_reservations: u32,
+}
+
+/// Secondary collateral held in the position's ATA for a non-mode mint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct CollateralLeg {
+    pub mint: Pubkey,
+    pub feed_id: [u8; 32],          // Pyth USD feed (from the registry)
+    pub haircut_bps: u16,
+    pub priority: u8,               // Lower is slashed first
+    pub decimals: u8,
+    pub amount: u64,                // Tokens held in the leg vault
+    pub value: u64,                 // Haircut USD value in mode mint units
+    pub valued_slot: u64,
+}
+
+/// Sponsor's contributions to a provider position (attribution only)
+#[account]
+#[derive(InitSpace)]
+pub struct SponsorLedger {
+    pub position: Pubkey,
+    pub sponsor: Pubkey,
+    pub deposited: u64,             // Cumulative collateral contributed
+    pub shares: u128,               // Cumulative shares minted to the position
+    pub bump: u8,
+}
+
+/// Pooled collateral for a (mode_id, mint) pair
+///
+/// Exchange rate is total_assets / total_shares. Position totals are derived
+/// from it, so vault-wide adjustments only touch this account.
+#[account]
+#[derive(InitSpace)]
+pub struct ModeVault {
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub total_assets: u64,
+    pub total_shares: u128,
+    pub bump: u8,
+    pub peg_halted: bool,           // Stablecoin outside its peg bound
+}
+
+impl ModeVault {
+    /// Shares minted for a deposit of `assets` (rounds down)
+    pub fn shares_for_assets(&self, assets: u64) -> Result<u128> {
+        if self.total_shares == 0 || self.total_assets == 0 {
+            return Ok(assets as u128);
+        }
+        (assets as u128)
+            .checked_mul(self.total_shares)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(self.total_assets as u128)
+            .ok_or(error!(ErrorCode::Overflow))
+    }
+    
+    /// Shares burned to remove `assets` (rounds up)
+    pub fn shares_for_assets_round_up(&self, assets: u64) -> Result<u128> {
+        if self.total_shares == 0 || self.total_assets == 0 {
+            return Ok(assets as u128);
+        }
+        let numerator = (assets as u128)
+            .checked_mul(self.total_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        let denominator = self.total_assets as u128;
+        Ok(numerator.div_ceil(denominator))
+    }
+    
+    /// Asset value of `shares` (rounds down)
+    pub fn assets_for_shares(&self, shares: u128) -> Result<u64> {
+        if self.total_shares == 0 {
+            return Ok(0);
+        }
+        let assets = shares
+            .checked_mul(self.total_assets as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(self.total_shares)
+            .ok_or(ErrorCode::Overflow)?;
+        u64::try_from(assets).map_err(|_| error!(ErrorCode::Overflow))
+    }
 }
 
 // ============================================================================
@@ -405,6 +3922,16 @@ pub struct CollateralDeposited {
     pub new_total: u64,
 }
 
+#[event]
+pub struct CollateralSponsored {
+    pub provider: Pubkey,
+    pub sponsor: Pubkey,
+    pub mode_id: u32,
+    pub amount: u64,
+    pub shares: u128,
+    pub new_total: u64,
+}
+
 #[event]
 pub struct CollateralWithdrawn {
     pub provider: Pubkey,
@@ -413,6 +3940,63 @@ pub struct CollateralWithdrawn {
     pub new_total: u64,
 }
 
+#[event]
+pub struct ProviderExitRequested {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub position: Pubkey,
+    pub reserved: u64,              // Still locked by live sessions
+    pub open_reservations: u32,
+}
+
+#[event]
+pub struct ProviderExitReady {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub position: Pubkey,
+}
+
+#[event]
+pub struct WithdrawalRequested {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub amount: u64,
+    pub unlock_slot: u64,
+}
+
+#[event]
+pub struct WithdrawalAllowlistProposed {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub destinations: Vec<Pubkey>,
+    pub effective_slot: u64,
+}
+
+#[event]
+pub struct WithdrawalAllowlistApplied {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub count: u8,
+}
+
+#[event]
+pub struct PegStatusRefreshed {
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub deviation_bps: u64,
+    pub halted: bool,
+}
+
+#[event]
+pub struct PositionHealthFlagged {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub free: u64,
+    pub free_usd: u64,
+    pub min_free_usd: u64,
+    pub undercollateralized: bool,
+}
+
 #[event]
 pub struct CollateralReserved {
     pub provider: Pubkey,
@@ -437,6 +4021,150 @@ pub struct CollateralSlashed {
     pub user: Pubkey,
     pub new_total: u64,
     pub new_reserved: u64,
+    pub leg_payout: u64,            // Part of payout_amount paid in leg tokens (mode mint units)
+    pub uncovered: u64,             // Part of payout_amount no collateral was left for
+}
+
+#[event]
+pub struct SlashShortfall {
+    pub provider: Pubkey,
+    pub session: Pubkey,
+    pub shortfall: u64,             // Payout neither the reservation nor collateral covered
+    pub insurance_paid: u64,        // Part of shortfall topped up by the insurance fund
+}
+
+#[event]
+pub struct PositionFrozen {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+}
+
+#[event]
+pub struct PositionUnfreezeRequested {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub unlock_slot: u64,
+}
+
+#[event]
+pub struct PositionUnfrozen {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+}
+
+#[event]
+pub struct CollateralDeployed {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub amount: u64,
+    pub deployed: u64,
+}
+
+#[event]
+pub struct CollateralRecalled {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub principal: u64,
+    pub received: u64,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct OperatorsSet {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub operators: Vec<Pubkey>,
+}
+
+#[event]
+pub struct PositionTransferProposed {
+    pub position: Pubkey,
+    pub provider: Pubkey,
+    pub new_provider: Pubkey,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub position: Pubkey,
+    pub old_provider: Pubkey,
+    pub new_provider: Pubkey,
+}
+
+#[event]
+pub struct PositionSplit {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub source: Pubkey,
+    pub new_position: Pubkey,
+    pub amount: u64,
+    pub shares: u128,
+    pub source_total: u64,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct CollateralMigrated {
+    pub provider: Pubkey,
+    pub from_mode_id: u32,
+    pub to_mode_id: u32,
+    pub amount: u64,                // Taken from the source position
+    pub received: u64,              // Credited to the target, net of transfer fees
+    pub source_total: u64,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct PositionsMerged {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub position: Pubkey,
+    pub source: Pubkey,
+    pub shares: u128,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct PositionMetadataRefreshed {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub total: u64,
+    pub reserved: u64,
+}
+
+#[event]
+pub struct CollateralLegDeposited {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub leg_amount: u64,
+    pub leg_value: u64,
+}
+
+#[event]
+pub struct CollateralLegWithdrawn {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub leg_amount: u64,
+    pub leg_value: u64,
+}
+
+#[event]
+pub struct LegValueRefreshed {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub leg_value: u64,
+}
+
+#[event]
+pub struct DelegatedValueRefreshed {
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub backing: Pubkey,
+    pub delegated_value: u64,
 }
 
 // ============================================================================
@@ -461,4 +4189,94 @@ pub enum ErrorCode {
     PayoutExceedsReserved,
     #[msg("Wrong provider")]
     WrongProvider,
+    #[msg("Collateral mint does not match position")]
+    MintMismatch,
+    #[msg("Deposit too small to mint shares")]
+    ZeroShares,
+    #[msg("Withdrawal destination not allowlisted")]
+    DestinationNotAllowlisted,
+    #[msg("Invalid withdrawal allowlist")]
+    InvalidWithdrawalAllowlist,
+    #[msg("No pending withdrawal allowlist")]
+    NoPendingWithdrawalAllowlist,
+    #[msg("Withdrawal allowlist timelock not passed")]
+    WithdrawalAllowlistTimelocked,
+    #[msg("Collateral stablecoin is depegged")]
+    CollateralDepegged,
+    #[msg("Mode is not a stablecoin mode")]
+    NotStablecoinMode,
+    #[msg("No pending withdrawal request")]
+    NoPendingWithdrawal,
+    #[msg("Withdrawal is still unbonding")]
+    WithdrawalUnbonding,
+    #[msg("Deposit exceeds the mode's per-provider cap")]
+    ProviderCapExceeded,
+    #[msg("Deposit exceeds the mode's global cap")]
+    GlobalCapExceeded,
+    #[msg("Position already has the maximum number of collateral legs")]
+    TooManyCollateralLegs,
+    #[msg("Position has no collateral leg for this mint")]
+    UnknownCollateralLeg,
+    #[msg("Collateral leg accounts missing for slash")]
+    LegAccountsRequired,
+    #[msg("Position NFT metadata accounts required on first deposit")]
+    PositionMetadataAccountsRequired,
+    #[msg("No position transfer pending for this key")]
+    PositionTransferNotPending,
+    #[msg("New owner does not hold the position NFT")]
+    PositionNftNotHeld,
+    #[msg("Invalid operator list")]
+    InvalidOperators,
+    #[msg("Yield adapter not allowlisted for this mode")]
+    YieldAdapterNotAllowed,
+    #[msg("No collateral deployed")]
+    NothingDeployed,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Position is frozen")]
+    PositionFrozen,
+    #[msg("Position is not frozen")]
+    PositionNotFrozen,
+    #[msg("Unfreeze timelock not passed")]
+    UnfreezeTimelocked,
+    #[msg("Collateral mint has a Token-2022 extension the mode does not allow")]
+    UnsupportedMintExtension,
+    #[msg("Invalid position")]
+    InvalidPosition,
+    #[msg("Position has reserved, unbonding, deployed or leg collateral")]
+    PositionNotIdle,
+    #[msg("Withdrawal allowlists differ")]
+    AllowlistMismatch,
+    #[msg("Rent receiver is not the reservation payer")]
+    InvalidRentReceiver,
+    #[msg("Caller program is not allowlisted")]
+    UnauthorizedCaller,
+    #[msg("Mode has no collateral health floor")]
+    HealthCheckDisabled,
+    #[msg("Price update account required")]
+    PriceUpdateRequired,
+    #[msg("Position is flagged undercollateralized")]
+    PositionUndercollateralized,
+    #[msg("Insurance authority account required")]
+    InsuranceAuthorityRequired,
+    #[msg("Invalid insurance authority")]
+    InvalidInsuranceAuthority,
+    #[msg("Position is exiting")]
+    PositionExiting,
+    #[msg("Invalid release batch")]
+    InvalidReleaseBatch,
+    #[msg("Position NFT mint must be an SPL Token mint")]
+    InvalidPositionNftMint,
+    #[msg("Mode requires a Token-2022 non-transferable position NFT")]
+    PositionNftNotSoulbound,
+    #[msg("Invalid slash recipients")]
+    InvalidSlashRecipients,
+    #[msg("Session authority does not match the session")]
+    SessionAuthorityMismatch,
+    #[msg("LP pool accounts required to value LP collateral")]
+    LpPoolAccountsRequired,
+    #[msg("Delegated backing accounts required to slash delegated stake")]
+    DelegationAccountsRequired,
+    #[msg("Provider backing is not bound to this position")]
+    InvalidProviderBacking,
 }
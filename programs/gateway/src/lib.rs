@@ -1,9 +1,16 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Mint};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self, load_instruction_at_checked,
+};
+use anchor_spl::token::{self, spl_token, Mint, SyncNative, Token, TokenAccount};
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 declare_id!("GateWay1111111111111111111111111111111111111");
 
+pub const ED25519_PROGRAM_ID: Pubkey = anchor_lang::solana_program::ed25519_program::ID;
+
 /// Gateway Program
 /// 
 /// Bridges external DEX swaps to session escrow and collateral vault flows.
@@ -14,6 +21,7 @@ declare_id!("GateWay1111111111111111111111111111111111111");
 /// - Only allowlisted pools can be used
 /// - Pyth price freshness and confidence enforced
 /// - Max trade size limits
+/// - Keeper flows spend only within user-signed approvals
 #[program]
 pub mod gateway {
     use super::*;
@@ -34,6 +42,7 @@ pub mod gateway {
         
         config.authority = ctx.accounts.authority.key();
         config.max_slippage_bps = max_slippage_bps;
+        config.min_slippage_bps = 0;
         config.max_trade_size = max_trade_size;
         config.pyth_max_age_seconds = pyth_max_age_seconds;
         config.pyth_max_conf_ratio_bps = pyth_max_conf_ratio_bps;
@@ -41,6 +50,8 @@ pub mod gateway {
         config.swap_program_count = 0;
         config.pool_count = 0;
         config.mode_feed_count = 0;
+        config.max_price_divergence_bps = 0;
+        config.divergence_window_slots = 0;
         config.bump = ctx.bumps.config;
         
         let authority = config.authority;
@@ -145,11 +156,17 @@ pub mod gateway {
     }
 
     /// Add Pyth feed for a mode's mint
+    ///
+    /// A non-zero peg_max_dev_bps marks the mint as a USD stablecoin; swaps
+    /// touching it are halted while its price is outside the peg bound.
     pub fn add_mode_feed(
         ctx: Context<ModifyConfig>,
         mint: Pubkey,
         feed_id: [u8; 32],
+        peg_max_dev_bps: u16,
     ) -> Result<()> {
+        require!(peg_max_dev_bps <= 10_000, GatewayError::InvalidPegBound);
+        
         let config = &mut ctx.accounts.config;
         
         let count = config.mode_feed_count as usize;
@@ -167,10 +184,80 @@ pub mod gateway {
             );
         }
         
-        config.mode_feeds[count] = ModeFeed { mint, feed_id };
+        config.mode_feeds[count] = ModeFeed { mint, feed_id, peg_max_dev_bps };
         config.mode_feed_count += 1;
         
-        emit!(ModeFeedAdded { mint, feed_id });
+        emit!(ModeFeedAdded { mint, feed_id, peg_max_dev_bps });
+        
+        Ok(())
+    }
+
+    /// Configure the consecutive-slot price divergence breaker
+    ///
+    /// A swap is rejected if its pair price moved more than max_divergence_bps
+    /// from the last accepted execution within window_slots. Zero disables.
+    pub fn set_price_divergence_breaker(
+        ctx: Context<ModifyConfig>,
+        max_divergence_bps: u16,
+        window_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_divergence_bps <= 10_000,
+            GatewayError::InvalidDivergenceBps
+        );
+        
+        let config = &mut ctx.accounts.config;
+        config.max_price_divergence_bps = max_divergence_bps;
+        config.divergence_window_slots = window_slots;
+        
+        emit!(PriceDivergenceBreakerUpdated {
+            max_divergence_bps,
+            window_slots,
+        });
+        
+        Ok(())
+    }
+
+    /// Set the floor and cap for confidence-derived slippage
+    ///
+    /// Swaps tolerate the combined confidence ratio of both legs, clamped
+    /// to [min_slippage_bps, max_slippage_bps].
+    pub fn set_slippage_bounds(
+        ctx: Context<ModifyConfig>,
+        min_slippage_bps: u16,
+        max_slippage_bps: u16,
+    ) -> Result<()> {
+        require!(
+            min_slippage_bps <= max_slippage_bps && max_slippage_bps <= 10_000,
+            GatewayError::InvalidSlippageBounds
+        );
+        
+        let config = &mut ctx.accounts.config;
+        config.min_slippage_bps = min_slippage_bps;
+        config.max_slippage_bps = max_slippage_bps;
+        
+        emit!(SlippageBoundsUpdated {
+            min_slippage_bps,
+            max_slippage_bps,
+        });
+        
+        Ok(())
+    }
+
+    /// Initialize last-execution price tracking for a swap pair
+    pub fn init_pair_price_state(ctx: Context<InitPairPriceState>) -> Result<()> {
+        let state = &mut ctx.accounts.pair_price_state;
+        
+        state.input_mint = ctx.accounts.input_mint.key();
+        state.output_mint = ctx.accounts.output_mint.key();
+        state.last_price_ratio = 0;
+        state.last_slot = 0;
+        state.bump = ctx.bumps.pair_price_state;
+        
+        emit!(PairPriceStateInitialized {
+            input_mint: state.input_mint,
+            output_mint: state.output_mint,
+        });
         
         Ok(())
     }
@@ -179,7 +266,7 @@ pub mod gateway {
     pub fn swap_and_fund_session(
         ctx: Context<SwapAndFundSession>,
         amount_in: u64,
-        _min_amount_out: u64,
+        min_amount_out: u64,
     ) -> Result<()> {
         let config = &ctx.accounts.config;
         
@@ -200,40 +287,245 @@ pub mod gateway {
         }
         require!(swap_allowed, GatewayError::SwapProgramNotAllowlisted);
         
-        // Load and validate prices
-        let _price_in = pyth_helpers::validate_price(
+        // Load and validate prices against each leg's registered feed
+        let feed_in = mode_feed_id(config, &ctx.accounts.input_mint.key())?;
+        let feed_out = mode_feed_id(config, &ctx.accounts.output_mint.key())?;
+        
+        let price_in = pyth_helpers::validate_price(
             &ctx.accounts.input_price_update,
-            &config.native_feed_id,
+            &feed_in,
             config.pyth_max_age_seconds,
             config.pyth_max_conf_ratio_bps,
         )?;
         
-        let _price_out = pyth_helpers::validate_price(
+        let price_out = pyth_helpers::validate_price(
             &ctx.accounts.output_price_update,
-            &config.native_feed_id,
+            &feed_out,
             config.pyth_max_age_seconds,
             config.pyth_max_conf_ratio_bps,
         )?;
         
-        // TODO: Calculate conservative_min_out
+        // Dampen single-slot oracle spikes
+        check_price_divergence(
+            &mut ctx.accounts.pair_price_state,
+            &ctx.accounts.config,
+            &price_in,
+            &price_out,
+        )?;
+        
+        // Halt if either leg is a depegged stablecoin
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.input_mint.key(), &price_in)?;
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.output_mint.key(), &price_out)?;
+        
+        // Slippage bound follows live oracle confidence, capped by config
+        let config = &ctx.accounts.config;
+        let slippage_bps = pyth_helpers::dynamic_slippage_bps(
+            &price_in,
+            &price_out,
+            config.min_slippage_bps,
+            config.max_slippage_bps,
+        );
+        let min_amount_out = pyth_helpers::conservative_min_out(
+            amount_in,
+            &price_in,
+            &price_out,
+            slippage_bps,
+        )?
+        .max(min_amount_out);
+        
         // TODO: Execute swap CPI
         // TODO: Fund session CPI
         
         emit!(SwapAndFundStubbed {
             user: ctx.accounts.user.key(),
             amount_in,
+            min_amount_out,
+            slippage_bps,
+            session: ctx.accounts.session.key(),
+        });
+        
+        Ok(())
+    }
+
+    /// Keeper swaps tokens and funds a session under a user-signed approval (STUB)
+    ///
+    /// The user signs (mint, destination, amount_cap, expiry_slot, nonce)
+    /// off-chain, where destination is the session escrow token account the
+    /// swap output funds; the signature is checked via the Ed25519
+    /// instruction preceding this one.
+    /// The user's token account delegates to the gateway config PDA once, and
+    /// each approval bounds how much keepers may move through it, so no
+    /// open-ended delegate is handed to any keeper.
+    pub fn keeper_swap_and_fund_session(
+        ctx: Context<KeeperSwapAndFundSession>,
+        amount_in: u64,
+        min_amount_out: u64,
+        amount_cap: u64,
+        expiry_slot: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let config = &ctx.accounts.config;
+        
+        require!(
+            amount_in <= config.max_trade_size,
+            GatewayError::TradeTooLarge
+        );
+        
+        // Validate swap program is allowlisted
+        let swap_program = ctx.accounts.swap_program.key();
+        let swap_count = config.swap_program_count as usize;
+        let mut swap_allowed = false;
+        for i in 0..swap_count {
+            if config.allowlisted_swap_programs[i] == swap_program {
+                swap_allowed = true;
+                break;
+            }
+        }
+        require!(swap_allowed, GatewayError::SwapProgramNotAllowlisted);
+        
+        // Verify the user's signed approval
+        require!(clock.slot <= expiry_slot, GatewayError::ApprovalExpired);
+        let user_key = ctx.accounts.user.key();
+        let mint_key = ctx.accounts.input_mint.key();
+        let destination = ctx.accounts.escrow_token_account.key();
+        verify_spending_approval_signature(
+            &ctx.accounts.instructions_sysvar,
+            &user_key,
+            &mint_key,
+            &destination,
+            amount_cap,
+            expiry_slot,
+            nonce,
+        )?;
+        
+        // Consume from the approval
+        let approval_key = ctx.accounts.spending_approval.key();
+        let approval = &mut ctx.accounts.spending_approval;
+        if approval.user == Pubkey::default() {
+            approval.user = user_key;
+            approval.mint = mint_key;
+            approval.destination = destination;
+            approval.amount_cap = amount_cap;
+            approval.expiry_slot = expiry_slot;
+            approval.nonce = nonce;
+            approval.spent = 0;
+            approval.revoked = false;
+            approval.bump = ctx.bumps.spending_approval;
+        }
+        require!(!approval.revoked, GatewayError::ApprovalRevoked);
+        require!(
+            approval.mint == mint_key
+                && approval.destination == destination
+                && approval.amount_cap == amount_cap
+                && approval.expiry_slot == expiry_slot,
+            GatewayError::ApprovalMismatch
+        );
+        
+        let new_spent = approval.spent
+            .checked_add(amount_in)
+            .ok_or(GatewayError::Overflow)?;
+        require!(new_spent <= approval.amount_cap, GatewayError::ApprovalCapExceeded);
+        approval.spent = new_spent;
+        let remaining = approval.amount_cap - new_spent;
+        
+        // Load and validate prices against each leg's registered feed
+        let feed_in = mode_feed_id(config, &ctx.accounts.input_mint.key())?;
+        let feed_out = mode_feed_id(config, &ctx.accounts.output_mint.key())?;
+        
+        let price_in = pyth_helpers::validate_price(
+            &ctx.accounts.input_price_update,
+            &feed_in,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        let price_out = pyth_helpers::validate_price(
+            &ctx.accounts.output_price_update,
+            &feed_out,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        // Dampen single-slot oracle spikes
+        check_price_divergence(
+            &mut ctx.accounts.pair_price_state,
+            &ctx.accounts.config,
+            &price_in,
+            &price_out,
+        )?;
+        
+        // Halt if either leg is a depegged stablecoin
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.input_mint.key(), &price_in)?;
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.output_mint.key(), &price_out)?;
+        
+        // Slippage bound follows live oracle confidence, capped by config
+        let config = &ctx.accounts.config;
+        let slippage_bps = pyth_helpers::dynamic_slippage_bps(
+            &price_in,
+            &price_out,
+            config.min_slippage_bps,
+            config.max_slippage_bps,
+        );
+        let min_amount_out = pyth_helpers::conservative_min_out(
+            amount_in,
+            &price_in,
+            &price_out,
+            slippage_bps,
+        )?
+        .max(min_amount_out);
+        
+        // TODO: Execute swap CPI (config PDA signs as delegate of user_input_token)
+        // TODO: Fund session CPI
+        
+        emit!(SpendingApprovalUsed {
+            approval: approval_key,
+            user: user_key,
+            keeper: ctx.accounts.keeper.key(),
+            amount: amount_in,
+            remaining,
+        });
+        
+        emit!(SwapAndFundStubbed {
+            user: user_key,
+            amount_in,
+            min_amount_out,
+            slippage_bps,
             session: ctx.accounts.session.key(),
         });
         
         Ok(())
     }
 
+    /// Revoke a spending approval nonce before or after it has been used
+    pub fn revoke_spending_approval(
+        ctx: Context<RevokeSpendingApproval>,
+        nonce: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let approval = &mut ctx.accounts.spending_approval;
+        
+        if approval.user == Pubkey::default() {
+            approval.user = user_key;
+            approval.nonce = nonce;
+            approval.bump = ctx.bumps.spending_approval;
+        }
+        approval.revoked = true;
+        
+        emit!(SpendingApprovalRevoked {
+            user: user_key,
+            nonce,
+        });
+        
+        Ok(())
+    }
+
     /// Swap tokens and deposit as collateral (STUB)
     pub fn swap_and_deposit_collateral(
         ctx: Context<SwapAndDepositCollateral>,
         amount_in: u64,
         mode_id: u32,
-        _min_amount_out: u64,
+        min_amount_out: u64,
     ) -> Result<()> {
         let config = &ctx.accounts.config;
         
@@ -254,76 +546,671 @@ pub mod gateway {
         }
         require!(swap_allowed, GatewayError::SwapProgramNotAllowlisted);
         
-        // Load prices
-        let _price_in = pyth_helpers::validate_price(
+        // Load prices against each leg's registered feed
+        let feed_in = mode_feed_id(config, &ctx.accounts.input_mint.key())?;
+        let feed_out = mode_feed_id(config, &ctx.accounts.collateral_mint.key())?;
+        
+        let price_in = pyth_helpers::validate_price(
             &ctx.accounts.input_price_update,
-            &config.native_feed_id,
+            &feed_in,
             config.pyth_max_age_seconds,
             config.pyth_max_conf_ratio_bps,
         )?;
         
-        let _price_out = pyth_helpers::validate_price(
+        let price_out = pyth_helpers::validate_price(
             &ctx.accounts.output_price_update,
-            &config.native_feed_id,
+            &feed_out,
             config.pyth_max_age_seconds,
             config.pyth_max_conf_ratio_bps,
         )?;
         
+        // Dampen single-slot oracle spikes
+        check_price_divergence(
+            &mut ctx.accounts.pair_price_state,
+            &ctx.accounts.config,
+            &price_in,
+            &price_out,
+        )?;
+        
+        // Halt if either leg is a depegged stablecoin
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.input_mint.key(), &price_in)?;
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.collateral_mint.key(), &price_out)?;
+        
+        // Slippage bound follows live oracle confidence, capped by config
+        let config = &ctx.accounts.config;
+        let slippage_bps = pyth_helpers::dynamic_slippage_bps(
+            &price_in,
+            &price_out,
+            config.min_slippage_bps,
+            config.max_slippage_bps,
+        );
+        let min_amount_out = pyth_helpers::conservative_min_out(
+            amount_in,
+            &price_in,
+            &price_out,
+            slippage_bps,
+        )?
+        .max(min_amount_out);
+        
+        // TODO: Execute swap CPI
+        // TODO: collateral_vault::deposit CPI
+        
         emit!(SwapAndDepositStubbed {
             provider: ctx.accounts.provider.key(),
             amount_in,
+            min_amount_out,
+            slippage_bps,
             mode_id,
         });
         
         Ok(())
     }
-}
 
-// ============================================================================
-// Constants
-// ============================================================================
+    /// Wrap SOL, swap to the native mint and stake in naked_staking
+    ///
+    /// Wraps `lamports` into the user's wSOL account, then routes it through
+    /// an allowlisted swap program and pool: `swap_data` is the swap
+    /// instruction data and the remaining accounts are its accounts, which
+    /// must include the pool. Whatever native token the swap delivers to
+    /// `user_native_token` is staked. The wSOL price comes from the mode feed
+    /// registered for the wrapped SOL mint.
+    pub fn stake_with_sol<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StakeWithSol<'info>>,
+        lamports: u64,
+        min_amount_out: u64,
+        swap_data: Vec<u8>,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        
+        require!(
+            lamports <= config.max_trade_size,
+            GatewayError::TradeTooLarge
+        );
+        
+        // Validate swap program and pool are allowlisted
+        let swap_program = ctx.accounts.swap_program.key();
+        let swap_count = config.swap_program_count as usize;
+        require!(
+            config.allowlisted_swap_programs[..swap_count].contains(&swap_program),
+            GatewayError::SwapProgramNotAllowlisted
+        );
+        let pool = ctx.accounts.pool.key();
+        let pool_count = config.pool_count as usize;
+        require!(
+            config.allowlisted_pools[..pool_count].contains(&pool),
+            GatewayError::PoolNotAllowlisted
+        );
+        
+        // Load and validate prices (wSOL in, native out)
+        let sol_feed_id = config.mode_feeds[..config.mode_feed_count as usize]
+            .iter()
+            .find(|feed| feed.mint == spl_token::native_mint::ID)
+            .map(|feed| feed.feed_id)
+            .ok_or(GatewayError::PriceFeedNotFound)?;
+        
+        let price_in = pyth_helpers::validate_price(
+            &ctx.accounts.input_price_update,
+            &sol_feed_id,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        let price_out = pyth_helpers::validate_price(
+            &ctx.accounts.output_price_update,
+            &config.native_feed_id,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        // Dampen single-slot oracle spikes
+        check_price_divergence(
+            &mut ctx.accounts.pair_price_state,
+            &ctx.accounts.config,
+            &price_in,
+            &price_out,
+        )?;
+        
+        // Wrap SOL
+        let transfer_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.user.to_account_info(),
+            to: ctx.accounts.user_wsol_token.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            transfer_accounts,
+        );
+        anchor_lang::system_program::transfer(transfer_ctx, lamports)?;
+        
+        let sync_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.user_wsol_token.to_account_info(),
+            },
+        );
+        token::sync_native(sync_ctx)?;
+        
+        // Slippage bound follows live oracle confidence, capped by config
+        let config = &ctx.accounts.config;
+        let slippage_bps = pyth_helpers::dynamic_slippage_bps(
+            &price_in,
+            &price_out,
+            config.min_slippage_bps,
+            config.max_slippage_bps,
+        );
+        let min_amount_out = pyth_helpers::conservative_min_out(
+            lamports,
+            &price_in,
+            &price_out,
+            slippage_bps,
+        )?
+        .max(min_amount_out);
+        
+        // Swap wSOL -> native mint into user_native_token
+        let native_before = ctx.accounts.user_native_token.amount;
+        invoke_swap(
+            &ctx.accounts.swap_program,
+            &pool,
+            ctx.remaining_accounts,
+            swap_data,
+        )?;
+        ctx.accounts.user_native_token.reload()?;
+        let amount_out = ctx.accounts.user_native_token.amount
+            .checked_sub(native_before)
+            .ok_or(GatewayError::Overflow)?;
+        require!(amount_out >= min_amount_out, GatewayError::SlippageExceeded);
+        
+        // Stake the swap output (output_price_update doubles as the pool feed)
+        let cpi_accounts = naked_staking::cpi::accounts::StakeNative {
+            pool: ctx.accounts.staking_pool.to_account_info(),
+            reward_epochs: ctx.accounts.staking_reward_epochs.as_ref().map(|info| info.to_account_info()),
+            position: ctx.accounts.staking_position.to_account_info(),
+            vault: ctx.accounts.staking_vault.to_account_info(),
+            user_token_account: ctx.accounts.user_native_token.to_account_info(),
+            pyth_feed: ctx.accounts.output_price_update.to_account_info(),
+            user: ctx.accounts.user.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.naked_staking_program.to_account_info(),
+            cpi_accounts,
+        );
+        naked_staking::cpi::stake_native(cpi_ctx, amount_out)?;
+        
+        emit!(StakedWithSol {
+            user: ctx.accounts.user.key(),
+            lamports,
+            amount_out,
+            min_amount_out,
+            slippage_bps,
+            staking_pool: ctx.accounts.staking_pool.key(),
+        });
+        
+        Ok(())
+    }
 
-pub const MAX_SWAP_PROGRAMS: usize = 8;
-pub const MAX_POOLS: usize = 16;
-pub const MAX_MODE_FEEDS: usize = 16;
+    /// Claim a session payout and swap it into another token (swap STUB)
+    ///
+    /// CPIs session_escrow::claim_stall or claim_sla_failure with the user
+    /// as signer, measures the payout that landed in the user's session-mint
+    /// account, then routes it through an allowlisted swap and pool into
+    /// `output_mint` (e.g. USDC) with a conservative_min_out floor. Both legs
+    /// are priced from the registered mode feeds.
+    pub fn claim_and_swap_payout(
+        ctx: Context<ClaimAndSwapPayout>,
+        claim: PayoutClaim,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        
+        // Validate swap program and pool are allowlisted
+        let swap_program = ctx.accounts.swap_program.key();
+        let swap_count = config.swap_program_count as usize;
+        require!(
+            config.allowlisted_swap_programs[..swap_count].contains(&swap_program),
+            GatewayError::SwapProgramNotAllowlisted
+        );
+        let pool = ctx.accounts.pool.key();
+        let pool_count = config.pool_count as usize;
+        require!(
+            config.allowlisted_pools[..pool_count].contains(&pool),
+            GatewayError::PoolNotAllowlisted
+        );
+        
+        // Load and validate prices (session mint in, output mint out)
+        let feed_in = mode_feed_id(config, &ctx.accounts.payment_mint.key())?;
+        let feed_out = mode_feed_id(config, &ctx.accounts.output_mint.key())?;
+        
+        let price_in = pyth_helpers::validate_price(
+            &ctx.accounts.input_price_update,
+            &feed_in,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        let price_out = pyth_helpers::validate_price(
+            &ctx.accounts.output_price_update,
+            &feed_out,
+            config.pyth_max_age_seconds,
+            config.pyth_max_conf_ratio_bps,
+        )?;
+        
+        // Dampen single-slot oracle spikes
+        check_price_divergence(
+            &mut ctx.accounts.pair_price_state,
+            &ctx.accounts.config,
+            &price_in,
+            &price_out,
+        )?;
+        
+        // Halt if either leg is a depegged stablecoin
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.payment_mint.key(), &price_in)?;
+        check_stable_peg(&ctx.accounts.config, &ctx.accounts.output_mint.key(), &price_out)?;
+        
+        // Claim into the user's session-mint account
+        let balance_before = ctx.accounts.user_token_account.amount;
+        let escrow_before = token::accessor::amount(&ctx.accounts.escrow_token_account)?;
+        let program = ctx.accounts.session_escrow_program.to_account_info();
+        let remaining = ctx.remaining_accounts.to_vec();
+        match claim {
+            PayoutClaim::Stall => {
+                let cpi_accounts = session_escrow::cpi::accounts::ClaimStall {
+                    session: ctx.accounts.session.to_account_info(),
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                    vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                    escrow_token_account: ctx.accounts.escrow_token_account.to_account_info(),
+                    payment_mint: ctx.accounts.payment_mint.to_account_info(),
+                    user_token_account: ctx.accounts.user_token_account.to_account_info(),
+                    registry: ctx.accounts.registry.to_account_info(),
+                    insurance_fund_token_account: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                    insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                    session_bond: ctx.accounts.session_bond.as_ref().map(|a| a.to_account_info()),
+                    user: ctx.accounts.user.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                    collateral_vault_event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    active_sessions: ctx.accounts.active_sessions.to_account_info(),
+                    event_queue: ctx.accounts.event_queue.to_account_info(),
+                    event_authority: ctx.accounts.session_escrow_event_authority.to_account_info(),
+                    program: program.clone(),
+                };
+                let cpi_ctx = CpiContext::new(program, cpi_accounts).with_remaining_accounts(remaining);
+                session_escrow::cpi::claim_stall(cpi_ctx)?;
+            }
+            PayoutClaim::SlaFailure => {
+                let cpi_accounts = session_escrow::cpi::accounts::ClaimSlaFailure {
+                    session: ctx.accounts.session.to_account_info(),
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                    vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                    escrow_token_account: ctx.accounts.escrow_token_account.to_account_info(),
+                    payment_mint: ctx.accounts.payment_mint.to_account_info(),
+                    user_token_account: ctx.accounts.user_token_account.to_account_info(),
+                    registry: ctx.accounts.registry.to_account_info(),
+                    insurance_fund_token_account: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                    insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                    session_bond: ctx.accounts.session_bond.as_ref().map(|a| a.to_account_info()),
+                    user: ctx.accounts.user.to_account_info(),
+                    service_credit: ctx.accounts.service_credit.as_ref().map(|a| a.to_account_info()),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                    collateral_vault_event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    active_sessions: ctx.accounts.active_sessions.to_account_info(),
+                    event_queue: ctx.accounts.event_queue.to_account_info(),
+                    event_authority: ctx.accounts.session_escrow_event_authority.to_account_info(),
+                    program: program.clone(),
+                };
+                let cpi_ctx = CpiContext::new(program, cpi_accounts).with_remaining_accounts(remaining);
+                session_escrow::cpi::claim_sla_failure(cpi_ctx)?;
+            }
+        }
+        
+        // Only the payout is swapped. The claim also refunds the user's
+        // escrow (everything but unspent sponsorship) into the same account;
+        // that refund is the escrow's balance drop and is left unswapped.
+        ctx.accounts.user_token_account.reload()?;
+        let escrow_refunded = escrow_before
+            .saturating_sub(token::accessor::amount(&ctx.accounts.escrow_token_account)?);
+        let amount_in = ctx.accounts.user_token_account.amount
+            .saturating_sub(balance_before)
+            .saturating_sub(escrow_refunded);
+        
+        let config = &ctx.accounts.config;
+        require!(
+            amount_in <= config.max_trade_size,
+            GatewayError::TradeTooLarge
+        );
+        
+        // Slippage bound follows live oracle confidence, capped by config
+        let slippage_bps = pyth_helpers::dynamic_slippage_bps(
+            &price_in,
+            &price_out,
+            config.min_slippage_bps,
+            config.max_slippage_bps,
+        );
+        let min_amount_out = pyth_helpers::conservative_min_out(
+            amount_in,
+            &price_in,
+            &price_out,
+            slippage_bps,
+        )?
+        .max(min_amount_out);
+        
+        // TODO: Execute swap CPI (session mint -> output mint into user_output_token)
+        
+        emit!(ClaimSwapStubbed {
+            user: ctx.accounts.user.key(),
+            session: ctx.accounts.session.key(),
+            claim,
+            amount_in,
+            min_amount_out,
+            slippage_bps,
+        });
+        
+        Ok(())
+    }
+}
 
 // ============================================================================
-// Accounts
+// Helpers
 // ============================================================================
 
-#[derive(Accounts)]
-pub struct InitGatewayConfig<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + GatewayConfig::INIT_SPACE,
-        seeds = [b"gateway_config"],
-        bump
-    )]
-    pub config: Account<'info, GatewayConfig>,
+/// Invoke an allowlisted swap program with caller-built instruction data
+///
+/// `accounts` are the swap instruction's accounts in order and must include
+/// the allowlisted `pool`. Signer and writable flags are taken from the
+/// transaction, so only signatures the caller already gave are forwarded.
+fn invoke_swap<'info>(
+    swap_program: &AccountInfo<'info>,
+    pool: &Pubkey,
+    accounts: &[AccountInfo<'info>],
+    data: Vec<u8>,
+) -> Result<()> {
+    require!(
+        accounts.iter().any(|info| info.key() == *pool),
+        GatewayError::PoolNotAllowlisted
+    );
     
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    let ix = Instruction {
+        program_id: swap_program.key(),
+        accounts: accounts
+            .iter()
+            .map(|info| AccountMeta {
+                pubkey: info.key(),
+                is_signer: info.is_signer,
+                is_writable: info.is_writable,
+            })
+            .collect(),
+        data,
+    };
+    let mut infos = accounts.to_vec();
+    infos.push(swap_program.clone());
+    invoke(&ix, &infos)?;
     
-    pub system_program: Program<'info, System>,
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct ModifyConfig<'info> {
-    #[account(
-        mut,
-        seeds = [b"gateway_config"],
-        bump = config.bump,
-        has_one = authority @ GatewayError::Unauthorized
-    )]
-    pub config: Account<'info, GatewayConfig>,
+/// Reject a swap whose pair price diverges too far from the last accepted
+/// execution within the configured slot window, then record it.
+fn check_price_divergence(
+    state: &mut Account<PairPriceState>,
+    config: &GatewayConfig,
+    price_in: &pyth_helpers::PriceData,
+    price_out: &pyth_helpers::PriceData,
+) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let ratio = pair_price_ratio(price_in, price_out)?;
     
-    pub authority: Signer<'info>,
+    let breaker_enabled = config.max_price_divergence_bps > 0
+        && config.divergence_window_slots > 0;
+    let within_window = state.last_slot != 0
+        && slot.saturating_sub(state.last_slot) <= config.divergence_window_slots;
+    
+    if breaker_enabled && within_window && state.last_price_ratio > 0 {
+        let diff = ratio.abs_diff(state.last_price_ratio);
+        let divergence_bps = diff
+            .checked_mul(10_000)
+            .ok_or(GatewayError::Overflow)?
+            / state.last_price_ratio;
+        
+        if divergence_bps > config.max_price_divergence_bps as u128 {
+            emit!(PriceDivergenceRejected {
+                input_mint: state.input_mint,
+                output_mint: state.output_mint,
+                last_price_ratio: state.last_price_ratio,
+                price_ratio: ratio,
+                divergence_bps: divergence_bps as u64,
+            });
+            return err!(GatewayError::PriceDivergenceExceeded);
+        }
+    }
+    
+    state.last_price_ratio = ratio;
+    state.last_slot = slot;
+    
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct SwapAndFundSession<'info> {
-    #[account(
+/// Pyth feed registered for a mint
+fn mode_feed_id(config: &GatewayConfig, mint: &Pubkey) -> Result<[u8; 32]> {
+    config.mode_feeds[..config.mode_feed_count as usize]
+        .iter()
+        .find(|feed| feed.mint == *mint)
+        .map(|feed| feed.feed_id)
+        .ok_or(error!(GatewayError::PriceFeedNotFound))
+}
+
+/// Reject a swap leg whose mint is a registered stablecoin trading outside
+/// its peg bound. Mints without a peg bound pass through.
+fn check_stable_peg(
+    config: &GatewayConfig,
+    mint: &Pubkey,
+    price: &pyth_helpers::PriceData,
+) -> Result<()> {
+    let peg_max_dev_bps = config.mode_feeds[..config.mode_feed_count as usize]
+        .iter()
+        .find(|feed| feed.mint == *mint)
+        .map(|feed| feed.peg_max_dev_bps)
+        .unwrap_or(0);
+    
+    if peg_max_dev_bps > 0 {
+        pyth_helpers::assert_peg(price, pyth_helpers::PEG_TARGET_ONE_USD, peg_max_dev_bps)?;
+    }
+    
+    Ok(())
+}
+
+/// Verify the Ed25519 instruction preceding this one carries the user's
+/// signature over (program_id, user, mint, destination, amount_cap,
+/// expiry_slot, nonce)
+fn verify_spending_approval_signature(
+    instructions_sysvar: &AccountInfo,
+    user: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    amount_cap: u64,
+    expiry_slot: u64,
+    nonce: u64,
+) -> Result<()> {
+    let current_ix_idx = instructions::load_current_index_checked(instructions_sysvar)
+        .map_err(|_| GatewayError::InvalidEd25519Instruction)?;
+    
+    // Ed25519 instruction must be immediately before this one
+    require!(current_ix_idx > 0, GatewayError::InvalidEd25519Instruction);
+    
+    let ed25519_ix = load_instruction_at_checked(
+        (current_ix_idx - 1) as usize,
+        instructions_sysvar,
+    ).map_err(|_| GatewayError::InvalidEd25519Instruction)?;
+    
+    require!(
+        ed25519_ix.program_id == ED25519_PROGRAM_ID,
+        GatewayError::InvalidEd25519Instruction
+    );
+    let (pubkey, message) = parse_ed25519_single_signature(&ed25519_ix.data)?;
+    
+    let mut expected_message = Vec::with_capacity(32 + 32 + 32 + 32 + 8 + 8 + 8);
+    expected_message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    expected_message.extend_from_slice(&user.to_bytes());
+    expected_message.extend_from_slice(&mint.to_bytes());
+    expected_message.extend_from_slice(&destination.to_bytes());
+    expected_message.extend_from_slice(&amount_cap.to_le_bytes());
+    expected_message.extend_from_slice(&expiry_slot.to_le_bytes());
+    expected_message.extend_from_slice(&nonce.to_le_bytes());
+    
+    require!(pubkey == user.as_ref(), GatewayError::ApprovalSignerMismatch);
+    require!(message == expected_message.as_slice(), GatewayError::SignatureMessageMismatch);
+    
+    Ok(())
+}
+
+/// Parse an Ed25519 precompile instruction holding exactly one signature
+///
+/// Layout: [num_signatures: u8][padding: u8] followed by the offsets entry of
+/// seven little-endian u16s: signature_offset, signature_ix_index,
+/// pubkey_offset, pubkey_ix_index, message_offset, message_size,
+/// message_ix_index. All instruction indexes must be u16::MAX (data in this
+/// instruction), otherwise the precompile may have verified bytes from
+/// another instruction. Returns the (pubkey, message) the precompile checked.
+fn parse_ed25519_single_signature(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const SIGNATURE_LEN: usize = 64;
+    const PUBKEY_LEN: usize = 32;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+    
+    fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+        let end = offset.checked_add(len).ok_or(GatewayError::InvalidEd25519Instruction)?;
+        data.get(offset..end).ok_or_else(|| error!(GatewayError::InvalidEd25519Instruction))
+    }
+    
+    require!(
+        data.len() >= HEADER_LEN + OFFSETS_LEN && data[0] == 1,
+        GatewayError::InvalidEd25519Instruction
+    );
+    
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_offset = read_u16(HEADER_LEN) as usize;
+    let signature_ix_index = read_u16(HEADER_LEN + 2);
+    let pubkey_offset = read_u16(HEADER_LEN + 4) as usize;
+    let pubkey_ix_index = read_u16(HEADER_LEN + 6);
+    let message_offset = read_u16(HEADER_LEN + 8) as usize;
+    let message_size = read_u16(HEADER_LEN + 10) as usize;
+    let message_ix_index = read_u16(HEADER_LEN + 12);
+    
+    require!(
+        signature_ix_index == THIS_INSTRUCTION
+            && pubkey_ix_index == THIS_INSTRUCTION
+            && message_ix_index == THIS_INSTRUCTION,
+        GatewayError::InvalidEd25519Instruction
+    );
+    
+    slice(data, signature_offset, SIGNATURE_LEN)?;
+    let pubkey = slice(data, pubkey_offset, PUBKEY_LEN)?;
+    let message = slice(data, message_offset, message_size)?;
+    
+    Ok((pubkey, message))
+}
+
+/// Input/output price ratio scaled by PRICE_RATIO_PRECISION
+fn pair_price_ratio(
+    price_in: &pyth_helpers::PriceData,
+    price_out: &pyth_helpers::PriceData,
+) -> Result<u128> {
+    let in_usd = price_in.price_in_decimals(PRICE_RATIO_DECIMALS)? as u128;
+    let out_usd = price_out.price_in_decimals(PRICE_RATIO_DECIMALS)? as u128;
+    require!(in_usd > 0 && out_usd > 0, GatewayError::InvalidPrice);
+    
+    in_usd
+        .checked_mul(PRICE_RATIO_PRECISION)
+        .ok_or(GatewayError::Overflow)?
+        .checked_div(out_usd)
+        .ok_or(error!(GatewayError::Overflow))
+}
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const MAX_SWAP_PROGRAMS: usize = 8;
+pub const MAX_POOLS: usize = 16;
+pub const MAX_MODE_FEEDS: usize = 16;
+
+/// Decimals prices are normalized to before computing a pair ratio
+pub const PRICE_RATIO_DECIMALS: u8 = 8;
+pub const PRICE_RATIO_PRECISION: u128 = 1_000_000_000;
+
+// ============================================================================
+// Accounts
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct InitGatewayConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GatewayConfig::INIT_SPACE,
+        seeds = [b"gateway_config"],
+        bump
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ModifyConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"gateway_config"],
+        bump = config.bump,
+        has_one = authority @ GatewayError::Unauthorized
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitPairPriceState<'info> {
+    #[account(
+        seeds = [b"gateway_config"],
+        bump = config.bump,
+        has_one = authority @ GatewayError::Unauthorized
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PairPriceState::INIT_SPACE,
+        seeds = [b"pair_price", input_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
+    pub input_mint: Account<'info, Mint>,
+    pub output_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SwapAndFundSession<'info> {
+    #[account(
         seeds = [b"gateway_config"],
         bump = config.bump
     )]
@@ -354,12 +1241,111 @@ pub struct SwapAndFundSession<'info> {
     pub input_mint: Account<'info, Mint>,
     pub output_mint: Account<'info, Mint>,
     
+    #[account(
+        mut,
+        seeds = [b"pair_price", input_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump = pair_price_state.bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
     pub token_program: Program<'info, Token>,
     
     /// CHECK: session_escrow program
     pub session_escrow_program: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(amount_in: u64, min_amount_out: u64, amount_cap: u64, expiry_slot: u64, nonce: u64)]
+pub struct KeeperSwapAndFundSession<'info> {
+    #[account(
+        seeds = [b"gateway_config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    /// CHECK: Approval signer, verified via Ed25519 introspection
+    pub user: AccountInfo<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + SpendingApproval::INIT_SPACE,
+        seeds = [b"spend_approval", user.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub spending_approval: Account<'info, SpendingApproval>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub session: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_input_token.owner == user.key() @ GatewayError::ApprovalMismatch,
+        constraint = user_input_token.mint == input_mint.key() @ GatewayError::ApprovalMismatch
+    )]
+    pub user_input_token: Account<'info, TokenAccount>,
+    
+    /// Approved destination: the session's escrow for the swap output
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == session.key() @ GatewayError::ApprovalMismatch,
+        constraint = escrow_token_account.mint == output_mint.key() @ GatewayError::ApprovalMismatch
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    pub input_price_update: Account<'info, PriceUpdateV2>,
+    pub output_price_update: Account<'info, PriceUpdateV2>,
+    
+    /// CHECK: Validated against allowlist
+    pub swap_program: AccountInfo<'info>,
+    
+    /// CHECK: Passed to swap program
+    pub pool: AccountInfo<'info>,
+    
+    pub input_mint: Account<'info, Mint>,
+    pub output_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"pair_price", input_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump = pair_price_state.bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
+    /// CHECK: Instructions sysvar for Ed25519 verification
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: session_escrow program
+    pub session_escrow_program: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct RevokeSpendingApproval<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + SpendingApproval::INIT_SPACE,
+        seeds = [b"spend_approval", user.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub spending_approval: Account<'info, SpendingApproval>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct SwapAndDepositCollateral<'info> {
     #[account(
@@ -393,12 +1379,204 @@ pub struct SwapAndDepositCollateral<'info> {
     pub input_mint: Account<'info, Mint>,
     pub collateral_mint: Account<'info, Mint>,
     
+    #[account(
+        mut,
+        seeds = [b"pair_price", input_mint.key().as_ref(), collateral_mint.key().as_ref()],
+        bump = pair_price_state.bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
     pub token_program: Program<'info, Token>,
     
     /// CHECK: collateral_vault program
     pub collateral_vault_program: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct StakeWithSol<'info> {
+    #[account(
+        seeds = [b"gateway_config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_wsol_token.owner == user.key() @ GatewayError::InvalidTokenAccount,
+        constraint = user_wsol_token.mint == spl_token::native_mint::ID @ GatewayError::InvalidTokenAccount
+    )]
+    pub user_wsol_token: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = user_native_token.owner == user.key() @ GatewayError::InvalidTokenAccount,
+        constraint = user_native_token.mint == native_mint.key() @ GatewayError::InvalidTokenAccount
+    )]
+    pub user_native_token: Account<'info, TokenAccount>,
+    
+    pub input_price_update: Account<'info, PriceUpdateV2>,
+    pub output_price_update: Account<'info, PriceUpdateV2>,
+    
+    /// CHECK: Validated against allowlist
+    pub swap_program: AccountInfo<'info>,
+    
+    /// CHECK: Validated against allowlist, passed to swap program
+    pub pool: AccountInfo<'info>,
+    
+    #[account(address = spl_token::native_mint::ID)]
+    pub wsol_mint: Account<'info, Mint>,
+    pub native_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"pair_price", wsol_mint.key().as_ref(), native_mint.key().as_ref()],
+        bump = pair_price_state.bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
+    /// CHECK: Validated by naked_staking program
+    #[account(mut)]
+    pub staking_pool: AccountInfo<'info>,
+    
+    /// CHECK: Validated by naked_staking program
+    #[account(mut)]
+    pub staking_position: AccountInfo<'info>,
+    
+    /// CHECK: Validated by naked_staking program
+    #[account(mut)]
+    pub staking_vault: AccountInfo<'info>,
+    
+    /// CHECK: Validated by naked_staking program; required once its epoch budgets are enabled
+    #[account(mut)]
+    pub staking_reward_epochs: Option<AccountInfo<'info>>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    
+    pub naked_staking_program: Program<'info, naked_staking::program::NakedStaking>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAndSwapPayout<'info> {
+    #[account(
+        seeds = [b"gateway_config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, GatewayConfig>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub session: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub position: AccountInfo<'info>,
+    
+    /// CHECK: Session's reservation record; validated by collateral_vault
+    #[account(mut)]
+    pub reservation: AccountInfo<'info>,
+    
+    /// CHECK: Reservation record payer (rent refund); validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: AccountInfo<'info>,
+    
+    /// CHECK: collateral_vault caller allowlist; validated by collateral_vault
+    pub vault_callers: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub mode_vault: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub vault_token_account: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub escrow_token_account: AccountInfo<'info>,
+    
+    /// User's session-mint account receiving the claim (the swap input)
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ GatewayError::InvalidTokenAccount,
+        constraint = user_token_account.mint == payment_mint.key() @ GatewayError::InvalidTokenAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = user_output_token.owner == user.key() @ GatewayError::InvalidTokenAccount,
+        constraint = user_output_token.mint == output_mint.key() @ GatewayError::InvalidTokenAccount
+    )]
+    pub user_output_token: Account<'info, TokenAccount>,
+    
+    /// CHECK: Validated by session_escrow program
+    pub registry: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub insurance_fund_token_account: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Validated by collateral_vault program
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub session_bond: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Validated by session_escrow program (claim_sla_failure only)
+    #[account(mut)]
+    pub service_credit: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub active_sessions: AccountInfo<'info>,
+    
+    /// CHECK: Validated by session_escrow program
+    #[account(mut)]
+    pub event_queue: AccountInfo<'info>,
+    
+    pub input_price_update: Account<'info, PriceUpdateV2>,
+    pub output_price_update: Account<'info, PriceUpdateV2>,
+    
+    /// CHECK: Validated against allowlist
+    pub swap_program: AccountInfo<'info>,
+    
+    /// CHECK: Validated against allowlist, passed to swap program
+    pub pool: AccountInfo<'info>,
+    
+    pub payment_mint: Account<'info, Mint>,
+    pub output_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"pair_price", payment_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump = pair_price_state.bump
+    )]
+    pub pair_price_state: Account<'info, PairPriceState>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    /// CHECK: collateral_vault program, validated by session_escrow
+    pub collateral_vault_program: AccountInfo<'info>,
+    
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: AccountInfo<'info>,
+    
+    /// CHECK: session_escrow's event authority PDA; validated by session_escrow
+    pub session_escrow_event_authority: AccountInfo<'info>,
+    
+    /// CHECK: session_escrow program
+    #[account(address = session_escrow::ID)]
+    pub session_escrow_program: AccountInfo<'info>,
+}
+
 // ============================================================================
 // State
 // ============================================================================
@@ -407,7 +1585,8 @@ pub struct SwapAndDepositCollateral<'info> {
 #[derive(InitSpace)]
 pub struct GatewayConfig {
     pub authority: Pubkey,
-    pub max_slippage_bps: u16,
+    pub max_slippage_bps: u16,      // Cap on confidence-derived slippage
+    pub min_slippage_bps: u16,      // Floor on confidence-derived slippage
     pub max_trade_size: u64,
     pub pyth_max_age_seconds: u64,
     pub pyth_max_conf_ratio_bps: u16,
@@ -425,6 +1604,36 @@ pub struct GatewayConfig {
     pub mode_feeds: [ModeFeed; MAX_MODE_FEEDS],
     pub mode_feed_count: u8,
     
+    // Price divergence breaker (0 = disabled)
+    pub max_price_divergence_bps: u16,
+    pub divergence_window_slots: u64,
+    
+    pub bump: u8,
+}
+
+/// Usage of a user-signed spending approval, keyed by (user, nonce)
+#[account]
+#[derive(InitSpace)]
+pub struct SpendingApproval {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,        // Escrow token account the swap output funds
+    pub amount_cap: u64,
+    pub spent: u64,
+    pub expiry_slot: u64,
+    pub nonce: u64,
+    pub revoked: bool,
+    pub bump: u8,
+}
+
+/// Last accepted execution price for a swap pair
+#[account]
+#[derive(InitSpace)]
+pub struct PairPriceState {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub last_price_ratio: u128,
+    pub last_slot: u64,
     pub bump: u8,
 }
 
@@ -432,6 +1641,14 @@ pub struct GatewayConfig {
 pub struct ModeFeed {
     pub mint: Pubkey,
     pub feed_id: [u8; 32],
+    pub peg_max_dev_bps: u16,  // 0 = not a stablecoin
+}
+
+/// Session claim routed through claim_and_swap_payout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutClaim {
+    Stall,
+    SlaFailure,
 }
 
 // ============================================================================
@@ -465,19 +1682,86 @@ pub struct PoolAdded {
 pub struct ModeFeedAdded {
     pub mint: Pubkey,
     pub feed_id: [u8; 32],
+    pub peg_max_dev_bps: u16,
+}
+
+#[event]
+pub struct SlippageBoundsUpdated {
+    pub min_slippage_bps: u16,
+    pub max_slippage_bps: u16,
+}
+
+#[event]
+pub struct PriceDivergenceBreakerUpdated {
+    pub max_divergence_bps: u16,
+    pub window_slots: u64,
+}
+
+#[event]
+pub struct PairPriceStateInitialized {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+}
+
+#[event]
+pub struct PriceDivergenceRejected {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub last_price_ratio: u128,
+    pub price_ratio: u128,
+    pub divergence_bps: u64,
+}
+
+#[event]
+pub struct SpendingApprovalUsed {
+    pub approval: Pubkey,
+    pub user: Pubkey,
+    pub keeper: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct SpendingApprovalRevoked {
+    pub user: Pubkey,
+    pub nonce: u64,
 }
 
 #[event]
 pub struct SwapAndFundStubbed {
     pub user: Pubkey,
     pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub slippage_bps: u16,
     pub session: Pubkey,
 }
 
+#[event]
+pub struct StakedWithSol {
+    pub user: Pubkey,
+    pub lamports: u64,
+    pub amount_out: u64,
+    pub min_amount_out: u64,
+    pub slippage_bps: u16,
+    pub staking_pool: Pubkey,
+}
+
+#[event]
+pub struct ClaimSwapStubbed {
+    pub user: Pubkey,
+    pub session: Pubkey,
+    pub claim: PayoutClaim,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub slippage_bps: u16,
+}
+
 #[event]
 pub struct SwapAndDepositStubbed {
     pub provider: Pubkey,
     pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub slippage_bps: u16,
     pub mode_id: u32,
 }
 
@@ -509,4 +1793,32 @@ pub enum GatewayError {
     SlippageExceeded,
     #[msg("Price feed not found for mint")]
     PriceFeedNotFound,
+    #[msg("Invalid divergence bps")]
+    InvalidDivergenceBps,
+    #[msg("Price diverged too far from last execution")]
+    PriceDivergenceExceeded,
+    #[msg("Invalid oracle price")]
+    InvalidPrice,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Invalid Ed25519 signature instruction")]
+    InvalidEd25519Instruction,
+    #[msg("Signature message mismatch")]
+    SignatureMessageMismatch,
+    #[msg("Approval not signed by user")]
+    ApprovalSignerMismatch,
+    #[msg("Spending approval expired")]
+    ApprovalExpired,
+    #[msg("Spending approval revoked")]
+    ApprovalRevoked,
+    #[msg("Spending approval terms mismatch")]
+    ApprovalMismatch,
+    #[msg("Spending approval cap exceeded")]
+    ApprovalCapExceeded,
+    #[msg("Token account owner or mint mismatch")]
+    InvalidTokenAccount,
+    #[msg("Peg bound exceeds 100%")]
+    InvalidPegBound,
+    #[msg("Invalid slippage bounds")]
+    InvalidSlippageBounds,
 }
@@ -1,10 +1,35 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::Hasher;
 
 declare_id!("ModeReg111111111111111111111111111111111111");
 
 /// Maximum number of verifiers in the allowlist
 pub const MAX_VERIFIERS: usize = 10;
 
+/// Maximum haircut applied to LP-token collateral (90%)
+pub const MAX_LP_HAIRCUT_BPS: u16 = 9_000;
+
+/// Maximum share of a claim slash routed to the insurance fund (20%)
+pub const MAX_INSURANCE_BPS: u16 = 2_000;
+
+/// Ceiling the admin can set for per-session referral shares (10%)
+pub const MAX_REFERRAL_BPS: u16 = 1_000;
+
+/// Ceiling on the reservation fee charged per fee period (1%)
+pub const MAX_RESERVATION_FEE_BPS: u16 = 100;
+
+/// Slots over which reservation_fee_bps accrues in full (≈ 1 day at 400ms)
+pub const RESERVATION_FEE_PERIOD_SLOTS: u64 = 216_000;
+
+/// Shortest nonzero max_session_slots (≈ 1 day at 400ms)
+pub const MIN_MAX_SESSION_SLOTS: u64 = 216_000;
+
+/// Maximum programs allowed to drive collateral_vault reserve/release/slash
+pub const MAX_VAULT_CALLERS: usize = 4;
+
+/// Layout version of the registry snapshot hash preimage
+pub const SNAPSHOT_VERSION: u8 = 2;
+
 /// Mode Registry Program
 ///
 /// Manages allowlist of collateral/payment mints with per-mint parameters.
@@ -24,6 +49,12 @@ pub mod mode_registry {
         registry.verifier_count = 0;
         registry.verifiers = [Pubkey::default(); MAX_VERIFIERS];
         registry.bump = ctx.bumps.registry;
+        registry.snapshot_seq = 0;
+        registry.paused = false;
+        registry.insurance_fund = Pubkey::default();
+        registry.insurance_bps = 0;
+        registry.max_referral_bps = 0;
+        registry.rotation_count = 0;
 
         emit!(RegistryInitialized {
             admin: registry.admin,
@@ -61,6 +92,24 @@ pub mod mode_registry {
         mode.activation_slot = activation_slot;
         mode.is_active = false; // Must be activated after timelock
         mode.is_disabled = false;
+        mode.is_lp_collateral = false;
+        mode.lp_haircut_bps = 0;
+        mode.max_concurrent_sessions = 0;
+        mode.peg_feed_id = [0u8; 32];
+        mode.peg_max_dev_bps = 0;
+        mode.close_dispute_window_slots = 0;
+        mode.min_bucket_slots = 0;
+        mode.max_bucket_slots = 0;
+        mode.max_premium_bps = 0;
+        mode.max_session_slots = 0;
+        mode.timing_bounds = SessionTimingBounds::default();
+        mode.unbonding_slots = 0;
+        mode.yield_adapter = Pubkey::default();
+        mode.allowed_mint_extensions = 0;
+        mode.reservation_fee_bps = 0;
+        mode.health_feed_id = [0u8; 32];
+        mode.min_free_collateral_usd = 0;
+        mode.soulbound_position_nft = false;
         mode.bump = ctx.bumps.mode;
 
         let registry = &mut ctx.accounts.registry;
@@ -157,6 +206,387 @@ pub mod mode_registry {
         Ok(())
     }
 
+    /// Mark a mode's mint as an allowlisted LP token with a valuation haircut (admin only)
+    ///
+    /// collateral_vault's USD health checks value LP collateral at the pool's
+    /// fair value (see pyth_helpers::lp_pool_fair_value_usd), from the reserve
+    /// token accounts and a Pyth feed per reserve, minus the haircut. The
+    /// reserves are fixed on approval; once set, the haircut can only be
+    /// increased.
+    pub fn set_lp_collateral(
+        ctx: Context<SetLpCollateral>,
+        lp_haircut_bps: u16,
+        feed_id_a: [u8; 32],
+        feed_id_b: [u8; 32],
+    ) -> Result<()> {
+        let reserve_a = ctx.accounts.reserve_a.key();
+        let reserve_b = ctx.accounts.reserve_b.key();
+        require!(reserve_a != reserve_b, ErrorCode::InvalidLpPool);
+        require!(lp_haircut_bps <= MAX_LP_HAIRCUT_BPS, ErrorCode::LpHaircutTooHigh);
+
+        let lp_pool = &mut ctx.accounts.lp_pool;
+        if lp_pool.reserve_a == Pubkey::default() {
+            lp_pool.mode_id = ctx.accounts.mode.mode_id;
+            lp_pool.reserve_a = reserve_a;
+            lp_pool.reserve_b = reserve_b;
+            lp_pool.decimals_a = ctx.accounts.mint_a.decimals;
+            lp_pool.decimals_b = ctx.accounts.mint_b.decimals;
+            lp_pool.bump = ctx.bumps.lp_pool;
+        } else {
+            require!(
+                lp_pool.reserve_a == reserve_a && lp_pool.reserve_b == reserve_b,
+                ErrorCode::InvalidLpPool
+            );
+        }
+        lp_pool.feed_id_a = feed_id_a;
+        lp_pool.feed_id_b = feed_id_b;
+
+        let mode = &mut ctx.accounts.mode;
+        if mode.is_lp_collateral {
+            require!(
+                lp_haircut_bps >= mode.lp_haircut_bps,
+                ErrorCode::CannotReduceLpHaircut
+            );
+        }
+
+        mode.is_lp_collateral = true;
+        mode.lp_haircut_bps = lp_haircut_bps;
+
+        emit!(LpCollateralConfigured {
+            mode_id: mode.mode_id,
+            mint: mode.mint,
+            lp_haircut_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Mark a mode's mint as a USD stablecoin with a peg bound (admin only)
+    ///
+    /// collateral_vault halts deposits and reservations for the mode while the
+    /// Pyth price of peg_feed_id is more than peg_max_dev_bps off $1.00.
+    /// 0 = not a stablecoin mode.
+    pub fn set_stable_peg(
+        ctx: Context<UpdateModeParams>,
+        peg_feed_id: [u8; 32],
+        peg_max_dev_bps: u16,
+    ) -> Result<()> {
+        require!(peg_max_dev_bps <= 10_000, ErrorCode::InvalidPegBound);
+
+        let mode = &mut ctx.accounts.mode;
+        mode.peg_feed_id = peg_feed_id;
+        mode.peg_max_dev_bps = peg_max_dev_bps;
+
+        emit!(StablePegConfigured {
+            mode_id: mode.mode_id,
+            peg_feed_id,
+            peg_max_dev_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Cap how many sessions a provider may have active at once (admin only)
+    ///
+    /// Enforced by session_escrow at ack_start. 0 = unlimited.
+    pub fn set_max_concurrent_sessions(
+        ctx: Context<UpdateModeParams>,
+        max_concurrent_sessions: u32,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.max_concurrent_sessions = max_concurrent_sessions;
+
+        emit!(MaxConcurrentSessionsSet {
+            mode_id: mode.mode_id,
+            max_concurrent_sessions,
+        });
+
+        Ok(())
+    }
+
+    /// Set the dispute window after a user closes a session (admin only)
+    ///
+    /// Enforced by session_escrow: claim_stall and claim_sla_failure stay
+    /// open and finalize_close is blocked until the window lapses. 0 = none.
+    pub fn set_close_dispute_window(
+        ctx: Context<UpdateModeParams>,
+        close_dispute_window_slots: u64,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.close_dispute_window_slots = close_dispute_window_slots;
+
+        emit!(CloseDisputeWindowSet {
+            mode_id: mode.mode_id,
+            close_dispute_window_slots,
+        });
+
+        Ok(())
+    }
+
+    /// Set how long an active session may go without progress (admin only)
+    ///
+    /// Enforced by session_escrow: once a session has made no progress for
+    /// max_session_slots, anyone can expire it. 0 = sessions never expire;
+    /// otherwise at least MIN_MAX_SESSION_SLOTS.
+    pub fn set_max_session_slots(
+        ctx: Context<UpdateModeParams>,
+        max_session_slots: u64,
+    ) -> Result<()> {
+        require!(
+            max_session_slots == 0 || max_session_slots >= MIN_MAX_SESSION_SLOTS,
+            ErrorCode::InvalidSessionBounds
+        );
+
+        let mode = &mut ctx.accounts.mode;
+        mode.max_session_slots = max_session_slots;
+
+        emit!(MaxSessionSlotsSet {
+            mode_id: mode.mode_id,
+            max_session_slots,
+        });
+
+        Ok(())
+    }
+
+    /// Approve a secondary collateral mint for a mode's positions (admin only)
+    ///
+    /// collateral_vault values legs of this mint through the Pyth feed,
+    /// minus the haircut, and counts them 1:1 against the (stablecoin) mode
+    /// mint. Priority is fixed on approval: lower legs are slashed first once
+    /// a position's primary collateral runs out. Like LP haircuts, a leg
+    /// haircut can only be increased.
+    pub fn set_collateral_leg(
+        ctx: Context<SetCollateralLeg>,
+        feed_id: [u8; 32],
+        haircut_bps: u16,
+        priority: u8,
+    ) -> Result<()> {
+        let mode = &ctx.accounts.mode;
+        let mint = ctx.accounts.leg_mint.key();
+        require!(mint != mode.mint, ErrorCode::InvalidCollateralLeg);
+        require!(haircut_bps <= MAX_LP_HAIRCUT_BPS, ErrorCode::LpHaircutTooHigh);
+
+        let leg_config = &mut ctx.accounts.leg_config;
+        if leg_config.mint == Pubkey::default() {
+            leg_config.mode_id = mode.mode_id;
+            leg_config.mint = mint;
+            leg_config.priority = priority;
+            leg_config.bump = ctx.bumps.leg_config;
+        } else {
+            require!(
+                haircut_bps >= leg_config.haircut_bps,
+                ErrorCode::CannotReduceLpHaircut
+            );
+        }
+        leg_config.feed_id = feed_id;
+        leg_config.haircut_bps = haircut_bps;
+
+        emit!(CollateralLegConfigured {
+            mode_id: mode.mode_id,
+            mint,
+            haircut_bps,
+            priority: leg_config.priority,
+        });
+
+        Ok(())
+    }
+
+    /// Allowlist the yield adapter program for a mode's idle collateral (admin only)
+    ///
+    /// The adapter fronts a lending protocol (marginfi, Kamino, ...) behind
+    /// collateral_vault's adapter_deposit / adapter_withdraw_all interface.
+    /// Pubkey::default() disables new deployments; recalls keep working.
+    pub fn set_yield_adapter(ctx: Context<UpdateModeParams>, yield_adapter: Pubkey) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.yield_adapter = yield_adapter;
+
+        emit!(YieldAdapterSet {
+            mode_id: mode.mode_id,
+            yield_adapter,
+        });
+
+        Ok(())
+    }
+
+    /// Set the Token-2022 mint extensions collateral_vault accepts (admin only)
+    ///
+    /// Bit n allows spl_token_2022 ExtensionType n on the mode mint; 0 admits
+    /// only classic SPL mints and extension-free Token-2022 mints. Checked on
+    /// every deposit, so clearing a bit stops new collateral but never traps
+    /// existing collateral.
+    pub fn set_allowed_mint_extensions(
+        ctx: Context<UpdateModeParams>,
+        allowed_mint_extensions: u64,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.allowed_mint_extensions = allowed_mint_extensions;
+
+        emit!(AllowedMintExtensionsSet {
+            mode_id: mode.mode_id,
+            allowed_mint_extensions,
+        });
+
+        Ok(())
+    }
+
+    /// Set the minimum USD value of a position's free collateral (admin only)
+    ///
+    /// For volatile collateral mints. collateral_vault values free collateral
+    /// with the Pyth price of health_feed_id: withdrawal requests that would
+    /// leave a position with live reservations below min_free_collateral_usd
+    /// fail, and flag_undercollateralized blocks new reservations on positions
+    /// already below it. min_free_collateral_usd is in PEG_DECIMALS; 0 = off.
+    pub fn set_collateral_health(
+        ctx: Context<UpdateModeParams>,
+        health_feed_id: [u8; 32],
+        min_free_collateral_usd: u64,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.health_feed_id = health_feed_id;
+        mode.min_free_collateral_usd = min_free_collateral_usd;
+
+        emit!(CollateralHealthConfigured {
+            mode_id: mode.mode_id,
+            health_feed_id,
+            min_free_collateral_usd,
+        });
+
+        Ok(())
+    }
+
+    /// Set the fee providers earn on collateral reserved for a session (admin only)
+    ///
+    /// reservation_fee_bps of a session's reserved amount accrues per
+    /// RESERVATION_FEE_PERIOD_SLOTS, pro rata per slot, from ack until the
+    /// reservation is released. session_escrow snapshots the rate at ack and
+    /// pays the fee from escrow at finalize_close. 0 = no fee.
+    pub fn set_reservation_fee_bps(
+        ctx: Context<UpdateModeParams>,
+        reservation_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            reservation_fee_bps <= MAX_RESERVATION_FEE_BPS,
+            ErrorCode::ReservationFeeTooHigh
+        );
+
+        let mode = &mut ctx.accounts.mode;
+        mode.reservation_fee_bps = reservation_fee_bps;
+
+        emit!(ReservationFeeSet {
+            mode_id: mode.mode_id,
+            reservation_fee_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Require non-transferable position NFTs for new positions (admin only)
+    ///
+    /// When set, collateral_vault only opens positions (deposit, split)
+    /// whose NFT mint is a Token-2022 mint with the NonTransferable
+    /// extension, binding the position to the operator that opened it.
+    /// Existing positions keep the NFT they were minted with.
+    pub fn set_soulbound_position_nft(
+        ctx: Context<UpdateModeParams>,
+        soulbound: bool,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.soulbound_position_nft = soulbound;
+
+        emit!(SoulboundPositionNftSet {
+            mode_id: mode.mode_id,
+            soulbound,
+        });
+
+        Ok(())
+    }
+
+    /// Set the collateral unbonding period (admin only)
+    ///
+    /// Enforced by collateral_vault: execute_withdraw only succeeds
+    /// unbonding_slots after request_withdraw, so claims can still land on a
+    /// provider that exits right after a bad session. 0 = no delay.
+    pub fn set_unbonding_slots(
+        ctx: Context<UpdateModeParams>,
+        unbonding_slots: u64,
+    ) -> Result<()> {
+        let mode = &mut ctx.accounts.mode;
+        mode.unbonding_slots = unbonding_slots;
+
+        emit!(UnbondingSlotsSet {
+            mode_id: mode.mode_id,
+            unbonding_slots,
+        });
+
+        Ok(())
+    }
+
+    /// Set session term bounds for open_session_from_mode (admin only)
+    ///
+    /// Bid sessions opened from this mode must use a bucket size within
+    /// [min_bucket_slots, max_bucket_slots] and a premium <= max_premium_bps.
+    pub fn set_session_bounds(
+        ctx: Context<UpdateModeParams>,
+        min_bucket_slots: u64,
+        max_bucket_slots: u64,
+        max_premium_bps: u16,
+    ) -> Result<()> {
+        require!(
+            min_bucket_slots > 0 && min_bucket_slots <= max_bucket_slots,
+            ErrorCode::InvalidSessionBounds
+        );
+        require!(max_premium_bps <= 10_000, ErrorCode::InvalidSessionBounds);
+
+        let mode = &mut ctx.accounts.mode;
+        mode.min_bucket_slots = min_bucket_slots;
+        mode.max_bucket_slots = max_bucket_slots;
+        mode.max_premium_bps = max_premium_bps;
+
+        emit!(SessionBoundsSet {
+            mode_id: mode.mode_id,
+            min_bucket_slots,
+            max_bucket_slots,
+            max_premium_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Set session timing bounds (admin only)
+    ///
+    /// Enforced by session_escrow on every open path: start deadline, stall
+    /// timeout, SLA warmup, SLA window, terminate window and max duration
+    /// must fall within [min, max] (max = 0 leaves the upper end unbounded).
+    /// A nonzero duration max also forbids sessions without a duration limit.
+    pub fn set_timing_bounds(
+        ctx: Context<UpdateModeParams>,
+        timing_bounds: SessionTimingBounds,
+    ) -> Result<()> {
+        for bounds in [
+            &timing_bounds.start_deadline,
+            &timing_bounds.stall_timeout,
+            &timing_bounds.sla_warmup,
+            &timing_bounds.sla_window,
+            &timing_bounds.terminate_window,
+            &timing_bounds.duration,
+        ] {
+            require!(
+                bounds.max == 0 || bounds.min <= bounds.max,
+                ErrorCode::InvalidSessionBounds
+            );
+        }
+
+        let mode = &mut ctx.accounts.mode;
+        mode.timing_bounds = timing_bounds;
+
+        emit!(TimingBoundsSet {
+            mode_id: mode.mode_id,
+            timing_bounds,
+        });
+
+        Ok(())
+    }
+
     /// Add a verifier to the allowlist (admin only)
     ///
     /// Verifiers are trusted parties that can submit latency attestations
@@ -215,6 +645,57 @@ pub mod mode_registry {
         Ok(())
     }
 
+    /// Rotate a verifier key to a successor (admin only)
+    ///
+    /// Replaces the old key in the allowlist and records (old -> new,
+    /// effective_slot) so session_escrow accepts the successor, and no longer
+    /// the old key, for sessions pinned to it from effective_slot onwards.
+    /// A key rotated before can be rotated again once its previous rotation
+    /// is effective; a pending one is never overwritten.
+    pub fn rotate_verifier(
+        ctx: Context<RotateVerifier>,
+        old_verifier: Pubkey,
+        new_verifier: Pubkey,
+        effective_slot: u64,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let count = registry.verifier_count as usize;
+
+        require!(
+            !registry.verifiers[..count].contains(&new_verifier),
+            ErrorCode::VerifierAlreadyExists
+        );
+        let index = registry.verifiers[..count]
+            .iter()
+            .position(|v| *v == old_verifier)
+            .ok_or(ErrorCode::VerifierNotFound)?;
+        registry.verifiers[index] = new_verifier;
+
+        let rotation = &mut ctx.accounts.verifier_rotation;
+        require!(
+            rotation.old_verifier == Pubkey::default()
+                || Clock::get()?.slot >= rotation.effective_slot,
+            ErrorCode::VerifierRotationPending
+        );
+        if rotation.old_verifier == Pubkey::default() {
+            registry.rotation_count = registry.rotation_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        rotation.old_verifier = old_verifier;
+        rotation.new_verifier = new_verifier;
+        rotation.effective_slot = effective_slot;
+        rotation.bump = ctx.bumps.verifier_rotation;
+
+        emit!(VerifierRotated {
+            old_verifier,
+            new_verifier,
+            effective_slot,
+        });
+
+        Ok(())
+    }
+
     /// Check if a pubkey is an authorized verifier
     pub fn is_verifier(ctx: Context<IsVerifier>, verifier: Pubkey) -> Result<bool> {
         let registry = &ctx.accounts.registry;
@@ -228,6 +709,120 @@ pub mod mode_registry {
         Ok(false)
     }
 
+    /// Hash the current registry, mode and verifier state into a snapshot PDA
+    ///
+    /// Permissionless. remaining_accounts holds every registered Mode account
+    /// in ascending mode_id order, followed by every VerifierRotation record
+    /// in ascending old_verifier order. The preimage also covers the pause
+    /// flag, insurance and referral settings and the vault caller allowlist.
+    /// Each call writes a new snapshot at the next sequence number, so
+    /// indexers can detect drift and prove which configuration applied at a
+    /// given slot.
+    pub fn compute_registry_snapshot(ctx: Context<ComputeRegistrySnapshot>) -> Result<()> {
+        let clock = Clock::get()?;
+        let registry = &ctx.accounts.registry;
+
+        let mode_count = registry.mode_count as usize;
+        require!(
+            ctx.remaining_accounts.len() >= mode_count,
+            ErrorCode::SnapshotModeCountMismatch
+        );
+        let (mode_infos, rotation_infos) = ctx.remaining_accounts.split_at(mode_count);
+        require!(
+            rotation_infos.len() == registry.rotation_count as usize,
+            ErrorCode::SnapshotRotationCountMismatch
+        );
+
+        let mut hasher = Hasher::default();
+        hasher.hash(&[SNAPSHOT_VERSION]);
+        hasher.hash(registry.admin.as_ref());
+        hasher.hash(&registry.mode_count.to_le_bytes());
+        hasher.hash(&[registry.verifier_count]);
+        for verifier in registry.verifiers.iter().take(registry.verifier_count as usize) {
+            hasher.hash(verifier.as_ref());
+        }
+        hasher.hash(&[registry.paused as u8]);
+        hasher.hash(registry.insurance_fund.as_ref());
+        hasher.hash(&registry.insurance_bps.to_le_bytes());
+        hasher.hash(&registry.max_referral_bps.to_le_bytes());
+
+        // Vault caller allowlist; never set = empty marker
+        let vault_callers = ctx.accounts.vault_callers.try_borrow_data()?;
+        if vault_callers.is_empty() {
+            hasher.hash(&[0]);
+        } else {
+            require!(
+                ctx.accounts.vault_callers.owner == &crate::ID,
+                ErrorCode::InvalidSnapshotVaultCallers
+            );
+            VaultCallers::try_deserialize(&mut &vault_callers[..])?;
+            hasher.hash(&[1]);
+            hasher.hash(&vault_callers[8..]);
+        }
+
+        let mut last_mode_id: Option<u32> = None;
+        for mode_info in mode_infos.iter() {
+            require!(mode_info.owner == &crate::ID, ErrorCode::InvalidSnapshotMode);
+            let data = mode_info.try_borrow_data()?;
+            let mode = Mode::try_deserialize(&mut &data[..])?;
+
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"mode", &mode.mode_id.to_le_bytes()],
+                &crate::ID,
+            );
+            require!(mode_info.key() == expected, ErrorCode::InvalidSnapshotMode);
+            if let Some(last) = last_mode_id {
+                require!(mode.mode_id > last, ErrorCode::SnapshotModesNotSorted);
+            }
+            last_mode_id = Some(mode.mode_id);
+
+            hasher.hash(&data[8..]);
+        }
+
+        let mut last_old_verifier: Option<Pubkey> = None;
+        for rotation_info in rotation_infos.iter() {
+            require!(rotation_info.owner == &crate::ID, ErrorCode::InvalidSnapshotRotation);
+            let data = rotation_info.try_borrow_data()?;
+            let rotation = VerifierRotation::try_deserialize(&mut &data[..])?;
+
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"verifier_rotation", rotation.old_verifier.as_ref()],
+                &crate::ID,
+            );
+            require!(rotation_info.key() == expected, ErrorCode::InvalidSnapshotRotation);
+            if let Some(last) = last_old_verifier {
+                require!(rotation.old_verifier > last, ErrorCode::InvalidSnapshotRotation);
+            }
+            last_old_verifier = Some(rotation.old_verifier);
+
+            hasher.hash(&data[8..]);
+        }
+
+        let snapshot_hash = hasher.result().to_bytes();
+        let sequence = registry.snapshot_seq;
+
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.sequence = sequence;
+        snapshot.version = SNAPSHOT_VERSION;
+        snapshot.snapshot_hash = snapshot_hash;
+        snapshot.slot = clock.slot;
+        snapshot.mode_count = registry.mode_count;
+        snapshot.verifier_count = registry.verifier_count;
+        snapshot.bump = ctx.bumps.snapshot;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.snapshot_seq = sequence.checked_add(1).ok_or(ErrorCode::Overflow)?;
+
+        emit!(RegistrySnapshotComputed {
+            sequence,
+            version: SNAPSHOT_VERSION,
+            snapshot_hash,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
     /// Transfer admin authority to new address
     pub fn transfer_admin(ctx: Context<TransferAdmin>, new_admin: Pubkey) -> Result<()> {
         let registry = &mut ctx.accounts.registry;
@@ -241,28 +836,106 @@ pub mod mode_registry {
 
         Ok(())
     }
-}
-
-// ============================================================================
-// Accounts
-// ============================================================================
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + Registry::INIT_SPACE,
-        seeds = [b"registry"],
-        bump
-    )]
-    pub registry: Account<'info, Registry>,
+    /// Set the protocol-wide pause flag (admin only)
+    ///
+    /// While paused, session_escrow rejects new sessions, funding and permit
+    /// redemption. Refunds, claims and closes are unaffected so funds are
+    /// never locked by a pause.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.paused = paused;
 
-    #[account(mut)]
-    pub admin: Signer<'info>,
+        emit!(ProtocolPauseSet { paused });
 
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
+
+    /// Route a slice of session claim slashes to the protocol insurance fund (admin only)
+    ///
+    /// `insurance_fund` owns the fund's token accounts (one per mode mint).
+    /// Capped at MAX_INSURANCE_BPS so claimants always receive most of a slash.
+    pub fn set_insurance_fund(
+        ctx: Context<SetInsuranceFund>,
+        insurance_fund: Pubkey,
+        insurance_bps: u16,
+    ) -> Result<()> {
+        require!(insurance_bps <= MAX_INSURANCE_BPS, ErrorCode::InsuranceBpsTooHigh);
+        require!(
+            insurance_bps == 0 || insurance_fund != Pubkey::default(),
+            ErrorCode::InvalidInsuranceFund
+        );
+
+        let registry = &mut ctx.accounts.registry;
+        registry.insurance_fund = insurance_fund;
+        registry.insurance_bps = insurance_bps;
+
+        emit!(InsuranceFundSet {
+            insurance_fund,
+            insurance_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Bound the referral share sessions may pay out of spend (admin only)
+    ///
+    /// 0 disables referrals for new configurations. Capped at MAX_REFERRAL_BPS.
+    pub fn set_max_referral_bps(ctx: Context<SetMaxReferralBps>, max_referral_bps: u16) -> Result<()> {
+        require!(max_referral_bps <= MAX_REFERRAL_BPS, ErrorCode::ReferralBpsTooHigh);
+
+        ctx.accounts.registry.max_referral_bps = max_referral_bps;
+
+        emit!(MaxReferralBpsSet { max_referral_bps });
+
+        Ok(())
+    }
+
+    /// Replace the programs allowed to drive collateral_vault (admin only)
+    ///
+    /// collateral_vault's reserve, release and slash_and_pay only run when
+    /// the transaction's top-level instruction belongs to one of these
+    /// programs: session_escrow, plus any program that CPIs into it (e.g.
+    /// gateway). An empty list halts those instructions.
+    pub fn set_vault_callers(ctx: Context<SetVaultCallers>, programs: Vec<Pubkey>) -> Result<()> {
+        require!(programs.len() <= MAX_VAULT_CALLERS, ErrorCode::InvalidVaultCallers);
+        for (i, program) in programs.iter().enumerate() {
+            require!(*program != Pubkey::default(), ErrorCode::InvalidVaultCallers);
+            require!(!programs[..i].contains(program), ErrorCode::InvalidVaultCallers);
+        }
+
+        let vault_callers = &mut ctx.accounts.vault_callers;
+        vault_callers.programs = [Pubkey::default(); MAX_VAULT_CALLERS];
+        vault_callers.programs[..programs.len()].copy_from_slice(&programs);
+        vault_callers.count = programs.len() as u8;
+        vault_callers.bump = ctx.bumps.vault_callers;
+
+        emit!(VaultCallersSet { programs });
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Accounts
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Registry::INIT_SPACE,
+        seeds = [b"registry"],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
 #[instruction(mode_id: u32)]
@@ -347,6 +1020,80 @@ pub struct UpdateModeParams<'info> {
     pub admin: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SetLpCollateral<'info> {
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        mut,
+        seeds = [b"mode", &mode.mode_id.to_le_bytes()],
+        bump = mode.bump
+    )]
+    pub mode: Account<'info, Mode>,
+
+    /// Pool reserve token accounts backing the LP mint
+    pub reserve_a: Account<'info, anchor_spl::token::TokenAccount>,
+    pub reserve_b: Account<'info, anchor_spl::token::TokenAccount>,
+
+    #[account(address = reserve_a.mint)]
+    pub mint_a: Account<'info, anchor_spl::token::Mint>,
+
+    #[account(address = reserve_b.mint)]
+    pub mint_b: Account<'info, anchor_spl::token::Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + LpPoolConfig::INIT_SPACE,
+        seeds = [b"lp_pool", &mode.mode_id.to_le_bytes()],
+        bump
+    )]
+    pub lp_pool: Account<'info, LpPoolConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCollateralLeg<'info> {
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        seeds = [b"mode", &mode.mode_id.to_le_bytes()],
+        bump = mode.bump
+    )]
+    pub mode: Account<'info, Mode>,
+
+    /// Secondary collateral mint being approved
+    pub leg_mint: Account<'info, anchor_spl::token::Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + CollateralLegConfig::INIT_SPACE,
+        seeds = [b"leg_cfg", &mode.mode_id.to_le_bytes(), leg_mint.key().as_ref()],
+        bump
+    )]
+    pub leg_config: Account<'info, CollateralLegConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct AddVerifier<'info> {
     #[account(
@@ -373,6 +1120,32 @@ pub struct RemoveVerifier<'info> {
     pub admin: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(old_verifier: Pubkey)]
+pub struct RotateVerifier<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + VerifierRotation::INIT_SPACE,
+        seeds = [b"verifier_rotation", old_verifier.as_ref()],
+        bump
+    )]
+    pub verifier_rotation: Account<'info, VerifierRotation>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct IsVerifier<'info> {
     #[account(
@@ -382,6 +1155,37 @@ pub struct IsVerifier<'info> {
     pub registry: Account<'info, Registry>,
 }
 
+#[derive(Accounts)]
+pub struct ComputeRegistrySnapshot<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    /// CHECK: ["vault_callers"] PDA; hashed when initialized
+    #[account(
+        seeds = [b"vault_callers"],
+        bump
+    )]
+    pub vault_callers: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RegistrySnapshot::INIT_SPACE,
+        seeds = [b"snapshot", &registry.snapshot_seq.to_le_bytes()],
+        bump
+    )]
+    pub snapshot: Account<'info, RegistrySnapshot>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct TransferAdmin<'info> {
     #[account(
@@ -395,6 +1199,69 @@ pub struct TransferAdmin<'info> {
     pub admin: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInsuranceFund<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxReferralBps<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultCallers<'info> {
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + VaultCallers::INIT_SPACE,
+        seeds = [b"vault_callers"],
+        bump
+    )]
+    pub vault_callers: Account<'info, VaultCallers>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 // ============================================================================
 // State
 // ============================================================================
@@ -413,6 +1280,69 @@ pub struct Registry {
     pub verifiers: [Pubkey; MAX_VERIFIERS],
     /// PDA bump
     pub bump: u8,
+    /// Sequence number of the next registry snapshot
+    pub snapshot_seq: u64,
+    /// Protocol-wide pause: blocks new session activity, never exits
+    pub paused: bool,
+    /// Owner of the protocol insurance fund token accounts
+    pub insurance_fund: Pubkey,
+    /// Share of every session claim slash routed to the insurance fund
+    pub insurance_bps: u16,
+    /// Maximum referral share a session may pay its referrer
+    pub max_referral_bps: u16,
+    /// Number of VerifierRotation records created
+    pub rotation_count: u32,
+}
+
+/// Programs allowed to drive collateral_vault reserve/release/slash
+#[account]
+#[derive(InitSpace)]
+pub struct VaultCallers {
+    /// Allowlisted top-level programs
+    pub programs: [Pubkey; MAX_VAULT_CALLERS],
+    /// Number of entries in use
+    pub count: u8,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl VaultCallers {
+    pub fn contains(&self, program: &Pubkey) -> bool {
+        self.programs[..self.count as usize].contains(program)
+    }
+}
+
+/// Successor record for a rotated verifier key
+#[account]
+#[derive(InitSpace)]
+pub struct VerifierRotation {
+    /// Retired verifier key
+    pub old_verifier: Pubkey,
+    /// Successor accepted for sessions pinned to old_verifier
+    pub new_verifier: Pubkey,
+    /// Slot from which the successor is accepted
+    pub effective_slot: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RegistrySnapshot {
+    /// Monotonic snapshot sequence number
+    pub sequence: u64,
+    /// Preimage layout version (SNAPSHOT_VERSION at time of capture)
+    pub version: u8,
+    /// sha256 over registry, verifier, rotation, vault caller and mode state
+    pub snapshot_hash: [u8; 32],
+    /// Slot the snapshot was taken at
+    pub slot: u64,
+    /// Number of modes hashed
+    pub mode_count: u32,
+    /// Number of verifiers hashed
+    pub verifier_count: u8,
+    /// PDA bump
+    pub bump: u8,
 }
 
 #[account]
@@ -428,7 +1358,8 @@ pub struct Mode {
     pub per_provider_cap: u64,
     /// Maximum total collateral across all providers
     pub global_cap: u64,
-    /// Current total deposited across all providers
+    /// Current total deposited across all providers (unused; collateral_vault
+    /// enforces global_cap against its mode vault's total_assets)
     pub global_deposited: u64,
     /// Slot after which mode can be activated
     pub activation_slot: u64,
@@ -436,10 +1367,107 @@ pub struct Mode {
     pub is_active: bool,
     /// Whether mode has been disabled (blocks new activity)
     pub is_disabled: bool,
+    /// Whether the mode mint is an allowlisted LP token
+    pub is_lp_collateral: bool,
+    /// Haircut applied to LP fair value in basis points
+    pub lp_haircut_bps: u16,
+    /// Maximum concurrently active sessions per provider (0 = unlimited)
+    pub max_concurrent_sessions: u32,
+    /// Pyth feed for the stablecoin peg check
+    pub peg_feed_id: [u8; 32],
+    /// Maximum deviation from $1.00 in basis points (0 = not a stablecoin)
+    pub peg_max_dev_bps: u16,
+    /// Slots after close_session during which claims can still be filed
+    pub close_dispute_window_slots: u64,
+    /// Minimum SLA bucket size for sessions opened from this mode
+    pub min_bucket_slots: u64,
+    /// Maximum SLA bucket size for sessions opened from this mode
+    pub max_bucket_slots: u64,
+    /// Maximum bid premium in basis points
+    pub max_premium_bps: u16,
+    /// Slots without progress after which a session can be expired (0 = never)
+    pub max_session_slots: u64,
+    /// Allowed ranges for session timing parameters
+    pub timing_bounds: SessionTimingBounds,
+    /// Slots between a collateral withdrawal request and its execution
+    pub unbonding_slots: u64,
+    /// Allowlisted yield adapter program for idle collateral (default = none)
+    pub yield_adapter: Pubkey,
+    /// Token-2022 mint extensions accepted for collateral (bit n = ExtensionType n)
+    pub allowed_mint_extensions: u64,
+    /// Provider fee on reserved collateral, in bps per RESERVATION_FEE_PERIOD_SLOTS
+    pub reservation_fee_bps: u16,
+    /// Pyth USD feed for the mode mint used by collateral health checks
+    pub health_feed_id: [u8; 32],
+    /// Minimum USD value of a position's free collateral (PEG_DECIMALS; 0 = off)
+    pub min_free_collateral_usd: u64,
+    /// New position NFTs must be Token-2022 non-transferable mints
+    pub soulbound_position_nft: bool,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// Constant-product pool whose LP token is a mode's mint
+#[account]
+#[derive(InitSpace)]
+pub struct LpPoolConfig {
+    /// LP-token mode
+    pub mode_id: u32,
+    /// Pool reserve token accounts; fixed on approval
+    pub reserve_a: Pubkey,
+    pub reserve_b: Pubkey,
+    /// Reserve mint decimals
+    pub decimals_a: u8,
+    pub decimals_b: u8,
+    /// Pyth USD feeds valuing each reserve
+    pub feed_id_a: [u8; 32],
+    pub feed_id_b: [u8; 32],
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// Secondary collateral mint approved for a mode's positions
+#[account]
+#[derive(InitSpace)]
+pub struct CollateralLegConfig {
+    /// Mode whose positions may post this mint
+    pub mode_id: u32,
+    /// Secondary collateral mint
+    pub mint: Pubkey,
+    /// Pyth USD feed used to value the mint
+    pub feed_id: [u8; 32],
+    /// Haircut applied to the leg's USD value in basis points
+    pub haircut_bps: u16,
+    /// Slash order across legs (lower first); fixed on approval
+    pub priority: u8,
     /// PDA bump
     pub bump: u8,
 }
 
+/// Inclusive slot range; max = 0 means no upper bound
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SlotBounds {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SlotBounds {
+    pub fn contains(&self, slots: u64) -> bool {
+        slots >= self.min && (self.max == 0 || slots <= self.max)
+    }
+}
+
+/// Per-mode bounds on the timing terms a session may be opened with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SessionTimingBounds {
+    pub start_deadline: SlotBounds,
+    pub stall_timeout: SlotBounds,
+    pub sla_warmup: SlotBounds,
+    pub sla_window: SlotBounds,
+    pub terminate_window: SlotBounds,
+    pub duration: SlotBounds,
+}
+
 // ============================================================================
 // Events
 // ============================================================================
@@ -476,6 +1504,97 @@ pub struct ModeParamsUpdated {
     pub global_cap: u64,
 }
 
+#[event]
+pub struct LpCollateralConfigured {
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub lp_haircut_bps: u16,
+}
+
+#[event]
+pub struct StablePegConfigured {
+    pub mode_id: u32,
+    pub peg_feed_id: [u8; 32],
+    pub peg_max_dev_bps: u16,
+}
+
+#[event]
+pub struct MaxConcurrentSessionsSet {
+    pub mode_id: u32,
+    pub max_concurrent_sessions: u32,
+}
+
+#[event]
+pub struct CloseDisputeWindowSet {
+    pub mode_id: u32,
+    pub close_dispute_window_slots: u64,
+}
+
+#[event]
+pub struct MaxSessionSlotsSet {
+    pub mode_id: u32,
+    pub max_session_slots: u64,
+}
+
+#[event]
+pub struct CollateralLegConfigured {
+    pub mode_id: u32,
+    pub mint: Pubkey,
+    pub haircut_bps: u16,
+    pub priority: u8,
+}
+
+#[event]
+pub struct YieldAdapterSet {
+    pub mode_id: u32,
+    pub yield_adapter: Pubkey,
+}
+
+#[event]
+pub struct AllowedMintExtensionsSet {
+    pub mode_id: u32,
+    pub allowed_mint_extensions: u64,
+}
+
+#[event]
+pub struct CollateralHealthConfigured {
+    pub mode_id: u32,
+    pub health_feed_id: [u8; 32],
+    pub min_free_collateral_usd: u64,
+}
+
+#[event]
+pub struct ReservationFeeSet {
+    pub mode_id: u32,
+    pub reservation_fee_bps: u16,
+}
+
+#[event]
+pub struct SoulboundPositionNftSet {
+    pub mode_id: u32,
+    pub soulbound: bool,
+}
+
+#[event]
+pub struct UnbondingSlotsSet {
+    pub mode_id: u32,
+    pub unbonding_slots: u64,
+}
+
+#[event]
+pub struct SessionBoundsSet {
+    pub mode_id: u32,
+    pub min_bucket_slots: u64,
+    pub max_bucket_slots: u64,
+    pub max_premium_bps: u16,
+}
+
+#[event]
+pub struct TimingBoundsSet {
+    pub mode_id: u32,
+    pub timing_bounds: SessionTimingBounds,
+}
+
 #[event]
 pub struct VerifierAdded {
     pub verifier: Pubkey,
@@ -486,12 +1605,48 @@ pub struct VerifierRemoved {
     pub verifier: Pubkey,
 }
 
+#[event]
+pub struct VerifierRotated {
+    pub old_verifier: Pubkey,
+    pub new_verifier: Pubkey,
+    pub effective_slot: u64,
+}
+
+#[event]
+pub struct RegistrySnapshotComputed {
+    pub sequence: u64,
+    pub version: u8,
+    pub snapshot_hash: [u8; 32],
+    pub slot: u64,
+}
+
 #[event]
 pub struct AdminTransferred {
     pub old_admin: Pubkey,
     pub new_admin: Pubkey,
 }
 
+#[event]
+pub struct ProtocolPauseSet {
+    pub paused: bool,
+}
+
+#[event]
+pub struct InsuranceFundSet {
+    pub insurance_fund: Pubkey,
+    pub insurance_bps: u16,
+}
+
+#[event]
+pub struct MaxReferralBpsSet {
+    pub max_referral_bps: u16,
+}
+
+#[event]
+pub struct VaultCallersSet {
+    pub programs: Vec<Pubkey>,
+}
+
 // ============================================================================
 // Errors
 // ============================================================================
@@ -524,4 +1679,42 @@ pub enum ErrorCode {
     VerifierAlreadyExists,
     #[msg("Verifier not found")]
     VerifierNotFound,
+    #[msg("LP haircut too high (max 90%)")]
+    LpHaircutTooHigh,
+    #[msg("Cannot reduce LP haircut")]
+    CannotReduceLpHaircut,
+    #[msg("Snapshot requires every registered mode")]
+    SnapshotModeCountMismatch,
+    #[msg("Invalid mode account for snapshot")]
+    InvalidSnapshotMode,
+    #[msg("Snapshot modes must be in ascending mode_id order")]
+    SnapshotModesNotSorted,
+    #[msg("Peg bound exceeds 100%")]
+    InvalidPegBound,
+    #[msg("Invalid session term bounds")]
+    InvalidSessionBounds,
+    #[msg("Insurance share too high (max 20%)")]
+    InsuranceBpsTooHigh,
+    #[msg("Insurance fund owner required")]
+    InvalidInsuranceFund,
+    #[msg("Referral share too high (max 10%)")]
+    ReferralBpsTooHigh,
+    #[msg("Collateral leg mint must differ from the mode mint")]
+    InvalidCollateralLeg,
+    #[msg("Invalid vault caller list")]
+    InvalidVaultCallers,
+    #[msg("Reservation fee too high (max 1% per fee period)")]
+    ReservationFeeTooHigh,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Verifier already has a pending rotation")]
+    VerifierRotationPending,
+    #[msg("Invalid LP pool reserves")]
+    InvalidLpPool,
+    #[msg("Snapshot requires every verifier rotation record")]
+    SnapshotRotationCountMismatch,
+    #[msg("Invalid or unsorted verifier rotation account for snapshot")]
+    InvalidSnapshotRotation,
+    #[msg("Invalid vault callers account for snapshot")]
+    InvalidSnapshotVaultCallers,
 }
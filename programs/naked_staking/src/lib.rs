@@ -1,5 +1,5 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, MintTo};
+use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer, MintTo};
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 declare_id!("NakedStk1111111111111111111111111111111111");
@@ -37,6 +37,22 @@ pub mod naked_staking {
     
     /// Default min stake duration (~2 minutes, flash loan protection)
     pub const DEFAULT_MIN_STAKE_DURATION: u64 = 300;
+    
+    /// Max share of a provider slash absorbed by its delegators (50%)
+    pub const MAX_DELEGATOR_SLASH_SHARE_BPS: u16 = 5_000;
+    
+    /// Number of epochs (current + future) that can hold a funded budget
+    pub const MAX_FUNDED_EPOCHS: usize = 8;
+    
+    /// Minimum epoch length (~1 day), bounds epoch catch-up per update
+    pub const MIN_EPOCH_LENGTH_SLOTS: u64 = 216_000;
+    
+    /// Maximum early-unstake penalty (20%, charged at the start of the lock)
+    pub const MAX_EARLY_UNSTAKE_PENALTY_BPS: u16 = 2_000;
+    
+    /// collateral_vault program, whose provider positions backings stand behind
+    pub const COLLATERAL_VAULT_PROGRAM_ID: Pubkey =
+        anchor_lang::solana_program::pubkey!("CoVau1t111111111111111111111111111111111111");
 
     // ========================================================================
     // Section 2: Initialize Pool
@@ -100,8 +116,21 @@ pub mod naked_staking {
         pool.deposit_cap = deposit_cap; // 0 = unlimited
         pool.paused = false;
         
+        // Delegation (delegators absorb no slashes until configured)
+        pool.delegator_slash_share_bps = 0;
+        
+        // Claims (no minimum interval until configured)
+        pool.min_claim_interval_slots = 0;
+        
+        // Epoch budgets (disabled until enable_epoch_budgets)
+        pool.epoch_budgets_enabled = false;
+        
+        // Early-unstake penalty (off until configured)
+        pool.early_unstake_penalty_bps = 0;
+        pool.penalty_lock_slots = 0;
+        
         // Reserved (zero-initialized by default)
-        pool.reserved = [0u8; 32];
+        pool.reserved = [0u8; 11];
         
         emit!(NativePoolInitialized {
             authority: pool.authority,
@@ -148,7 +177,7 @@ pub mod naked_staking {
         
         // Update pool rewards accumulator BEFORE changing weights
         let pool = &mut ctx.accounts.pool;
-        update_reward_accumulator(pool, &clock)?;
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
         
         // Get or create position
         let position = &mut ctx.accounts.position;
@@ -160,7 +189,8 @@ pub mod naked_staking {
             position.bump = ctx.bumps.position;
             position.staked_at_slot = clock.slot;
             position.pending_rewards = 0;
-            position.reserved = [0u8; 16];
+            position.delegated_amount = 0;
+            position.reserved = [0u8; 8];
         } else {
             // Accrue pending rewards before weight change
             accrue_position_rewards(pool, position)?;
@@ -227,6 +257,12 @@ pub mod naked_staking {
             NakedStakingError::InsufficientStake
         );
         
+        // Delegated stake must be undelegated before it can leave the vault
+        require!(
+            amount <= position.amount.saturating_sub(position.delegated_amount),
+            NakedStakingError::StakeDelegated
+        );
+        
         // Load and validate Pyth price
         let price_data = &ctx.accounts.pyth_feed;
         let pool = &ctx.accounts.pool;
@@ -234,7 +270,7 @@ pub mod naked_staking {
         
         // Update pool rewards accumulator
         let pool = &mut ctx.accounts.pool;
-        update_reward_accumulator(pool, &clock)?;
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
         
         // Accrue pending rewards
         let position = &mut ctx.accounts.position;
@@ -254,11 +290,6 @@ pub mod naked_staking {
         // Update position
         position.amount = new_amount;
         position.weight = new_weight;
-        position.reward_debt = new_weight
-            .checked_mul(pool.reward_per_share)
-            .ok_or(NakedStakingError::Overflow)?
-            .checked_div(PRECISION)
-            .ok_or(NakedStakingError::Overflow)?;
         
         // Update pool totals
         pool.total_staked = pool.total_staked
@@ -269,10 +300,37 @@ pub mod naked_staking {
             .checked_add(new_weight)
             .ok_or(NakedStakingError::Overflow)?;
         
+        // Early-unstake penalty goes to the remaining stakers; waived when
+        // nobody else is left to receive it
+        let penalty = if pool.total_weight > new_weight {
+            early_unstake_penalty(pool, amount, slots_since_stake)?
+        } else {
+            0
+        };
+        if penalty > 0 {
+            let others_weight = pool.total_weight - new_weight;
+            let increment = (penalty as u128)
+                .checked_mul(PRECISION)
+                .ok_or(NakedStakingError::Overflow)?
+                .checked_div(others_weight)
+                .ok_or(NakedStakingError::Overflow)?;
+            pool.reward_per_share = pool.reward_per_share
+                .checked_add(increment)
+                .ok_or(NakedStakingError::Overflow)?;
+        }
+        // Debt at the post-penalty accumulator: the unstaker's remaining
+        // weight does not share its own penalty
+        position.reward_debt = new_weight
+            .checked_mul(pool.reward_per_share)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(PRECISION)
+            .ok_or(NakedStakingError::Overflow)?;
+        
         // Transfer tokens back to user
         let bump = pool.bump;
         let seeds = &[b"native_pool".as_ref(), &[bump]];
         let signer_seeds = &[&seeds[..]];
+        let epoch_budgets = pool.epoch_budgets_enabled;
         
         let cpi_accounts = Transfer {
             from: ctx.accounts.vault.to_account_info(),
@@ -284,13 +342,50 @@ pub mod naked_staking {
             cpi_accounts,
             signer_seeds,
         );
-        token::transfer(cpi_ctx, amount)?;
+        token::transfer(cpi_ctx, amount - penalty)?;
+        
+        // Back the penalty's rewards: into the reward vault under epoch
+        // budgets, otherwise burned since claims mint
+        if penalty > 0 {
+            if epoch_budgets {
+                let reward_vault = ctx.accounts.reward_vault
+                    .as_ref()
+                    .ok_or(NakedStakingError::RewardVaultRequired)?;
+                let cpi_accounts = Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: reward_vault.to_account_info(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                token::transfer(cpi_ctx, penalty)?;
+            } else {
+                let native_mint = ctx.accounts.native_mint
+                    .as_ref()
+                    .ok_or(NakedStakingError::NativeMintRequired)?;
+                let cpi_accounts = Burn {
+                    mint: native_mint.to_account_info(),
+                    from: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds,
+                );
+                token::burn(cpi_ctx, penalty)?;
+            }
+        }
         
         emit!(NativeUnstaked {
             user: ctx.accounts.user.key(),
             amount,
             remaining: position.amount,
             weight: new_weight,
+            penalty,
         });
         
         Ok(())
@@ -305,7 +400,7 @@ pub mod naked_staking {
         
         // Update pool rewards accumulator
         let pool = &mut ctx.accounts.pool;
-        update_reward_accumulator(pool, &clock)?;
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
         
         // Accrue and claim
         let position = &mut ctx.accounts.position;
@@ -314,6 +409,12 @@ pub mod naked_staking {
         let rewards_to_claim = position.pending_rewards;
         require!(rewards_to_claim > 0, NakedStakingError::NoRewardsToClaim);
         
+        // Rate-limit claims; rewards keep accruing in pending_rewards meanwhile
+        require!(
+            clock.slot.saturating_sub(position.last_claim_slot) >= pool.min_claim_interval_slots,
+            NakedStakingError::ClaimTooSoon
+        );
+        
         // Reset pending
         position.pending_rewards = 0;
         position.last_claim_slot = clock.slot;
@@ -335,17 +436,36 @@ pub mod naked_staking {
             .try_into()
             .map_err(|_| NakedStakingError::Overflow)?;
         
-        let cpi_accounts = MintTo {
-            mint: ctx.accounts.reward_mint.to_account_info(),
-            to: ctx.accounts.user_reward_account.to_account_info(),
-            authority: ctx.accounts.pool.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-        token::mint_to(cpi_ctx, rewards_u64)?;
+        if pool.epoch_budgets_enabled {
+            // Pre-funded budgets: pay out of the reward vault
+            let reward_vault = ctx.accounts.reward_vault
+                .as_ref()
+                .ok_or(NakedStakingError::RewardVaultRequired)?;
+            
+            let cpi_accounts = Transfer {
+                from: reward_vault.to_account_info(),
+                to: ctx.accounts.user_reward_account.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::transfer(cpi_ctx, rewards_u64)?;
+        } else {
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.user_reward_account.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            token::mint_to(cpi_ctx, rewards_u64)?;
+        }
         
         emit!(NativeRewardsClaimed {
             user: ctx.accounts.user.key(),
@@ -396,6 +516,445 @@ pub mod naked_staking {
         emit!(DepositCapUpdated { new_cap });
         Ok(())
     }
+    
+    pub fn update_min_claim_interval(ctx: Context<AdminUpdate>, slots: u64) -> Result<()> {
+        ctx.accounts.pool.min_claim_interval_slots = slots;
+        emit!(MinClaimIntervalUpdated { slots });
+        Ok(())
+    }
+    
+    pub fn set_delegator_slash_share(ctx: Context<AdminUpdate>, slash_share_bps: u16) -> Result<()> {
+        require!(
+            slash_share_bps <= MAX_DELEGATOR_SLASH_SHARE_BPS,
+            NakedStakingError::InvalidSlashShareBps
+        );
+        ctx.accounts.pool.delegator_slash_share_bps = slash_share_bps;
+        emit!(DelegatorSlashShareUpdated { slash_share_bps });
+        Ok(())
+    }
+    
+    /// Configure the early-unstake penalty
+    ///
+    /// Unstaking within `lock_slots` of the last stake forfeits
+    /// `penalty_bps` of the amount, scaled by the share of the lock still
+    /// remaining. The forfeit is added to reward_per_share for the remaining
+    /// stakers, so it needs the staked token to be the reward token.
+    /// penalty_bps = 0 disables it.
+    pub fn set_early_unstake_penalty(
+        ctx: Context<AdminUpdate>,
+        penalty_bps: u16,
+        lock_slots: u64,
+    ) -> Result<()> {
+        require!(
+            penalty_bps <= MAX_EARLY_UNSTAKE_PENALTY_BPS,
+            NakedStakingError::InvalidPenaltyBps
+        );
+        let pool = &mut ctx.accounts.pool;
+        if penalty_bps > 0 {
+            require!(lock_slots > 0, NakedStakingError::InvalidPenaltyBps);
+            require!(
+                pool.native_mint == pool.reward_mint,
+                NakedStakingError::PenaltyRequiresNativeRewards
+            );
+        }
+        pool.early_unstake_penalty_bps = penalty_bps;
+        pool.penalty_lock_slots = lock_slots;
+        emit!(EarlyUnstakePenaltyUpdated { penalty_bps, lock_slots });
+        Ok(())
+    }
+
+    // ========================================================================
+    // Section 7: Delegated Staking
+    // ========================================================================
+    
+    /// Provider opts in to receiving delegated stake
+    ///
+    /// The backing stands behind the provider's collateral_vault position in
+    /// `mode_id`; the position PDA is derived from the signing provider, so
+    /// only the position's owner can open it.
+    pub fn init_provider_backing(ctx: Context<InitProviderBacking>, mode_id: u32) -> Result<()> {
+        let backing = &mut ctx.accounts.backing;
+        backing.pool = ctx.accounts.pool.key();
+        backing.provider = ctx.accounts.provider.key();
+        backing.bump = ctx.bumps.backing;
+        backing.total_delegated = 0;
+        backing.total_shares = 0;
+        backing.fee_per_share = 0;
+        backing.total_fees_distributed = 0;
+        backing.total_slashed = 0;
+        backing.position = ctx.accounts.provider_position.key();
+        backing.mode_id = mode_id;
+        backing.share_epoch = 0;
+        backing.last_epoch_fee_per_share = 0;
+        
+        emit!(ProviderBackingInitialized {
+            provider: backing.provider,
+            backing: ctx.accounts.backing.key(),
+        });
+        
+        Ok(())
+    }
+    
+    /// Route part of a staked position to back a provider
+    ///
+    /// Delegated tokens stay in the vault and keep earning native rewards, but
+    /// cannot be unstaked until undelegated.
+    pub fn delegate_stake(ctx: Context<DelegateStake>, amount: u64) -> Result<()> {
+        require!(amount > 0, NakedStakingError::ZeroAmount);
+        require!(!ctx.accounts.pool.paused, NakedStakingError::PoolPaused);
+        
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let backing = &mut ctx.accounts.backing;
+        let delegation = &mut ctx.accounts.delegation;
+        
+        if delegation.owner == Pubkey::default() {
+            delegation.owner = ctx.accounts.user.key();
+            delegation.backing = backing.key();
+            delegation.bump = ctx.bumps.delegation;
+            delegation.shares = 0;
+            delegation.amount = 0;
+            delegation.fee_debt = 0;
+            delegation.pending_fees = 0;
+            delegation.share_epoch = backing.share_epoch;
+        }
+        
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
+        sync_delegation(pool, position, backing, delegation)?;
+        
+        let undelegated = position.amount.saturating_sub(position.delegated_amount);
+        require!(amount <= undelegated, NakedStakingError::InsufficientStake);
+        
+        // Mint shares at the current backing exchange rate
+        let new_shares = if backing.total_shares == 0 || backing.total_delegated == 0 {
+            amount as u128
+        } else {
+            (amount as u128)
+                .checked_mul(backing.total_shares)
+                .ok_or(NakedStakingError::Overflow)?
+                .checked_div(backing.total_delegated as u128)
+                .ok_or(NakedStakingError::Overflow)?
+        };
+        
+        delegation.shares = delegation.shares
+            .checked_add(new_shares)
+            .ok_or(NakedStakingError::Overflow)?;
+        delegation.amount = delegation.amount
+            .checked_add(amount)
+            .ok_or(NakedStakingError::Overflow)?;
+        delegation.fee_debt = delegation.shares
+            .checked_mul(backing.fee_per_share)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(naked_staking::PRECISION)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        backing.total_shares = backing.total_shares
+            .checked_add(new_shares)
+            .ok_or(NakedStakingError::Overflow)?;
+        backing.total_delegated = backing.total_delegated
+            .checked_add(amount)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        position.delegated_amount = position.delegated_amount
+            .checked_add(amount)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        emit!(StakeDelegated {
+            user: ctx.accounts.user.key(),
+            provider: backing.provider,
+            amount,
+            delegation_amount: delegation.amount,
+            backing_total: backing.total_delegated,
+        });
+        
+        Ok(())
+    }
+    
+    /// Withdraw delegated stake back to the undelegated balance
+    pub fn undelegate_stake(ctx: Context<UndelegateStake>, amount: u64) -> Result<()> {
+        require!(amount > 0, NakedStakingError::ZeroAmount);
+        
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let backing = &mut ctx.accounts.backing;
+        let delegation = &mut ctx.accounts.delegation;
+        
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
+        sync_delegation(pool, position, backing, delegation)?;
+        
+        require!(amount <= delegation.amount, NakedStakingError::InsufficientDelegation);
+        
+        // Burn shares proportional to the amount withdrawn
+        let burned_shares = if amount == delegation.amount {
+            delegation.shares
+        } else {
+            (amount as u128)
+                .checked_mul(delegation.shares)
+                .ok_or(NakedStakingError::Overflow)?
+                .checked_div(delegation.amount as u128)
+                .ok_or(NakedStakingError::Overflow)?
+        };
+        
+        delegation.shares = delegation.shares.saturating_sub(burned_shares);
+        delegation.amount = delegation.amount.saturating_sub(amount);
+        delegation.fee_debt = delegation.shares
+            .checked_mul(backing.fee_per_share)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(naked_staking::PRECISION)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        backing.total_shares = backing.total_shares.saturating_sub(burned_shares);
+        backing.total_delegated = backing.total_delegated.saturating_sub(amount);
+        reset_empty_backing(backing)?;
+        
+        position.delegated_amount = position.delegated_amount.saturating_sub(amount);
+        
+        emit!(StakeUndelegated {
+            user: ctx.accounts.user.key(),
+            provider: backing.provider,
+            amount,
+            delegation_amount: delegation.amount,
+            backing_total: backing.total_delegated,
+        });
+        
+        Ok(())
+    }
+    
+    /// Provider shares session fees with its delegators (paid in native token)
+    pub fn distribute_provider_fees(ctx: Context<DistributeProviderFees>, amount: u64) -> Result<()> {
+        require!(amount > 0, NakedStakingError::ZeroAmount);
+        require!(
+            ctx.accounts.backing.total_shares > 0,
+            NakedStakingError::NoDelegations
+        );
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.provider_token_account.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+        
+        let backing = &mut ctx.accounts.backing;
+        let increment = (amount as u128)
+            .checked_mul(naked_staking::PRECISION)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(backing.total_shares)
+            .ok_or(NakedStakingError::Overflow)?;
+        backing.fee_per_share = backing.fee_per_share
+            .checked_add(increment)
+            .ok_or(NakedStakingError::Overflow)?;
+        backing.total_fees_distributed = backing.total_fees_distributed
+            .checked_add(amount)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        emit!(ProviderFeesDistributed {
+            provider: backing.provider,
+            amount,
+            fee_per_share: backing.fee_per_share,
+        });
+        
+        Ok(())
+    }
+    
+    /// Claim accrued provider fee share for a delegation
+    pub fn claim_delegation_fees(ctx: Context<ClaimDelegationFees>) -> Result<()> {
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let backing = &mut ctx.accounts.backing;
+        let delegation = &mut ctx.accounts.delegation;
+        
+        update_reward_accumulator(pool, ctx.accounts.reward_epochs.as_mut(), &clock)?;
+        sync_delegation(pool, position, backing, delegation)?;
+        
+        let fees = delegation.pending_fees;
+        require!(fees > 0, NakedStakingError::NoRewardsToClaim);
+        delegation.pending_fees = 0;
+        
+        let fees_u64: u64 = fees
+            .try_into()
+            .map_err(|_| NakedStakingError::Overflow)?;
+        
+        let bump = pool.bump;
+        let seeds = &[b"native_pool".as_ref(), &[bump]];
+        let signer_seeds = &[&seeds[..]];
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.pool.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token::transfer(cpi_ctx, fees_u64)?;
+        
+        emit!(DelegationFeesClaimed {
+            user: ctx.accounts.user.key(),
+            provider: ctx.accounts.backing.provider,
+            amount: fees_u64,
+        });
+        
+        Ok(())
+    }
+    
+    /// Cover part of a collateral_vault slash from the backing's delegators
+    ///
+    /// Signed by the backed position itself, so only collateral_vault can
+    /// call it (from slash_and_pay, once the position's own collateral and
+    /// legs fall short). Delegators lose at most delegator_slash_share_bps of
+    /// the current backing per call. Individual positions are written down
+    /// lazily on their next delegation interaction. Returns the loss taken.
+    pub fn slash_delegated_stake(
+        ctx: Context<SlashDelegatedStake>,
+        shortfall: u64,
+    ) -> Result<u64> {
+        let pool = &mut ctx.accounts.pool;
+        let backing = &mut ctx.accounts.backing;
+        
+        let slashable = (backing.total_delegated as u128)
+            .checked_mul(pool.delegator_slash_share_bps as u128)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(naked_staking::BPS_DENOMINATOR as u128)
+            .ok_or(NakedStakingError::Overflow)?;
+        let loss = (slashable as u64).min(shortfall);
+        require!(loss > 0, NakedStakingError::ZeroAmount);
+        
+        backing.total_delegated = backing.total_delegated.saturating_sub(loss);
+        backing.total_slashed = backing.total_slashed
+            .checked_add(loss)
+            .ok_or(NakedStakingError::Overflow)?;
+        reset_empty_backing(backing)?;
+        pool.total_staked = pool.total_staked.saturating_sub(loss);
+        
+        let bump = pool.bump;
+        let provider = backing.provider;
+        let backing_total = backing.total_delegated;
+        let seeds = &[b"native_pool".as_ref(), &[bump]];
+        let signer_seeds = &[&seeds[..]];
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: ctx.accounts.slash_recipient.to_account_info(),
+            authority: ctx.accounts.pool.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token::transfer(cpi_ctx, loss)?;
+        
+        emit!(DelegatedStakeSlashed {
+            provider,
+            shortfall,
+            delegator_loss: loss,
+            backing_total,
+        });
+        
+        Ok(loss)
+    }
+
+    // ========================================================================
+    // Section 8: Epoch Reward Budgets
+    // ========================================================================
+    
+    /// Switch the pool from an open-ended rate to pre-funded epoch budgets
+    ///
+    /// One-way. Rewards accrued so far are settled at the open-ended rate;
+    /// afterwards the accumulator only draws from funded epoch budgets.
+    pub fn enable_epoch_budgets(
+        ctx: Context<EnableEpochBudgets>,
+        epoch_length_slots: u64,
+    ) -> Result<()> {
+        require!(
+            epoch_length_slots >= MIN_EPOCH_LENGTH_SLOTS,
+            NakedStakingError::InvalidEpochLength
+        );
+        
+        let clock = Clock::get()?;
+        let reward_vault_key = ctx.accounts.reward_vault.key();
+        let pool = &mut ctx.accounts.pool;
+        
+        require!(
+            !pool.epoch_budgets_enabled,
+            NakedStakingError::EpochBudgetsAlreadyEnabled
+        );
+        
+        // Settle at the open-ended rate while the flag is still off
+        update_reward_accumulator(pool, None, &clock)?;
+        pool.epoch_budgets_enabled = true;
+        
+        let reward_epochs = &mut ctx.accounts.reward_epochs;
+        reward_epochs.pool = pool.key();
+        reward_epochs.bump = ctx.bumps.reward_epochs;
+        reward_epochs.epoch_length_slots = epoch_length_slots;
+        reward_epochs.epochs_start_slot = clock.slot;
+        reward_epochs.current_epoch = 0;
+        reward_epochs.epoch_budgets = [0u64; MAX_FUNDED_EPOCHS];
+        
+        emit!(EpochBudgetsEnabled {
+            epoch_length_slots,
+            start_slot: clock.slot,
+            reward_vault: reward_vault_key,
+        });
+        
+        Ok(())
+    }
+    
+    /// Deposit reward tokens as the budget for a current or upcoming epoch
+    pub fn fund_reward_epoch(
+        ctx: Context<FundRewardEpoch>,
+        epoch: u64,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, NakedStakingError::ZeroAmount);
+        require!(
+            ctx.accounts.pool.epoch_budgets_enabled,
+            NakedStakingError::EpochBudgetsNotEnabled
+        );
+        
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        
+        // Settle and roll to the current epoch before indexing the ring
+        update_reward_accumulator(pool, Some(&mut ctx.accounts.reward_epochs), &clock)?;
+        
+        let reward_epochs = &mut ctx.accounts.reward_epochs;
+        require!(
+            epoch >= reward_epochs.current_epoch
+                && epoch < reward_epochs.current_epoch.saturating_add(MAX_FUNDED_EPOCHS as u64),
+            NakedStakingError::EpochOutOfRange
+        );
+        
+        let idx = (epoch % MAX_FUNDED_EPOCHS as u64) as usize;
+        reward_epochs.epoch_budgets[idx] = reward_epochs.epoch_budgets[idx]
+            .checked_add(amount)
+            .ok_or(NakedStakingError::Overflow)?;
+        let epoch_budget = reward_epochs.epoch_budgets[idx];
+        
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.funder_token_account.to_account_info(),
+            to: ctx.accounts.reward_vault.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+        
+        emit!(RewardEpochFunded {
+            epoch,
+            amount,
+            epoch_budget,
+        });
+        
+        Ok(())
+    }
 }
 
 // ============================================================================
@@ -403,10 +962,26 @@ pub mod naked_staking {
 // ============================================================================
 
 /// Update the global reward accumulator based on slots elapsed
-fn update_reward_accumulator(pool: &mut Account<NativeStakePool>, clock: &Clock) -> Result<()> {
+///
+/// `reward_epochs` is required once epoch budgets are enabled.
+fn update_reward_accumulator(
+    pool: &mut Account<NativeStakePool>,
+    reward_epochs: Option<&mut Account<RewardEpochs>>,
+    clock: &Clock,
+) -> Result<()> {
     let current_slot = clock.slot;
     
+    let mut reward_epochs = if pool.epoch_budgets_enabled {
+        Some(reward_epochs.ok_or(NakedStakingError::RewardEpochsRequired)?)
+    } else {
+        None
+    };
+    
     if pool.total_weight == 0 {
+        // Nobody to pay: unspent budgets carry forward untouched
+        if let Some(reward_epochs) = reward_epochs.as_deref_mut() {
+            roll_reward_epochs(reward_epochs, current_slot)?;
+        }
         pool.last_update_slot = current_slot;
         return Ok(());
     }
@@ -416,10 +991,18 @@ fn update_reward_accumulator(pool: &mut Account<NativeStakePool>, clock: &Clock)
         return Ok(());
     }
     
-    // Calculate new rewards: slots * rate
-    let new_rewards = (slots_elapsed as u128)
-        .checked_mul(pool.reward_rate_per_slot as u128)
-        .ok_or(NakedStakingError::Overflow)?;
+    // Calculate new rewards: slots * rate, capped by epoch budgets if enabled
+    let new_rewards = match reward_epochs.as_deref_mut() {
+        Some(reward_epochs) => draw_epoch_budgets(
+            reward_epochs,
+            pool.reward_rate_per_slot,
+            pool.last_update_slot,
+            current_slot,
+        )?,
+        None => (slots_elapsed as u128)
+            .checked_mul(pool.reward_rate_per_slot as u128)
+            .ok_or(NakedStakingError::Overflow)?,
+    };
     
     // Increment per-share: (new_rewards * PRECISION) / total_weight
     let increment = new_rewards
@@ -437,6 +1020,134 @@ fn update_reward_accumulator(pool: &mut Account<NativeStakePool>, clock: &Clock)
     Ok(())
 }
 
+/// Epoch index containing `slot`
+fn epoch_at_slot(epochs: &RewardEpochs, slot: u64) -> u64 {
+    slot.saturating_sub(epochs.epochs_start_slot) / epochs.epoch_length_slots
+}
+
+/// Ring index of `epoch`
+fn epoch_index(epoch: u64) -> usize {
+    (epoch % MAX_FUNDED_EPOCHS as u64) as usize
+}
+
+/// Advance current_epoch to the epoch containing `slot`
+///
+/// Unspent budget of each ended epoch carries into the next one. At most
+/// MAX_FUNDED_EPOCHS steps: past that every funded epoch has ended.
+fn roll_reward_epochs(epochs: &mut RewardEpochs, slot: u64) -> Result<()> {
+    let target = epoch_at_slot(epochs, slot);
+    let gap = target.saturating_sub(epochs.current_epoch);
+    if gap == 0 {
+        return Ok(());
+    }
+    
+    if gap >= MAX_FUNDED_EPOCHS as u64 {
+        // Every funded epoch has ended: merge all budgets into the target
+        let mut carried: u64 = 0;
+        for budget in epochs.epoch_budgets.iter_mut() {
+            carried = carried.checked_add(*budget).ok_or(NakedStakingError::Overflow)?;
+            *budget = 0;
+        }
+        epochs.epoch_budgets[epoch_index(target)] = carried;
+    } else {
+        for _ in 0..gap {
+            let idx = epoch_index(epochs.current_epoch);
+            let next_idx = epoch_index(epochs.current_epoch + 1);
+            let carried = epochs.epoch_budgets[idx];
+            epochs.epoch_budgets[idx] = 0;
+            epochs.epoch_budgets[next_idx] = epochs.epoch_budgets[next_idx]
+                .checked_add(carried)
+                .ok_or(NakedStakingError::Overflow)?;
+            epochs.current_epoch += 1;
+        }
+    }
+    epochs.current_epoch = target;
+    
+    Ok(())
+}
+
+/// Rewards earned from `from_slot` to `current_slot`, paced by
+/// `rate_per_slot` and drawn from each epoch's remaining budget
+///
+/// Walks epoch by epoch only while later epochs hold their own budget
+/// (at most MAX_FUNDED_EPOCHS segments). Once only the current epoch's
+/// budget is left, carry-over makes every following epoch identical, so
+/// the rest is drawn in one closed-form step.
+fn draw_epoch_budgets(
+    epochs: &mut RewardEpochs,
+    rate_per_slot: u64,
+    from_slot: u64,
+    current_slot: u64,
+) -> Result<u128> {
+    let mut slot = from_slot;
+    let mut drawn_total: u128 = 0;
+    
+    while slot < current_slot {
+        roll_reward_epochs(epochs, slot)?;
+        
+        let idx = epoch_index(epochs.current_epoch);
+        let only_current = epochs.epoch_budgets
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == idx || *b == 0);
+        
+        let segment_end = if only_current {
+            current_slot
+        } else {
+            let epoch_end = epochs.current_epoch
+                .checked_add(1)
+                .and_then(|e| e.checked_mul(epochs.epoch_length_slots))
+                .and_then(|s| s.checked_add(epochs.epochs_start_slot))
+                .ok_or(NakedStakingError::Overflow)?;
+            epoch_end.min(current_slot)
+        };
+        
+        let wanted = ((segment_end - slot) as u128)
+            .checked_mul(rate_per_slot as u128)
+            .ok_or(NakedStakingError::Overflow)?;
+        // Bounded by the u64 budget
+        let drawn = wanted.min(epochs.epoch_budgets[idx] as u128) as u64;
+        epochs.epoch_budgets[idx] -= drawn;
+        drawn_total = drawn_total
+            .checked_add(drawn as u128)
+            .ok_or(NakedStakingError::Overflow)?;
+        
+        // Nothing left anywhere: skip straight to the current slot
+        if only_current || epochs.epoch_budgets.iter().all(|b| *b == 0) {
+            break;
+        }
+        slot = segment_end;
+    }
+    
+    roll_reward_epochs(epochs, current_slot)?;
+    
+    Ok(drawn_total)
+}
+
+/// Early-unstake penalty on `amount`, in native tokens
+///
+/// penalty_bps of the amount, scaled linearly by the part of the
+/// penalty lock (counted from the position's last stake) still remaining.
+fn early_unstake_penalty(
+    pool: &NativeStakePool,
+    amount: u64,
+    slots_since_stake: u64,
+) -> Result<u64> {
+    if pool.early_unstake_penalty_bps == 0 || slots_since_stake >= pool.penalty_lock_slots {
+        return Ok(0);
+    }
+    
+    let remaining = pool.penalty_lock_slots - slots_since_stake;
+    let penalty = (amount as u128)
+        .checked_mul(pool.early_unstake_penalty_bps as u128)
+        .and_then(|v| v.checked_mul(remaining as u128))
+        .ok_or(NakedStakingError::Overflow)?
+        / (naked_staking::BPS_DENOMINATOR as u128 * pool.penalty_lock_slots as u128);
+    
+    // Bounded by amount since penalty_bps < BPS_DENOMINATOR
+    Ok(penalty as u64)
+}
+
 /// Accrue pending rewards for a position
 fn accrue_position_rewards(
     pool: &NativeStakePool, 
@@ -464,6 +1175,111 @@ fn accrue_position_rewards(
     Ok(())
 }
 
+/// Bring a delegation up to date with its backing
+///
+/// Accrues fee share and writes down the position by any slash loss since the
+/// last sync. The pool accumulator must be updated before calling.
+fn sync_delegation(
+    pool: &mut NativeStakePool,
+    position: &mut Account<NativeStakePosition>,
+    backing: &ProviderBacking,
+    delegation: &mut Account<Delegation>,
+) -> Result<()> {
+    // Shares from before a share supply reset are worth nothing; fees they
+    // earned are settled at the reset's fee_per_share when it is the last one
+    let stale = delegation.share_epoch != backing.share_epoch;
+    let fee_per_share = if !stale {
+        backing.fee_per_share
+    } else if delegation.share_epoch.saturating_add(1) == backing.share_epoch {
+        backing.last_epoch_fee_per_share
+    } else {
+        0
+    };
+    
+    // Fee share accrued on current shares
+    let accumulated_fees = delegation.shares
+        .checked_mul(fee_per_share)
+        .ok_or(NakedStakingError::Overflow)?
+        .checked_div(naked_staking::PRECISION)
+        .ok_or(NakedStakingError::Overflow)?;
+    delegation.pending_fees = delegation.pending_fees
+        .checked_add(accumulated_fees.saturating_sub(delegation.fee_debt))
+        .ok_or(NakedStakingError::Overflow)?;
+    delegation.fee_debt = accumulated_fees;
+    
+    if stale {
+        delegation.shares = 0;
+        delegation.fee_debt = 0;
+        delegation.share_epoch = backing.share_epoch;
+    }
+    
+    // Current token value of the delegation's shares
+    let current_value = if backing.total_shares == 0 {
+        0
+    } else {
+        delegation.shares
+            .checked_mul(backing.total_delegated as u128)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(backing.total_shares)
+            .ok_or(NakedStakingError::Overflow)? as u64
+    };
+    
+    let loss = delegation.amount.saturating_sub(current_value);
+    if loss == 0 {
+        return Ok(());
+    }
+    
+    // Settle native rewards at the old weight before writing the position down
+    accrue_position_rewards(pool, position)?;
+    
+    let old_amount = position.amount;
+    let old_weight = position.weight;
+    let new_amount = old_amount.saturating_sub(loss);
+    let new_weight = if old_amount == 0 {
+        0
+    } else {
+        old_weight
+            .checked_mul(new_amount as u128)
+            .ok_or(NakedStakingError::Overflow)?
+            .checked_div(old_amount as u128)
+            .ok_or(NakedStakingError::Overflow)?
+    };
+    
+    position.amount = new_amount;
+    position.delegated_amount = position.delegated_amount.saturating_sub(loss);
+    position.weight = new_weight;
+    position.reward_debt = new_weight
+        .checked_mul(pool.reward_per_share)
+        .ok_or(NakedStakingError::Overflow)?
+        .checked_div(naked_staking::PRECISION)
+        .ok_or(NakedStakingError::Overflow)?;
+    
+    pool.total_weight = pool.total_weight
+        .saturating_sub(old_weight)
+        .checked_add(new_weight)
+        .ok_or(NakedStakingError::Overflow)?;
+    
+    delegation.amount = current_value;
+    
+    Ok(())
+}
+
+/// Start a new share epoch once a backing holds no stake
+///
+/// Leftover shares (after a full slash, or rounding dust on undelegation)
+/// would otherwise dilute new delegations, which mint shares 1:1 into an
+/// empty backing. Delegations from the old epoch are zeroed on their next sync.
+fn reset_empty_backing(backing: &mut ProviderBacking) -> Result<()> {
+    if backing.total_delegated == 0 && backing.total_shares > 0 {
+        backing.total_shares = 0;
+        backing.last_epoch_fee_per_share = backing.fee_per_share;
+        backing.share_epoch = backing.share_epoch
+            .checked_add(1)
+            .ok_or(NakedStakingError::Overflow)?;
+    }
+    Ok(())
+}
+
 /// Calculate USD-weighted stake with discount
 /// weight = (amount * price * discount_bps) / BPS_DENOMINATOR
 fn calculate_usd_weight(amount: u64, price: i64, discount_bps: u16) -> Result<u128> {
@@ -546,9 +1362,36 @@ pub struct NativeStakePool {
     pub deposit_cap: u64,
     pub paused: bool,
     
+    // Delegation
+    pub delegator_slash_share_bps: u16,
+    
+    // Claims
+    pub min_claim_interval_slots: u64,
+    
+    // Epoch reward budgets (false = open-ended rate; state in RewardEpochs)
+    pub epoch_budgets_enabled: bool,
+    
+    // Early-unstake penalty (early_unstake_penalty_bps == 0 = off)
+    pub early_unstake_penalty_bps: u16,
+    pub penalty_lock_slots: u64,
+    
     // Future expansion
-    #[max_len(32)]
-    pub reserved: [u8; 32],
+    #[max_len(11)]
+    pub reserved: [u8; 11],
+}
+
+/// Epoch reward budgets, created by enable_epoch_budgets
+#[account]
+#[derive(InitSpace)]
+pub struct RewardEpochs {
+    pub pool: Pubkey,
+    pub bump: u8,
+    
+    pub epoch_length_slots: u64,
+    pub epochs_start_slot: u64,
+    pub current_epoch: u64,
+    #[max_len(8)]
+    pub epoch_budgets: [u64; MAX_FUNDED_EPOCHS],   // Ring indexed by epoch % MAX_FUNDED_EPOCHS
 }
 
 #[account]
@@ -571,9 +1414,53 @@ pub struct NativeStakePosition {
     pub last_stake_slot: u64,
     pub last_claim_slot: u64,
     
+    // Delegation
+    pub delegated_amount: u64,
+    
     // Future expansion
-    #[max_len(16)]
-    pub reserved: [u8; 16],
+    #[max_len(8)]
+    pub reserved: [u8; 8],
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProviderBacking {
+    pub pool: Pubkey,
+    pub provider: Pubkey,
+    pub bump: u8,
+    
+    // Delegated stake (shares absorb slashes pro rata)
+    pub total_delegated: u64,
+    pub total_shares: u128,
+    
+    // Provider fee accumulator (MasterChef)
+    pub fee_per_share: u128,
+    pub total_fees_distributed: u64,
+    pub total_slashed: u64,
+    
+    // Backed collateral_vault position (signs slash_delegated_stake)
+    pub position: Pubkey,
+    pub mode_id: u32,
+    
+    // Share supply resets (reset_empty_backing)
+    pub share_epoch: u64,
+    pub last_epoch_fee_per_share: u128, // fee_per_share at the latest reset
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Delegation {
+    pub owner: Pubkey,
+    pub backing: Pubkey,
+    pub bump: u8,
+    
+    pub shares: u128,
+    pub amount: u64,                    // Token value at last sync
+    
+    pub fee_debt: u128,
+    pub pending_fees: u128,
+    
+    pub share_epoch: u64,               // Backing share epoch the shares belong to
 }
 
 // ============================================================================
@@ -625,6 +1512,14 @@ pub struct StakeNative<'info> {
     )]
     pub pool: Account<'info, NativeStakePool>,
     
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
     #[account(
         init_if_needed,
         payer = user,
@@ -670,6 +1565,14 @@ pub struct UnstakeNative<'info> {
     )]
     pub pool: Account<'info, NativeStakePool>,
     
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
     #[account(
         mut,
         seeds = [b"native_pos", pool.key().as_ref(), user.key().as_ref()],
@@ -698,6 +1601,21 @@ pub struct UnstakeNative<'info> {
     )]
     pub pyth_feed: Account<'info, PriceUpdateV2>,
     
+    /// Receives early-unstake penalties once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+    
+    /// Native mint, to burn early-unstake penalties without epoch budgets
+    #[account(
+        mut,
+        constraint = native_mint.key() == pool.native_mint
+    )]
+    pub native_mint: Option<Account<'info, Mint>>,
+    
     #[account(mut)]
     pub user: Signer<'info>,
     
@@ -713,6 +1631,14 @@ pub struct ClaimNativeRewards<'info> {
     )]
     pub pool: Account<'info, NativeStakePool>,
     
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
     #[account(
         mut,
         seeds = [b"native_pos", pool.key().as_ref(), user.key().as_ref()],
@@ -734,12 +1660,97 @@ pub struct ClaimNativeRewards<'info> {
     )]
     pub user_reward_account: Account<'info, TokenAccount>,
     
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+    
     #[account(mut)]
     pub user: Signer<'info>,
     
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct EnableEpochBudgets<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump,
+        has_one = authority @ NakedStakingError::Unauthorized
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RewardEpochs::INIT_SPACE,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump
+    )]
+    pub reward_epochs: Account<'info, RewardEpochs>,
+    
+    #[account(
+        constraint = reward_mint.key() == pool.reward_mint
+    )]
+    pub reward_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = reward_mint,
+        token::authority = pool,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewardEpoch<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump,
+        has_one = authority @ NakedStakingError::Unauthorized
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Account<'info, RewardEpochs>,
+    
+    #[account(
+        mut,
+        seeds = [b"reward_vault"],
+        bump
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = funder_token_account.mint == pool.reward_mint,
+        constraint = funder_token_account.owner == authority.key()
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 pub struct AdminUpdate<'info> {
     #[account(
@@ -753,6 +1764,263 @@ pub struct AdminUpdate<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(mode_id: u32)]
+pub struct InitProviderBacking<'info> {
+    #[account(
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + ProviderBacking::INIT_SPACE,
+        seeds = [b"backing", pool.key().as_ref(), provider.key().as_ref()],
+        bump
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    /// CHECK: the provider's collateral_vault position in `mode_id`; the seeds
+    /// bind it to the signer and the owner check proves it exists
+    #[account(
+        seeds = [b"pos", provider.key().as_ref(), &mode_id.to_le_bytes()],
+        bump,
+        seeds::program = naked_staking::COLLATERAL_VAULT_PROGRAM_ID,
+        owner = naked_staking::COLLATERAL_VAULT_PROGRAM_ID @ NakedStakingError::InvalidProviderPosition
+    )]
+    pub provider_position: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateStake<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_pos", pool.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ NakedStakingError::WrongOwner
+    )]
+    pub position: Account<'info, NativeStakePosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"backing", pool.key().as_ref(), backing.provider.as_ref()],
+        bump = backing.bump
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Delegation::INIT_SPACE,
+        seeds = [b"delegation", backing.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UndelegateStake<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_pos", pool.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ NakedStakingError::WrongOwner
+    )]
+    pub position: Account<'info, NativeStakePosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"backing", pool.key().as_ref(), backing.provider.as_ref()],
+        bump = backing.bump
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    #[account(
+        mut,
+        seeds = [b"delegation", backing.key().as_ref(), user.key().as_ref()],
+        bump = delegation.bump,
+        constraint = delegation.owner == user.key() @ NakedStakingError::WrongOwner
+    )]
+    pub delegation: Account<'info, Delegation>,
+    
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeProviderFees<'info> {
+    #[account(
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    #[account(
+        mut,
+        seeds = [b"backing", pool.key().as_ref(), provider.key().as_ref()],
+        bump = backing.bump
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_vault"],
+        bump,
+        constraint = vault.key() == pool.vault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = provider_token_account.mint == pool.native_mint,
+        constraint = provider_token_account.owner == provider.key()
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+    
+    pub provider: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDelegationFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    /// Required once epoch budgets are enabled
+    #[account(
+        mut,
+        seeds = [b"reward_epochs", pool.key().as_ref()],
+        bump = reward_epochs.bump
+    )]
+    pub reward_epochs: Option<Account<'info, RewardEpochs>>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_pos", pool.key().as_ref(), user.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ NakedStakingError::WrongOwner
+    )]
+    pub position: Account<'info, NativeStakePosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"backing", pool.key().as_ref(), backing.provider.as_ref()],
+        bump = backing.bump
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    #[account(
+        mut,
+        seeds = [b"delegation", backing.key().as_ref(), user.key().as_ref()],
+        bump = delegation.bump,
+        constraint = delegation.owner == user.key() @ NakedStakingError::WrongOwner
+    )]
+    pub delegation: Account<'info, Delegation>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_vault"],
+        bump,
+        constraint = vault.key() == pool.vault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.mint == pool.native_mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    pub user: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SlashDelegatedStake<'info> {
+    #[account(
+        mut,
+        seeds = [b"native_pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, NativeStakePool>,
+    
+    #[account(
+        mut,
+        seeds = [b"backing", pool.key().as_ref(), backing.provider.as_ref()],
+        bump = backing.bump,
+        has_one = position @ NakedStakingError::Unauthorized
+    )]
+    pub backing: Account<'info, ProviderBacking>,
+    
+    /// Backed collateral_vault position, signing via CPI
+    pub position: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"native_vault"],
+        bump,
+        constraint = vault.key() == pool.vault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    
+    /// Receives the delegators' share of the slash
+    #[account(
+        mut,
+        constraint = slash_recipient.mint == pool.native_mint
+    )]
+    pub slash_recipient: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
 // ============================================================================
 // Events
 // ============================================================================
@@ -779,6 +2047,7 @@ pub struct NativeUnstaked {
     pub amount: u64,
     pub remaining: u64,
     pub weight: u128,
+    pub penalty: u64,           // Part of amount forfeited to the remaining stakers
 }
 
 #[event]
@@ -798,6 +2067,82 @@ pub struct DepositCapUpdated {
     pub new_cap: u64,
 }
 
+#[event]
+pub struct MinClaimIntervalUpdated {
+    pub slots: u64,
+}
+
+#[event]
+pub struct EpochBudgetsEnabled {
+    pub epoch_length_slots: u64,
+    pub start_slot: u64,
+    pub reward_vault: Pubkey,
+}
+
+#[event]
+pub struct RewardEpochFunded {
+    pub epoch: u64,
+    pub amount: u64,
+    pub epoch_budget: u64,
+}
+
+#[event]
+pub struct DelegatorSlashShareUpdated {
+    pub slash_share_bps: u16,
+}
+
+#[event]
+pub struct EarlyUnstakePenaltyUpdated {
+    pub penalty_bps: u16,
+    pub lock_slots: u64,
+}
+
+#[event]
+pub struct ProviderBackingInitialized {
+    pub provider: Pubkey,
+    pub backing: Pubkey,
+}
+
+#[event]
+pub struct StakeDelegated {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub delegation_amount: u64,
+    pub backing_total: u64,
+}
+
+#[event]
+pub struct StakeUndelegated {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub delegation_amount: u64,
+    pub backing_total: u64,
+}
+
+#[event]
+pub struct ProviderFeesDistributed {
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub fee_per_share: u128,
+}
+
+#[event]
+pub struct DelegationFeesClaimed {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DelegatedStakeSlashed {
+    pub provider: Pubkey,
+    pub shortfall: u64,
+    pub delegator_loss: u64,
+    pub backing_total: u64,
+}
+
 #[event]
 pub struct PoolPaused {}
 
@@ -836,4 +2181,36 @@ pub enum NakedStakingError {
     WrongOwner,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Stake is delegated - undelegate first")]
+    StakeDelegated,
+    #[msg("Insufficient delegated balance")]
+    InsufficientDelegation,
+    #[msg("Provider has no delegations")]
+    NoDelegations,
+    #[msg("Invalid slash share bps - exceeds maximum")]
+    InvalidSlashShareBps,
+    #[msg("Claim too soon - minimum claim interval not reached")]
+    ClaimTooSoon,
+    #[msg("Invalid epoch length")]
+    InvalidEpochLength,
+    #[msg("Epoch budgets already enabled")]
+    EpochBudgetsAlreadyEnabled,
+    #[msg("Epoch budgets not enabled")]
+    EpochBudgetsNotEnabled,
+    #[msg("Epoch outside fundable range")]
+    EpochOutOfRange,
+    #[msg("Reward vault required when epoch budgets are enabled")]
+    RewardVaultRequired,
+    #[msg("Invalid reward vault")]
+    InvalidRewardVault,
+    #[msg("Invalid early-unstake penalty")]
+    InvalidPenaltyBps,
+    #[msg("Early-unstake penalty requires the staked token as reward token")]
+    PenaltyRequiresNativeRewards,
+    #[msg("Native mint required to burn the early-unstake penalty")]
+    NativeMintRequired,
+    #[msg("Reward epochs account required when epoch budgets are enabled")]
+    RewardEpochsRequired,
+    #[msg("Not the provider's collateral vault position")]
+    InvalidProviderPosition,
 }
@@ -5,6 +5,12 @@
 use anchor_lang::prelude::*;
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
+/// Decimals used for peg targets (e.g. 100_000_000 = $1.00)
+pub const PEG_DECIMALS: u8 = 8;
+
+/// $1.00 expressed in PEG_DECIMALS
+pub const PEG_TARGET_ONE_USD: u64 = 100_000_000;
+
 /// Price data extracted from Pyth oracle
 #[derive(Debug, Clone, Copy)]
 pub struct PriceData {
@@ -110,6 +116,39 @@ pub fn assert_conf(price: i64, conf: u64, max_conf_ratio_bps: u16) -> Result<()>
     Ok(())
 }
 
+/// Deviation of a price from a USD peg, in basis points of the target
+/// 
+/// # Arguments
+/// * `price` - The stablecoin price data
+/// * `target_usd` - Peg target in PEG_DECIMALS (e.g. PEG_TARGET_ONE_USD)
+pub fn peg_deviation_bps(price: &PriceData, target_usd: u64) -> Result<u64> {
+    require!(price.price > 0 && target_usd > 0, PythError::InvalidPrice);
+    
+    let price_usd = price.price_in_decimals(PEG_DECIMALS)?;
+    let deviation = (price_usd.abs_diff(target_usd) as u128)
+        .saturating_mul(10_000)
+        / target_usd as u128;
+    
+    Ok(u64::try_from(deviation).unwrap_or(u64::MAX))
+}
+
+/// Assert a stablecoin price is within max_dev_bps of its USD peg
+/// 
+/// # Arguments
+/// * `price` - The stablecoin price data
+/// * `target_usd` - Peg target in PEG_DECIMALS (e.g. PEG_TARGET_ONE_USD)
+/// * `max_dev_bps` - Maximum allowed deviation in basis points (either direction)
+pub fn assert_peg(price: &PriceData, target_usd: u64, max_dev_bps: u16) -> Result<()> {
+    let deviation_bps = peg_deviation_bps(price, target_usd)?;
+    
+    require!(
+        deviation_bps <= max_dev_bps as u64,
+        PythError::Depegged
+    );
+    
+    Ok(())
+}
+
 /// Calculate conservative minimum output for a swap
 /// 
 /// Uses worst-case pricing: sell at (price - conf), buy at (price + conf),
@@ -179,6 +218,32 @@ pub fn conservative_min_out(
     Ok(min_out as u64)
 }
 
+/// Derive a swap slippage bound from the live confidence of both legs
+/// 
+/// The combined confidence ratio of input and output is used as the
+/// tolerance, clamped to [floor_bps, cap_bps]: calm markets get a tight
+/// bound, volatile markets a wider one up to the cap.
+/// 
+/// # Arguments
+/// * `price_in` - Input token price data
+/// * `price_out` - Output token price data
+/// * `floor_bps` - Minimum slippage tolerance in basis points
+/// * `cap_bps` - Maximum slippage tolerance in basis points
+pub fn dynamic_slippage_bps(
+    price_in: &PriceData,
+    price_out: &PriceData,
+    floor_bps: u16,
+    cap_bps: u16,
+) -> u16 {
+    let combined_conf_bps = price_in
+        .conf_ratio_bps()
+        .saturating_add(price_out.conf_ratio_bps());
+    
+    combined_conf_bps
+        .max(floor_bps as u64)
+        .min(cap_bps as u64) as u16
+}
+
 /// Validate price update meets all constraints
 pub fn validate_price(
     price_update: &Account<PriceUpdateV2>,
@@ -200,8 +265,8 @@ pub fn usd_to_token_amount(
 ) -> Result<u64> {
     require!(price_data.price > 0, PythError::InvalidPrice);
     
-    // USD value in smallest units * 10^token_decimals / price
-    let exp_adjustment = (token_decimals as i32) - (usd_decimals as i32) + price_data.exponent;
+    // usd_value * 10^(token_decimals - usd_decimals - exponent) / price
+    let exp_adjustment = (token_decimals as i32) - (usd_decimals as i32) - price_data.exponent;
     
     let result = if exp_adjustment >= 0 {
         (usd_value as u128)
@@ -229,8 +294,8 @@ pub fn token_amount_to_usd(
 ) -> Result<u64> {
     require!(price_data.price > 0, PythError::InvalidPrice);
     
-    // token_amount * price / 10^(token_decimals - usd_decimals + exponent)
-    let exp_adjustment = (token_decimals as i32) - (usd_decimals as i32) + price_data.exponent;
+    // token_amount * price / 10^(token_decimals - usd_decimals - exponent)
+    let exp_adjustment = (token_decimals as i32) - (usd_decimals as i32) - price_data.exponent;
     
     let result = if exp_adjustment >= 0 {
         (token_amount as u128)
@@ -249,6 +314,154 @@ pub fn token_amount_to_usd(
     Ok(result as u64)
 }
 
+/// Integer square root (floor) for fair-value LP pricing
+pub fn isqrt_u128(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Start above the root without overflowing at u128::MAX
+    let mut x = n / 2 + 1;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// Fair USD value of a constant-product pool's total liquidity
+///
+/// Uses the manipulation-resistant formula 2 * sqrt(value_a * value_b), where
+/// each leg is valued from pool reserves and its Pyth price. Skewing reserves
+/// within a block cannot raise the product, so the fair value cannot be pumped.
+///
+/// # Arguments
+/// * `reserve_a` / `reserve_b` - Pool reserves in each token's native units
+/// * `decimals_a` / `decimals_b` - Token decimals for each leg
+/// * `price_a` / `price_b` - Pyth prices for each leg
+/// * `usd_decimals` - Decimals of the returned USD value
+pub fn lp_pool_fair_value_usd(
+    reserve_a: u64,
+    decimals_a: u8,
+    price_a: &PriceData,
+    reserve_b: u64,
+    decimals_b: u8,
+    price_b: &PriceData,
+    usd_decimals: u8,
+) -> Result<u64> {
+    let value_a = token_amount_to_usd(reserve_a, decimals_a, price_a, usd_decimals)?;
+    let value_b = token_amount_to_usd(reserve_b, decimals_b, price_b, usd_decimals)?;
+
+    let product = (value_a as u128)
+        .checked_mul(value_b as u128)
+        .ok_or(error!(PythError::Overflow))?;
+    let fair_value = isqrt_u128(product)
+        .checked_mul(2)
+        .ok_or(error!(PythError::Overflow))?;
+
+    u64::try_from(fair_value).map_err(|_| error!(PythError::Overflow))
+}
+
+/// USD value of an LP token amount given the pool's fair value and LP supply
+pub fn lp_amount_to_usd(lp_amount: u64, pool_fair_value_usd: u64, lp_supply: u64) -> Result<u64> {
+    require!(lp_supply > 0, PythError::InvalidLpSupply);
+
+    let value = (lp_amount as u128)
+        .checked_mul(pool_fair_value_usd as u128)
+        .ok_or(error!(PythError::Overflow))?
+        .checked_div(lp_supply as u128)
+        .ok_or(error!(PythError::Overflow))?;
+
+    u64::try_from(value).map_err(|_| error!(PythError::Overflow))
+}
+
+/// Price of one whole LP token implied by its pool's fair value
+///
+/// Returned as PriceData (exponent -usd_decimals) so LP collateral goes
+/// through the same USD valuation as a Pyth-priced mint. `publish_time`
+/// should be the older of the two leg prices.
+pub fn lp_token_price(
+    pool_fair_value_usd: u64,
+    lp_supply: u64,
+    lp_decimals: u8,
+    usd_decimals: u8,
+    publish_time: i64,
+) -> Result<PriceData> {
+    let one_lp = 10u64
+        .checked_pow(lp_decimals as u32)
+        .ok_or(error!(PythError::Overflow))?;
+    let price = lp_amount_to_usd(one_lp, pool_fair_value_usd, lp_supply)?;
+
+    Ok(PriceData {
+        price: i64::try_from(price).map_err(|_| error!(PythError::Overflow))?,
+        conf: 0,
+        exponent: -(usd_decimals as i32),
+        publish_time,
+    })
+}
+
+/// Apply a collateral haircut: value * (10_000 - haircut_bps) / 10_000
+pub fn apply_haircut(value: u64, haircut_bps: u16) -> Result<u64> {
+    require!(haircut_bps <= 10_000, PythError::InvalidHaircut);
+
+    let kept = (value as u128)
+        .checked_mul(10_000u128 - haircut_bps as u128)
+        .ok_or(error!(PythError::Overflow))?
+        / 10_000;
+
+    Ok(kept as u64)
+}
+
+/// USD value of a token amount at the lower edge of the confidence interval
+/// 
+/// Used for collateral health, where overstating value is the unsafe side.
+/// A confidence interval wider than the price values the amount at zero.
+/// 
+/// # Arguments
+/// * `token_amount` - Amount in the token's native units
+/// * `token_decimals` - Token mint decimals
+/// * `price_data` - Token price data
+/// * `usd_decimals` - Decimals of the returned USD value (e.g. PEG_DECIMALS)
+pub fn conservative_usd_value(
+    token_amount: u64,
+    token_decimals: u8,
+    price_data: &PriceData,
+    usd_decimals: u8,
+) -> Result<u64> {
+    require!(price_data.price > 0, PythError::InvalidPrice);
+    
+    let low = price_data.price.unsigned_abs().saturating_sub(price_data.conf);
+    if low == 0 {
+        return Ok(0);
+    }
+    let low_price = PriceData {
+        price: i64::try_from(low).map_err(|_| error!(PythError::Overflow))?,
+        ..*price_data
+    };
+    
+    token_amount_to_usd(token_amount, token_decimals, &low_price, usd_decimals)
+}
+
+/// Assert a token amount is worth at least min_usd at its conservative price
+/// 
+/// # Arguments
+/// * `token_amount` - Amount in the token's native units
+/// * `token_decimals` - Token mint decimals
+/// * `price_data` - Token price data
+/// * `min_usd` - Minimum value in PEG_DECIMALS
+pub fn assert_min_usd_value(
+    token_amount: u64,
+    token_decimals: u8,
+    price_data: &PriceData,
+    min_usd: u64,
+) -> Result<()> {
+    let value = conservative_usd_value(token_amount, token_decimals, price_data, PEG_DECIMALS)?;
+    
+    require!(value >= min_usd, PythError::BelowMinUsdValue);
+    
+    Ok(())
+}
+
 #[error_code]
 pub enum PythError {
     #[msg("Price is too old")]
@@ -261,6 +474,14 @@ pub enum PythError {
     Overflow,
     #[msg("Feed ID mismatch")]
     FeedIdMismatch,
+    #[msg("LP supply must be greater than zero")]
+    InvalidLpSupply,
+    #[msg("Haircut exceeds 100%")]
+    InvalidHaircut,
+    #[msg("Stablecoin price outside peg bound")]
+    Depegged,
+    #[msg("Collateral value below the minimum USD health threshold")]
+    BelowMinUsdValue,
 }
 
 #[cfg(test)]
@@ -559,6 +780,46 @@ mod tests {
         assert_eq!(min_out, 0);
     }
 
+    // ==================== Dynamic Slippage Tests ====================
+
+    #[test]
+    fn test_dynamic_slippage_tracks_conf() {
+        // 0.2% + 0.3% conf -> 50 bps
+        let price_in = PriceData {
+            price: 100000000,
+            conf: 200000,
+            exponent: -8,
+            publish_time: 0,
+        };
+        let price_out = PriceData {
+            price: 100000000,
+            conf: 300000,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert_eq!(dynamic_slippage_bps(&price_in, &price_out, 10, 300), 50);
+    }
+
+    #[test]
+    fn test_dynamic_slippage_clamped() {
+        let calm = PriceData {
+            price: 100000000,
+            conf: 1000, // 0.1 bps
+            exponent: -8,
+            publish_time: 0,
+        };
+        let volatile = PriceData {
+            price: 100000000,
+            conf: 5000000, // 5%
+            exponent: -8,
+            publish_time: 0,
+        };
+        // Calm market is held at the floor
+        assert_eq!(dynamic_slippage_bps(&calm, &calm, 10, 300), 10);
+        // Volatile market is capped
+        assert_eq!(dynamic_slippage_bps(&volatile, &calm, 10, 300), 300);
+    }
+
     // ==================== USD/Token Conversion Tests ====================
 
     #[test]
@@ -613,6 +874,211 @@ mod tests {
         assert!(result.is_err());
     }
 
+    // ==================== LP Valuation Tests ====================
+
+    #[test]
+    fn test_isqrt_u128() {
+        assert_eq!(isqrt_u128(0), 0);
+        assert_eq!(isqrt_u128(1), 1);
+        assert_eq!(isqrt_u128(15), 3);
+        assert_eq!(isqrt_u128(16), 4);
+        assert_eq!(isqrt_u128(u64::MAX as u128 * u64::MAX as u128), u64::MAX as u128);
+        assert_eq!(isqrt_u128(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_lp_pool_fair_value_balanced() {
+        // 100 SOL at $100 and 10,000 USDC at $1 -> $20,000 of liquidity
+        let price_sol = PriceData {
+            price: 10000000000, // $100.00 with exp -8
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        let price_usdc = PriceData {
+            price: 100000000, // $1.00 with exp -8
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+
+        let value = lp_pool_fair_value_usd(
+            100_000_000_000, 9, &price_sol,
+            10_000_000_000, 6, &price_usdc,
+            6,
+        ).unwrap();
+
+        assert_eq!(value, 20_000_000_000);
+    }
+
+    #[test]
+    fn test_lp_pool_fair_value_resists_skewed_reserves() {
+        // Skewed reserves worth $40,000 at spot sum, but fair value stays at $20,000
+        let price = PriceData {
+            price: 100000000,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+
+        let skewed = lp_pool_fair_value_usd(
+            39_000_000_000, 6, &price,
+            1_000_000_000, 6, &price,
+            6,
+        ).unwrap();
+
+        // 2 * sqrt(39,000 * 1,000) ≈ $12,490 < $40,000 spot sum
+        assert!(skewed < 40_000_000_000);
+        assert_eq!(skewed, 12_489_995_996);
+    }
+
+    #[test]
+    fn test_lp_amount_to_usd() {
+        // 10% of supply of a $20,000 pool = $2,000
+        let value = lp_amount_to_usd(100, 20_000_000_000, 1_000).unwrap();
+        assert_eq!(value, 2_000_000_000);
+    }
+
+    #[test]
+    fn test_lp_token_price() {
+        // 1,000 LP (6 decimals) over a $20,000 pool -> $20 per LP token
+        let price = lp_token_price(20_000_000_000, 1_000_000_000, 6, 6, 42).unwrap();
+        assert_eq!(price.price, 20_000_000);
+        assert_eq!(price.exponent, -6);
+        assert_eq!(price.publish_time, 42);
+
+        let value = token_amount_to_usd(2_500_000, 6, &price, 6).unwrap();
+        assert_eq!(value, 50_000_000);
+    }
+
+    #[test]
+    fn test_lp_amount_to_usd_zero_supply_rejected() {
+        assert!(lp_amount_to_usd(100, 20_000_000_000, 0).is_err());
+    }
+
+    #[test]
+    fn test_apply_haircut() {
+        assert_eq!(apply_haircut(1_000_000, 0).unwrap(), 1_000_000);
+        assert_eq!(apply_haircut(1_000_000, 2_500).unwrap(), 750_000);
+        assert_eq!(apply_haircut(1_000_000, 10_000).unwrap(), 0);
+        assert!(apply_haircut(1_000_000, 10_001).is_err());
+    }
+
+    // ==================== Peg Tests ====================
+
+    #[test]
+    fn test_peg_deviation_bps() {
+        // $0.99 with exp -8 -> 100 bps below $1.00
+        let price = PriceData {
+            price: 99000000,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert_eq!(peg_deviation_bps(&price, PEG_TARGET_ONE_USD).unwrap(), 100);
+
+        // $1.005 with exp -6 -> 50 bps above
+        let price = PriceData {
+            price: 1005000,
+            conf: 0,
+            exponent: -6,
+            publish_time: 0,
+        };
+        assert_eq!(peg_deviation_bps(&price, PEG_TARGET_ONE_USD).unwrap(), 50);
+    }
+
+    #[test]
+    fn test_assert_peg_within_and_at_bound() {
+        let price = PriceData {
+            price: 99500000, // $0.995
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(assert_peg(&price, PEG_TARGET_ONE_USD, 100).is_ok());
+        assert!(assert_peg(&price, PEG_TARGET_ONE_USD, 50).is_ok());
+    }
+
+    #[test]
+    fn test_assert_peg_depeg_rejected() {
+        // $0.97 below peg and $1.03 above peg, 200 bps bound
+        let below = PriceData {
+            price: 97000000,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        let above = PriceData {
+            price: 103000000,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(assert_peg(&below, PEG_TARGET_ONE_USD, 200).is_err());
+        assert!(assert_peg(&above, PEG_TARGET_ONE_USD, 200).is_err());
+    }
+
+    #[test]
+    fn test_assert_peg_invalid_price_rejected() {
+        let price = PriceData {
+            price: 0,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(assert_peg(&price, PEG_TARGET_ONE_USD, 10_000).is_err());
+    }
+
+    // ==================== Collateral Health Tests ====================
+
+    #[test]
+    fn test_conservative_usd_value_uses_lower_bound() {
+        // 10 tokens (6 decimals) at $2.00 +/- $0.10 -> $19.00
+        let price = PriceData {
+            price: 200000000,
+            conf: 10000000,
+            exponent: -8,
+            publish_time: 0,
+        };
+        let value = conservative_usd_value(10_000_000, 6, &price, PEG_DECIMALS).unwrap();
+        assert_eq!(value, 1_900_000_000);
+    }
+
+    #[test]
+    fn test_conservative_usd_value_conf_wider_than_price() {
+        let price = PriceData {
+            price: 100,
+            conf: 150,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert_eq!(conservative_usd_value(1_000_000, 6, &price, PEG_DECIMALS).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_assert_min_usd_value() {
+        // 10 tokens at $2.00 +/- $0.10 are worth $19.00 conservatively
+        let price = PriceData {
+            price: 200000000,
+            conf: 10000000,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(assert_min_usd_value(10_000_000, 6, &price, 1_900_000_000).is_ok());
+        assert!(assert_min_usd_value(10_000_000, 6, &price, 1_900_000_001).is_err());
+    }
+
+    #[test]
+    fn test_assert_min_usd_value_invalid_price_rejected() {
+        let price = PriceData {
+            price: -1,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        };
+        assert!(assert_min_usd_value(10_000_000, 6, &price, 0).is_err());
+    }
+
     // ==================== Staleness Tests (assert_fresh logic) ====================
     // Note: assert_fresh requires Clock::get() which needs Solana runtime.
     // These tests document the expected behavior; integration tests cover actual execution.
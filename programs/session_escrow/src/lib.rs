@@ -1,15 +1,76 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_lang::solana_program::sysvar::instructions::{
     self, load_instruction_at_checked,
 };
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use collateral_vault::cpi::accounts::{Reserve, Release, SlashAndPay};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
+use collateral_vault::cpi::accounts::{Deposit, Reserve, Release, SlashAndPay};
 use collateral_vault::program::CollateralVault;
-use collateral_vault::ProviderPosition;
+use collateral_vault::{ModeVault, ProviderPosition};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 declare_id!("SessEsc111111111111111111111111111111111111");
 
 pub const ED25519_PROGRAM_ID: Pubkey = anchor_lang::solana_program::ed25519_program::ID;
+pub const SECP256K1_PROGRAM_ID: Pubkey = anchor_lang::solana_program::secp256k1_program::ID;
+
+/// Number of records retained by the event queue ring buffer
+pub const EVENT_QUEUE_CAPACITY: usize = 64;
+
+/// Maximum verifiers in a session's bucket failure quorum
+pub const MAX_QUORUM_VERIFIERS: usize = 5;
+
+/// Ceiling for a per-session collateral ratio override (500%, as in mode_registry)
+pub const MAX_SESSION_CR_BPS: u16 = 50_000;
+
+/// Maximum recipients a session's claim payouts can be split across
+pub const MAX_PAYOUT_SPLITS: usize = 4;
+
+/// Maximum buckets covered by one report_bucket_failures_batch call
+pub const MAX_BATCH_BUCKET_REPORTS: usize = 32;
+
+/// Recent bucket failures remembered for provider disputes (ring buffer)
+pub const MAX_DISPUTABLE_FAILURES: usize = 16;
+
+/// Maximum per-reason bucket penalty weight
+pub const MAX_PENALTY_WEIGHT: u8 = 10;
+
+/// Session metadata limits
+pub const MAX_METADATA_URI_LEN: usize = 200;
+pub const MAX_METADATA_ENTRIES: usize = 4;
+pub const MAX_METADATA_KEY_LEN: usize = 16;
+pub const MAX_METADATA_VALUE_LEN: usize = 64;
+
+/// Maximum depth of a delivery Merkle proof
+pub const MAX_DELIVERY_PROOF_DEPTH: usize = 32;
+
+/// Bandwidth checkpoints retained per session (ring buffer); also the number
+/// of sub-windows the SLA window is divided into for checkpoint spacing
+pub const MAX_BANDWIDTH_CHECKPOINTS: usize = 16;
+
+/// Slots a stalled session stays claimable before expire_session may
+/// settle it without a stall payout (≈ 1 day at 400ms)
+pub const STALL_CLAIM_WINDOW_SLOTS: u64 = 216_000;
+
+/// Oracle bounds for converting a USD max_spend at open
+pub const USD_PRICE_MAX_AGE_SECONDS: u64 = 60;
+pub const USD_PRICE_MAX_CONF_BPS: u16 = 200;
+
+/// Token-2022 mint extensions accepted for session payment mints
+pub const ALLOWED_MINT_EXTENSIONS: [ExtensionType; 3] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+];
 
 /// Session Escrow Program (IMMUTABLE)
 ///
@@ -32,12 +93,41 @@ pub mod session_escrow {
     pub const BID_PREMIUM_WEIGHT: u64 = 50; // 50% weight on premium for bid coverage
     pub const BID_SLA_WEIGHT: u64 = 50;     // 50% weight on SLA strictness
 
+    /// Minimum slots between payout destination changes (~1 day)
+    pub const PAYOUT_DESTINATION_COOLDOWN_SLOTS: u64 = 216_000;
+
+    /// Slots a provider-requested close waits before it can be finalized (~12 hours)
+    pub const PROVIDER_CLOSE_COOLDOWN_SLOTS: u64 = 108_000;
+
+    /// Slots after a bucket failure is reported during which the provider can dispute it (~1 hour)
+    pub const BUCKET_DISPUTE_WINDOW_SLOTS: u64 = 9_000;
+
+    /// Maximum sessions opened by one open_sessions_batch call
+    pub const MAX_BATCH_SESSIONS: usize = 8;
+
+    // Penalty clawback constants
+    pub const CLAWBACK_CLEAN_BUCKETS: u64 = 12;  // Consecutive clean buckets per clawback
+    pub const CLAWBACK_BPS: u64 = 2500;          // Share of penalty_accrued returned per streak
+
+    /// Share of the penalty paid to whoever cranks settle_expired_violation (1%)
+    pub const CRANK_TIP_BPS: u64 = 100;
+
+    /// Slots after ack within which delivery must begin before claim_late_start opens (~10 minutes)
+    pub const LATE_START_GRACE_SLOTS: u64 = 1_500;
+
+    /// Late-start compensation for an ack at the very end of the start deadline (2% of base coverage)
+    pub const LATE_START_MAX_COMP_BPS: u64 = 200;
+
     /// Open a new session between user and provider
     ///
     /// When is_bid is true:
     /// - Computes additional bid_coverage_p from premium and SLA targets
     /// - Sets SLA window timing (sla_window_start_slot = current_slot + warmup_slots)
     /// - Reserves total collateral (reserve_base + reserve_bid)
+    ///
+    /// The mode must exist, be active and not disabled, and its mint must
+    /// match payment_mint. Collateral is sized with the mode's cr_bps, or a
+    /// higher cr_bps requested by the user.
     pub fn open_session(
         ctx: Context<OpenSession>,
         session_nonce: u64,
@@ -53,1710 +143,8987 @@ pub mod session_escrow {
         fail_payout_bps: u16,
         latency_target_ms: u16,
         bandwidth_min_chunks: u32,
+        bandwidth_min_bytes: u64,
         sla_warmup_slots: u64,
         sla_window_slots: u64,
         // Bucketed SLA parameters (only used if is_bid)
         bucket_slots: u64,
         terminate_window_slots: u64,
         max_penalty_bps: u16,
+        grace_buckets: u32,
+        penalty_weights: PenaltyWeights,
         verifier_pubkey: Pubkey,
+        // Wallet receiving refunds and claim payouts (defaults to user)
+        payout_destination: Option<Pubkey>,
+        // EVM address signing permits via secp256k1 (None = Ed25519 by user)
+        permit_eth_address: Option<[u8; 20]>,
+        // Requested collateral ratio (0 = mode floor); provider accepts at ack
+        cr_bps: u16,
+        // Idempotency key; replays under another nonce fail (see client_request)
+        client_request_id: Option<[u8; 16]>,
+        // Slots after ack before either party can force a close (0 = unlimited)
+        max_duration_slots: u64,
     ) -> Result<()> {
         let clock = Clock::get()?;
 
-        // Compute base coverage (always computed)
-        let base_coverage_p = compute_insurance_coverage(max_spend, price_per_chunk);
-        let cr_bps: u64 = 15000;
-        let reserve_base = base_coverage_p
-            .checked_mul(cr_bps)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::Overflow)?;
+        let mode = &ctx.accounts.mode;
+        require!(mode.is_active && !mode.is_disabled, ErrorCode::ModeNotActive);
+        assert_supported_payment_mint(&ctx.accounts.payment_mint)?;
 
-        // Compute bid coverage if in bid mode
-        let (bid_coverage_p, reserve_bid, sla_window_start_slot, sla_window_end_slot) = if is_bid {
-            let bid_cov = compute_bid_coverage(
-                max_spend,
-                premium_bps,
-                latency_target_ms,
-                bandwidth_min_chunks,
-            );
-            let res_bid = bid_cov
-                .checked_mul(cr_bps)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_div(10000)
-                .ok_or(ErrorCode::Overflow)?;
+        let terms = SessionTerms {
+            chunk_size,
+            price_per_chunk,
+            max_spend,
+            start_deadline_slots,
+            stall_timeout_slots,
+            is_bid,
+            premium_bps,
+            fail_payout_bps,
+            latency_target_ms,
+            bandwidth_min_chunks,
+            bandwidth_min_bytes,
+            sla_warmup_slots,
+            sla_window_slots,
+            bucket_slots,
+            terminate_window_slots,
+            max_penalty_bps,
+            grace_buckets,
+            penalty_weights,
+            verifier_pubkey,
+            payout_destination,
+            permit_eth_address,
+            cr_bps,
+            max_duration_slots,
+        };
+        validate_timing_for_mode(&terms, mode)?;
+        let parties = SessionParties {
+            user: ctx.accounts.user.key(),
+            provider: ctx.accounts.provider.key(),
+            mode_id,
+            mint: ctx.accounts.payment_mint.key(),
+            session_nonce,
+            bump: ctx.bumps.session,
+        };
+        let cr_bps = effective_cr_bps(cr_bps, mode)?;
+        let session_key = ctx.accounts.session.key();
 
-            let window_start = clock.slot
-                .checked_add(sla_warmup_slots)
-                .ok_or(ErrorCode::Overflow)?;
-            let window_end = window_start
-                .checked_add(sla_window_slots)
-                .ok_or(ErrorCode::Overflow)?;
+        init_session(&mut ctx.accounts.session, parties, &terms, cr_bps, clock.slot)?;
+
+        if let Some(request_id) = client_request_id {
+            let lookup_info = ctx
+                .accounts
+                .client_request
+                .as_ref()
+                .ok_or(ErrorCode::ClientRequestAccountRequired)?;
+            register_client_request(
+                lookup_info,
+                &ctx.accounts.user,
+                &ctx.accounts.system_program,
+                request_id,
+                session_key,
+            )?;
+            ctx.accounts.session.client_request_id = request_id;
+        }
 
-            (bid_cov, res_bid, window_start, window_end)
-        } else {
-            (0, 0, 0, 0)
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionOpened, session_key, max_spend)?;
+
+        emit_cpi!(session_opened_event(session_key, &ctx.accounts.session));
+
+        Ok(())
+    }
+
+    /// Open a session using the mode's registry configuration
+    ///
+    /// The payment mint and collateral ratio come from the Mode account, and
+    /// the terms are checked against the mode's premium cap and bucket sizing
+    /// bounds, so clients cannot open inconsistent or degenerate sessions.
+    pub fn open_session_from_mode(
+        ctx: Context<OpenSessionFromMode>,
+        session_nonce: u64,
+        mode_id: u32,
+        terms: SessionTerms,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let mode = &ctx.accounts.mode;
+
+        require!(mode.is_active && !mode.is_disabled, ErrorCode::ModeNotActive);
+        assert_supported_payment_mint(&ctx.accounts.payment_mint)?;
+        validate_terms_for_mode(&terms, mode)?;
+
+        let parties = SessionParties {
+            user: ctx.accounts.user.key(),
+            provider: ctx.accounts.provider.key(),
+            mode_id,
+            mint: mode.mint,
+            session_nonce,
+            bump: ctx.bumps.session,
         };
+        let cr_bps = effective_cr_bps(terms.cr_bps, mode)?;
+        let session_key = ctx.accounts.session.key();
 
-        // Total reserve required
-        let total_reserve = reserve_base
-            .checked_add(reserve_bid)
-            .ok_or(ErrorCode::Overflow)?;
+        init_session(&mut ctx.accounts.session, parties, &terms, cr_bps, clock.slot)?;
 
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionOpened, session_key, terms.max_spend)?;
+
+        emit_cpi!(session_opened_event(session_key, &ctx.accounts.session));
+
+        Ok(())
+    }
+
+    /// Open a session whose max_spend is denominated in USD
+    ///
+    /// Same as open_session_from_mode, except terms.max_spend is ignored:
+    /// max_spend_usd (PEG_DECIMALS, i.e. 100_000_000 = $1.00) is converted to
+    /// token units with the mode's Pyth feed. Both the USD target and the
+    /// token amount are stored so pricing UIs can quote in dollars.
+    pub fn open_session_usd(
+        ctx: Context<OpenSessionUsd>,
+        session_nonce: u64,
+        mode_id: u32,
+        max_spend_usd: u64,
+        mut terms: SessionTerms,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let mode = &ctx.accounts.mode;
+
+        require!(mode.is_active && !mode.is_disabled, ErrorCode::ModeNotActive);
+        require!(mode.peg_feed_id != [0u8; 32], ErrorCode::UsdPricingUnavailable);
+        require!(max_spend_usd > 0, ErrorCode::InvalidSessionTerms);
+        assert_supported_payment_mint(&ctx.accounts.payment_mint)?;
+
+        let price = pyth_helpers::validate_price(
+            &ctx.accounts.price_update,
+            &mode.peg_feed_id,
+            USD_PRICE_MAX_AGE_SECONDS,
+            USD_PRICE_MAX_CONF_BPS,
+        )?;
+        terms.max_spend = pyth_helpers::usd_to_token_amount(
+            max_spend_usd,
+            pyth_helpers::PEG_DECIMALS,
+            &price,
+            ctx.accounts.payment_mint.decimals,
+        )?;
+        validate_terms_for_mode(&terms, mode)?;
+
+        let parties = SessionParties {
+            user: ctx.accounts.user.key(),
+            provider: ctx.accounts.provider.key(),
+            mode_id,
+            mint: mode.mint,
+            session_nonce,
+            bump: ctx.bumps.session,
+        };
+        let cr_bps = effective_cr_bps(terms.cr_bps, mode)?;
         let session_key = ctx.accounts.session.key();
-        let user_key = ctx.accounts.user.key();
-        let provider_key = ctx.accounts.provider.key();
-        let mint_key = ctx.accounts.payment_mint.key();
-        let session_bump = ctx.bumps.session;
-        let start_deadline_slot = clock.slot
-            .checked_add(start_deadline_slots)
-            .ok_or(ErrorCode::Overflow)?;
 
-        let session = &mut ctx.accounts.session;
-        session.user = user_key;
-        session.provider = provider_key;
-        session.mode_id = mode_id;
-        session.mint = mint_key;
-        session.session_nonce = session_nonce;
-        session.chunk_size = chunk_size;
-        session.price_per_chunk = price_per_chunk;
-        session.max_spend = max_spend;
-        session.total_spent = 0;
-        session.reserve_r = total_reserve;
-        session.start_deadline_slot = start_deadline_slot;
-        session.stall_timeout_slots = stall_timeout_slots;
-        session.last_progress_slot = 0;
-        session.state = SessionState::Open;
-        session.acked = false;
-        session.next_permit_nonce = 0;
-        session.bump = session_bump;
-
-        // Bid/SLA fields
-        session.is_bid = is_bid;
-        session.premium_bps = premium_bps;
-        session.fail_payout_bps = fail_payout_bps;
-        session.latency_target_ms = latency_target_ms;
-        session.bandwidth_min_chunks = bandwidth_min_chunks;
-        session.sla_warmup_slots = sla_warmup_slots;
-        session.sla_window_slots = sla_window_slots;
-        session.sla_window_start_slot = sla_window_start_slot;
-        session.sla_window_end_slot = sla_window_end_slot;
-
-        // Insurance split
-        session.base_coverage_p = base_coverage_p;
-        session.bid_coverage_p = bid_coverage_p;
-        session.reserve_base = reserve_base;
-        session.reserve_bid = reserve_bid;
+        init_session(&mut ctx.accounts.session, parties, &terms, cr_bps, clock.slot)?;
+        ctx.accounts.session.max_spend_usd = max_spend_usd;
 
-        // SLA state
-        session.sla_status = SlaStatus::None;
-        session.sla_failure_reason = SlaFailureReason::None;
-        session.latency_attested = false;
-
-        // Nonce tracking for bandwidth SLA (legacy window-level)
-        session.nonce_at_window_start = 0;
-        session.nonce_at_window_end = 0;
-
-        // Bucketed SLA configuration (compute if is_bid)
-        if is_bid && bucket_slots > 0 {
-            let buckets_total_computed = compute_buckets_total(sla_window_slots, bucket_slots)?;
-            let bucket_penalty_computed = compute_bucket_penalty(
-                total_reserve,
-                max_penalty_bps,
-                buckets_total_computed,
-            )?;
-            session.bucket_slots = bucket_slots;
-            session.buckets_total = buckets_total_computed;
-            session.bucket_penalty = bucket_penalty_computed;
-        } else {
-            session.bucket_slots = 0;
-            session.buckets_total = 0;
-            session.bucket_penalty = 0;
-        }
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionOpened, session_key, terms.max_spend)?;
+
+        emit_cpi!(session_opened_event(session_key, &ctx.accounts.session));
+
+        Ok(())
+    }
+
+    /// Open several sessions with one provider in a single instruction
+    ///
+    /// Each nonce opens a session on the same terms as open_session_from_mode.
+    /// remaining_accounts holds one (session, escrow_token_account) pair per
+    /// nonce, in order: the session PDA for ["sess", user, nonce] and its
+    /// escrow ATA, both uninitialized and writable.
+    pub fn open_sessions_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenSessionsBatch<'info>>,
+        mode_id: u32,
+        session_nonces: Vec<u64>,
+        terms: SessionTerms,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let rent = Rent::get()?;
+        let mode = &ctx.accounts.mode;
 
-        // Bucketed downtime tracking (initialized to zero)
-        session.buckets_failed = 0;
-        session.buckets_failed_bitmap = [0u8; 128];
+        require!(
+            !session_nonces.is_empty() && session_nonces.len() <= MAX_BATCH_SESSIONS,
+            ErrorCode::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == session_nonces.len() * 2,
+            ErrorCode::InvalidBatchAccount
+        );
+        require!(mode.is_active && !mode.is_disabled, ErrorCode::ModeNotActive);
+        assert_supported_payment_mint(&ctx.accounts.payment_mint)?;
+        validate_terms_for_mode(&terms, mode)?;
 
-        // Termination window
-        session.first_violation_slot = 0;
-        session.terminate_window_slots = terminate_window_slots;
-        session.terminate_deadline_slot = 0;
+        let user_key = ctx.accounts.user.key();
+        let provider_key = ctx.accounts.provider.key();
+        let mint_key = mode.mint;
+        let cr_bps = effective_cr_bps(terms.cr_bps, mode)?;
+        let space = 8 + Session::INIT_SPACE;
+
+        for (nonce, accounts) in session_nonces.iter().zip(ctx.remaining_accounts.chunks(2)) {
+            let session_info = &accounts[0];
+            let escrow_info = &accounts[1];
+
+            let nonce_bytes = nonce.to_le_bytes();
+            let (expected_session, bump) = Pubkey::find_program_address(
+                &[b"sess", user_key.as_ref(), &nonce_bytes],
+                ctx.program_id,
+            );
+            require_keys_eq!(session_info.key(), expected_session, ErrorCode::InvalidBatchAccount);
+            require!(
+                session_info.data_is_empty() && session_info.owner == &anchor_lang::system_program::ID,
+                ErrorCode::InvalidBatchAccount
+            );
 
-        // Penalty accounting
-        session.penalty_accrued = 0;
+            // Create the session PDA
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            create_pda_account(
+                session_info,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &rent,
+                space,
+                seeds,
+            )?;
 
-        // Attester configuration
-        session.verifier_pubkey = verifier_pubkey;
+            // Create the escrow ATA (the ATA program checks its address)
+            anchor_spl::associated_token::create(CpiContext::new(
+                ctx.accounts.associated_token_program.to_account_info(),
+                anchor_spl::associated_token::Create {
+                    payer: ctx.accounts.user.to_account_info(),
+                    associated_token: escrow_info.clone(),
+                    authority: session_info.clone(),
+                    mint: ctx.accounts.payment_mint.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+            ))?;
+
+            let mut session = Account::<Session>::try_from_unchecked(session_info)?;
+            let parties = SessionParties {
+                user: user_key,
+                provider: provider_key,
+                mode_id,
+                mint: mint_key,
+                session_nonce: *nonce,
+                bump,
+            };
+            init_session(&mut session, parties, &terms, cr_bps, clock.slot)?;
+            session.exit(ctx.program_id)?;
 
-        // Convenience flags
-        session.terminated_for_cause = false;
+            record_event(&mut ctx.accounts.event_queue, EventKind::SessionOpened, expected_session, terms.max_spend)?;
 
-        emit!(SessionOpened {
-            session: session_key,
-            user: user_key,
-            provider: provider_key,
-            mode_id,
-            max_spend,
-            base_coverage_p,
-            reserve_r: total_reserve,
-            start_deadline_slot,
-            is_bid,
-            premium_bps,
-            fail_payout_bps,
-            bid_coverage_p,
-            reserve_base,
-            reserve_bid,
-        });
+            emit_cpi!(session_opened_event(expected_session, &session));
+        }
 
         Ok(())
     }
 
-    /// Fund the session escrow (user deposits)
+    /// Fund the session escrow (user, or a sponsor on the user's behalf)
+    ///
+    /// For Token-2022 mints with a transfer fee, the escrow is credited with
+    /// the amount actually received, which is what the event reports. Any
+    /// funder other than the user becomes the session's sponsor (one per
+    /// session); unspent sponsored funds are returned to the sponsor first
+    /// via withdraw_sponsor_refund, and only the rest is refunded to the user.
     pub fn fund_session(ctx: Context<FundSession>, amount: u64) -> Result<()> {
         require!(amount > 0, ErrorCode::ZeroAmount);
 
+        let funder_key = ctx.accounts.funder.key();
         let session = &ctx.accounts.session;
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
         require!(
             session.state == SessionState::Open || session.state == SessionState::Active,
             ErrorCode::SessionNotFundable
         );
+        let sponsor = funding_sponsor(session, funder_key)?;
 
         let session_key = ctx.accounts.session.key();
         let current_balance = ctx.accounts.escrow_token_account.amount;
+        let decimals = ctx.accounts.payment_mint.decimals;
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_account.to_account_info(),
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.funder_token_account.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
             to: ctx.accounts.escrow_token_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
+            authority: ctx.accounts.funder.to_account_info(),
         };
         let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        // Measure what arrived (transfer fees are withheld from the destination)
+        ctx.accounts.escrow_token_account.reload()?;
+        let new_balance = ctx.accounts.escrow_token_account.amount;
+        let received = new_balance.saturating_sub(current_balance);
+
+        if let Some(sponsor) = sponsor {
+            credit_sponsor(&mut ctx.accounts.session, sponsor, received)?;
+        }
 
-        emit!(SessionFunded {
+        emit_cpi!(SessionFunded {
             session: session_key,
             amount,
-            new_balance: current_balance.checked_add(amount).unwrap_or(0),
+            received,
+            new_balance,
+            sponsor,
         });
 
         Ok(())
     }
 
-    /// Provider acknowledges session start and reserves collateral
-    pub fn ack_start(ctx: Context<AckStart>) -> Result<()> {
-        let clock = Clock::get()?;
-        let session_key = ctx.accounts.session.key();
+    /// Fund a wSOL session escrow with native SOL (user or sponsor)
+    ///
+    /// Transfers lamports straight into the session's wSOL escrow ATA and
+    /// syncs it, so users need not pre-wrap. Sponsorship works as in
+    /// fund_session. Refunds and claims are paid in wSOL; closing the
+    /// receiving wSOL account unwraps them.
+    pub fn fund_session_sol(ctx: Context<FundSessionSol>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, ErrorCode::ZeroAmount);
+
+        let funder_key = ctx.accounts.funder.key();
+        let session = &ctx.accounts.session;
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::SessionNotFundable
+        );
+        let sponsor = funding_sponsor(session, funder_key)?;
 
-        let session = &mut ctx.accounts.session;
+        let session_key = ctx.accounts.session.key();
+        let current_balance = ctx.accounts.escrow_token_account.amount;
 
-        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
-        require!(!session.acked, ErrorCode::AlreadyAcked);
-        require!(clock.slot <= session.start_deadline_slot, ErrorCode::StartDeadlinePassed);
+        let transfer_accounts = anchor_lang::system_program::Transfer {
+            from: ctx.accounts.funder.to_account_info(),
+            to: ctx.accounts.escrow_token_account.to_account_info(),
+        };
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            transfer_accounts,
+        );
+        anchor_lang::system_program::transfer(transfer_ctx, lamports)?;
 
-        let reserve_r = session.reserve_r;
+        let sync_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.escrow_token_account.to_account_info(),
+            },
+        );
+        token_interface::sync_native(sync_ctx)?;
 
-        session.acked = true;
-        session.state = SessionState::Active;
-        session.last_progress_slot = clock.slot;
+        ctx.accounts.escrow_token_account.reload()?;
+        let new_balance = ctx.accounts.escrow_token_account.amount;
+        let received = new_balance.saturating_sub(current_balance);
 
-        // For bid sessions, set SLA status to Pending
-        if session.is_bid {
-            session.sla_status = SlaStatus::Pending;
+        if let Some(sponsor) = sponsor {
+            credit_sponsor(&mut ctx.accounts.session, sponsor, received)?;
         }
 
-        // CPI to collateral_vault::reserve()
-        let cpi_accounts = Reserve {
-            position: ctx.accounts.position.to_account_info(),
-            provider: ctx.accounts.provider.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.collateral_vault_program.to_account_info(),
-            cpi_accounts,
-        );
-        collateral_vault::cpi::reserve(cpi_ctx, session_key, reserve_r)?;
-
-        emit!(SessionStarted {
+        emit_cpi!(SessionFunded {
             session: session_key,
-            started_at_slot: clock.slot,
+            amount: lamports,
+            received,
+            new_balance,
+            sponsor,
         });
 
         Ok(())
     }
 
-    /// Snapshot the nonce at SLA window start (callable by anyone after window starts)
-    pub fn snapshot_window_start(ctx: Context<SnapshotWindowStart>) -> Result<()> {
-        let clock = Clock::get()?;
+    /// Configure keeper-driven escrow top-ups (user only)
+    ///
+    /// The user separately approves the session PDA as SPL delegate on their
+    /// token account; the approved allowance caps total top-ups. A zero
+    /// top_up_amount disables auto top-up.
+    pub fn set_auto_top_up(
+        ctx: Context<SetAutoTopUp>,
+        threshold: u64,
+        top_up_amount: u64,
+    ) -> Result<()> {
         let session_key = ctx.accounts.session.key();
         let session = &mut ctx.accounts.session;
 
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(clock.slot >= session.sla_window_start_slot, ErrorCode::SlaWindowNotStarted);
-        require!(session.nonce_at_window_start == 0, ErrorCode::WindowAlreadySnapshotted);
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::InvalidSessionState
+        );
+        require!(top_up_amount == 0 || threshold > 0, ErrorCode::InvalidAutoTopUp);
 
-        session.nonce_at_window_start = session.next_permit_nonce;
+        session.auto_top_up_threshold = threshold;
+        session.auto_top_up_amount = top_up_amount;
 
-        emit!(SlaWindowStartSnapshotted {
+        emit_cpi!(AutoTopUpSet {
             session: session_key,
-            nonce_at_start: session.nonce_at_window_start,
-            slot: clock.slot,
+            threshold,
+            top_up_amount,
         });
 
         Ok(())
     }
 
-    /// Provider redeems a permit to withdraw from escrow
+    /// Set per-permit and per-window redemption limits (user only)
     ///
-    /// For bid sessions, the effective price includes the premium:
-    /// price_per_unit_effective = base_price * (1 + premium_bps/10_000)
-    pub fn redeem_permit(
-        ctx: Context<RedeemPermit>,
-        permit_nonce: u64,
-        amount: u64,
-        expiry_slot: u64,
+    /// Bounds how fast a leaked batch of signed permits can drain max_spend:
+    /// each permit may redeem at most max_permit_amount, and at most
+    /// rate_limit_amount may be redeemed per fixed window of rate_limit_slots.
+    /// Zero disables the respective limit. Changing limits restarts the window.
+    pub fn set_redemption_limits(
+        ctx: Context<SetRedemptionLimits>,
+        max_permit_amount: u64,
+        rate_limit_amount: u64,
+        rate_limit_slots: u64,
     ) -> Result<()> {
         let clock = Clock::get()?;
-
-        let session_info = ctx.accounts.session.to_account_info();
-        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
-        let escrow_balance = ctx.accounts.escrow_token_account.amount;
-
         let session = &mut ctx.accounts.session;
 
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(clock.slot <= expiry_slot, ErrorCode::PermitExpired);
-        require!(permit_nonce == session.next_permit_nonce, ErrorCode::InvalidPermitNonce);
+        require!(
+            session.state == SessionState::Open
+                || session.state == SessionState::Active
+                || session.state == SessionState::Closing,
+            ErrorCode::InvalidSessionState
+        );
+        require!(
+            (rate_limit_slots == 0) == (rate_limit_amount == 0),
+            ErrorCode::InvalidRedemptionLimits
+        );
 
-        verify_permit_signature(
-            &ctx.accounts.instructions_sysvar,
-            &session.user,
-            &session_key,
-            &session.provider,
-            permit_nonce,
-            amount,
-            expiry_slot,
-        )?;
+        session.max_permit_amount = max_permit_amount;
+        session.rate_limit_amount = rate_limit_amount;
+        session.rate_limit_slots = rate_limit_slots;
+        session.rate_window_start_slot = clock.slot;
+        session.rate_window_redeemed = 0;
+
+        emit_cpi!(RedemptionLimitsSet {
+            session: session_key,
+            max_permit_amount,
+            rate_limit_amount,
+            rate_limit_slots,
+        });
 
-        // For bid sessions, the amount should already include the premium
-        // effective_price = base_price * (1 + premium_bps/10_000)
-        // This is enforced client-side when creating permits
+        Ok(())
+    }
+
+    /// Top up escrow from the user's token account via the session's SPL delegation (permissionless)
+    ///
+    /// Callable by any keeper while the escrow balance is below the user's
+    /// threshold. Pulls auto_top_up_amount (or the remaining allowance, if
+    /// smaller) with the session PDA signing as delegate.
+    pub fn fund_session_delegated(ctx: Context<FundSessionDelegated>) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let current_balance = ctx.accounts.escrow_token_account.amount;
+        let decimals = ctx.accounts.payment_mint.decimals;
 
-        require!(amount <= escrow_balance, ErrorCode::InsufficientEscrow);
+        let session = &ctx.accounts.session;
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::SessionNotFundable
+        );
+        require!(session.auto_top_up_amount > 0, ErrorCode::AutoTopUpNotConfigured);
+        require!(
+            current_balance < session.auto_top_up_threshold,
+            ErrorCode::TopUpThresholdNotReached
+        );
 
-        let new_total_spent = session.total_spent.checked_add(amount).ok_or(ErrorCode::Overflow)?;
-        require!(new_total_spent <= session.max_spend, ErrorCode::MaxSpendExceeded);
+        let user_token = &ctx.accounts.user_token_account;
+        require!(
+            user_token.delegate == COption::Some(session_key),
+            ErrorCode::SessionNotDelegate
+        );
+        let amount = session.auto_top_up_amount.min(user_token.delegated_amount);
+        require!(amount > 0, ErrorCode::ZeroAmount);
 
         let user_key = session.user;
         let nonce_bytes = session.session_nonce.to_le_bytes();
         let bump = session.bump;
-
-        session.total_spent = new_total_spent;
-        session.next_permit_nonce = permit_nonce.checked_add(1).ok_or(ErrorCode::Overflow)?;
-        session.last_progress_slot = clock.slot;
-        let total_spent = session.total_spent;
-
-        let _ = session;
-
         let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
         let signer_seeds = &[seeds];
 
-        let cpi_accounts = Transfer {
-            from: escrow_info,
-            to: provider_token_info,
-            authority: session_info,
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.escrow_token_account.to_account_info(),
+            authority: ctx.accounts.session.to_account_info(),
         };
-        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, amount)?;
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        // Measure what arrived (transfer fees are withheld from the destination)
+        ctx.accounts.escrow_token_account.reload()?;
+        let new_balance = ctx.accounts.escrow_token_account.amount;
+        let received = new_balance.saturating_sub(current_balance);
 
-        emit!(PermitRedeemed {
+        emit_cpi!(SessionAutoToppedUp {
             session: session_key,
-            permit_nonce,
+            keeper: ctx.accounts.keeper.key(),
             amount,
-            total_spent,
+            received,
+            new_balance,
         });
 
         Ok(())
     }
 
-    /// Evaluate bandwidth SLA after window ends
+    /// Withdraw escrow above what the session can still spend (user only)
     ///
-    /// Callable by anyone after the SLA window has ended.
-    /// Compares nonce progression within the window against target.
-    /// If chunks delivered < bandwidth_min_chunks, marks SLA as Failed.
-    pub fn evaluate_bandwidth_sla(ctx: Context<EvaluateBandwidthSla>) -> Result<()> {
-        let clock = Clock::get()?;
-        let session_key = ctx.accounts.session.key();
-        let session = &mut ctx.accounts.session;
+    /// Only the balance over (max_spend - total_spent) is withdrawable, so
+    /// every permit the provider could still redeem stays fully funded. The
+    /// reservation fee accrued so far is held back on top of that.
+    pub fn withdraw_excess_escrow(ctx: Context<WithdrawExcessEscrow>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
 
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(
-            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::None,
-            ErrorCode::SlaAlreadyEvaluated
+        let slot = Clock::get()?.slot;
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &ctx.accounts.session;
+
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::InvalidSessionState
         );
-        require!(clock.slot > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
-        require!(session.nonce_at_window_start > 0, ErrorCode::WindowStartNotSnapshotted);
 
-        // Snapshot the end nonce
-        session.nonce_at_window_end = session.next_permit_nonce;
+        // Sponsored funds are only ever returned to the sponsor
+        let remaining_spend = session.max_spend.saturating_sub(session.total_spent);
+        let accrued_fee = if session.acked && session.reservation_fee_bps > 0 {
+            reservation_fee_amount(session.reserve_r, session.reservation_fee_bps, session.acked_slot, slot)?
+                .saturating_sub(session.reservation_fee_paid)
+        } else {
+            0
+        };
+        let held = remaining_spend
+            .max(session.sponsored_amount)
+            .checked_add(accrued_fee)
+            .ok_or(ErrorCode::Overflow)?;
+        let excess = escrow_balance.saturating_sub(held);
+        require!(amount <= excess, ErrorCode::ExceedsExcessEscrow);
 
-        // Calculate chunks delivered during the window
-        let chunks_delivered = session.nonce_at_window_end
-            .saturating_sub(session.nonce_at_window_start);
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
 
-        // Check if bandwidth target was met
-        let bandwidth_passed = chunks_delivered >= session.bandwidth_min_chunks as u64;
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
 
-        if !bandwidth_passed {
-            // Update failure reason
-            match session.sla_failure_reason {
-                SlaFailureReason::None => {
-                    session.sla_failure_reason = SlaFailureReason::Bandwidth;
-                }
-                SlaFailureReason::Latency => {
-                    session.sla_failure_reason = SlaFailureReason::Both;
-                }
-                _ => {}
-            }
-            session.sla_status = SlaStatus::Failed;
+        let cpi_accounts = TransferChecked {
+            from: escrow_info,
+            mint: mint_info,
+            to: user_token_info,
+            authority: session_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        emit_cpi!(ExcessEscrowWithdrawn {
+            session: session_key,
+            amount,
+            new_balance: escrow_balance - amount,
+        });
+
+        Ok(())
+    }
+
+    /// Raise a session's max_spend (user and provider co-sign)
+    ///
+    /// Recomputes base and bid coverage for the new max_spend. If the session
+    /// has started, the extra collateral is reserved via collateral_vault;
+    /// otherwise the full reserve is taken at ack_start as usual. Bucket
+    /// penalties keep the terms agreed at open.
+    pub fn increase_max_spend(ctx: Context<IncreaseMaxSpend>, new_max_spend: u64) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::InvalidSessionState
+        );
+        require!(new_max_spend > session.max_spend, ErrorCode::MaxSpendNotIncreased);
+
+        let base_coverage_p = compute_insurance_coverage(new_max_spend, session.price_per_chunk);
+        let reserve_base = base_coverage_p
+            .checked_mul(session.cr_bps as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let (bid_coverage_p, reserve_bid) = if session.is_bid {
+            let bid_cov = compute_bid_coverage(
+                new_max_spend,
+                session.premium_bps,
+                session.latency_target_ms,
+                session.bandwidth_min_chunks,
+            );
+            let res_bid = bid_cov
+                .checked_mul(session.cr_bps as u64)
+                .ok_or(ErrorCode::Overflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::Overflow)?;
+            (bid_cov, res_bid)
+        } else {
+            (0, 0)
+        };
+
+        let total_reserve = reserve_base
+            .checked_add(reserve_bid)
+            .ok_or(ErrorCode::Overflow)?;
+        let additional_reserve = total_reserve.saturating_sub(session.reserve_r);
+        let old_max_spend = session.max_spend;
+        let acked = session.acked;
+
+        session.max_spend = new_max_spend;
+        session.base_coverage_p = base_coverage_p;
+        session.bid_coverage_p = bid_coverage_p;
+        session.reserve_base = reserve_base;
+        session.reserve_bid = reserve_bid;
+        session.reserve_r = session.reserve_r.max(total_reserve);
+        let reserve_r = session.reserve_r;
+
+        let _ = session;
+
+        // CPI to collateral_vault::reserve() for the additional collateral
+        if acked && additional_reserve > 0 {
+            let cpi_accounts = Reserve {
+                position: ctx.accounts.position.to_account_info(),
+                mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                provider: ctx.accounts.provider.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                instructions_sysvar: ctx.accounts.instructions_sysvar.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                cpi_accounts,
+            );
+            collateral_vault::cpi::reserve(cpi_ctx, session_key, additional_reserve)?;
         }
 
-        emit!(SlaEvaluated {
+        emit_cpi!(MaxSpendIncreased {
             session: session_key,
-            sla_type: SlaType::Bandwidth,
-            passed: bandwidth_passed,
-            actual_value: chunks_delivered,
-            target_value: session.bandwidth_min_chunks as u64,
+            old_max_spend,
+            new_max_spend,
+            base_coverage_p,
+            bid_coverage_p,
+            reserve_r,
         });
 
         Ok(())
     }
 
-    /// Submit latency attestation from allowlisted verifier
+    /// Change the wallet receiving refunds and claim payouts (user only)
     ///
-    /// Only callable by addresses in the verifier allowlist.
-    /// If rtt_p90_ms > latency_target_ms, marks SLA as Failed.
-    pub fn submit_latency_attestation(
-        ctx: Context<SubmitLatencyAttestation>,
-        rtt_p90_ms: u16,
-        measurement_window_start: u64,
-        measurement_window_end: u64,
+    /// Rate-limited by PAYOUT_DESTINATION_COOLDOWN_SLOTS so a compromised
+    /// funding key cannot repeatedly redirect settlements.
+    pub fn set_payout_destination(
+        ctx: Context<SetPayoutDestination>,
+        new_destination: Pubkey,
     ) -> Result<()> {
+        let clock = Clock::get()?;
         let session_key = ctx.accounts.session.key();
         let session = &mut ctx.accounts.session;
 
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(!session.latency_attested, ErrorCode::LatencyAlreadyAttested);
-
-        // Validate measurement window overlaps with SLA window
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
         require!(
-            measurement_window_start <= session.sla_window_end_slot &&
-            measurement_window_end >= session.sla_window_start_slot,
-            ErrorCode::InvalidMeasurementWindow
+            session.state == SessionState::Open
+                || session.state == SessionState::Active
+                || session.state == SessionState::Closing,
+            ErrorCode::InvalidSessionState
         );
+        let next_allowed_slot = session.payout_destination_set_slot
+            .checked_add(PAYOUT_DESTINATION_COOLDOWN_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(clock.slot >= next_allowed_slot, ErrorCode::PayoutDestinationCooldown);
 
-        session.latency_attested = true;
+        let old_destination = session.payout_destination;
+        session.payout_destination = new_destination;
+        session.payout_destination_set_slot = clock.slot;
 
-        // Check if latency target was violated
-        let latency_passed = rtt_p90_ms <= session.latency_target_ms;
+        emit_cpi!(PayoutDestinationUpdated {
+            session: session_key,
+            old_destination,
+            new_destination,
+            slot: clock.slot,
+        });
 
-        if !latency_passed {
-            // Update failure reason
-            match session.sla_failure_reason {
-                SlaFailureReason::None => {
-                    session.sla_failure_reason = SlaFailureReason::Latency;
-                }
-                SlaFailureReason::Bandwidth => {
-                    session.sla_failure_reason = SlaFailureReason::Both;
-                }
-                _ => {}
-            }
-            session.sla_status = SlaStatus::Failed;
+        Ok(())
+    }
+
+    /// Split claim payouts across up to MAX_PAYOUT_SPLITS recipients (user only, before provider ack)
+    ///
+    /// Applies to claim_stall, claim_sla_failure and terminate_for_cause
+    /// slashes. Shares must sum to 10_000 bps; each recipient's share is
+    /// floored and the rounding dust goes to the first recipient. An empty
+    /// list restores payment to the payout destination. Escrow refunds always
+    /// go to the payout destination.
+    pub fn set_payout_splits(
+        ctx: Context<SetPayoutSplits>,
+        splits: Vec<PayoutSplit>,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+
+        require!(splits.len() <= MAX_PAYOUT_SPLITS, ErrorCode::InvalidPayoutSplits);
+        let mut total_bps: u32 = 0;
+        for (i, split) in splits.iter().enumerate() {
+            require!(
+                split.recipient != Pubkey::default()
+                    && split.bps > 0
+                    && !splits[..i].iter().any(|s| s.recipient == split.recipient),
+                ErrorCode::InvalidPayoutSplits
+            );
+            total_bps += split.bps as u32;
         }
+        require!(splits.is_empty() || total_bps == 10_000, ErrorCode::InvalidPayoutSplits);
+
+        session.payout_splits = [PayoutSplit::default(); MAX_PAYOUT_SPLITS];
+        session.payout_splits[..splits.len()].copy_from_slice(&splits);
+        session.payout_split_count = splits.len() as u8;
 
-        emit!(SlaEvaluated {
+        emit_cpi!(PayoutSplitsSet {
             session: session_key,
-            sla_type: SlaType::Latency,
-            passed: latency_passed,
-            actual_value: rtt_p90_ms as u64,
-            target_value: session.latency_target_ms as u64,
+            splits,
         });
 
-        emit!(LatencyAttestationSubmitted {
+        Ok(())
+    }
+
+    /// Turn an unfunded session into a shared session (user only, before provider ack)
+    ///
+    /// Shared sessions are funded by any number of contributors via
+    /// contribute_shared. The payout destination is pinned to the session
+    /// itself, so every refund and cash claim settles into the escrow; once
+    /// the session is Closed or Claimed each contributor withdraws a share of
+    /// that pool pro rata to their contribution.
+    pub fn enable_shared_session(ctx: Context<EnableSharedSession>) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            escrow_balance == 0
+                && session.settlement_mode == SettlementMode::Cash
+                && session.payout_split_count == 0,
+            ErrorCode::InvalidSharedSessionSetup
+        );
+
+        session.is_shared = true;
+        session.payout_destination = session_key;
+
+        emit_cpi!(SharedSessionEnabled {
             session: session_key,
-            verifier: ctx.accounts.verifier.key(),
-            rtt_p90_ms,
-            measurement_window_start,
-            measurement_window_end,
+            user: session.user,
         });
 
         Ok(())
     }
 
-    /// Mark SLA as Met (callable after window ends if no failures)
-    pub fn finalize_sla_met(ctx: Context<FinalizeSla>) -> Result<()> {
-        let clock = Clock::get()?;
-        let session = &mut ctx.accounts.session;
+    /// Co-fund a shared session's escrow (any contributor)
+    ///
+    /// The amount actually received (net of Token-2022 transfer fees) is
+    /// credited to the contributor's ["contrib", session, contributor] record.
+    /// Only accepted before ack_start: once spending starts, a late
+    /// contribution would share pro rata in what earlier ones already paid.
+    pub fn contribute_shared(ctx: Context<ContributeShared>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
 
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(session.sla_status == SlaStatus::Pending, ErrorCode::SlaAlreadyEvaluated);
-        require!(clock.slot > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
-        require!(session.sla_failure_reason == SlaFailureReason::None, ErrorCode::SlaHasFailures);
+        let session = &ctx.accounts.session;
+        require!(session.is_shared, ErrorCode::NotSharedSession);
+        require!(session.state == SessionState::Open, ErrorCode::SessionNotFundable);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
 
-        session.sla_status = SlaStatus::Met;
+        let session_key = ctx.accounts.session.key();
+        let contributor_key = ctx.accounts.contributor.key();
+        let current_balance = ctx.accounts.escrow_token_account.amount;
+        let decimals = ctx.accounts.payment_mint.decimals;
 
-        emit!(SlaFinalized {
-            session: ctx.accounts.session.key(),
-            status: SlaStatus::Met,
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.contributor_token_account.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.escrow_token_account.to_account_info(),
+            authority: ctx.accounts.contributor.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        // Measure what arrived (transfer fees are withheld from the destination)
+        ctx.accounts.escrow_token_account.reload()?;
+        let received = ctx.accounts.escrow_token_account.amount.saturating_sub(current_balance);
+
+        let contribution = &mut ctx.accounts.contribution;
+        let is_new = contribution.session == Pubkey::default();
+        if is_new {
+            contribution.session = session_key;
+            contribution.contributor = contributor_key;
+            contribution.amount = 0;
+            contribution.bump = ctx.bumps.contribution;
+        }
+        contribution.amount = contribution.amount.checked_add(received).ok_or(ErrorCode::Overflow)?;
+        let contributor_total = contribution.amount;
+
+        let session = &mut ctx.accounts.session;
+        if is_new {
+            session.shared_contributor_count = session.shared_contributor_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        session.shared_total_contributed = session.shared_total_contributed
+            .checked_add(received)
+            .ok_or(ErrorCode::Overflow)?;
+        let total_contributed = session.shared_total_contributed;
+
+        emit_cpi!(SharedContributionAdded {
+            session: session_key,
+            contributor: contributor_key,
+            received,
+            contributor_total,
+            total_contributed,
         });
 
         Ok(())
     }
 
-    /// Claim SLA failure payout
+    /// Withdraw a contributor's pro-rata share of a settled shared session
     ///
-    /// Requires sla_status == Failed.
-    /// Computes payout = (base_coverage_p + bid_coverage_p) * fail_payout_bps / 10_000
-    /// Pays from reserve_bid first, then reserve_base if needed.
-    pub fn claim_sla_failure(ctx: Context<ClaimSlaFailure>) -> Result<()> {
+    /// The escrow balance is snapshotted on the first withdrawal after the
+    /// session is Closed or Claimed; each contributor receives
+    /// pool * contribution / total_contributed, and the last one also sweeps
+    /// the rounding dust. The contribution record is closed to the contributor.
+    pub fn withdraw_shared_share(ctx: Context<WithdrawSharedShare>) -> Result<()> {
         let session_info = ctx.accounts.session.to_account_info();
         let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let contributor_token_info = ctx.accounts.contributor_token_account.to_account_info();
         let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
+        let contributor_key = ctx.accounts.contributor.key();
         let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let contributed = ctx.accounts.contribution.amount;
 
         let session = &mut ctx.accounts.session;
 
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.sla_status == SlaStatus::Failed, ErrorCode::SlaNotFailed);
+        require!(session.is_shared, ErrorCode::NotSharedSession);
         require!(
-            session.state == SessionState::Active,
+            session.state == SessionState::Closed || session.state == SessionState::Claimed,
             ErrorCode::InvalidSessionState
         );
 
-        // Calculate total insured amount (base + bid coverage)
-        let total_insured = session.base_coverage_p
-            .checked_add(session.bid_coverage_p)
-            .ok_or(ErrorCode::Overflow)?;
-
-        // Calculate payout using session's fail_payout_bps
-        let payout = total_insured
-            .checked_mul(session.fail_payout_bps as u64)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(10000)
+        if !session.shared_pool_snapshotted {
+            session.shared_pool = escrow_balance;
+            session.shared_pool_snapshotted = true;
+        }
+        session.shared_settled_count = session.shared_settled_count
+            .checked_add(1)
             .ok_or(ErrorCode::Overflow)?;
 
-        // Cap payout at total reserved
-        let actual_payout = payout.min(session.reserve_r);
+        let share = if session.shared_settled_count >= session.shared_contributor_count {
+            // Last contributor sweeps the rounding dust
+            escrow_balance
+        } else {
+            ((session.shared_pool as u128)
+                .checked_mul(contributed as u128)
+                .ok_or(ErrorCode::Overflow)?
+                .checked_div(session.shared_total_contributed as u128)
+                .unwrap_or(0) as u64)
+                .min(escrow_balance)
+        };
 
         let user_key = session.user;
         let nonce_bytes = session.session_nonce.to_le_bytes();
         let bump = session.bump;
-        let reserve_r = session.reserve_r;
-
-        session.state = SessionState::Claimed;
 
         let _ = session;
 
-        // CPI to collateral_vault::slash_and_pay()
-        let cpi_accounts = SlashAndPay {
-            position: ctx.accounts.position.to_account_info(),
-            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
-            user_token_account: ctx.accounts.user_token_account.to_account_info(),
-            session_authority: session_info.clone(),
-            token_program: token_program_info.clone(),
-        };
-        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-        let signer_seeds = &[seeds];
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.collateral_vault_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-        collateral_vault::cpi::slash_and_pay(cpi_ctx, session_key, actual_payout)?;
-
-        // Release remaining reserved collateral
-        let remaining_reserve = reserve_r.saturating_sub(actual_payout);
-        if remaining_reserve > 0 {
-            let release_accounts = Release {
-                position: ctx.accounts.position.to_account_info(),
-                session_authority: ctx.accounts.session.to_account_info(),
-            };
-            let release_ctx = CpiContext::new_with_signer(
-                ctx.accounts.collateral_vault_program.to_account_info(),
-                release_accounts,
-                signer_seeds,
-            );
-            collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
-        }
-
-        // Refund remaining escrow to user
-        if escrow_balance > 0 {
+        if share > 0 {
             let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
             let signer_seeds = &[seeds];
 
-            let cpi_accounts = Transfer {
+            let cpi_accounts = TransferChecked {
                 from: escrow_info,
-                to: user_token_info,
-                authority: ctx.accounts.session.to_account_info(),
+                mint: mint_info,
+                to: contributor_token_info,
+                authority: session_info,
             };
             let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-            token::transfer(cpi_ctx, escrow_balance)?;
+            token_interface::transfer_checked(cpi_ctx, share, decimals)?;
         }
 
-        emit!(SlaFailureClaimed {
+        emit_cpi!(SharedShareWithdrawn {
             session: session_key,
-            payout: actual_payout,
-            escrow_refunded: escrow_balance,
-            remaining_reserve_released: remaining_reserve,
-            failure_reason: ctx.accounts.session.sla_failure_reason,
+            contributor: contributor_key,
+            contributed,
+            amount: share,
         });
 
         Ok(())
     }
 
-    /// User initiates session close
-    pub fn close_session(ctx: Context<CloseSession>) -> Result<()> {
-        let session_key = ctx.accounts.session.key();
-        let session = &mut ctx.accounts.session;
+    /// Create the program's event queue (permissionless, one per program)
+    pub fn init_event_queue(ctx: Context<InitEventQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.event_queue;
+        queue.next_seq = 0;
+        queue.head = 0;
+        queue.bump = ctx.bumps.event_queue;
 
-        require!(
-            session.state == SessionState::Active || session.state == SessionState::Open,
-            ErrorCode::InvalidSessionState
-        );
+        Ok(())
+    }
 
-        session.state = SessionState::Closing;
-
-        emit!(SessionClosing { session: session_key });
+    /// Create the provider's concurrent session counter (provider pays)
+    pub fn init_active_sessions(ctx: Context<InitActiveSessions>) -> Result<()> {
+        let active_sessions = &mut ctx.accounts.active_sessions;
+        active_sessions.provider = ctx.accounts.provider.key();
+        active_sessions.count = 0;
+        active_sessions.bump = ctx.bumps.active_sessions;
 
         Ok(())
     }
 
-    /// Finalize session close and release collateral
-    pub fn finalize_close(ctx: Context<FinalizeClose>) -> Result<()> {
-        let session_info = ctx.accounts.session.to_account_info();
-        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
+    /// Provider escrows an extra bond for this session
+    ///
+    /// Held in the session-owned ["bond", session] token account. Claims pay
+    /// from the bond before slashing the provider's pooled collateral; what
+    /// is left is returned by withdraw_session_bond once the session ends.
+    /// Only accepted after ack_start, once the provider can no longer be
+    /// reassigned away from the bond.
+    pub fn post_session_bond(ctx: Context<PostSessionBond>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+
+        let session = &ctx.accounts.session;
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::InvalidSessionState
+        );
+        require!(session.acked, ErrorCode::SessionNotStarted);
+
         let session_key = ctx.accounts.session.key();
-        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let current_balance = ctx.accounts.session_bond.amount;
+        let decimals = ctx.accounts.payment_mint.decimals;
+
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.provider_token_account.to_account_info(),
+            mint: ctx.accounts.payment_mint.to_account_info(),
+            to: ctx.accounts.session_bond.to_account_info(),
+            authority: ctx.accounts.provider.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        // Measure what arrived (transfer fees are withheld from the destination)
+        ctx.accounts.session_bond.reload()?;
+        let new_balance = ctx.accounts.session_bond.amount;
+        let received = new_balance.saturating_sub(current_balance);
 
         let session = &mut ctx.accounts.session;
+        session.bond_posted = session.bond_posted.checked_add(received).ok_or(ErrorCode::Overflow)?;
 
-        require!(session.state == SessionState::Closing, ErrorCode::InvalidSessionState);
+        emit_cpi!(SessionBondPosted {
+            session: session_key,
+            provider: session.provider,
+            received,
+            bond_balance: new_balance,
+        });
 
-        let user_key = session.user;
-        let nonce_bytes = session.session_nonce.to_le_bytes();
-        let bump = session.bump;
-        let reserve_r = session.reserve_r;
-        let was_active = session.acked;
+        Ok(())
+    }
 
-        // For bid sessions that were never evaluated as failed, mark SLA as Met
-        if session.is_bid && session.sla_status == SlaStatus::Pending {
-            session.sla_status = SlaStatus::Met;
-        }
+    /// Return the unslashed session bond to the provider after the session ends
+    pub fn withdraw_session_bond(ctx: Context<WithdrawSessionBond>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let bond_balance = ctx.accounts.session_bond.amount;
+        let decimals = ctx.accounts.payment_mint.decimals;
 
-        session.state = SessionState::Closed;
+        let session = &ctx.accounts.session;
+        require!(
+            session.state == SessionState::Closed || session.state == SessionState::Claimed,
+            ErrorCode::InvalidSessionState
+        );
 
-        let _ = session;
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
 
-        // CPI to collateral_vault::release() if session was active
-        if was_active {
-            let cpi_accounts = Release {
-                position: ctx.accounts.position.to_account_info(),
-                session_authority: ctx.accounts.session.to_account_info(),
+        if bond_balance > 0 {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.session_bond.to_account_info(),
+                mint: ctx.accounts.payment_mint.to_account_info(),
+                to: ctx.accounts.provider_token_account.to_account_info(),
+                authority: session_info.clone(),
             };
-            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-            let signer_seeds = &[seeds];
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.collateral_vault_program.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
                 cpi_accounts,
                 signer_seeds,
             );
-            collateral_vault::cpi::release(cpi_ctx, session_key, reserve_r)?;
+            token_interface::transfer_checked(cpi_ctx, bond_balance, decimals)?;
         }
 
-        if escrow_balance > 0 {
-            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-            let signer_seeds = &[seeds];
-
-            let cpi_accounts = Transfer {
-                from: escrow_info,
-                to: user_token_info,
-                authority: session_info,
-            };
-            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-            token::transfer(cpi_ctx, escrow_balance)?;
-        }
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.session_bond.to_account_info(),
+            destination: ctx.accounts.provider.to_account_info(),
+            authority: session_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_ctx)?;
 
-        emit!(SessionClosed {
+        emit_cpi!(SessionBondWithdrawn {
             session: session_key,
-            refunded: escrow_balance,
+            provider: provider_key,
+            amount: bond_balance,
         });
 
         Ok(())
     }
 
-    /// Claim for no-start (provider didn't ack - no collateral was reserved)
-    pub fn claim_no_start(ctx: Context<ClaimNoStart>) -> Result<()> {
+    /// Provider acknowledges session start and reserves collateral
+    ///
+    /// accepted_cr_bps must equal the session's collateral ratio, so a
+    /// provider never reserves against a ratio it did not agree to.
+    pub fn ack_start(ctx: Context<AckStart>, accepted_cr_bps: u16) -> Result<()> {
         let clock = Clock::get()?;
-
-        let session_info = ctx.accounts.session.to_account_info();
-        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
-        let escrow_balance = ctx.accounts.escrow_token_account.amount;
 
         let session = &mut ctx.accounts.session;
 
         require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
-        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
-        require!(clock.slot > session.start_deadline_slot, ErrorCode::DeadlineNotPassed);
+        require!(!session.acked, ErrorCode::AlreadyAcked);
+        require!(clock.slot <= session.start_deadline_slot, ErrorCode::StartDeadlinePassed);
+        require!(accepted_cr_bps == session.cr_bps, ErrorCode::CrNotAccepted);
 
-        // No collateral was reserved (provider never acked), just refund escrow
-        let user_key = session.user;
-        let nonce_bytes = session.session_nonce.to_le_bytes();
-        let bump = session.bump;
+        let reserve_r = session.reserve_r;
 
-        session.state = SessionState::Claimed;
+        // Enforce the mode's per-provider concurrency cap
+        let active_sessions = &mut ctx.accounts.active_sessions;
+        let cap = ctx.accounts.mode.max_concurrent_sessions;
+        require!(
+            cap == 0 || active_sessions.count < cap,
+            ErrorCode::ConcurrentSessionCapReached
+        );
+        active_sessions.count = active_sessions.count.checked_add(1).ok_or(ErrorCode::Overflow)?;
 
-        let _ = session;
+        session.acked = true;
+        let state_changed = transition_state(session, session_key, SessionState::Active, clock.slot);
+        session.last_progress_slot = clock.slot;
+        session.acked_slot = clock.slot;
+        session.duration_end_slot = duration_end_slot(session.max_duration_slots, clock.slot)?;
+        session.reservation_fee_bps = ctx.accounts.mode.reservation_fee_bps;
 
-        if escrow_balance > 0 {
-            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-            let signer_seeds = &[seeds];
+        // For bid sessions, set SLA status to Pending
+        if session.is_bid {
+            session.sla_status = SlaStatus::Pending;
+        }
 
-            let cpi_accounts = Transfer {
-                from: escrow_info,
-                to: user_token_info,
-                authority: session_info,
-            };
-            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-            token::transfer(cpi_ctx, escrow_balance)?;
+        // CPI to collateral_vault::reserve()
+        let cpi_accounts = Reserve {
+            position: ctx.accounts.position.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            reservation: ctx.accounts.reservation.to_account_info(),
+            provider: ctx.accounts.provider.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            vault_callers: ctx.accounts.vault_callers.to_account_info(),
+            instructions_sysvar: ctx.accounts.instructions_sysvar.to_account_info(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            program: ctx.accounts.collateral_vault_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.collateral_vault_program.to_account_info(),
+            cpi_accounts,
+        );
+        collateral_vault::cpi::reserve(cpi_ctx, session_key, reserve_r)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionStarted, session_key, reserve_r)?;
+
+        emit_cpi!(SessionStarted {
+            session: session_key,
+            started_at_slot: clock.slot,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Snapshot the nonce at SLA window start (callable by anyone after window starts)
+    pub fn snapshot_window_start(ctx: Context<SnapshotWindowStart>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(clock.slot >= session.sla_window_start_slot, ErrorCode::SlaWindowNotStarted);
+        require!(session.nonce_at_window_start == 0, ErrorCode::WindowAlreadySnapshotted);
+
+        session.nonce_at_window_start = session.next_permit_nonce;
+
+        emit_cpi!(SlaWindowStartSnapshotted {
+            session: session_key,
+            nonce_at_start: session.nonce_at_window_start,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Record a bandwidth checkpoint inside the SLA window (permissionless)
+    ///
+    /// Checkpoints are at least sla_window_slots / MAX_BANDWIDTH_CHECKPOINTS
+    /// apart. Delivery between consecutive checkpoints is checked against the
+    /// bandwidth target prorated to the sub-window's length, so a provider
+    /// can't backload all delivery into the end of the window; any failed
+    /// sub-window fails the bandwidth SLA at evaluate_bandwidth_sla. The first
+    /// checkpoint also serves as the window-start nonce snapshot.
+    pub fn snapshot_bandwidth_checkpoint(ctx: Context<SnapshotBandwidthCheckpoint>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(clock.slot >= session.sla_window_start_slot, ErrorCode::SlaWindowNotStarted);
+        require!(clock.slot <= session.sla_window_end_slot, ErrorCode::ReportOutsideSlaWindow);
+
+        let delivered = delivered_chunk_counter(session);
+        let mut subwindow_passed = true;
+        if let Some(previous) = last_bandwidth_checkpoint(session) {
+            let min_spacing = session.sla_window_slots / MAX_BANDWIDTH_CHECKPOINTS as u64;
+            let elapsed = clock.slot.saturating_sub(previous.slot);
+            require!(elapsed >= min_spacing.max(1), ErrorCode::CheckpointTooSoon);
+
+            subwindow_passed = bandwidth_target_met(
+                session,
+                delivered.saturating_sub(previous.delivered),
+                elapsed,
+            );
+            if !subwindow_passed {
+                session.bandwidth_subwindows_failed = session.bandwidth_subwindows_failed
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        } else if session.nonce_at_window_start == 0 {
+            session.nonce_at_window_start = session.next_permit_nonce;
         }
 
-        emit!(ClaimPaid {
+        let index = (session.bandwidth_checkpoint_count as usize) % MAX_BANDWIDTH_CHECKPOINTS;
+        session.bandwidth_checkpoints[index] = BandwidthCheckpoint {
+            slot: clock.slot,
+            delivered,
+        };
+        session.bandwidth_checkpoint_count = session.bandwidth_checkpoint_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit_cpi!(BandwidthCheckpointed {
             session: session_key,
-            claim_type: ClaimType::NoStart,
-            payout: 0, // No payout since no collateral was reserved
-            escrow_refunded: escrow_balance,
+            index: index as u8,
+            slot: clock.slot,
+            delivered,
+            subwindow_passed,
         });
 
         Ok(())
     }
 
-    /// Claim for stall - slash provider collateral and pay user
-    pub fn claim_stall(ctx: Context<ClaimStall>) -> Result<()> {
+    /// Provider redeems a permit to withdraw from escrow
+    ///
+    /// For bid sessions, the effective price includes the premium:
+    /// price_per_unit_effective = base_price * (1 + premium_bps/10_000)
+    ///
+    /// Sessions with chained receipts must pass `chain`: its chain_hash must
+    /// equal sha256(delivery_chain_hash || permit_nonce || chunks_digest) and
+    /// is appended to the signed permit message, so the user attests to the
+    /// delivery log with every payment.
+    pub fn redeem_permit(
+        ctx: Context<RedeemPermit>,
+        permit_nonce: u64,
+        amount: u64,
+        expiry_slot: u64,
+        chain: Option<DeliveryChainLink>,
+    ) -> Result<()> {
         let clock = Clock::get()?;
 
         let session_info = ctx.accounts.session.to_account_info();
         let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
         let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
         let escrow_balance = ctx.accounts.escrow_token_account.amount;
 
         let session = &mut ctx.accounts.session;
 
-        require!(session.state == SessionState::Active, ErrorCode::InvalidSessionState);
-        require!(session.acked, ErrorCode::SessionNotStarted);
-
-        let stall_deadline = session.last_progress_slot
-            .checked_add(session.stall_timeout_slots)
-            .ok_or(ErrorCode::Overflow)?;
-        require!(clock.slot > stall_deadline, ErrorCode::StallTimeoutNotReached);
+        let PermitRedemption { credits_used, referral_amount, provider_amount } = apply_permit_redemption(
+            session,
+            &ctx.accounts.instructions_sysvar,
+            &session_key,
+            permit_nonce,
+            amount,
+            expiry_slot,
+            chain,
+            escrow_balance,
+            clock.slot,
+        )?;
 
-        let payout = session.base_coverage_p.min(session.reserve_r);
         let user_key = session.user;
         let nonce_bytes = session.session_nonce.to_le_bytes();
         let bump = session.bump;
-
-        session.state = SessionState::Claimed;
+        let total_spent = session.total_spent;
+        let receipt_hash = session.receipt_hash;
+        let delivery_chain_hash = session.delivery_chain_hash;
 
         let _ = session;
 
-        // CPI to collateral_vault::slash_and_pay()
-        let cpi_accounts = SlashAndPay {
-            position: ctx.accounts.position.to_account_info(),
-            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
-            user_token_account: ctx.accounts.user_token_account.to_account_info(),
-            session_authority: session_info.clone(),
-            token_program: token_program_info.clone(),
-        };
         let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
         let signer_seeds = &[seeds];
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.collateral_vault_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-        collateral_vault::cpi::slash_and_pay(cpi_ctx, session_key, payout)?;
 
-        // Refund remaining escrow to user
-        if escrow_balance > 0 {
-            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-            let signer_seeds = &[seeds];
+        if referral_amount > 0 {
+            let referrer_token_info = ctx
+                .accounts
+                .referrer_token_account
+                .as_ref()
+                .ok_or(ErrorCode::ReferrerAccountRequired)?
+                .to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: escrow_info.clone(),
+                mint: mint_info.clone(),
+                to: referrer_token_info,
+                authority: session_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, referral_amount, decimals)?;
+        }
 
-            let cpi_accounts = Transfer {
+        if provider_amount > 0 {
+            let cpi_accounts = TransferChecked {
                 from: escrow_info,
-                to: user_token_info,
+                mint: mint_info,
+                to: provider_token_info,
                 authority: session_info,
             };
             let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-            token::transfer(cpi_ctx, escrow_balance)?;
+            token_interface::transfer_checked(cpi_ctx, provider_amount, decimals)?;
         }
 
-        emit!(ClaimPaid {
+        record_event(&mut ctx.accounts.event_queue, EventKind::PermitRedeemed, session_key, amount)?;
+
+        emit_cpi!(PermitRedeemed {
             session: session_key,
-            claim_type: ClaimType::Stall,
-            payout,
-            escrow_refunded: escrow_balance,
+            permit_nonce,
+            amount,
+            total_spent,
+            credits_used,
+            referral_amount,
+            receipt_hash,
+            delivery_chain_hash,
+            slot: clock.slot,
         });
 
         Ok(())
     }
 
-    // =========================================================================
-    // BUCKETED SLA INSTRUCTIONS (Phase 1: Latency + PrivacyMode only)
-    // =========================================================================
-
-    /// Report a bucket failure (latency or privacy mode violation)
+    /// Provider redeems a permit straight into their collateral position
     ///
-    /// Requires Ed25519 signature verification via Instructions sysvar.
-    /// The Ed25519 precompile instruction must immediately precede this instruction.
-    ///
-    /// Effects:
-    /// - First failure: sets first_violation_slot, terminate_deadline_slot, sla_status = Violated
-    /// - Sets bucket bit in bitmap (idempotent protection)
-    /// - Increments buckets_failed counter
-    /// - Combines failure reason
-    pub fn report_bucket_failure(
-        ctx: Context<ReportBucketFailure>,
-        bucket_index: u64,
-        bucket_start_slot: u64,
-        failure_reason: SlaFailureReason,
+    /// Validated exactly like redeem_permit, but the provider's cash share is
+    /// re-deposited via collateral_vault::deposit in the same transaction,
+    /// compounding earnings into the position (and its stake weight). The
+    /// amount deposited is what actually reached provider_token_account, net
+    /// of any transfer fee.
+    pub fn redeem_permit_to_collateral(
+        ctx: Context<RedeemPermitToCollateral>,
+        permit_nonce: u64,
+        amount: u64,
+        expiry_slot: u64,
+        chain: Option<DeliveryChainLink>,
     ) -> Result<()> {
         let clock = Clock::get()?;
-        let now = clock.slot;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let provider_balance_before = ctx.accounts.provider_token_account.amount;
+
         let session = &mut ctx.accounts.session;
 
-        // === Status guards ===
-        require!(session.is_bid, ErrorCode::NotBidSession);
-        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(
-            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated,
-            ErrorCode::SlaAlreadyEvaluated
-        );
+        let PermitRedemption { credits_used, referral_amount, provider_amount } = apply_permit_redemption(
+            session,
+            &ctx.accounts.instructions_sysvar,
+            &session_key,
+            permit_nonce,
+            amount,
+            expiry_slot,
+            chain,
+            escrow_balance,
+            clock.slot,
+        )?;
+        require!(provider_amount > 0, ErrorCode::NothingToDeposit);
 
-        // === Window bounds ===
-        require!(
-            now >= session.sla_window_start_slot && now <= session.sla_window_end_slot,
-            ErrorCode::ReportOutsideSlaWindow
-        );
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let mode_id = session.mode_id;
+        let total_spent = session.total_spent;
+        let receipt_hash = session.receipt_hash;
+        let delivery_chain_hash = session.delivery_chain_hash;
 
-        // === Termination deadline (if already violated) ===
-        if session.sla_status == SlaStatus::Violated {
-            require!(
-                now <= session.terminate_deadline_slot,
-                ErrorCode::ReportAfterDeadline
-            );
+        let _ = session;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        if referral_amount > 0 {
+            let referrer_token_info = ctx
+                .accounts
+                .referrer_token_account
+                .as_ref()
+                .ok_or(ErrorCode::ReferrerAccountRequired)?
+                .to_account_info();
+            let cpi_accounts = TransferChecked {
+                from: escrow_info.clone(),
+                mint: mint_info.clone(),
+                to: referrer_token_info,
+                authority: session_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, referral_amount, decimals)?;
         }
 
-        // === Bucket bounds ===
-        require!(bucket_index < session.buckets_total, ErrorCode::BucketIndexOutOfBounds);
+        let cpi_accounts = TransferChecked {
+            from: escrow_info,
+            mint: mint_info.clone(),
+            to: provider_token_info.clone(),
+            authority: session_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, signer_seeds);
+        token_interface::transfer_checked(cpi_ctx, provider_amount, decimals)?;
 
-        // === Bucket alignment ===
-        let expected_bucket_start = checked_bucket_start(
-            session.sla_window_start_slot,
-            bucket_index,
-            session.bucket_slots,
-        ).ok_or(ErrorCode::Overflow)?;
-        require!(bucket_start_slot == expected_bucket_start, ErrorCode::BucketSlotMismatch);
+        // Deposit what actually arrived (net of any transfer fee)
+        ctx.accounts.provider_token_account.reload()?;
+        let deposited = ctx.accounts.provider_token_account.amount
+            .checked_sub(provider_balance_before)
+            .ok_or(ErrorCode::Overflow)?;
 
-        // === Attester auth ===
-        require!(
-            ctx.accounts.verifier.key() == session.verifier_pubkey,
-            ErrorCode::InvalidAttester
+        let deposit_accounts = Deposit {
+            position: ctx.accounts.position.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            mode: ctx.accounts.mode.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+            provider_token_account: provider_token_info,
+            collateral_mint: mint_info,
+            position_nft_mint: ctx.accounts.position_nft_mint.to_account_info(),
+            provider_nft_account: ctx.accounts.provider_nft_account.to_account_info(),
+            nft_metadata: ctx.accounts.nft_metadata.as_ref().map(|a| a.to_account_info()),
+            nft_master_edition: ctx.accounts.nft_master_edition.as_ref().map(|a| a.to_account_info()),
+            token_metadata_program: ctx.accounts.token_metadata_program.as_ref().map(|a| a.to_account_info()),
+            provider: ctx.accounts.provider.to_account_info(),
+            token_program: ctx.accounts.position_nft_token_program.to_account_info(),
+            collateral_token_program: token_program_info,
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            program: ctx.accounts.collateral_vault_program.to_account_info(),
+        };
+        let deposit_ctx = CpiContext::new(
+            ctx.accounts.collateral_vault_program.to_account_info(),
+            deposit_accounts,
         );
+        collateral_vault::cpi::deposit(deposit_ctx, mode_id, deposited)?;
 
-        // === Ed25519 signature verification via Instructions sysvar ===
-        verify_bucket_failure_signature(
-            &ctx.accounts.instructions_sysvar,
-            &session.verifier_pubkey,
-            &session_key,
-            bucket_index,
-            bucket_start_slot,
-            failure_reason,
-        )?;
+        record_event(&mut ctx.accounts.event_queue, EventKind::PermitRedeemed, session_key, amount)?;
+
+        emit_cpi!(PermitRedeemed {
+            session: session_key,
+            permit_nonce,
+            amount,
+            total_spent,
+            credits_used,
+            referral_amount,
+            receipt_hash,
+            delivery_chain_hash,
+            slot: clock.slot,
+        });
+
+        emit_cpi!(PermitRedeemedToCollateral {
+            session: session_key,
+            provider: ctx.accounts.provider.key(),
+            mode_id,
+            deposited,
+        });
+
+        Ok(())
+    }
+
+    /// Set the marketplace referrer paid a share of each redemption (user only, before provider ack)
+    ///
+    /// referral_bps of every cash redemption goes to the referrer's token
+    /// account instead of the provider, bounded by the registry's
+    /// max_referral_bps. A zero share clears the referral.
+    pub fn set_referrer(ctx: Context<SetReferrer>, referrer: Pubkey, referral_bps: u16) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let max_referral_bps = ctx.accounts.registry.max_referral_bps;
+        let session = &mut ctx.accounts.session;
 
-        // === Bitmap deduplication ===
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(referral_bps <= max_referral_bps, ErrorCode::InvalidReferral);
         require!(
-            !bit_is_set(&session.buckets_failed_bitmap, bucket_index),
-            ErrorCode::BucketAlreadyReported
+            referral_bps == 0 || referrer != Pubkey::default(),
+            ErrorCode::InvalidReferral
         );
-        set_bit(&mut session.buckets_failed_bitmap, bucket_index);
 
-        // === First violation: set termination window ===
-        if session.sla_status == SlaStatus::Pending {
-            session.first_violation_slot = now;
-            session.terminate_deadline_slot = now.saturating_add(session.terminate_window_slots);
-            session.sla_status = SlaStatus::Violated;
-        }
+        session.referrer = if referral_bps == 0 { Pubkey::default() } else { referrer };
+        session.referral_bps = referral_bps;
 
-        // === Increment failure counter ===
-        session.buckets_failed = session.buckets_failed
-            .checked_add(1)
-            .ok_or(ErrorCode::Overflow)?;
+        emit_cpi!(ReferrerSet {
+            session: session_key,
+            referrer: session.referrer,
+            referral_bps,
+        });
 
-        // === Accrue penalty ===
-        session.penalty_accrued = session.penalty_accrued
-            .checked_add(session.bucket_penalty)
-            .ok_or(ErrorCode::Overflow)?
-            .min(session.reserve_r);  // Cap at total collateral
+        Ok(())
+    }
 
-        // === Combine failure reason ===
-        session.sla_failure_reason = combine_failure_reason(
-            session.sla_failure_reason,
-            failure_reason,
-        );
+    /// Require every permit to extend the hash-chained delivery log (user only, before provider ack)
+    ///
+    /// Once enabled, redeem_permit must carry the digest of the chunks each
+    /// permit pays for, chaining a tamper-evident, user-signed delivery log
+    /// in delivery_chain_hash for use in disputes.
+    pub fn set_chained_receipts(ctx: Context<SetChainedReceipts>, required: bool) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
 
-        emit!(BucketFailureReported {
+        session.chained_receipts_required = required;
+
+        emit_cpi!(ChainedReceiptsSet {
             session: session_key,
-            bucket_index,
-            bucket_start_slot,
-            failure_reason,
-            buckets_failed: session.buckets_failed,
-            penalty_accrued: session.penalty_accrued,
-            is_first_violation: session.buckets_failed == 1,
+            required,
         });
 
         Ok(())
     }
 
-    /// Terminate session for cause (client exercises termination right)
+    /// Require Merkle-proven delivery for the bandwidth SLA (user only, before provider ack)
     ///
-    /// Requires sla_status == Violated and within termination window.
-    /// Effects:
-    /// - Refunds 100% escrow to user
-    /// - Slashes penalty_accrued from provider collateral
-    /// - Releases remaining collateral to provider
-    /// - Sets sla_status = TerminatedForCause
-    pub fn terminate_for_cause(ctx: Context<TerminateForCause>) -> Result<()> {
-        let clock = Clock::get()?;
-        let now = clock.slot;
+    /// When required, evaluate_bandwidth_sla counts only chunks proven against
+    /// the provider's committed delivery root instead of permit nonces, which a
+    /// provider can inflate by redeeming tiny permits.
+    pub fn set_delivery_proof_requirement(
+        ctx: Context<SetDeliveryProofRequirement>,
+        required: bool,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
 
-        let session_info = ctx.accounts.session.to_account_info();
-        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+
+        session.delivery_proofs_required = required;
+
+        emit_cpi!(DeliveryProofRequirementSet {
+            session: session_key,
+            required,
+        });
+
+        Ok(())
+    }
+
+    /// User sets GPU compute SLA targets (before provider ack)
+    ///
+    /// Enables submit_compute_attestation: a bucket whose attested
+    /// utilization or throughput falls below either target fails with
+    /// SlaFailureReason::Compute. A zero target is not enforced.
+    pub fn set_compute_sla(
+        ctx: Context<SetComputeSla>,
+        min_utilization_pct: u8,
+        min_tokens_per_sec: u32,
+    ) -> Result<()> {
         let session_key = ctx.accounts.session.key();
-        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(
+            min_utilization_pct <= 100 && (min_utilization_pct > 0 || min_tokens_per_sec > 0),
+            ErrorCode::InvalidComputeTarget
+        );
 
+        session.compute_min_utilization_pct = min_utilization_pct;
+        session.compute_min_tokens_per_sec = min_tokens_per_sec;
+
+        emit_cpi!(ComputeSlaSet {
+            session: session_key,
+            min_utilization_pct,
+            min_tokens_per_sec,
+        });
+
+        Ok(())
+    }
+
+    /// Provider commits the Merkle root of chunks delivered in the SLA window
+    ///
+    /// Leaves are sha256(0x00 || chunk_index || chunk_hash) with chunk_index
+    /// little-endian, for chunk_index in 0..chunk_count; inner nodes are
+    /// sha256(0x01 || left || right). Can be committed once per session.
+    pub fn commit_delivery_root(
+        ctx: Context<CommitDeliveryRoot>,
+        delivery_root: [u8; 32],
+        chunk_count: u64,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
         let session = &mut ctx.accounts.session;
 
-        // === Status guards ===
         require!(session.is_bid, ErrorCode::NotBidSession);
         require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
-        require!(session.sla_status == SlaStatus::Violated, ErrorCode::SessionNotViolated);
-        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
-
-        // === Termination window check ===
         require!(
-            now <= session.terminate_deadline_slot,
-            ErrorCode::TerminationWindowExpired
+            session.sla_status == SlaStatus::Pending,
+            ErrorCode::SlaAlreadyEvaluated
+        );
+        require!(session.delivery_root == [0u8; 32], ErrorCode::DeliveryRootAlreadyCommitted);
+        require!(
+            delivery_root != [0u8; 32] && chunk_count > 0,
+            ErrorCode::InvalidDeliveryRoot
         );
 
-        // Compute penalty: min(penalty_accrued, bucket_penalty * buckets_failed, reserve_r)
-        let computed_penalty = session.bucket_penalty
-            .checked_mul(session.buckets_failed)
-            .ok_or(ErrorCode::Overflow)?;
-        let actual_penalty = computed_penalty
-            .min(session.penalty_accrued)
-            .min(session.reserve_r);
-
-        let user_key = session.user;
-        let nonce_bytes = session.session_nonce.to_le_bytes();
-        let bump = session.bump;
-        let reserve_r = session.reserve_r;
-        let buckets_failed = session.buckets_failed;
-        let failure_reason = session.sla_failure_reason;
+        session.delivery_root = delivery_root;
+        session.delivery_chunk_count = chunk_count;
 
-        // Update state
-        session.sla_status = SlaStatus::TerminatedForCause;
-        session.terminated_for_cause = true;
-        session.state = SessionState::Claimed;
+        emit_cpi!(DeliveryRootCommitted {
+            session: session_key,
+            delivery_root,
+            chunk_count,
+        });
 
-        let _ = session;
+        Ok(())
+    }
 
-        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-        let signer_seeds = &[seeds];
+    /// Verify inclusion proofs of delivered chunks (user or session verifier)
+    ///
+    /// Each valid proof adds one chunk to chunks_proven. Proofs must be for
+    /// strictly increasing chunk indexes across calls, so no chunk counts twice.
+    /// A verifier checker follows registry rotation like report_bucket_failure.
+    pub fn verify_delivery_proofs(
+        ctx: Context<VerifyDeliveryProofs>,
+        proofs: Vec<DeliveryProof>,
+    ) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let checker = ctx.accounts.checker.key();
+        let session = &mut ctx.accounts.session;
 
-        // === Slash penalty from provider collateral ===
-        if actual_penalty > 0 {
-            let cpi_accounts = SlashAndPay {
-                position: ctx.accounts.position.to_account_info(),
-                vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
-                user_token_account: ctx.accounts.user_token_account.to_account_info(),
-                session_authority: session_info.clone(),
-                token_program: token_program_info.clone(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.collateral_vault_program.to_account_info(),
-                cpi_accounts,
-                signer_seeds,
-            );
-            collateral_vault::cpi::slash_and_pay(cpi_ctx, session_key, actual_penalty)?;
+        if checker != session.user {
+            let rotation = load_verifier_rotation(&ctx.accounts.verifier_rotation)?;
+            authorize_pinned_verifier(session, checker, rotation.as_ref(), now)?;
         }
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(
+            session.sla_status == SlaStatus::Pending,
+            ErrorCode::SlaAlreadyEvaluated
+        );
+        require!(session.delivery_root != [0u8; 32], ErrorCode::DeliveryRootNotCommitted);
 
-        // === Release remaining collateral to provider ===
-        let remaining_reserve = reserve_r.saturating_sub(actual_penalty);
-        if remaining_reserve > 0 {
-            let release_accounts = Release {
-                position: ctx.accounts.position.to_account_info(),
-                session_authority: ctx.accounts.session.to_account_info(),
-            };
-            let release_ctx = CpiContext::new_with_signer(
-                ctx.accounts.collateral_vault_program.to_account_info(),
-                release_accounts,
-                signer_seeds,
+        for delivery_proof in proofs.iter() {
+            require!(
+                delivery_proof.chunk_index >= session.next_proof_index
+                    && delivery_proof.chunk_index < session.delivery_chunk_count,
+                ErrorCode::InvalidDeliveryProof
+            );
+            require!(
+                delivery_proof.proof.len() <= MAX_DELIVERY_PROOF_DEPTH
+                    && verify_delivery_inclusion(&session.delivery_root, delivery_proof),
+                ErrorCode::InvalidDeliveryProof
             );
-            collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
-        }
 
-        // === Refund 100% escrow to user ===
-        if escrow_balance > 0 {
-            let cpi_accounts = Transfer {
-                from: escrow_info,
-                to: user_token_info,
-                authority: ctx.accounts.session.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-            token::transfer(cpi_ctx, escrow_balance)?;
+            session.next_proof_index = delivery_proof.chunk_index
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+            session.chunks_proven = session.chunks_proven
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
         }
 
-        emit!(SessionTerminatedForCause {
+        emit_cpi!(DeliveryProofsVerified {
             session: session_key,
-            penalty_paid: actual_penalty,
-            escrow_refunded: escrow_balance,
-            buckets_failed,
-            failure_reason,
-            remaining_collateral_released: remaining_reserve,
+            verified: proofs.len() as u64,
+            chunks_proven: session.chunks_proven,
         });
 
         Ok(())
     }
 
-    /// Settle SLA after window ends (alternative to terminate_for_cause)
+    /// Evaluate bandwidth SLA after window ends
     ///
-    /// Callable after sla_window_end_slot.
-    /// Effects:
-    /// - If buckets_failed == 0: sla_status = Met, premium released to host
-    /// - If buckets_failed > 0: sla_status = Failed, penalty slashed, remaining released
-    pub fn settle_sla(ctx: Context<SettleSla>) -> Result<()> {
+    /// Callable by anyone after the SLA window has ended.
+    /// Delivered chunks are the Merkle-proven count once a delivery root is
+    /// committed (or proofs are required); otherwise nonce progression within
+    /// the window is used.
+    /// If bandwidth_min_bytes is set, delivered bytes (chunks * chunk_size) are
+    /// compared against it; otherwise chunks delivered < bandwidth_min_chunks
+    /// marks SLA as Failed.
+    /// With bandwidth checkpoints, the last sub-window (up to the window end) is
+    /// checked too, and any failed sub-window fails the SLA.
+    pub fn evaluate_bandwidth_sla(ctx: Context<EvaluateBandwidthSla>) -> Result<()> {
         let clock = Clock::get()?;
-        let now = clock.slot;
-
-        let session_info = ctx.accounts.session.to_account_info();
-        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
-        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
-        let user_token_info = ctx.accounts.user_token_account.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
         let session_key = ctx.accounts.session.key();
-        let escrow_balance = ctx.accounts.escrow_token_account.amount;
-
         let session = &mut ctx.accounts.session;
 
-        // === Status guards ===
         require!(session.is_bid, ErrorCode::NotBidSession);
         require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
         require!(
-            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated,
+            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::None,
             ErrorCode::SlaAlreadyEvaluated
         );
-        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+        require!(clock.slot > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
 
-        // === Window must be ended ===
-        require!(now > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
+        let use_delivery_proofs = uses_delivery_proofs(session);
+        if !use_delivery_proofs {
+            require!(session.nonce_at_window_start > 0, ErrorCode::WindowStartNotSnapshotted);
+        }
 
-        let user_key = session.user;
-        let nonce_bytes = session.session_nonce.to_le_bytes();
-        let bump = session.bump;
-        let reserve_r = session.reserve_r;
-        let buckets_failed = session.buckets_failed;
+        // Close the final checkpoint sub-window at the window end
+        if let Some(previous) = last_bandwidth_checkpoint(session) {
+            let tail_slots = session.sla_window_end_slot.saturating_sub(previous.slot);
+            let tail_delivered = delivered_chunk_counter(session).saturating_sub(previous.delivered);
+            if tail_slots > 0 && !bandwidth_target_met(session, tail_delivered, tail_slots) {
+                session.bandwidth_subwindows_failed = session.bandwidth_subwindows_failed
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        }
 
-        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
-        let signer_seeds = &[seeds];
+        // Snapshot the end nonce
+        session.nonce_at_window_end = session.next_permit_nonce;
 
-        if buckets_failed == 0 {
-            // === SLA MET: Premium to host, release all collateral ===
-            session.sla_status = SlaStatus::Met;
-            session.state = SessionState::Closed;
+        // Calculate chunks delivered during the window
+        let chunks_delivered = if use_delivery_proofs {
+            session.chunks_proven
+        } else {
+            session.nonce_at_window_end
+                .saturating_sub(session.nonce_at_window_start)
+        };
 
-            // Release all collateral
-            let release_accounts = Release {
-                position: ctx.accounts.position.to_account_info(),
+        let bytes_delivered = chunks_delivered.saturating_mul(session.chunk_size);
+
+        // Check if bandwidth target was met (byte target takes precedence)
+        let bandwidth_passed = bandwidth_target_met(session, chunks_delivered, session.sla_window_slots)
+            && session.bandwidth_subwindows_failed == 0;
+
+        if !bandwidth_passed {
+            // Update failure reason
+            session.sla_failure_reasons = combine_failure_reason(
+                session.sla_failure_reasons,
+                SlaFailureReason::Bandwidth,
+            );
+            session.sla_status = SlaStatus::Failed;
+        }
+
+        emit_cpi!(SlaEvaluated {
+            session: session_key,
+            sla_type: SlaType::Bandwidth,
+            passed: bandwidth_passed,
+            actual_value: chunks_delivered,
+            target_value: session.bandwidth_min_chunks as u64,
+            actual_bytes: bytes_delivered,
+            target_bytes: session.bandwidth_min_bytes,
+        });
+
+        Ok(())
+    }
+
+    /// Submit latency attestation from allowlisted verifier
+    ///
+    /// Only callable by addresses in the verifier allowlist.
+    /// If rtt_p90_ms > latency_target_ms, marks SLA as Failed.
+    pub fn submit_latency_attestation(
+        ctx: Context<SubmitLatencyAttestation>,
+        rtt_p90_ms: u16,
+        measurement_window_start: u64,
+        measurement_window_end: u64,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(!session.latency_attested, ErrorCode::LatencyAlreadyAttested);
+
+        // Validate measurement window overlaps with SLA window
+        require!(
+            measurement_window_start <= session.sla_window_end_slot &&
+            measurement_window_end >= session.sla_window_start_slot,
+            ErrorCode::InvalidMeasurementWindow
+        );
+
+        session.latency_attested = true;
+
+        // Check if latency target was violated
+        let latency_passed = rtt_p90_ms <= session.latency_target_ms;
+
+        if !latency_passed {
+            // Update failure reason
+            session.sla_failure_reasons = combine_failure_reason(
+                session.sla_failure_reasons,
+                SlaFailureReason::Latency,
+            );
+            session.sla_status = SlaStatus::Failed;
+        }
+
+        emit_cpi!(SlaEvaluated {
+            session: session_key,
+            sla_type: SlaType::Latency,
+            passed: latency_passed,
+            actual_value: rtt_p90_ms as u64,
+            target_value: session.latency_target_ms as u64,
+            actual_bytes: 0,
+            target_bytes: 0,
+        });
+
+        emit_cpi!(LatencyAttestationSubmitted {
+            session: session_key,
+            verifier: ctx.accounts.verifier.key(),
+            rtt_p90_ms,
+            measurement_window_start,
+            measurement_window_end,
+        });
+
+        Ok(())
+    }
+
+    /// Mark SLA as Met (callable after window ends if no failures)
+    pub fn finalize_sla_met(ctx: Context<FinalizeSla>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(session.sla_status == SlaStatus::Pending, ErrorCode::SlaAlreadyEvaluated);
+        require!(clock.slot > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
+        require!(session.sla_failure_reasons == 0, ErrorCode::SlaHasFailures);
+
+        session.sla_status = SlaStatus::Met;
+
+        emit_cpi!(SlaFinalized {
+            session: ctx.accounts.session.key(),
+            status: SlaStatus::Met,
+        });
+
+        Ok(())
+    }
+
+    /// Claim SLA failure payout
+    ///
+    /// Requires sla_status == Failed.
+    /// Computes payout = (base_coverage_p + bid_coverage_p) * fail_payout_bps / 10_000
+    /// Pays from reserve_bid first, then reserve_base if needed.
+    pub fn claim_sla_failure(ctx: Context<ClaimSlaFailure>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.sla_status == SlaStatus::Failed, ErrorCode::SlaNotFailed);
+        require!(
+            session.state == SessionState::Active
+                || in_close_dispute_window(session, Clock::get()?.slot),
+            ErrorCode::InvalidSessionState
+        );
+
+        // Calculate total insured amount (base + bid coverage)
+        let total_insured = session.base_coverage_p
+            .checked_add(session.bid_coverage_p)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // Calculate payout using session's fail_payout_bps
+        let payout = total_insured
+            .checked_mul(session.fail_payout_bps as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // Cap payout at total reserved
+        let actual_payout = payout.min(session.reserve_r);
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+        let payout_splits = session.payout_splits;
+        let split_count = session.payout_split_count as usize;
+
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, Clock::get()?.slot);
+
+        let _ = session;
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        let bond_paid = if settle_in_credits {
+            // Payout is owed as service credits; collateral is not slashed
+            let credits_issued = issue_service_credits(
+                ctx.accounts.service_credit.as_mut(),
+                &session_key,
+                &user_key,
+                &provider_key,
+                &mint_key,
+                actual_payout,
+            )?;
+            emit_cpi!(credits_issued);
+            0
+        } else {
+            // CPI to collateral_vault::slash_and_pay(), split across payout recipients if set
+            let slash_accounts = SlashCpiAccounts {
+                collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                destination: ctx.accounts.user_token_account.to_account_info(),
                 session_authority: session_info.clone(),
+                token_program: token_program_info.clone(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                insurance_bps: ctx.accounts.registry.insurance_bps,
+                mint: mint_info.clone(),
+                decimals,
+                bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+                leg_accounts: ctx.remaining_accounts.to_vec(),
+            };
+            slash_to_payees(
+                &slash_accounts,
+                ctx.remaining_accounts,
+                &payout_splits[..split_count],
+                &mint_key,
+                session_key,
+                actual_payout,
+                signer_seeds,
+            )?
+        };
+
+        // Release remaining reserved collateral (bond-paid amounts never left it)
+        let slashed = if settle_in_credits { 0 } else { actual_payout };
+        let remaining_reserve = reserve_r.saturating_sub(slashed - bond_paid);
+        if remaining_reserve > 0 {
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: ctx.accounts.session.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
             };
             let release_ctx = CpiContext::new_with_signer(
                 ctx.accounts.collateral_vault_program.to_account_info(),
                 release_accounts,
                 signer_seeds,
             );
-            collateral_vault::cpi::release(release_ctx, session_key, reserve_r)?;
+            collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
+        }
 
-            // Transfer premium (escrow) to provider
-            if escrow_balance > 0 {
-                let cpi_accounts = Transfer {
-                    from: escrow_info,
-                    to: provider_token_info,
-                    authority: session_info,
-                };
-                let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-                token::transfer(cpi_ctx, escrow_balance)?;
-            }
+        // Refund remaining escrow to user
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance);
+        if user_refund > 0 {
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: ctx.accounts.session.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SlaFailureClaimed, session_key, slashed)?;
+
+        emit_cpi!(SlaFailureClaimed {
+            session: session_key,
+            payout: slashed,
+            escrow_refunded: user_refund,
+            remaining_reserve_released: remaining_reserve,
+            failure_reasons: ctx.accounts.session.sla_failure_reasons,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// User initiates session close
+    pub fn close_session(ctx: Context<CloseSession>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let dispute_window_slots = ctx.accounts.mode.close_dispute_window_slots;
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Active || session.state == SessionState::Open,
+            ErrorCode::InvalidSessionState
+        );
+
+        // Claims stay open until the mode's dispute window lapses
+        let state_changed = transition_state(session, session_key, SessionState::Closing, clock.slot);
+        session.close_unlock_slot = clock.slot
+            .checked_add(dispute_window_slots)
+            .ok_or(ErrorCode::Overflow)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::CloseRequested, session_key, 0)?;
+
+        emit_cpi!(SessionClosing { session: session_key });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Provider starts a graceful close of an active session
+    ///
+    /// Moves the session to Closing and leaves PROVIDER_CLOSE_COOLDOWN_SLOTS
+    /// for the provider to redeem permits for work already delivered; stall
+    /// and SLA claims stay open during the cooldown. finalize_close then
+    /// releases collateral and refunds unused escrow to the user. A provider
+    /// with a failed or violated SLA, or an already claimable stall, cannot
+    /// close its way out of the claim.
+    pub fn provider_close_request(ctx: Context<ProviderCloseRequest>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Active, ErrorCode::InvalidSessionState);
+        require!(
+            session.sla_status != SlaStatus::Failed && session.sla_status != SlaStatus::Violated,
+            ErrorCode::ClaimPending
+        );
+        if session.acked {
+            let stall_deadline = session.last_progress_slot
+                .checked_add(session.stall_timeout_slots)
+                .ok_or(ErrorCode::Overflow)?;
+            require!(clock.slot <= stall_deadline, ErrorCode::ClaimPending);
+        }
+
+        let close_unlock_slot = clock.slot
+            .checked_add(PROVIDER_CLOSE_COOLDOWN_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let state_changed = transition_state(session, session_key, SessionState::Closing, clock.slot);
+        session.provider_close_requested = true;
+        session.close_unlock_slot = close_unlock_slot;
+        let provider = session.provider;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::CloseRequested, session_key, 0)?;
+
+        emit_cpi!(ProviderCloseRequested {
+            session: session_key,
+            provider,
+            close_unlock_slot,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Either party force-closes a session that has reached its maximum duration
+    ///
+    /// Moves an active session past duration_end_slot to Closing exactly as
+    /// close_session does, so claims stay open for the mode's dispute window
+    /// and finalize_close then settles normally.
+    pub fn close_at_max_duration(ctx: Context<CloseAtMaxDuration>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let closed_by = ctx.accounts.caller.key();
+        let dispute_window_slots = ctx.accounts.mode.close_dispute_window_slots;
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Active, ErrorCode::InvalidSessionState);
+        require!(
+            closed_by == session.user || closed_by == session.provider,
+            ErrorCode::NotSessionParty
+        );
+        require!(
+            session.duration_end_slot > 0 && clock.slot >= session.duration_end_slot,
+            ErrorCode::MaxDurationNotReached
+        );
+
+        let state_changed = transition_state(session, session_key, SessionState::Closing, clock.slot);
+        session.close_unlock_slot = clock.slot
+            .checked_add(dispute_window_slots)
+            .ok_or(ErrorCode::Overflow)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::CloseRequested, session_key, 0)?;
+
+        emit_cpi!(SessionMaxDurationReached {
+            session: session_key,
+            closed_by,
+            duration_end_slot: session.duration_end_slot,
+        });
+        emit_cpi!(SessionClosing { session: session_key });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Finalize session close and release collateral
+    pub fn finalize_close(ctx: Context<FinalizeClose>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+        let slot = Clock::get()?.slot;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Closing, ErrorCode::InvalidSessionState);
+        require!(
+            slot >= session.close_unlock_slot,
+            ErrorCode::CloseCooldownActive
+        );
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let was_active = session.acked;
+        let close_unlock_slot = session.close_unlock_slot;
+
+        // For bid sessions that were never evaluated as failed, mark SLA as Met
+        if session.is_bid && session.sla_status == SlaStatus::Pending {
+            session.sla_status = SlaStatus::Met;
+        }
+
+        let state_changed = transition_state(session, session_key, SessionState::Closed, slot);
+
+        let _ = session;
+
+        // CPI to collateral_vault::release() if session was active
+        if was_active {
+            let active_sessions = ctx
+                .accounts
+                .active_sessions
+                .as_mut()
+                .ok_or(ErrorCode::ActiveSessionsAccountRequired)?;
+            release_active_session(active_sessions);
+
+            let cpi_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: ctx.accounts.session.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            let signer_seeds = &[seeds];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(cpi_ctx, session_key, reserve_r)?;
+        }
+
+        // Reservation fee: accrues while the collateral is locked, up to the
+        // slot the close unlocked, and comes out of the user's share of escrow
+        let reservation_fee = pay_reservation_fee(
+            &mut ctx.accounts.session,
+            close_unlock_slot,
+            escrow_balance,
+            &escrow_info,
+            &mint_info,
+            ctx.accounts.provider_token_account.as_ref(),
+            &token_program_info,
+            decimals,
+        )?;
+        if reservation_fee > 0 {
+            emit_cpi!(ReservationFeePaid {
+                session: session_key,
+                provider: provider_key,
+                amount: reservation_fee,
+            });
+        }
+
+        let remaining_escrow = escrow_balance - reservation_fee;
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, remaining_escrow);
+        if user_refund > 0 {
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            let signer_seeds = &[seeds];
+
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionClosed, session_key, user_refund)?;
+
+        emit_cpi!(SessionClosed {
+            session: session_key,
+            refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Roll a closing or closed session into a fresh, already-started session
+    ///
+    /// User and provider co-sign. A new session PDA under new_session_nonce is
+    /// opened on `terms` (validated as in open_session_from_mode), acked in
+    /// place with its collateral reserved, and the old session's remaining
+    /// escrow is moved into it, so coverage never lapses between the two. The
+    /// old session keeps its own reservation until finalize_close; its
+    /// reservation fee is paid before the escrow rolls over.
+    pub fn renew_session(
+        ctx: Context<RenewSession>,
+        new_session_nonce: u64,
+        terms: SessionTerms,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let old_session_info = ctx.accounts.old_session.to_account_info();
+        let old_escrow_info = ctx.accounts.old_escrow_token_account.to_account_info();
+        let new_escrow_info = ctx.accounts.new_escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let old_session_key = ctx.accounts.old_session.key();
+        let new_session_key = ctx.accounts.new_session.key();
+        let old_escrow_balance = ctx.accounts.old_escrow_token_account.amount;
+
+        let old_session = &ctx.accounts.old_session;
+        require!(!old_session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(
+            old_session.state == SessionState::Closing || old_session.state == SessionState::Closed,
+            ErrorCode::InvalidSessionState
+        );
+        let old_close_unlock_slot = old_session.close_unlock_slot;
+        let provider_key = old_session.provider;
+
+        // The old session's reservation fee is settled before its escrow moves
+        let reservation_fee = pay_reservation_fee(
+            &mut ctx.accounts.old_session,
+            old_close_unlock_slot,
+            old_escrow_balance,
+            &old_escrow_info,
+            &mint_info,
+            ctx.accounts.provider_token_account.as_deref(),
+            &token_program_info,
+            decimals,
+        )?;
+        if reservation_fee > 0 {
+            emit_cpi!(ReservationFeePaid {
+                session: old_session_key,
+                provider: provider_key,
+                amount: reservation_fee,
+            });
+        }
+        let escrow_balance = old_escrow_balance - reservation_fee;
+
+        let old_session = &ctx.accounts.old_session;
+
+        let mode = &ctx.accounts.mode;
+        require!(mode.is_active && !mode.is_disabled, ErrorCode::ModeNotActive);
+        validate_terms_for_mode(&terms, mode)?;
+
+        let parties = SessionParties {
+            user: old_session.user,
+            provider: old_session.provider,
+            mode_id: old_session.mode_id,
+            mint: old_session.mint,
+            session_nonce: new_session_nonce,
+            bump: ctx.bumps.new_session,
+        };
+        let user_key = old_session.user;
+        let old_nonce_bytes = old_session.session_nonce.to_le_bytes();
+        let old_bump = old_session.bump;
+        let sponsor = old_session.sponsor;
+        let sponsored_amount = old_session.sponsored_amount.min(escrow_balance);
+        let cr_bps = effective_cr_bps(terms.cr_bps, mode)?;
+
+        // Enforce the mode's per-provider concurrency cap
+        let cap = mode.max_concurrent_sessions;
+        let active_sessions = &mut ctx.accounts.active_sessions;
+        require!(
+            cap == 0 || active_sessions.count < cap,
+            ErrorCode::ConcurrentSessionCapReached
+        );
+        active_sessions.count = active_sessions.count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+
+        // Open and start the new session in one step
+        let new_session = &mut ctx.accounts.new_session;
+        init_session(new_session, parties, &terms, cr_bps, clock.slot)?;
+        new_session.acked = true;
+        let state_changed = transition_state(new_session, new_session_key, SessionState::Active, clock.slot);
+        new_session.last_progress_slot = clock.slot;
+        new_session.acked_slot = clock.slot;
+        new_session.duration_end_slot = duration_end_slot(new_session.max_duration_slots, clock.slot)?;
+        new_session.reservation_fee_bps = mode.reservation_fee_bps;
+        // Unspent sponsorship rolls over with the escrow
+        new_session.sponsor = sponsor;
+        new_session.sponsored_amount = sponsored_amount;
+        if new_session.is_bid {
+            new_session.sla_status = SlaStatus::Pending;
+        }
+        let reserve_r = new_session.reserve_r;
+
+        // Move the remaining escrow across
+        if escrow_balance > 0 {
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &old_nonce_bytes, &[old_bump]];
+            let signer_seeds = &[seeds];
+
+            let cpi_accounts = TransferChecked {
+                from: old_escrow_info,
+                mint: mint_info,
+                to: new_escrow_info,
+                authority: old_session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, escrow_balance, decimals)?;
+        }
+
+        // CPI to collateral_vault::reserve() for the new session
+        let cpi_accounts = Reserve {
+            position: ctx.accounts.position.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            reservation: ctx.accounts.reservation.to_account_info(),
+            provider: ctx.accounts.provider.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            vault_callers: ctx.accounts.vault_callers.to_account_info(),
+            instructions_sysvar: ctx.accounts.instructions_sysvar.to_account_info(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            program: ctx.accounts.collateral_vault_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.collateral_vault_program.to_account_info(),
+            cpi_accounts,
+        );
+        collateral_vault::cpi::reserve(cpi_ctx, new_session_key, reserve_r)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionOpened, new_session_key, terms.max_spend)?;
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionStarted, new_session_key, reserve_r)?;
+
+        emit_cpi!(session_opened_event(new_session_key, &ctx.accounts.new_session));
+        emit_cpi!(SessionRenewed {
+            old_session: old_session_key,
+            new_session: new_session_key,
+            escrow_rolled: escrow_balance,
+            reserve_r,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Claim for no-start (provider didn't ack - no collateral was reserved)
+    pub fn claim_no_start(ctx: Context<ClaimNoStart>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(clock.slot > session.start_deadline_slot, ErrorCode::DeadlineNotPassed);
+
+        // No collateral was reserved (provider never acked), just refund escrow
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, clock.slot);
+
+        let _ = session;
+
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance);
+        if user_refund > 0 {
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            let signer_seeds = &[seeds];
+
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::ClaimPaid, session_key, 0)?;
+
+        emit_cpi!(ClaimPaid {
+            session: session_key,
+            claim_type: ClaimType::NoStart,
+            payout: 0, // No payout since no collateral was reserved
+            escrow_refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Reassign a session that was never started to a backup provider
+    ///
+    /// Usable instead of claim_no_start once the start deadline has passed
+    /// without an ack. Swaps the provider and restarts the start deadline
+    /// (and, for bid sessions, the SLA window) from the current slot, keeping
+    /// the funded escrow in place.
+    pub fn reassign_provider(
+        ctx: Context<ReassignProvider>,
+        new_provider: Pubkey,
+        start_deadline_slots: u64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(clock.slot > session.start_deadline_slot, ErrorCode::DeadlineNotPassed);
+        require!(new_provider != session.provider, ErrorCode::SameProvider);
+        require!(session.bond_posted == 0, ErrorCode::SessionBondOutstanding);
+        require!(
+            start_deadline_slots > 0
+                && ctx.accounts.mode.timing_bounds.start_deadline.contains(start_deadline_slots),
+            ErrorCode::TimingOutOfBounds
+        );
+
+        let old_provider = session.provider;
+        session.provider = new_provider;
+        session.start_deadline_slot = clock.slot
+            .checked_add(start_deadline_slots)
+            .ok_or(ErrorCode::Overflow)?;
+        session.start_window_slot = clock.slot;
+
+        if session.is_bid {
+            session.sla_window_start_slot = clock.slot
+                .checked_add(session.sla_warmup_slots)
+                .ok_or(ErrorCode::Overflow)?;
+            session.sla_window_end_slot = session.sla_window_start_slot
+                .checked_add(session.sla_window_slots)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        emit_cpi!(ProviderReassigned {
+            session: session_key,
+            old_provider,
+            new_provider,
+            start_deadline_slot: session.start_deadline_slot,
+        });
+
+        Ok(())
+    }
+
+    /// Claim for stall - slash provider collateral and pay user
+    pub fn claim_stall(ctx: Context<ClaimStall>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Active || in_close_dispute_window(session, clock.slot),
+            ErrorCode::InvalidSessionState
+        );
+        require!(session.acked, ErrorCode::SessionNotStarted);
+
+        let stall_deadline = session.last_progress_slot
+            .checked_add(session.stall_timeout_slots)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(clock.slot > stall_deadline, ErrorCode::StallTimeoutNotReached);
+
+        let payout = session.base_coverage_p.min(session.reserve_r);
+        let user_key = session.user;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let payout_splits = session.payout_splits;
+        let split_count = session.payout_split_count as usize;
+
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, clock.slot);
+
+        let _ = session;
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        // CPI to collateral_vault::slash_and_pay(), split across payout recipients if set
+        let slash_accounts = SlashCpiAccounts {
+            collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+            position: ctx.accounts.position.to_account_info(),
+            reservation: ctx.accounts.reservation.to_account_info(),
+            reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+            vault_callers: ctx.accounts.vault_callers.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+            destination: ctx.accounts.user_token_account.to_account_info(),
+            session_authority: session_info.clone(),
+            token_program: token_program_info.clone(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+            insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+            insurance_bps: ctx.accounts.registry.insurance_bps,
+            mint: mint_info.clone(),
+            decimals,
+            bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+            leg_accounts: ctx.remaining_accounts.to_vec(),
+        };
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+        let bond_paid = slash_to_payees(
+            &slash_accounts,
+            ctx.remaining_accounts,
+            &payout_splits[..split_count],
+            &mint_key,
+            session_key,
+            payout,
+            signer_seeds,
+        )?;
+
+        // Collateral covered by the session bond is no longer at risk
+        if bond_paid > 0 {
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: session_info.clone(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                release_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(release_ctx, session_key, bond_paid)?;
+        }
+
+        // Refund remaining escrow to user
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance);
+        if user_refund > 0 {
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            let signer_seeds = &[seeds];
+
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::ClaimPaid, session_key, payout)?;
+
+        emit_cpi!(ClaimPaid {
+            session: session_key,
+            claim_type: ClaimType::Stall,
+            payout,
+            escrow_refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Claim compensation for a late start - provider acked but never began delivering
+    ///
+    /// Once LATE_START_GRACE_SLOTS pass after ack with no progress, the user
+    /// can claim a one-time payout from reserved collateral. It scales with
+    /// how far into the start deadline the provider acked, up to
+    /// LATE_START_MAX_COMP_BPS of base coverage. The session stays active,
+    /// so claim_stall remains available if the stall continues.
+    pub fn claim_late_start(ctx: Context<ClaimLateStart>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Active, ErrorCode::InvalidSessionState);
+        require!(session.acked, ErrorCode::SessionNotStarted);
+        require!(!session.late_start_claimed, ErrorCode::LateStartAlreadyClaimed);
+        require!(session.last_progress_slot <= session.acked_slot, ErrorCode::DeliveryAlreadyStarted);
+
+        let grace_end = session.acked_slot
+            .checked_add(LATE_START_GRACE_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(clock.slot > grace_end, ErrorCode::LateStartGraceNotElapsed);
+
+        // Scale by the share of the start deadline the user spent waiting for ack
+        let start_window = session.start_deadline_slot.saturating_sub(session.start_window_slot);
+        let ack_delay = session.acked_slot
+            .saturating_sub(session.start_window_slot)
+            .min(start_window);
+        let payout = if start_window == 0 {
+            0
+        } else {
+            ((session.base_coverage_p.min(session.reserve_r) as u128)
+                .checked_mul(LATE_START_MAX_COMP_BPS as u128)
+                .ok_or(ErrorCode::Overflow)?
+                .checked_mul(ack_delay as u128)
+                .ok_or(ErrorCode::Overflow)?
+                / 10_000
+                / start_window as u128) as u64
+        };
+        require!(payout > 0, ErrorCode::NoLateStartCompensation);
+
+        let user_key = session.user;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let payout_splits = session.payout_splits;
+        let split_count = session.payout_split_count as usize;
+
+        session.late_start_claimed = true;
+
+        let _ = session;
+
+        let slash_accounts = SlashCpiAccounts {
+            collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+            position: ctx.accounts.position.to_account_info(),
+            reservation: ctx.accounts.reservation.to_account_info(),
+            reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+            vault_callers: ctx.accounts.vault_callers.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+            destination: ctx.accounts.user_token_account.to_account_info(),
+            session_authority: session_info.clone(),
+            token_program: token_program_info.clone(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+            insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+            insurance_bps: ctx.accounts.registry.insurance_bps,
+            mint: mint_info.clone(),
+            decimals,
+            bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+            leg_accounts: ctx.remaining_accounts.to_vec(),
+        };
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+        let bond_paid = slash_to_payees(
+            &slash_accounts,
+            ctx.remaining_accounts,
+            &payout_splits[..split_count],
+            &mint_key,
+            session_key,
+            payout,
+            signer_seeds,
+        )?;
+
+        // Only the collateral actually slashed leaves the session's reservation
+        let session = &mut ctx.accounts.session;
+        session.reserve_r = session.reserve_r
+            .checked_sub(payout - bond_paid)
+            .ok_or(ErrorCode::Overflow)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::ClaimPaid, session_key, payout)?;
+
+        emit_cpi!(ClaimPaid {
+            session: session_key,
+            claim_type: ClaimType::LateStart,
+            payout,
+            escrow_refunded: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Emergency exit when the provider's collateral position is unreachable
+    ///
+    /// Settlement paths that CPI into collateral_vault fail once the position
+    /// is closed or no longer backs this session's reservation. After verifying
+    /// that, the remaining escrow is refunded without touching the vault and the
+    /// session is marked Claimed.
+    pub fn emergency_refund(ctx: Context<EmergencyRefund>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Active || session.state == SessionState::Closing,
+            ErrorCode::InvalidSessionState
+        );
+        require!(session.acked, ErrorCode::SessionNotStarted);
+        require!(
+            reservation_unreachable(&ctx.accounts.position, session)?,
+            ErrorCode::PositionReachable
+        );
+
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, Clock::get()?.slot);
+
+        let _ = session;
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance);
+        if user_refund > 0 {
+            let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+            let signer_seeds = &[seeds];
+
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::ClaimPaid, session_key, 0)?;
+
+        emit_cpi!(ClaimPaid {
+            session: session_key,
+            claim_type: ClaimType::EmergencyRefund,
+            payout: 0, // Reservation cannot be slashed
+            escrow_refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Return unspent sponsored funds to the sponsor after the session ends
+    ///
+    /// Refund paths hold back up to sponsored_amount in escrow; the sponsor
+    /// withdraws it here once the session is Closed or Claimed.
+    pub fn withdraw_sponsor_refund(ctx: Context<WithdrawSponsorRefund>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let sponsor_token_info = ctx.accounts.sponsor_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Closed || session.state == SessionState::Claimed,
+            ErrorCode::InvalidSessionState
+        );
+
+        let amount = session.sponsored_amount.min(escrow_balance);
+        require!(amount > 0, ErrorCode::ZeroAmount);
+
+        let user_key = session.user;
+        let sponsor_key = session.sponsor;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+
+        session.sponsored_amount = 0;
+
+        let _ = session;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        let cpi_accounts = TransferChecked {
+            from: escrow_info,
+            mint: mint_info,
+            to: sponsor_token_info,
+            authority: session_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+
+        emit_cpi!(SponsorRefunded {
+            session: session_key,
+            sponsor: sponsor_key,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Close a settled session's escrow ATA and Session PDA, returning rent to the user
+    ///
+    /// Only for Closed or Claimed sessions whose escrow is empty and whose
+    /// provider bond has been withdrawn. Clients should not reuse a session nonce afterwards: permits are bound to the
+    /// session address, which a reopened session would share.
+    pub fn close_session_accounts(ctx: Context<CloseSessionAccounts>) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Closed || session.state == SessionState::Claimed,
+            ErrorCode::InvalidSessionState
+        );
+        require!(
+            ctx.accounts.escrow_token_account.amount == 0,
+            ErrorCode::EscrowNotEmpty
+        );
+        // Contributors close their own records when withdrawing their share
+        require!(
+            session.shared_settled_count >= session.shared_contributor_count,
+            ErrorCode::SharedSharesOutstanding
+        );
+        // withdraw_session_bond closes the bond account; it must be gone
+        // before the session that is its only authority disappears
+        require!(
+            ctx.accounts.session_bond.data_is_empty(),
+            ErrorCode::SessionBondOutstanding
+        );
+
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_token_account.to_account_info(),
+            destination: ctx.accounts.user.to_account_info(),
+            authority: ctx.accounts.session.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_ctx)?;
+
+        // Session PDA itself is closed to the user by the `close` constraint
+        emit_cpi!(SessionAccountsClosed {
+            session: session_key,
+            user: user_key,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a session that was opened but never funded, returning rent to the user
+    ///
+    /// The provider can decline an unfunded, unacked session at any time;
+    /// anyone else can clean it up once the start deadline has passed. The
+    /// escrow ATA, any metadata and the Session PDA are closed to the user.
+    pub fn provider_cancel_unfunded(ctx: Context<ProviderCancelUnfunded>) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let caller = ctx.accounts.caller.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+        require!(
+            caller == session.provider || clock.slot > session.start_deadline_slot,
+            ErrorCode::DeadlineNotPassed
+        );
+        require!(
+            ctx.accounts.escrow_token_account.amount == 0 && session.shared_contributor_count == 0,
+            ErrorCode::EscrowNotEmpty
+        );
+        require!(session.bond_posted == 0, ErrorCode::SessionBondOutstanding);
+
+        let user_key = session.user;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+
+        let state_changed = transition_state(session, session_key, SessionState::Closed, clock.slot);
+
+        let _ = session;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow_token_account.to_account_info(),
+            destination: ctx.accounts.user.to_account_info(),
+            authority: ctx.accounts.session.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_ctx)?;
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionCancelled, session_key, 0)?;
+
+        // Session PDA itself is closed to the user by the `close` constraint
+        emit_cpi!(UnfundedSessionCancelled {
+            session: session_key,
+            user: user_key,
+            cancelled_by: caller,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// User writes the session's metadata (created on first call)
+    ///
+    /// Stores a URI plus a few key-value entries (e.g. job id, region,
+    /// content hash) in a PDA ["session_meta", session] that indexers can
+    /// read to map sessions to off-chain jobs. Each call replaces the
+    /// previous contents.
+    pub fn set_session_metadata(
+        ctx: Context<SetSessionMetadata>,
+        uri: String,
+        entries: Vec<MetadataEntry>,
+    ) -> Result<()> {
+        let session = &ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::InvalidSessionState
+        );
+        require!(uri.len() <= MAX_METADATA_URI_LEN, ErrorCode::InvalidMetadata);
+        require!(entries.len() <= MAX_METADATA_ENTRIES, ErrorCode::InvalidMetadata);
+        require!(
+            entries.iter().all(|e| {
+                !e.key.is_empty()
+                    && e.key.len() <= MAX_METADATA_KEY_LEN
+                    && e.value.len() <= MAX_METADATA_VALUE_LEN
+            }),
+            ErrorCode::InvalidMetadata
+        );
+
+        let session_key = session.key();
+        let metadata = &mut ctx.accounts.session_metadata;
+        metadata.session = session_key;
+        metadata.uri = uri;
+        metadata.entries = entries;
+        metadata.bump = ctx.bumps.session_metadata;
+
+        emit_cpi!(SessionMetadataSet {
+            session: session_key,
+            uri: metadata.uri.clone(),
+            entry_count: metadata.entries.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Record the user's acknowledgement of a delivered chunk
+    ///
+    /// Submitted either by the user directly, or by the provider with the
+    /// user's signature over (program_id, "receipt", session, chunk_index,
+    /// chunk_hash) in the preceding instruction (Ed25519, or secp256k1 for
+    /// EVM-key sessions). Receipts count as progress, so a provider holding
+    /// them can defeat a bad-faith stall claim. chunk_index must increase.
+    pub fn post_receipt(
+        ctx: Context<PostReceipt>,
+        chunk_index: u64,
+        chunk_hash: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let session_key = ctx.accounts.session.key();
+        let submitter = ctx.accounts.submitter.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(chunk_index >= session.next_receipt_index, ErrorCode::InvalidReceiptIndex);
+
+        let user_signed = if submitter == session.user {
+            false
+        } else {
+            require!(submitter == session.provider, ErrorCode::WrongProvider);
+            let message = delivery_receipt_message(&session_key, chunk_index, &chunk_hash);
+            verify_user_signature(&ctx.accounts.instructions_sysvar, session, &message)?;
+            true
+        };
+
+        session.next_receipt_index = chunk_index.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        session.receipts_posted = session.receipts_posted
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+        session.delivery_receipt_hash = hashv(&[
+            &session.delivery_receipt_hash,
+            &chunk_index.to_le_bytes(),
+            &chunk_hash,
+        ])
+        .to_bytes();
+        session.last_progress_slot = clock.slot;
+
+        emit_cpi!(ReceiptPosted {
+            session: session_key,
+            chunk_index,
+            chunk_hash,
+            receipts_posted: session.receipts_posted,
+            delivery_receipt_hash: session.delivery_receipt_hash,
+            user_signed,
+        });
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // BUCKETED SLA INSTRUCTIONS (Phase 1: Latency + PrivacyMode only)
+    // =========================================================================
+
+    /// Submit an attested GPU compute measurement for one bucket
+    ///
+    /// The verifier (or quorum) signs the bucket's measured utilization and
+    /// tokens/s. Below either of the session's compute targets, the bucket
+    /// fails exactly as in report_bucket_failure with reason Compute.
+    pub fn submit_compute_attestation(
+        ctx: Context<SubmitComputeAttestation>,
+        bucket_index: u64,
+        bucket_start_slot: u64,
+        utilization_pct: u8,
+        tokens_per_sec: u32,
+    ) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.compute_min_utilization_pct > 0 || session.compute_min_tokens_per_sec > 0,
+            ErrorCode::ComputeSlaNotConfigured
+        );
+        check_bucket_report(session, bucket_index, bucket_start_slot, now)?;
+
+        // === Attestation ===
+        let verifier_key = ctx.accounts.verifier.key();
+        let message = compute_attestation_message(
+            &session_key,
+            bucket_index,
+            bucket_start_slot,
+            utilization_pct,
+            tokens_per_sec,
+        );
+        if session.quorum_threshold > 0 {
+            apply_quorum_rotations(session, ctx.remaining_accounts, now)?;
+            let quorum = &session.quorum_verifiers[..session.quorum_verifier_count as usize];
+            require!(quorum.contains(&verifier_key), ErrorCode::InvalidAttester);
+
+            verify_quorum_attestation(
+                &ctx.accounts.instructions_sysvar,
+                quorum,
+                session.quorum_threshold,
+                &message,
+            )?;
+        } else {
+            let rotation = load_verifier_rotation(&ctx.accounts.verifier_rotation)?;
+            authorize_pinned_verifier(session, verifier_key, rotation.as_ref(), now)?;
+
+            verify_verifier_attestation(
+                &ctx.accounts.instructions_sysvar,
+                &session.verifier_pubkey,
+                &message,
+            )?;
+        }
+
+        let passed = utilization_pct >= session.compute_min_utilization_pct
+            && tokens_per_sec >= session.compute_min_tokens_per_sec;
+
+        emit_cpi!(ComputeAttestationSubmitted {
+            session: session_key,
+            verifier: verifier_key,
+            bucket_index,
+            utilization_pct,
+            tokens_per_sec,
+            passed,
+        });
+
+        if !passed {
+            let is_first_violation =
+                record_bucket_failure(session, bucket_index, SlaFailureReason::Compute, now)?;
+
+            emit_cpi!(BucketFailureReported {
+                session: session_key,
+                bucket_index,
+                bucket_start_slot,
+                failure_reason: SlaFailureReason::Compute,
+                buckets_failed: session.buckets_failed,
+                penalty_accrued: session.penalty_accrued,
+                is_first_violation,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checkpoint delivered chunks at a bucket boundary (permissionless crank)
+    ///
+    /// Each call closes the bucket measured since the previous checkpoint and
+    /// starts measuring the current one. Delivery below the bandwidth target
+    /// prorated to the elapsed buckets fails the closed bucket with reason
+    /// Bandwidth, accruing bucket_penalty exactly like report_bucket_failure.
+    /// Cranking every bucket gives per-bucket resolution; a late crank
+    /// measures the whole span and fails only its first bucket. Buckets
+    /// already failed for another reason are not failed twice.
+    pub fn checkpoint_bucket_bandwidth(ctx: Context<CheckpointBucketBandwidth>) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        let current_bucket = now
+            .saturating_sub(session.sla_window_start_slot)
+            .checked_div(session.bucket_slots)
+            .ok_or(ErrorCode::Overflow)?
+            .min(session.buckets_total.saturating_sub(1));
+        let current_bucket_start = checked_bucket_start(
+            session.sla_window_start_slot,
+            current_bucket,
+            session.bucket_slots,
+        ).ok_or(ErrorCode::Overflow)?;
+        check_bucket_report(session, current_bucket, current_bucket_start, now)?;
+
+        let delivered = delivered_chunk_counter(session);
+
+        if session.bucket_bandwidth_tracking {
+            let closed_bucket = session.bucket_bandwidth_index;
+            require!(current_bucket > closed_bucket, ErrorCode::BucketNotEnded);
+
+            let span_slots = (current_bucket - closed_bucket)
+                .checked_mul(session.bucket_slots)
+                .ok_or(ErrorCode::Overflow)?;
+            let chunks = delivered.saturating_sub(session.bucket_bandwidth_counter);
+            let passed = bandwidth_target_met(session, chunks, span_slots);
+
+            emit_cpi!(BucketBandwidthMeasured {
+                session: session_key,
+                bucket_index: closed_bucket,
+                buckets_spanned: current_bucket - closed_bucket,
+                chunks_delivered: chunks,
+                passed,
+            });
+
+            if !passed && !bit_is_set(&session.buckets_failed_bitmap, closed_bucket) {
+                let is_first_violation =
+                    record_bucket_failure(session, closed_bucket, SlaFailureReason::Bandwidth, now)?;
+
+                emit_cpi!(BucketFailureReported {
+                    session: session_key,
+                    bucket_index: closed_bucket,
+                    bucket_start_slot: checked_bucket_start(
+                        session.sla_window_start_slot,
+                        closed_bucket,
+                        session.bucket_slots,
+                    ).ok_or(ErrorCode::Overflow)?,
+                    failure_reason: SlaFailureReason::Bandwidth,
+                    buckets_failed: session.buckets_failed,
+                    penalty_accrued: session.penalty_accrued,
+                    is_first_violation,
+                });
+            }
+        }
+
+        session.bucket_bandwidth_tracking = true;
+        session.bucket_bandwidth_index = current_bucket;
+        session.bucket_bandwidth_counter = delivered;
+
+        Ok(())
+    }
+
+    /// Report a bucket failure (latency or privacy mode violation)
+    ///
+    /// Requires Ed25519 signature verification via Instructions sysvar.
+    /// The Ed25519 precompile instruction must immediately precede this instruction.
+    ///
+    /// Effects:
+    /// - Sets bucket bit in bitmap (idempotent protection)
+    /// - Increments buckets_failed counter
+    /// - The first grace_buckets failures accrue no penalty
+    /// - First failure past grace: sets first_violation_slot, terminate_deadline_slot, sla_status = Violated
+    /// - Combines failure reason
+    pub fn report_bucket_failure(
+        ctx: Context<ReportBucketFailure>,
+        bucket_index: u64,
+        bucket_start_slot: u64,
+        failure_reason: SlaFailureReason,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.slot;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        check_bucket_report(session, bucket_index, bucket_start_slot, now)?;
+
+        let verifier_key = ctx.accounts.verifier.key();
+        if session.quorum_threshold > 0 {
+            // === Quorum: submitter is a member, threshold distinct attestations ===
+            apply_quorum_rotations(session, ctx.remaining_accounts, now)?;
+            let quorum = &session.quorum_verifiers[..session.quorum_verifier_count as usize];
+            require!(quorum.contains(&verifier_key), ErrorCode::InvalidAttester);
+
+            let message = bucket_failure_message(
+                &session_key,
+                bucket_index,
+                bucket_start_slot,
+                failure_reason,
+            );
+            verify_quorum_attestation(
+                &ctx.accounts.instructions_sysvar,
+                quorum,
+                session.quorum_threshold,
+                &message,
+            )?;
+        } else {
+            // === Attester auth (pinned key, or its registry-recorded successor) ===
+            let rotation = load_verifier_rotation(&ctx.accounts.verifier_rotation)?;
+            authorize_pinned_verifier(session, verifier_key, rotation.as_ref(), now)?;
+
+            // === Ed25519 signature verification via Instructions sysvar ===
+            let message = bucket_failure_message(
+                &session_key,
+                bucket_index,
+                bucket_start_slot,
+                failure_reason,
+            );
+            verify_verifier_attestation(
+                &ctx.accounts.instructions_sysvar,
+                &session.verifier_pubkey,
+                &message,
+            )?;
+        }
+
+        let is_first_violation = record_bucket_failure(session, bucket_index, failure_reason, now)?;
+
+        emit_cpi!(BucketFailureReported {
+            session: session_key,
+            bucket_index,
+            bucket_start_slot,
+            failure_reason,
+            buckets_failed: session.buckets_failed,
+            penalty_accrued: session.penalty_accrued,
+            is_first_violation,
+        });
+
+        Ok(())
+    }
+
+    /// Report failures for several buckets with one attestation
+    ///
+    /// For outages spanning many buckets. The verifier (or quorum) signs a
+    /// single message over the strictly increasing bucket_indices and the
+    /// failure reason; every bucket must pass the same checks as
+    /// report_bucket_failure and is recorded exactly as if reported alone.
+    pub fn report_bucket_failures_batch(
+        ctx: Context<ReportBucketFailure>,
+        bucket_indices: Vec<u64>,
+        failure_reason: SlaFailureReason,
+    ) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            !bucket_indices.is_empty() && bucket_indices.len() <= MAX_BATCH_BUCKET_REPORTS,
+            ErrorCode::InvalidBucketBatch
+        );
+        require!(
+            bucket_indices.windows(2).all(|pair| pair[0] < pair[1]),
+            ErrorCode::InvalidBucketBatch
+        );
+
+        let verifier_key = ctx.accounts.verifier.key();
+        let message = bucket_failures_batch_message(&session_key, &bucket_indices, failure_reason);
+        if session.quorum_threshold > 0 {
+            apply_quorum_rotations(session, ctx.remaining_accounts, now)?;
+            let quorum = &session.quorum_verifiers[..session.quorum_verifier_count as usize];
+            require!(quorum.contains(&verifier_key), ErrorCode::InvalidAttester);
+
+            verify_quorum_attestation(
+                &ctx.accounts.instructions_sysvar,
+                quorum,
+                session.quorum_threshold,
+                &message,
+            )?;
+        } else {
+            let rotation = load_verifier_rotation(&ctx.accounts.verifier_rotation)?;
+            authorize_pinned_verifier(session, verifier_key, rotation.as_ref(), now)?;
+
+            verify_verifier_attestation(
+                &ctx.accounts.instructions_sysvar,
+                &session.verifier_pubkey,
+                &message,
+            )?;
+        }
+
+        let mut is_first_violation = false;
+        for &bucket_index in bucket_indices.iter() {
+            let bucket_start_slot = checked_bucket_start(
+                session.sla_window_start_slot,
+                bucket_index,
+                session.bucket_slots,
+            ).ok_or(ErrorCode::Overflow)?;
+            check_bucket_report(session, bucket_index, bucket_start_slot, now)?;
+
+            is_first_violation |= record_bucket_failure(session, bucket_index, failure_reason, now)?;
+        }
+
+        emit_cpi!(BucketFailuresBatchReported {
+            session: session_key,
+            bucket_indices,
+            failure_reason,
+            buckets_failed: session.buckets_failed,
+            penalty_accrued: session.penalty_accrued,
+            is_first_violation,
+        });
+
+        Ok(())
+    }
+
+    /// Provider disputes a recent bucket failure with a counter-attestation
+    ///
+    /// Within BUCKET_DISPUTE_WINDOW_SLOTS of the report, a registry verifier
+    /// other than the session's attester(s) signs (program_id, "dispute",
+    /// session, bucket_index) in the preceding Ed25519 instruction. If upheld,
+    /// the bucket bit is cleared and the penalty it accrued is reversed; with
+    /// no penalized failures left the session returns to Pending. Only the
+    /// last MAX_DISPUTABLE_FAILURES reports can be disputed.
+    pub fn dispute_bucket_failure(
+        ctx: Context<DisputeBucketFailure>,
+        bucket_index: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let counter_verifier = ctx.accounts.counter_verifier.key();
+        let registry = &ctx.accounts.registry;
+        let session = &mut ctx.accounts.session;
+
+        // === Status guards ===
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(
+            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated,
+            ErrorCode::SlaAlreadyEvaluated
+        );
+        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+
+        // === Counter-attester: allowlisted and independent of the reporter ===
+        require!(
+            registry.verifiers[..registry.verifier_count as usize].contains(&counter_verifier),
+            ErrorCode::InvalidAttester
+        );
+        require!(
+            counter_verifier != session.verifier_pubkey
+                && !session.quorum_verifiers[..session.quorum_verifier_count as usize]
+                    .contains(&counter_verifier),
+            ErrorCode::InvalidCounterVerifier
+        );
+        verify_verifier_attestation(
+            &ctx.accounts.instructions_sysvar,
+            &counter_verifier,
+            &bucket_dispute_message(&session_key, bucket_index),
+        )?;
+
+        // === Locate the report inside its dispute window ===
+        let entry_index = session
+            .recent_failures
+            .iter()
+            .position(|f| f.reported_slot > 0 && f.bucket_index == bucket_index)
+            .ok_or(ErrorCode::FailureNotDisputable)?;
+        let failure = session.recent_failures[entry_index];
+        let dispute_deadline = failure.reported_slot
+            .checked_add(BUCKET_DISPUTE_WINDOW_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(now <= dispute_deadline, ErrorCode::FailureNotDisputable);
+
+        // === Reverse the failure ===
+        clear_bit(&mut session.buckets_failed_bitmap, bucket_index);
+        session.recent_failures[entry_index] = RecentBucketFailure::default();
+        session.buckets_failed = session.buckets_failed.saturating_sub(1);
+        session.penalty_units = session.penalty_units.saturating_sub(failure.penalty_units);
+        session.penalty_accrued = session.penalty_accrued.saturating_sub(failure.penalty);
+        if session.sla_status == SlaStatus::Violated && penalized_buckets(session) == 0 {
+            session.sla_status = SlaStatus::Pending;
+            session.first_violation_slot = 0;
+            session.terminate_deadline_slot = 0;
+        }
+
+        emit_cpi!(BucketFailureDisputed {
+            session: session_key,
+            bucket_index,
+            counter_verifier,
+            penalty_reversed: failure.penalty,
+            buckets_failed: session.buckets_failed,
+            penalty_accrued: session.penalty_accrued,
+        });
+
+        Ok(())
+    }
+
+    /// Claw back part of the accrued penalty after a clean streak
+    ///
+    /// After a violation, the verifier (or quorum) attests that at least
+    /// CLAWBACK_CLEAN_BUCKETS consecutive, completed buckets had no failure.
+    /// Each streak returns CLAWBACK_BPS of penalty_accrued to the provider;
+    /// buckets counted once cannot start another streak.
+    pub fn claim_penalty_clawback(
+        ctx: Context<ClaimPenaltyClawback>,
+        start_bucket_index: u64,
+        bucket_count: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.slot;
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        // === Status guards ===
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(session.sla_status == SlaStatus::Violated, ErrorCode::SessionNotViolated);
+        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+
+        // === Streak bounds ===
+        require!(bucket_count >= CLAWBACK_CLEAN_BUCKETS, ErrorCode::InvalidCleanStreak);
+        require!(start_bucket_index >= session.clawback_next_bucket, ErrorCode::InvalidCleanStreak);
+        let end_bucket_index = start_bucket_index
+            .checked_add(bucket_count)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(end_bucket_index <= session.buckets_total, ErrorCode::BucketIndexOutOfBounds);
+
+        // Streak must follow a failed bucket and every bucket in it must be over
+        require!(
+            (0..start_bucket_index).any(|i| bit_is_set(&session.buckets_failed_bitmap, i)),
+            ErrorCode::InvalidCleanStreak
+        );
+        let streak_end_slot = checked_bucket_start(
+            session.sla_window_start_slot,
+            end_bucket_index,
+            session.bucket_slots,
+        ).ok_or(ErrorCode::Overflow)?;
+        require!(now >= streak_end_slot, ErrorCode::InvalidCleanStreak);
+        require!(
+            (start_bucket_index..end_bucket_index)
+                .all(|i| !bit_is_set(&session.buckets_failed_bitmap, i)),
+            ErrorCode::InvalidCleanStreak
+        );
+
+        // === Attestation ===
+        let verifier_key = ctx.accounts.verifier.key();
+        let message = clean_streak_message(&session_key, start_bucket_index, bucket_count);
+        if session.quorum_threshold > 0 {
+            apply_quorum_rotations(session, ctx.remaining_accounts, now)?;
+            let quorum = &session.quorum_verifiers[..session.quorum_verifier_count as usize];
+            require!(quorum.contains(&verifier_key), ErrorCode::InvalidAttester);
+
+            verify_quorum_attestation(
+                &ctx.accounts.instructions_sysvar,
+                quorum,
+                session.quorum_threshold,
+                &message,
+            )?;
+        } else {
+            let rotation = load_verifier_rotation(&ctx.accounts.verifier_rotation)?;
+            authorize_pinned_verifier(session, verifier_key, rotation.as_ref(), now)?;
+
+            verify_verifier_attestation(
+                &ctx.accounts.instructions_sysvar,
+                &session.verifier_pubkey,
+                &message,
+            )?;
+        }
+
+        // === Forgive part of the accrued penalty ===
+        let clawback = session.penalty_accrued
+            .checked_mul(CLAWBACK_BPS)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::Overflow)?;
+        session.penalty_accrued -= clawback;
+        session.penalty_clawed_back = session.penalty_clawed_back
+            .checked_add(clawback)
+            .ok_or(ErrorCode::Overflow)?;
+        session.clawback_next_bucket = end_bucket_index;
+
+        emit_cpi!(PenaltyClawedBack {
+            session: session_key,
+            start_bucket_index,
+            bucket_count,
+            clawback,
+            penalty_accrued: session.penalty_accrued,
+        });
+
+        Ok(())
+    }
+
+    /// Terminate session for cause (client exercises termination right)
+    ///
+    /// Requires sla_status == Violated and within termination window.
+    /// Effects:
+    /// - Refunds 100% escrow to user
+    /// - Slashes penalty_accrued from provider collateral
+    /// - Releases remaining collateral to provider
+    /// - Sets sla_status = TerminatedForCause
+    pub fn terminate_for_cause(ctx: Context<TerminateForCause>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.slot;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        // === Status guards ===
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(session.sla_status == SlaStatus::Violated, ErrorCode::SessionNotViolated);
+        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+
+        // === Termination window check ===
+        require!(
+            now <= session.terminate_deadline_slot,
+            ErrorCode::TerminationWindowExpired
+        );
+
+        // Compute penalty: min(penalty_accrued, bucket_penalty * penalty_units, reserve_r)
+        let computed_penalty = session.bucket_penalty
+            .checked_mul(session.penalty_units)
+            .ok_or(ErrorCode::Overflow)?;
+        let actual_penalty = computed_penalty
+            .min(session.penalty_accrued)
+            .min(session.reserve_r);
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let buckets_failed = session.buckets_failed;
+        let failure_reasons = session.sla_failure_reasons;
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+        let payout_splits = session.payout_splits;
+        let split_count = session.payout_split_count as usize;
+
+        // Update state
+        session.sla_status = SlaStatus::TerminatedForCause;
+        session.terminated_for_cause = true;
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, clock.slot);
+
+        let _ = session;
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        // === Service-credit settlement: penalty owed as credits, nothing slashed ===
+        let slashed = if settle_in_credits { 0 } else { actual_penalty };
+        if settle_in_credits && actual_penalty > 0 {
+            let credits_issued = issue_service_credits(
+                ctx.accounts.service_credit.as_mut(),
+                &session_key,
+                &user_key,
+                &provider_key,
+                &mint_key,
+                actual_penalty,
+            )?;
+            emit_cpi!(credits_issued);
+        }
+
+        // === Slash penalty from provider collateral (split across payout recipients if set) ===
+        let bond_paid = if slashed > 0 {
+            let slash_accounts = SlashCpiAccounts {
+                collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                destination: ctx.accounts.user_token_account.to_account_info(),
+                session_authority: session_info.clone(),
+                token_program: token_program_info.clone(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                insurance_bps: ctx.accounts.registry.insurance_bps,
+                mint: mint_info.clone(),
+                decimals,
+                bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+                leg_accounts: ctx.remaining_accounts.to_vec(),
+            };
+            slash_to_payees(
+                &slash_accounts,
+                ctx.remaining_accounts,
+                &payout_splits[..split_count],
+                &mint_key,
+                session_key,
+                slashed,
+                signer_seeds,
+            )?
+        } else {
+            0
+        };
+
+        // === Release remaining collateral to provider (bond-paid amounts never left it) ===
+        let remaining_reserve = reserve_r.saturating_sub(slashed - bond_paid);
+        if remaining_reserve > 0 {
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: ctx.accounts.session.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                release_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
+        }
+
+        // === Refund 100% escrow to user ===
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance);
+        if user_refund > 0 {
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: ctx.accounts.session.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::TerminatedForCause, session_key, slashed)?;
+
+        emit_cpi!(SessionTerminatedForCause {
+            session: session_key,
+            penalty_paid: slashed,
+            escrow_refunded: user_refund,
+            buckets_failed,
+            failure_reasons,
+            remaining_collateral_released: remaining_reserve,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Settle SLA after window ends (alternative to terminate_for_cause)
+    ///
+    /// Callable after sla_window_end_slot.
+    /// Effects:
+    /// - If no failures past grace_buckets: sla_status = Met, premium released to host
+    /// - Otherwise: sla_status = Failed, penalty slashed, remaining released
+    pub fn settle_sla(ctx: Context<SettleSla>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.slot;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let provider_token_info = ctx.accounts.provider_token_account.to_account_info();
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        // === Status guards ===
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(
+            session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated,
+            ErrorCode::SlaAlreadyEvaluated
+        );
+        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+
+        // === Window must be ended ===
+        require!(now > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let buckets_failed = session.buckets_failed;
+        let penalized = penalized_buckets(session);
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        // Both outcomes are terminal
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        let to_state = if penalized == 0 { SessionState::Closed } else { SessionState::Claimed };
+        let state_changed = transition_state(session, session_key, to_state, clock.slot);
+
+        if penalized == 0 {
+            // === SLA MET: Premium to host, release all collateral ===
+            session.sla_status = SlaStatus::Met;
+
+            // Release all collateral
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: session_info.clone(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                release_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(release_ctx, session_key, reserve_r)?;
+
+            // Transfer premium (escrow) to provider
+            if escrow_balance > 0 {
+                let cpi_accounts = TransferChecked {
+                    from: escrow_info,
+                    mint: mint_info,
+                    to: provider_token_info,
+                    authority: session_info,
+                };
+                let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+                token_interface::transfer_checked(cpi_ctx, escrow_balance, decimals)?;
+            }
+
+            record_event(&mut ctx.accounts.event_queue, EventKind::SlaSettled, session_key, 0)?;
+
+            emit_cpi!(SlaSettled {
+                session: session_key,
+                status: SlaStatus::Met,
+                buckets_failed,
+                penalty_paid: 0,
+                premium_to_host: escrow_balance,
+                premium_refunded_to_user: 0,
+            });
+        } else {
+            // === SLA FAILED: Penalty slashed, premium split or refunded ===
+            session.sla_status = SlaStatus::Failed;
+
+            // Compute penalty (weighted by failure reason)
+            let computed_penalty = session.bucket_penalty
+                .checked_mul(session.penalty_units)
+                .ok_or(ErrorCode::Overflow)?;
+            let actual_penalty = computed_penalty
+                .min(session.penalty_accrued)
+                .min(reserve_r);
+
+            let _failure_reasons = session.sla_failure_reasons;
+            let _ = session;
+
+            // Service-credit settlement: penalty owed as credits, nothing slashed
+            let slashed = if settle_in_credits { 0 } else { actual_penalty };
+            if settle_in_credits && actual_penalty > 0 {
+                let credits_issued = issue_service_credits(
+                    ctx.accounts.service_credit.as_mut(),
+                    &session_key,
+                    &user_key,
+                    &provider_key,
+                    &mint_key,
+                    actual_penalty,
+                )?;
+                emit_cpi!(credits_issued);
+            }
+
+            // Slash penalty (insurance fund slice first)
+            let bond_paid = if slashed > 0 {
+                let slash_accounts = SlashCpiAccounts {
+                    collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                    vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                    destination: ctx.accounts.user_token_account.to_account_info(),
+                    session_authority: session_info.clone(),
+                    token_program: token_program_info.clone(),
+                    event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                    insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                    insurance_bps: ctx.accounts.registry.insurance_bps,
+                    mint: mint_info.clone(),
+                    decimals,
+                    bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+                    leg_accounts: ctx.remaining_accounts.to_vec(),
+                };
+                slash_to_payees(&slash_accounts, &[], &[], &mint_key, session_key, slashed, signer_seeds)?
+            } else {
+                0
+            };
+
+            // Release remaining collateral (bond-paid amounts never left it)
+            let remaining_reserve = reserve_r.saturating_sub(slashed - bond_paid);
+            if remaining_reserve > 0 {
+                let release_accounts = Release {
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    session_authority: ctx.accounts.session.to_account_info(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    program: ctx.accounts.collateral_vault_program.to_account_info(),
+                };
+                let release_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.collateral_vault_program.to_account_info(),
+                    release_accounts,
+                    signer_seeds,
+                );
+                collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
+            }
+
+            // Reservation fee accrued so far comes out of the user's share first
+            let reservation_fee = pay_reservation_fee(
+                &mut ctx.accounts.session,
+                now,
+                escrow_balance,
+                &escrow_info,
+                &mint_info,
+                Some(&ctx.accounts.provider_token_account),
+                &token_program_info,
+                decimals,
+            )?;
+            if reservation_fee > 0 {
+                emit_cpi!(ReservationFeePaid {
+                    session: session_key,
+                    provider: provider_key,
+                    amount: reservation_fee,
+                });
+            }
+
+            // Refund the rest of the escrow to user (SLA failed = no premium for host)
+            let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance - reservation_fee);
+            if user_refund > 0 {
+                let cpi_accounts = TransferChecked {
+                    from: escrow_info,
+                    mint: mint_info,
+                    to: user_token_info,
+                    authority: ctx.accounts.session.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+                token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+            }
+
+            record_event(&mut ctx.accounts.event_queue, EventKind::SlaSettled, session_key, slashed)?;
+
+            emit_cpi!(SlaSettled {
+                session: session_key,
+                status: SlaStatus::Failed,
+                buckets_failed,
+                penalty_paid: slashed,
+                premium_to_host: 0,
+                premium_refunded_to_user: user_refund,
+            });
+        }
+
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Permissionless crank settling a violation the user never terminated
+    ///
+    /// Callable by anyone once both terminate_deadline_slot and
+    /// sla_window_end_slot have passed on a Violated session. Settles as the
+    /// failed branch of settle_sla, except that CRANK_TIP_BPS of the penalty
+    /// is slashed to the caller instead of the user. Under service-credit
+    /// settlement only the remainder is issued as credits.
+    pub fn settle_expired_violation(ctx: Context<SettleExpiredViolation>) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.slot;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let cranker_token_info = ctx.accounts.cranker_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        // === Status guards ===
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(session.sla_status == SlaStatus::Violated, ErrorCode::SessionNotViolated);
+        require!(!session.terminated_for_cause, ErrorCode::SessionAlreadyTerminated);
+
+        // === User's termination window and the SLA window must both be over ===
+        require!(now > session.terminate_deadline_slot, ErrorCode::TerminationWindowOpen);
+        require!(now > session.sla_window_end_slot, ErrorCode::SlaWindowNotEnded);
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let buckets_failed = session.buckets_failed;
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+
+        let actual_penalty = session.bucket_penalty
+            .checked_mul(session.penalty_units)
+            .ok_or(ErrorCode::Overflow)?
+            .min(session.penalty_accrued)
+            .min(reserve_r);
+        let crank_tip = actual_penalty
+            .checked_mul(CRANK_TIP_BPS)
+            .ok_or(ErrorCode::Overflow)?
+            / 10_000;
+        let user_penalty = actual_penalty - crank_tip;
+
+        session.sla_status = SlaStatus::Failed;
+        let state_changed = transition_state(session, session_key, SessionState::Claimed, clock.slot);
+
+        let _ = session;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        // Service-credit settlement: user's share owed as credits, only the tip slashed
+        let slashed_to_user = if settle_in_credits { 0 } else { user_penalty };
+        if settle_in_credits && user_penalty > 0 {
+            let credits_issued = issue_service_credits(
+                ctx.accounts.service_credit.as_mut(),
+                &session_key,
+                &user_key,
+                &provider_key,
+                &mint_key,
+                user_penalty,
+            )?;
+            emit_cpi!(credits_issued);
+        }
+
+        let mut slash_accounts = SlashCpiAccounts {
+            collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+            position: ctx.accounts.position.to_account_info(),
+            reservation: ctx.accounts.reservation.to_account_info(),
+            reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+            vault_callers: ctx.accounts.vault_callers.to_account_info(),
+            mode_vault: ctx.accounts.mode_vault.to_account_info(),
+            vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+            destination: user_token_info.clone(),
+            session_authority: session_info.clone(),
+            token_program: token_program_info.clone(),
+            event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+            insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+            insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+            insurance_bps: ctx.accounts.registry.insurance_bps,
+            mint: mint_info.clone(),
+            decimals,
+            bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+            leg_accounts: ctx.remaining_accounts.to_vec(),
+        };
+
+        // Slash user's share (insurance fund slice first), then the crank tip
+        let mut bond_paid = if slashed_to_user > 0 {
+            slash_to_payees(&slash_accounts, &[], &[], &mint_key, session_key, slashed_to_user, signer_seeds)?
+        } else {
+            0
+        };
+        if crank_tip > 0 {
+            slash_accounts.destination = cranker_token_info;
+            slash_accounts.insurance_bps = 0;
+            slash_accounts.bond = slash_accounts
+                .bond
+                .map(|(bond, balance)| (bond, balance.saturating_sub(bond_paid)));
+            bond_paid += slash_to_payees(&slash_accounts, &[], &[], &mint_key, session_key, crank_tip, signer_seeds)?;
+        }
+
+        // Release remaining collateral (bond-paid amounts never left it)
+        let slashed = slashed_to_user + crank_tip;
+        let remaining_reserve = reserve_r.saturating_sub(slashed - bond_paid);
+        if remaining_reserve > 0 {
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: session_info.clone(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                release_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
+        }
+
+        // Reservation fee accrued so far comes out of the user's share first
+        let reservation_fee = pay_reservation_fee(
+            &mut ctx.accounts.session,
+            now,
+            escrow_balance,
+            &escrow_info,
+            &mint_info,
+            ctx.accounts.provider_token_account.as_deref(),
+            &token_program_info,
+            decimals,
+        )?;
+        if reservation_fee > 0 {
+            emit_cpi!(ReservationFeePaid {
+                session: session_key,
+                provider: provider_key,
+                amount: reservation_fee,
+            });
+        }
+
+        // Refund the rest of the escrow to user (SLA failed = no premium for host)
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance - reservation_fee);
+        if user_refund > 0 {
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::ExpiredViolationSettled, session_key, slashed)?;
+
+        emit_cpi!(ExpiredViolationSettled {
+            session: session_key,
+            cranker: ctx.accounts.cranker.key(),
+            buckets_failed,
+            penalty_paid: slashed_to_user,
+            crank_tip,
+            premium_refunded_to_user: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Permissionless crank expiring a session that stopped making progress
+    ///
+    /// Callable by anyone once an active session has gone the mode's
+    /// max_session_slots without progress, and never before the user has had
+    /// STALL_CLAIM_WINDOW_SLOTS past the stall timeout to claim_stall.
+    /// Outstanding SLA state is settled
+    /// (penalties past grace are slashed or credited, otherwise Met), the
+    /// remaining collateral is released and all escrow is refunded to the user.
+    pub fn expire_session(ctx: Context<ExpireSession>) -> Result<()> {
+        let clock = Clock::get()?;
+        let max_session_slots = ctx.accounts.mode.max_session_slots;
+
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+        require!(max_session_slots > 0, ErrorCode::SessionNotExpired);
+        let stall_claim_end = session.stall_timeout_slots
+            .checked_add(STALL_CLAIM_WINDOW_SLOTS)
+            .ok_or(ErrorCode::Overflow)?;
+        let expiry_slot = session.last_progress_slot
+            .checked_add(max_session_slots.max(stall_claim_end))
+            .ok_or(ErrorCode::Overflow)?;
+        require!(clock.slot >= expiry_slot, ErrorCode::SessionNotExpired);
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+
+        // === Settle outstanding SLA state ===
+        let mut penalty = 0;
+        if session.is_bid
+            && (session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated)
+        {
+            let penalized = penalized_buckets(session);
+            if penalized == 0 {
+                session.sla_status = SlaStatus::Met;
+            } else {
+                session.sla_status = SlaStatus::Failed;
+                penalty = session.bucket_penalty
+                    .checked_mul(session.penalty_units)
+                    .ok_or(ErrorCode::Overflow)?
+                    .min(session.penalty_accrued)
+                    .min(reserve_r);
+            }
+        }
+
+        let to_state = if penalty > 0 { SessionState::Claimed } else { SessionState::Closed };
+        let state_changed = transition_state(session, session_key, to_state, clock.slot);
+
+        let _ = session;
+
+        release_active_session(&mut ctx.accounts.active_sessions);
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        // Service-credit settlement: penalty owed as credits, nothing slashed
+        let slashed = if settle_in_credits { 0 } else { penalty };
+        if settle_in_credits && penalty > 0 {
+            let credits_issued = issue_service_credits(
+                ctx.accounts.service_credit.as_mut(),
+                &session_key,
+                &user_key,
+                &provider_key,
+                &mint_key,
+                penalty,
+            )?;
+            emit_cpi!(credits_issued);
+        }
+
+        let bond_paid = if slashed > 0 {
+            let slash_accounts = SlashCpiAccounts {
+                collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                destination: user_token_info.clone(),
+                session_authority: session_info.clone(),
+                token_program: token_program_info.clone(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                insurance_bps: ctx.accounts.registry.insurance_bps,
+                mint: mint_info.clone(),
+                decimals,
+                bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+                leg_accounts: ctx.remaining_accounts.to_vec(),
+            };
+            slash_to_payees(&slash_accounts, &[], &[], &mint_key, session_key, slashed, signer_seeds)?
+        } else {
+            0
+        };
+
+        // Release remaining collateral (bond-paid amounts never left it)
+        let released = reserve_r.saturating_sub(slashed - bond_paid);
+        if released > 0 {
+            let release_accounts = Release {
+                position: ctx.accounts.position.to_account_info(),
+                reservation: ctx.accounts.reservation.to_account_info(),
+                rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                session_authority: session_info.clone(),
+                vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                program: ctx.accounts.collateral_vault_program.to_account_info(),
+            };
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.collateral_vault_program.to_account_info(),
+                release_accounts,
+                signer_seeds,
+            );
+            collateral_vault::cpi::release(release_ctx, session_key, released)?;
+        }
+
+        // Reservation fee accrued so far comes out of the user's share first
+        let reservation_fee = pay_reservation_fee(
+            &mut ctx.accounts.session,
+            clock.slot,
+            escrow_balance,
+            &escrow_info,
+            &mint_info,
+            ctx.accounts.provider_token_account.as_deref(),
+            &token_program_info,
+            decimals,
+        )?;
+        if reservation_fee > 0 {
+            emit_cpi!(ReservationFeePaid {
+                session: session_key,
+                provider: provider_key,
+                amount: reservation_fee,
+            });
+        }
+
+        // Refund the rest of the escrow to user
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance - reservation_fee);
+        if user_refund > 0 {
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::SessionExpired, session_key, user_refund)?;
+
+        emit_cpi!(SessionExpired {
+            session: session_key,
+            penalty_paid: penalty,
+            collateral_released: released,
+            refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    /// Settle a session immediately by mutual agreement (user and provider co-sign)
+    ///
+    /// Work already paid through redeemed permits stays with the provider;
+    /// outstanding permits should be redeemed earlier in the same transaction.
+    /// Accrued SLA penalties past grace are applied as in settle_sla (slashed
+    /// or credited), the remaining collateral is released and all escrow is
+    /// refunded, without waiting for the SLA window or close cooldown.
+    pub fn terminate_mutual(ctx: Context<TerminateMutual>) -> Result<()> {
+        let session_info = ctx.accounts.session.to_account_info();
+        let escrow_info = ctx.accounts.escrow_token_account.to_account_info();
+        let mint_info = ctx.accounts.payment_mint.to_account_info();
+        let decimals = ctx.accounts.payment_mint.decimals;
+        let user_token_info = ctx.accounts.user_token_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let session_key = ctx.accounts.session.key();
+        let escrow_balance = ctx.accounts.escrow_token_account.amount;
+
+        let session = &mut ctx.accounts.session;
+
+        require!(
+            session.state == SessionState::Open
+                || session.state == SessionState::Active
+                || session.state == SessionState::Closing,
+            ErrorCode::InvalidSessionState
+        );
+
+        let user_key = session.user;
+        let provider_key = session.provider;
+        let mint_key = session.mint;
+        let nonce_bytes = session.session_nonce.to_le_bytes();
+        let bump = session.bump;
+        let reserve_r = session.reserve_r;
+        let was_acked = session.acked;
+        let settle_in_credits = session.settlement_mode == SettlementMode::ServiceCredit;
+
+        // === Apply accrued SLA penalties ===
+        let mut penalty = 0;
+        if was_acked
+            && session.is_bid
+            && (session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated)
+        {
+            let penalized = penalized_buckets(session);
+            if penalized == 0 {
+                session.sla_status = SlaStatus::Met;
+            } else {
+                session.sla_status = SlaStatus::Failed;
+                penalty = session.bucket_penalty
+                    .checked_mul(session.penalty_units)
+                    .ok_or(ErrorCode::Overflow)?
+                    .min(session.penalty_accrued)
+                    .min(reserve_r);
+            }
+        }
+
+        let to_state = if penalty > 0 { SessionState::Claimed } else { SessionState::Closed };
+        let slot = Clock::get()?.slot;
+        let state_changed = transition_state(session, session_key, to_state, slot);
+
+        let _ = session;
+
+        let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[bump]];
+        let signer_seeds = &[seeds];
+
+        // Service-credit settlement: penalty owed as credits, nothing slashed
+        let slashed = if settle_in_credits { 0 } else { penalty };
+        if settle_in_credits && penalty > 0 {
+            let credits_issued = issue_service_credits(
+                ctx.accounts.service_credit.as_mut(),
+                &session_key,
+                &user_key,
+                &provider_key,
+                &mint_key,
+                penalty,
+            )?;
+            emit_cpi!(credits_issued);
+        }
+
+        // Collateral is only reserved once the provider has acked
+        let mut released = 0;
+        if was_acked {
+            release_active_session(&mut ctx.accounts.active_sessions);
+
+            let bond_paid = if slashed > 0 {
+                let slash_accounts = SlashCpiAccounts {
+                    collateral_vault_program: ctx.accounts.collateral_vault_program.to_account_info(),
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    reservation_rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    mode_vault: ctx.accounts.mode_vault.to_account_info(),
+                    vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
+                    destination: user_token_info.clone(),
+                    session_authority: session_info.clone(),
+                    token_program: token_program_info.clone(),
+                    event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    insurance_fund: ctx.accounts.insurance_fund_token_account.as_ref().map(|a| a.to_account_info()),
+                    insurance_authority: ctx.accounts.insurance_authority.as_ref().map(|a| a.to_account_info()),
+                    insurance_bps: ctx.accounts.registry.insurance_bps,
+                    mint: mint_info.clone(),
+                    decimals,
+                    bond: bond_source(ctx.accounts.session_bond.as_deref(), ctx.accounts.session.bond_posted)?,
+                    leg_accounts: ctx.remaining_accounts.to_vec(),
+                };
+                slash_to_payees(&slash_accounts, &[], &[], &mint_key, session_key, slashed, signer_seeds)?
+            } else {
+                0
+            };
+
+            // Release remaining collateral (bond-paid amounts never left it)
+            released = reserve_r.saturating_sub(slashed - bond_paid);
+            if released > 0 {
+                let release_accounts = Release {
+                    position: ctx.accounts.position.to_account_info(),
+                    reservation: ctx.accounts.reservation.to_account_info(),
+                    rent_receiver: ctx.accounts.reservation_rent_receiver.to_account_info(),
+                    session_authority: session_info.clone(),
+                    vault_callers: ctx.accounts.vault_callers.to_account_info(),
+                    event_authority: ctx.accounts.collateral_vault_event_authority.to_account_info(),
+                    program: ctx.accounts.collateral_vault_program.to_account_info(),
+                };
+                let release_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.collateral_vault_program.to_account_info(),
+                    release_accounts,
+                    signer_seeds,
+                );
+                collateral_vault::cpi::release(release_ctx, session_key, released)?;
+            }
+        }
+
+        // Reservation fee accrued so far comes out of the user's share first
+        let reservation_fee = pay_reservation_fee(
+            &mut ctx.accounts.session,
+            slot,
+            escrow_balance,
+            &escrow_info,
+            &mint_info,
+            ctx.accounts.provider_token_account.as_deref(),
+            &token_program_info,
+            decimals,
+        )?;
+        if reservation_fee > 0 {
+            emit_cpi!(ReservationFeePaid {
+                session: session_key,
+                provider: provider_key,
+                amount: reservation_fee,
+            });
+        }
+
+        // Refund the rest of the escrow to user
+        let user_refund = user_refund_amount(ctx.accounts.session.sponsored_amount, escrow_balance - reservation_fee);
+        if user_refund > 0 {
+            let cpi_accounts = TransferChecked {
+                from: escrow_info,
+                mint: mint_info,
+                to: user_token_info,
+                authority: session_info,
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
+            token_interface::transfer_checked(cpi_ctx, user_refund, decimals)?;
+        }
+
+        record_event(&mut ctx.accounts.event_queue, EventKind::TerminatedMutually, session_key, user_refund)?;
+
+        emit_cpi!(SessionTerminatedMutually {
+            session: session_key,
+            penalty_paid: penalty,
+            collateral_released: released,
+            refunded: user_refund,
+        });
+        emit_cpi!(state_changed);
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // SERVICE CREDIT SETTLEMENT
+    // =========================================================================
+
+    /// Create the user's service-credit ledger for a provider and mint
+    ///
+    /// Credits are account balances, not tokens, so they cannot be transferred.
+    pub fn init_service_credit(ctx: Context<InitServiceCredit>) -> Result<()> {
+        let ledger = &mut ctx.accounts.service_credit;
+        ledger.user = ctx.accounts.user.key();
+        ledger.provider = ctx.accounts.provider.key();
+        ledger.mint = ctx.accounts.mint.key();
+        ledger.balance = 0;
+        ledger.total_issued = 0;
+        ledger.total_burned = 0;
+        ledger.bump = ctx.bumps.service_credit;
+
+        emit_cpi!(ServiceCreditInitialized {
+            service_credit: ctx.accounts.service_credit.key(),
+            user: ctx.accounts.user.key(),
+            provider: ctx.accounts.provider.key(),
+            mint: ctx.accounts.mint.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Require M-of-N verifier attestations for bucket failures (user only, before provider ack)
+    ///
+    /// With a quorum set, report_bucket_failure needs `threshold` distinct
+    /// quorum members to have signed the bucket report in Ed25519 instructions
+    /// of the same transaction, so one compromised verifier cannot slash alone.
+    /// An empty list with threshold 0 restores the single pinned verifier.
+    pub fn set_verifier_quorum(
+        ctx: Context<SetVerifierQuorum>,
+        verifiers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(session.is_bid, ErrorCode::NotBidSession);
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+
+        require!(
+            verifiers.len() <= MAX_QUORUM_VERIFIERS
+                && threshold as usize <= verifiers.len()
+                && (threshold == 0) == verifiers.is_empty(),
+            ErrorCode::InvalidQuorumConfig
+        );
+        for (i, verifier) in verifiers.iter().enumerate() {
+            require!(
+                *verifier != Pubkey::default() && !verifiers[..i].contains(verifier),
+                ErrorCode::InvalidQuorumConfig
+            );
+        }
+
+        session.quorum_verifiers = [Pubkey::default(); MAX_QUORUM_VERIFIERS];
+        session.quorum_verifiers[..verifiers.len()].copy_from_slice(&verifiers);
+        session.quorum_verifier_count = verifiers.len() as u8;
+        session.quorum_threshold = threshold;
+
+        emit_cpi!(VerifierQuorumSet {
+            session: session_key,
+            verifiers,
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Choose how SLA penalties are settled (user only, before provider ack)
+    ///
+    /// ServiceCredit: penalties are allocated to the user's credit ledger instead
+    /// of being slashed from collateral and paid out in tokens.
+    pub fn set_settlement_mode(
+        ctx: Context<SetSettlementMode>,
+        settlement_mode: SettlementMode,
+    ) -> Result<()> {
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+
+        require!(!session.is_shared, ErrorCode::SharedSessionUnsupported);
+        require!(session.state == SessionState::Open, ErrorCode::InvalidSessionState);
+        require!(!session.acked, ErrorCode::SessionAlreadyStarted);
+
+        if settlement_mode == SettlementMode::ServiceCredit {
+            let ledger = ctx
+                .accounts
+                .service_credit
+                .as_ref()
+                .ok_or(ErrorCode::ServiceCreditAccountRequired)?;
+            require!(
+                ledger.user == session.user
+                    && ledger.provider == session.provider
+                    && ledger.mint == session.mint,
+                ErrorCode::ServiceCreditMismatch
+            );
+        }
+
+        session.settlement_mode = settlement_mode;
+
+        emit_cpi!(SettlementModeSet {
+            session: session_key,
+            settlement_mode,
+        });
+
+        Ok(())
+    }
+
+    /// Burn service credits against this session's fees
+    ///
+    /// Applied credits cover permit redemptions before escrow is drawn.
+    /// Credits are burned immediately and are not returned on close.
+    pub fn apply_service_credits(ctx: Context<ApplyServiceCredits>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+
+        let session_key = ctx.accounts.session.key();
+        let session = &mut ctx.accounts.session;
+        let ledger = &mut ctx.accounts.service_credit;
+
+        require!(
+            session.state == SessionState::Open || session.state == SessionState::Active,
+            ErrorCode::SessionNotFundable
+        );
+        require!(
+            ledger.provider == session.provider && ledger.mint == session.mint,
+            ErrorCode::ServiceCreditMismatch
+        );
+        require!(amount <= ledger.balance, ErrorCode::InsufficientServiceCredits);
+
+        let credits_applied = session.credits_applied
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(credits_applied <= session.max_spend, ErrorCode::MaxSpendExceeded);
+
+        ledger.balance = ledger.balance.checked_sub(amount).ok_or(ErrorCode::Overflow)?;
+        ledger.total_burned = ledger.total_burned
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        session.credits_applied = credits_applied;
+
+        emit_cpi!(ServiceCreditsBurned {
+            session: session_key,
+            user: ledger.user,
+            provider: ledger.provider,
+            amount,
+            remaining_balance: ledger.balance,
+        });
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Allocate service credits to a user's ledger in lieu of a token payout
+fn issue_service_credits(
+    service_credit: Option<&mut Account<ServiceCredit>>,
+    session_key: &Pubkey,
+    user: &Pubkey,
+    provider: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> Result<ServiceCreditsIssued> {
+    let ledger = service_credit.ok_or(ErrorCode::ServiceCreditAccountRequired)?;
+    require!(
+        ledger.user == *user && ledger.provider == *provider && ledger.mint == *mint,
+        ErrorCode::ServiceCreditMismatch
+    );
+
+    ledger.balance = ledger.balance.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+    ledger.total_issued = ledger.total_issued
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+
+    Ok(ServiceCreditsIssued {
+        session: *session_key,
+        user: *user,
+        provider: *provider,
+        amount,
+        new_balance: ledger.balance,
+    })
+}
+
+// Bitmap helpers for bucket tracking (1024 buckets max)
+fn bit_is_set(bitmap: &[u8; 128], idx: u64) -> bool {
+    if idx >= 1024 {
+        return true; // Out of bounds treated as "already set" (reject)
+    }
+    let i = idx as usize;
+    let byte = i >> 3;
+    let bit = i & 7;
+    (bitmap[byte] & (1u8 << bit)) != 0
+}
+
+fn clear_bit(bitmap: &mut [u8; 128], idx: u64) {
+    if idx >= 1024 {
+        return; // Out of bounds no-op
+    }
+    let i = idx as usize;
+    let byte = i >> 3;
+    let bit = i & 7;
+    bitmap[byte] &= !(1u8 << bit);
+}
+
+fn set_bit(bitmap: &mut [u8; 128], idx: u64) {
+    if idx >= 1024 {
+        return; // Out of bounds no-op
+    }
+    let i = idx as usize;
+    let byte = i >> 3;
+    let bit = i & 7;
+    bitmap[byte] |= 1u8 << bit;
+}
+
+/// Compute bucket start slot with checked math
+fn checked_bucket_start(
+    sla_window_start: u64,
+    bucket_index: u64,
+    bucket_slots: u64,
+) -> Option<u64> {
+    let offset = bucket_index.checked_mul(bucket_slots)?;
+    sla_window_start.checked_add(offset)
+}
+
+/// Compute bucket end slot with checked math
+#[allow(dead_code)]
+fn checked_bucket_end(
+    sla_window_start: u64,
+    bucket_index: u64,
+    bucket_slots: u64,
+) -> Option<u64> {
+    let start = checked_bucket_start(sla_window_start, bucket_index, bucket_slots)?;
+    start.checked_add(bucket_slots)?.checked_sub(1)
+}
+
+/// Compute buckets_total with validation
+fn compute_buckets_total(sla_window_slots: u64, bucket_slots: u64) -> Result<u64> {
+    require!(bucket_slots > 0, ErrorCode::InvalidBucketConfig);
+    require!(sla_window_slots % bucket_slots == 0, ErrorCode::InvalidBucketConfig);
+    let total = sla_window_slots
+        .checked_div(bucket_slots)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(total > 0 && total <= 1024, ErrorCode::InvalidBucketConfig);
+    Ok(total)
+}
+
+/// Compute per-bucket penalty with checked math
+fn compute_bucket_penalty(
+    collateral: u64,
+    max_penalty_bps: u16,
+    buckets_total: u64,
+) -> Result<u64> {
+    // penalty_per_bucket = collateral * max_penalty_bps / (buckets_total * 10_000)
+    let numerator = (collateral as u128)
+        .checked_mul(max_penalty_bps as u128)
+        .ok_or(ErrorCode::Overflow)?;
+    let denominator = (buckets_total as u128)
+        .checked_mul(10_000)
+        .ok_or(ErrorCode::Overflow)?;
+    let result = numerator
+        .checked_div(denominator)
+        .ok_or(ErrorCode::Overflow)?;
+    u64::try_from(result).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Combine failure reasons into the session's bitmask
+fn combine_failure_reason(current: u8, new: SlaFailureReason) -> u8 {
+    current | new.mask()
+}
+
+/// Verify that `threshold` distinct quorum verifiers signed `expected_message`
+///
+/// Every Ed25519 instruction before this one is parsed strictly; each
+/// signature over the exact message from a quorum member counts once,
+/// however many times that member appears.
+fn verify_quorum_attestation(
+    instructions_sysvar: &AccountInfo,
+    quorum: &[Pubkey],
+    threshold: u8,
+    expected_message: &[u8],
+) -> Result<()> {
+    let current_ix_idx = instructions::load_current_index_checked(instructions_sysvar)
+        .map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
+    
+    let mut attested = [false; MAX_QUORUM_VERIFIERS];
+    for ix_idx in 0..current_ix_idx {
+        let ix = load_instruction_at_checked(ix_idx as usize, instructions_sysvar)
+            .map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
+        if ix.program_id != ED25519_PROGRAM_ID {
+            continue;
+        }
+        
+        for (signer, message) in parse_ed25519_signatures(&ix.data)? {
+            if message != expected_message {
+                continue;
+            }
+            if let Some(i) = quorum.iter().position(|v| v.as_ref() == signer) {
+                attested[i] = true;
+            }
+        }
+    }
+    
+    let attestations = attested.iter().filter(|a| **a).count();
+    require!(attestations >= threshold as usize, ErrorCode::QuorumNotReached);
+    
+    Ok(())
+}
+
+/// Bucket report message: (program_id, session, bucket_index, bucket_start, failure_reason)
+///
+/// failure_reason is the SlaFailureReason discriminant: 1 = Latency,
+/// 2 = Bandwidth, 3 = Both, 4 = PrivacyMode, 5 = Compute, 6 = Jitter,
+/// 7 = PacketLoss.
+fn bucket_failure_message(
+    session_key: &Pubkey,
+    bucket_index: u64,
+    bucket_start_slot: u64,
+    failure_reason: SlaFailureReason,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 32 + 8 + 8 + 1);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(&session_key.to_bytes());
+    message.extend_from_slice(&bucket_index.to_le_bytes());
+    message.extend_from_slice(&bucket_start_slot.to_le_bytes());
+    message.push(failure_reason as u8);
+    message
+}
+
+/// Bucket dispute counter-attestation message: (program_id, "dispute", session, bucket_index)
+fn bucket_dispute_message(session_key: &Pubkey, bucket_index: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 7 + 32 + 8);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(b"dispute");
+    message.extend_from_slice(&session_key.to_bytes());
+    message.extend_from_slice(&bucket_index.to_le_bytes());
+    message
+}
+
+/// Batched bucket failure message:
+/// (program_id, "bucket_batch", session, failure_reason, count: u16, bucket_indices...)
+fn bucket_failures_batch_message(
+    session_key: &Pubkey,
+    bucket_indices: &[u64],
+    failure_reason: SlaFailureReason,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 12 + 32 + 1 + 2 + 8 * bucket_indices.len());
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(b"bucket_batch");
+    message.extend_from_slice(&session_key.to_bytes());
+    message.push(failure_reason as u8);
+    message.extend_from_slice(&(bucket_indices.len() as u16).to_le_bytes());
+    for bucket_index in bucket_indices {
+        message.extend_from_slice(&bucket_index.to_le_bytes());
+    }
+    message
+}
+
+/// Compute attestation message:
+/// (program_id, "compute", session, bucket_index, bucket_start, utilization_pct, tokens_per_sec)
+fn compute_attestation_message(
+    session_key: &Pubkey,
+    bucket_index: u64,
+    bucket_start_slot: u64,
+    utilization_pct: u8,
+    tokens_per_sec: u32,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 7 + 32 + 8 + 8 + 1 + 4);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(b"compute");
+    message.extend_from_slice(&session_key.to_bytes());
+    message.extend_from_slice(&bucket_index.to_le_bytes());
+    message.extend_from_slice(&bucket_start_slot.to_le_bytes());
+    message.push(utilization_pct);
+    message.extend_from_slice(&tokens_per_sec.to_le_bytes());
+    message
+}
+
+/// Clean streak message: (program_id, "clean", session, start_bucket_index, bucket_count)
+fn clean_streak_message(
+    session_key: &Pubkey,
+    start_bucket_index: u64,
+    bucket_count: u64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 5 + 32 + 8 + 8);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(b"clean");
+    message.extend_from_slice(&session_key.to_bytes());
+    message.extend_from_slice(&start_bucket_index.to_le_bytes());
+    message.extend_from_slice(&bucket_count.to_le_bytes());
+    message
+}
+
+/// Accept reports from the session's pinned verifier or its successor
+///
+/// Once a registry rotation away from the pinned key is effective, only the
+/// successor is accepted and the session is re-pinned to it; before then
+/// only the pinned key is.
+fn authorize_pinned_verifier(
+    session: &mut Session,
+    verifier_key: Pubkey,
+    rotation: Option<&mode_registry::VerifierRotation>,
+    now: u64,
+) -> Result<()> {
+    let rotation = rotation.filter(|rotation| rotation.old_verifier == session.verifier_pubkey);
+
+    if let Some(rotation) = rotation {
+        if now >= rotation.effective_slot {
+            require_keys_eq!(verifier_key, rotation.new_verifier, ErrorCode::InvalidAttester);
+            session.verifier_pubkey = verifier_key;
+            return Ok(());
+        }
+        require!(
+            verifier_key != rotation.new_verifier,
+            ErrorCode::VerifierRotationNotEffective
+        );
+    }
+
+    require_keys_eq!(verifier_key, session.verifier_pubkey, ErrorCode::InvalidAttester);
+    Ok(())
+}
+
+/// Re-pin quorum members whose registry rotation is effective
+///
+/// remaining_accounts holds each member's ["verifier_rotation", member]
+/// PDA in quorum order (empty for members never rotated), so a retired key
+/// cannot keep counting towards the threshold.
+fn apply_quorum_rotations(session: &mut Session, rotations: &[AccountInfo], now: u64) -> Result<()> {
+    let count = session.quorum_verifier_count as usize;
+    require!(rotations.len() >= count, ErrorCode::InvalidVerifierRotation);
+
+    for (member, info) in session.quorum_verifiers[..count].iter_mut().zip(rotations) {
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"verifier_rotation", member.as_ref()],
+            &mode_registry::ID,
+        );
+        require_keys_eq!(info.key(), expected, ErrorCode::InvalidVerifierRotation);
+
+        if let Some(rotation) = load_verifier_rotation(info)? {
+            if rotation.old_verifier == *member && now >= rotation.effective_slot {
+                *member = rotation.new_verifier;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read a registry VerifierRotation record, if the PDA has been created
+fn load_verifier_rotation(info: &AccountInfo) -> Result<Option<mode_registry::VerifierRotation>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, mode_registry::ID, ErrorCode::InvalidVerifierRotation);
+
+    let data = info.try_borrow_data()?;
+    let rotation = mode_registry::VerifierRotation::try_deserialize(&mut &data[..])?;
+    Ok(Some(rotation))
+}
+
+/// Verify a single verifier's Ed25519 signature over `expected_message`
+///
+/// The Ed25519 instruction must immediately precede this one and is parsed
+/// strictly (see parse_ed25519_signatures).
+fn verify_verifier_attestation(
+    instructions_sysvar: &AccountInfo,
+    expected_verifier: &Pubkey,
+    expected_message: &[u8],
+) -> Result<()> {
+    let current_ix_idx = instructions::load_current_index_checked(instructions_sysvar)
+        .map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
+    require!(current_ix_idx > 0, ErrorCode::InvalidEd25519Instruction);
+
+    let ix = load_instruction_at_checked((current_ix_idx - 1) as usize, instructions_sysvar)
+        .map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
+    require!(ix.program_id == ED25519_PROGRAM_ID, ErrorCode::InvalidEd25519Instruction);
+
+    let (signer, message) = parse_ed25519_single_signature(&ix.data)?;
+    require!(signer == expected_verifier.as_ref(), ErrorCode::InvalidAttester);
+    require!(message == expected_message, ErrorCode::SignatureMessageMismatch);
+
+    Ok(())
+}
+
+fn compute_insurance_coverage(max_spend: u64, price_per_chunk: u64) -> u64 {
+    use session_escrow::{INSURANCE_A, INSURANCE_B, INSURANCE_MIN_BPS, INSURANCE_CAP_BPS};
+
+    let term_a = max_spend.saturating_mul(INSURANCE_A).saturating_div(10000);
+    let term_b = price_per_chunk.saturating_mul(INSURANCE_B).saturating_div(10000);
+    let raw_coverage = term_a.saturating_add(term_b);
+
+    let p_min = max_spend.saturating_mul(INSURANCE_MIN_BPS).saturating_div(10000);
+    let p_cap = max_spend.saturating_mul(INSURANCE_CAP_BPS).saturating_div(10000);
+
+    raw_coverage.max(p_min).min(p_cap)
+}
+
+/// Compute bid coverage based on premium and SLA strictness
+///
+/// bid_coverage_p = base_factor * (premium_weight * premium_bps + sla_weight * sla_strictness)
+/// where sla_strictness is derived from latency and bandwidth targets
+fn compute_bid_coverage(
+    max_spend: u64,
+    premium_bps: u16,
+    latency_target_ms: u16,
+    bandwidth_min_chunks: u32,
+) -> u64 {
+    use session_escrow::{BID_PREMIUM_WEIGHT, BID_SLA_WEIGHT};
+
+    // Premium contribution (higher premium = more coverage)
+    let premium_factor = (premium_bps as u64)
+        .saturating_mul(BID_PREMIUM_WEIGHT)
+        .saturating_div(10000);
+
+    // SLA strictness contribution
+    // Lower latency target = stricter = more coverage
+    // Higher bandwidth target = stricter = more coverage
+    let latency_strictness = if latency_target_ms > 0 {
+        // Normalize: 100ms target = 100, 50ms = 200, 200ms = 50
+        10000u64.saturating_div(latency_target_ms as u64)
+    } else {
+        100
+    };
+
+    let bandwidth_strictness = (bandwidth_min_chunks as u64).min(1000); // Cap at 1000 for normalization
+
+    let sla_factor = latency_strictness
+        .saturating_add(bandwidth_strictness)
+        .saturating_mul(BID_SLA_WEIGHT)
+        .saturating_div(10000);
+
+    // Combined factor applied to max_spend
+    let total_factor = premium_factor.saturating_add(sla_factor);
+
+    max_spend
+        .saturating_mul(total_factor)
+        .saturating_div(10000)
+}
+
+/// Delivery receipt message: (program_id, "receipt", session, chunk_index, chunk_hash)
+fn delivery_receipt_message(session_key: &Pubkey, chunk_index: u64, chunk_hash: &[u8; 32]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 7 + 32 + 8 + 32);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(b"receipt");
+    message.extend_from_slice(&session_key.to_bytes());
+    message.extend_from_slice(&chunk_index.to_le_bytes());
+    message.extend_from_slice(chunk_hash);
+    message
+}
+
+/// Fold a redemption into the session's rolling receipt hash
+///
+/// receipt_hash = sha256(prev || permit_nonce || amount || slot), all integers
+/// little-endian. Either party can recompute the chain from PermitRedeemed
+/// events to prove the exact redemption sequence.
+fn compute_receipt_hash(prev: &[u8; 32], permit_nonce: u64, amount: u64, slot: u64) -> [u8; 32] {
+    hashv(&[
+        prev,
+        &permit_nonce.to_le_bytes(),
+        &amount.to_le_bytes(),
+        &slot.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Whether a provider position can no longer honor a session's reservation
+///
+/// True if the provider's position PDA was closed, or the live position
+/// holds less reserved collateral than the session locked. Positions can
+/// change owner (collateral_vault::accept_position_transfer) while keeping
+/// their original seeds, so a live position is matched by its own seeds and
+/// by either its current or its original provider.
+fn reservation_unreachable(position: &AccountInfo, session: &Session) -> Result<bool> {
+    let mode_id_bytes = session.mode_id.to_le_bytes();
+    if position.owner != &collateral_vault::ID || position.data_is_empty() {
+        let (closed_key, _) = Pubkey::find_program_address(
+            &[b"pos", session.provider.as_ref(), &mode_id_bytes],
+            &collateral_vault::ID,
+        );
+        require_keys_eq!(position.key(), closed_key, ErrorCode::InvalidPosition);
+        return Ok(true);
+    }
+
+    let data = position.try_borrow_data()?;
+    let account = ProviderPosition::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(ErrorCode::InvalidPosition))?;
+    let expected_key = Pubkey::create_program_address(
+        &[b"pos", account.seed_provider.as_ref(), &mode_id_bytes, &[account.bump]],
+        &collateral_vault::ID,
+    )
+    .map_err(|_| error!(ErrorCode::InvalidPosition))?;
+    require_keys_eq!(position.key(), expected_key, ErrorCode::InvalidPosition);
+    require!(
+        account.provider == session.provider || account.seed_provider == session.provider,
+        ErrorCode::InvalidPosition
+    );
+
+    Ok(account.reserved < session.reserve_r)
+}
+
+/// Whether a closing session is still inside its dispute window
+///
+/// Covers both a user close's dispute window and a provider close's
+/// cooldown, so closing never cuts off a stall or SLA claim.
+fn in_close_dispute_window(session: &Session, slot: u64) -> bool {
+    session.state == SessionState::Closing && slot < session.close_unlock_slot
+}
+
+/// Guards shared by bucket-level SLA reports
+///
+/// The session must be an active bid session still being evaluated, the
+/// report must land inside the SLA window (and before the termination
+/// deadline once violated), and bucket_start_slot must be the exact start
+/// of bucket_index.
+fn check_bucket_report(
+    session: &Session,
+    bucket_index: u64,
+    bucket_start_slot: u64,
+    now: u64,
+) -> Result<()> {
+    // === Status guards ===
+    require!(session.is_bid, ErrorCode::NotBidSession);
+    require!(session.state == SessionState::Active, ErrorCode::SessionNotActive);
+    require!(
+        session.sla_status == SlaStatus::Pending || session.sla_status == SlaStatus::Violated,
+        ErrorCode::SlaAlreadyEvaluated
+    );
+
+    // === Window bounds ===
+    require!(
+        now >= session.sla_window_start_slot && now <= session.sla_window_end_slot,
+        ErrorCode::ReportOutsideSlaWindow
+    );
+
+    // === Termination deadline (if already violated) ===
+    if session.sla_status == SlaStatus::Violated {
+        require!(
+            now <= session.terminate_deadline_slot,
+            ErrorCode::ReportAfterDeadline
+        );
+    }
+
+    // === Bucket bounds ===
+    require!(bucket_index < session.buckets_total, ErrorCode::BucketIndexOutOfBounds);
+
+    // === Bucket alignment ===
+    let expected_bucket_start = checked_bucket_start(
+        session.sla_window_start_slot,
+        bucket_index,
+        session.bucket_slots,
+    ).ok_or(ErrorCode::Overflow)?;
+    require!(bucket_start_slot == expected_bucket_start, ErrorCode::BucketSlotMismatch);
+
+    Ok(())
+}
+
+/// Record an attested bucket failure
+///
+/// Marks the bucket in the bitmap, counts it, and once past grace_buckets
+/// opens the termination window and accrues the reason-weighted penalty.
+/// Returns whether this failure was the first violation.
+fn record_bucket_failure(
+    session: &mut Session,
+    bucket_index: u64,
+    failure_reason: SlaFailureReason,
+    now: u64,
+) -> Result<bool> {
+    // === Bitmap deduplication ===
+    require!(
+        !bit_is_set(&session.buckets_failed_bitmap, bucket_index),
+        ErrorCode::BucketAlreadyReported
+    );
+    set_bit(&mut session.buckets_failed_bitmap, bucket_index);
+
+    // === Increment failure counter ===
+    session.buckets_failed = session.buckets_failed
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+
+    let penalty_before = session.penalty_accrued;
+    let units_before = session.penalty_units;
+
+    let is_first_violation = if penalized_buckets(session) > 0 {
+        // === First violation past grace: set termination window ===
+        let first = session.sla_status == SlaStatus::Pending;
+        if first {
+            session.first_violation_slot = now;
+            session.terminate_deadline_slot = now.saturating_add(session.terminate_window_slots);
+            session.sla_status = SlaStatus::Violated;
+        }
+
+        // === Accrue penalty, weighted by failure reason ===
+        let weight = session.penalty_weights.weight(failure_reason);
+        session.penalty_units = session.penalty_units
+            .checked_add(weight)
+            .ok_or(ErrorCode::Overflow)?;
+        let weighted_penalty = session.bucket_penalty
+            .checked_mul(weight)
+            .ok_or(ErrorCode::Overflow)?;
+        session.penalty_accrued = session.penalty_accrued
+            .checked_add(weighted_penalty)
+            .ok_or(ErrorCode::Overflow)?
+            .min(session.reserve_r);  // Cap at total collateral
+
+        first
+    } else {
+        false
+    };
+
+    // === Combine failure reason ===
+    session.sla_failure_reasons = combine_failure_reason(
+        session.sla_failure_reasons,
+        failure_reason,
+    );
+
+    // === Remember for the provider's dispute window ===
+    let index = (session.recent_failure_count as usize) % MAX_DISPUTABLE_FAILURES;
+    session.recent_failures[index] = RecentBucketFailure {
+        bucket_index,
+        reported_slot: now,
+        penalty: session.penalty_accrued - penalty_before,
+        penalty_units: session.penalty_units - units_before,
+    };
+    session.recent_failure_count = session.recent_failure_count
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+
+    Ok(is_first_violation)
+}
+
+/// Create a program-owned PDA at `target`, as Anchor's `init` does
+///
+/// Anyone can send lamports to a not-yet-created PDA, which makes a plain
+/// create_account fail. A pre-funded address is instead topped up to
+/// rent-exempt, then allocated and assigned under the PDA's seeds.
+fn create_pda_account<'info>(
+    target: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &Rent,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let rent_exempt = rent.minimum_balance(space);
+    let current = target.lamports();
+    if current == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+                &[seeds],
+            ),
+            rent_exempt,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let top_up = rent_exempt.saturating_sub(current);
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: target.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate {
+                account_to_allocate: target.clone(),
+            },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign {
+                account_to_assign: target.clone(),
+            },
+            &[seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+/// Create the (user, request_id) lookup PDA pointing at a new session
+///
+/// The PDA can only be created once, so replaying the same logical request
+/// under a different session nonce fails with DuplicateClientRequest. A
+/// pre-funded but unallocated address still counts as unused.
+fn register_client_request<'info>(
+    lookup_info: &AccountInfo<'info>,
+    user: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    request_id: [u8; 16],
+    session_key: Pubkey,
+) -> Result<()> {
+    let user_key = user.key();
+    let (expected_lookup, bump) = Pubkey::find_program_address(
+        &[b"client_req", user_key.as_ref(), &request_id],
+        &crate::ID,
+    );
+    require_keys_eq!(lookup_info.key(), expected_lookup, ErrorCode::InvalidClientRequestAccount);
+    require!(
+        lookup_info.data_is_empty() && lookup_info.owner == &anchor_lang::system_program::ID,
+        ErrorCode::DuplicateClientRequest
+    );
+
+    let space = 8 + ClientRequest::INIT_SPACE;
+    let seeds: &[&[u8]] = &[b"client_req", user_key.as_ref(), &request_id, &[bump]];
+    create_pda_account(
+        lookup_info,
+        &user.to_account_info(),
+        &system_program.to_account_info(),
+        &Rent::get()?,
+        space,
+        seeds,
+    )?;
+
+    let mut lookup = Account::<ClientRequest>::try_from_unchecked(lookup_info)?;
+    lookup.user = user_key;
+    lookup.request_id = request_id;
+    lookup.session = session_key;
+    lookup.bump = bump;
+    lookup.exit(&crate::ID)
+}
+
+/// Failed buckets beyond the session's grace allowance
+/// Whether bandwidth is measured by Merkle-proven chunks instead of permit nonces
+fn uses_delivery_proofs(session: &Session) -> bool {
+    session.delivery_proofs_required || session.delivery_root != [0u8; 32]
+}
+
+/// Monotonic delivered-chunk counter used by bandwidth checkpoints
+fn delivered_chunk_counter(session: &Session) -> u64 {
+    if uses_delivery_proofs(session) {
+        session.chunks_proven
+    } else {
+        session.next_permit_nonce
+    }
+}
+
+/// Most recently recorded bandwidth checkpoint, if any
+fn last_bandwidth_checkpoint(session: &Session) -> Option<BandwidthCheckpoint> {
+    let count = session.bandwidth_checkpoint_count as usize;
+    if count == 0 {
+        return None;
+    }
+    Some(session.bandwidth_checkpoints[(count - 1) % MAX_BANDWIDTH_CHECKPOINTS])
+}
+
+/// Whether `chunks` delivered over `slots` meets the bandwidth target prorated
+/// from the full SLA window (byte target takes precedence)
+fn bandwidth_target_met(session: &Session, chunks: u64, slots: u64) -> bool {
+    let window_slots = session.sla_window_slots.max(1) as u128;
+    let slots = slots as u128;
+    if session.bandwidth_min_bytes > 0 {
+        let bytes = (chunks as u128).saturating_mul(session.chunk_size as u128);
+        bytes.saturating_mul(window_slots) >= (session.bandwidth_min_bytes as u128).saturating_mul(slots)
+    } else {
+        (chunks as u128).saturating_mul(window_slots)
+            >= (session.bandwidth_min_chunks as u128).saturating_mul(slots)
+    }
+}
+
+fn penalized_buckets(session: &Session) -> u64 {
+    session.buckets_failed.saturating_sub(session.grace_buckets as u64)
+}
+
+/// Parties and identity of a session being opened
+struct SessionParties {
+    user: Pubkey,
+    provider: Pubkey,
+    mode_id: u32,
+    mint: Pubkey,
+    session_nonce: u64,
+    bump: u8,
+}
+
+/// Initialize a new session from its terms
+///
+/// Shared by open_session and open_session_from_mode; cr_bps sizes the
+/// collateral reserve from coverage.
+fn init_session(
+    session: &mut Session,
+    parties: SessionParties,
+    terms: &SessionTerms,
+    cr_bps: u16,
+    slot: u64,
+) -> Result<()> {
+    // Compute base coverage (always computed)
+    let base_coverage_p = compute_insurance_coverage(terms.max_spend, terms.price_per_chunk);
+    let reserve_base = base_coverage_p
+        .checked_mul(cr_bps as u64)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Compute bid coverage if in bid mode
+    let (bid_coverage_p, reserve_bid, sla_window_start_slot, sla_window_end_slot) = if terms.is_bid {
+        let bid_cov = compute_bid_coverage(
+            terms.max_spend,
+            terms.premium_bps,
+            terms.latency_target_ms,
+            terms.bandwidth_min_chunks,
+        );
+        let res_bid = bid_cov
+            .checked_mul(cr_bps as u64)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let window_start = slot
+            .checked_add(terms.sla_warmup_slots)
+            .ok_or(ErrorCode::Overflow)?;
+        let window_end = window_start
+            .checked_add(terms.sla_window_slots)
+            .ok_or(ErrorCode::Overflow)?;
+
+        (bid_cov, res_bid, window_start, window_end)
+    } else {
+        (0, 0, 0, 0)
+    };
+
+    // Total reserve required
+    let total_reserve = reserve_base
+        .checked_add(reserve_bid)
+        .ok_or(ErrorCode::Overflow)?;
+
+    let start_deadline_slot = slot
+        .checked_add(terms.start_deadline_slots)
+        .ok_or(ErrorCode::Overflow)?;
+
+    session.user = parties.user;
+    session.provider = parties.provider;
+    session.mode_id = parties.mode_id;
+    session.mint = parties.mint;
+    session.session_nonce = parties.session_nonce;
+    session.chunk_size = terms.chunk_size;
+    session.price_per_chunk = terms.price_per_chunk;
+    session.max_spend = terms.max_spend;
+    session.max_spend_usd = 0;
+    session.total_spent = 0;
+    session.reserve_r = total_reserve;
+    session.cr_bps = cr_bps;
+    session.start_deadline_slot = start_deadline_slot;
+    session.stall_timeout_slots = terms.stall_timeout_slots;
+    session.last_progress_slot = 0;
+    session.state = SessionState::Open;
+    session.acked = false;
+    session.next_permit_nonce = 0;
+    session.bump = parties.bump;
+
+    // Bid/SLA fields
+    session.is_bid = terms.is_bid;
+    session.premium_bps = terms.premium_bps;
+    session.fail_payout_bps = terms.fail_payout_bps;
+    session.latency_target_ms = terms.latency_target_ms;
+    session.bandwidth_min_chunks = terms.bandwidth_min_chunks;
+    session.bandwidth_min_bytes = terms.bandwidth_min_bytes;
+    session.sla_warmup_slots = terms.sla_warmup_slots;
+    session.sla_window_slots = terms.sla_window_slots;
+    session.sla_window_start_slot = sla_window_start_slot;
+    session.sla_window_end_slot = sla_window_end_slot;
+
+    // Insurance split
+    session.base_coverage_p = base_coverage_p;
+    session.bid_coverage_p = bid_coverage_p;
+    session.reserve_base = reserve_base;
+    session.reserve_bid = reserve_bid;
+
+    // SLA state
+    session.sla_status = SlaStatus::None;
+    session.sla_failure_reasons = 0;
+    session.latency_attested = false;
+
+    // Nonce tracking for bandwidth SLA (legacy window-level)
+    session.nonce_at_window_start = 0;
+    session.nonce_at_window_end = 0;
+
+    // Bandwidth checkpoints (sliding sub-windows)
+    session.bandwidth_checkpoints = [BandwidthCheckpoint::default(); MAX_BANDWIDTH_CHECKPOINTS];
+    session.bandwidth_checkpoint_count = 0;
+    session.bandwidth_subwindows_failed = 0;
+
+    // Bucketed bandwidth (starts at the first crank)
+    session.bucket_bandwidth_tracking = false;
+    session.bucket_bandwidth_index = 0;
+    session.bucket_bandwidth_counter = 0;
+
+    // Bucketed SLA configuration (compute if is_bid)
+    if terms.is_bid && terms.bucket_slots > 0 {
+        let buckets_total_computed = compute_buckets_total(terms.sla_window_slots, terms.bucket_slots)?;
+        let bucket_penalty_computed = compute_bucket_penalty(
+            total_reserve,
+            terms.max_penalty_bps,
+            buckets_total_computed,
+        )?;
+        session.bucket_slots = terms.bucket_slots;
+        session.buckets_total = buckets_total_computed;
+        session.bucket_penalty = bucket_penalty_computed;
+        session.grace_buckets = terms.grace_buckets;
+        require!(terms.penalty_weights.is_valid(), ErrorCode::InvalidPenaltyWeights);
+    } else {
+        session.bucket_slots = 0;
+        session.buckets_total = 0;
+        session.bucket_penalty = 0;
+        session.grace_buckets = 0;
+    }
+
+    // Bucketed downtime tracking (initialized to zero)
+    session.buckets_failed = 0;
+    session.buckets_failed_bitmap = [0u8; 128];
+
+    // Termination window
+    session.first_violation_slot = 0;
+    session.terminate_window_slots = terms.terminate_window_slots;
+    session.terminate_deadline_slot = 0;
+
+    // Penalty accounting
+    session.penalty_accrued = 0;
+    session.penalty_weights = terms.penalty_weights;
+    session.penalty_units = 0;
+
+    // Attester configuration (quorum disabled until set_verifier_quorum)
+    session.verifier_pubkey = terms.verifier_pubkey;
+    session.quorum_verifiers = [Pubkey::default(); MAX_QUORUM_VERIFIERS];
+    session.quorum_verifier_count = 0;
+    session.quorum_threshold = 0;
+
+    // Convenience flags
+    session.terminated_for_cause = false;
+
+    // Settlement (cash by default, see set_settlement_mode)
+    session.settlement_mode = SettlementMode::Cash;
+    session.credits_applied = 0;
+    session.credits_consumed = 0;
+
+    // Receipt accumulator (zero until first redemption)
+    session.receipt_hash = [0u8; 32];
+
+    // Settlement destination
+    session.payout_destination = terms.payout_destination.unwrap_or(parties.user);
+    session.payout_destination_set_slot = slot;
+
+    // Close cooldown
+    session.provider_close_requested = false;
+    session.close_unlock_slot = 0;
+
+    // Delivery proofs (bandwidth SLA)
+    session.delivery_proofs_required = false;
+    session.delivery_root = [0u8; 32];
+    session.delivery_chunk_count = 0;
+    session.chunks_proven = 0;
+    session.next_proof_index = 0;
+
+    // Idempotency key (set by open_session when provided)
+    session.client_request_id = [0u8; 16];
+
+    // Delivery receipts
+    session.receipts_posted = 0;
+    session.next_receipt_index = 0;
+    session.delivery_receipt_hash = [0u8; 32];
+
+    // Compute SLA (disabled until set_compute_sla)
+    session.compute_min_utilization_pct = 0;
+    session.compute_min_tokens_per_sec = 0;
+
+    // Penalty clawback
+    session.clawback_next_bucket = 0;
+    session.penalty_clawed_back = 0;
+
+    // Permit signing scheme
+    match terms.permit_eth_address {
+        Some(eth_address) => {
+            require!(eth_address != [0u8; 20], ErrorCode::InvalidPermitSigner);
+            session.permit_scheme = PermitScheme::Secp256k1;
+            session.permit_eth_address = eth_address;
+        }
+        None => {
+            session.permit_scheme = PermitScheme::Ed25519;
+            session.permit_eth_address = [0u8; 20];
+        }
+    }
+
+    // Claim payout splits (disabled until set_payout_splits)
+    session.payout_split_count = 0;
+    session.payout_splits = [PayoutSplit::default(); MAX_PAYOUT_SPLITS];
+
+    // Shared session (disabled until enable_shared_session)
+    session.is_shared = false;
+    session.shared_total_contributed = 0;
+    session.shared_contributor_count = 0;
+    session.shared_settled_count = 0;
+    session.shared_pool = 0;
+    session.shared_pool_snapshotted = false;
+
+    // Auto top-up (disabled until set_auto_top_up)
+    session.auto_top_up_threshold = 0;
+    session.auto_top_up_amount = 0;
+
+    // Performance bond (none until post_session_bond)
+    session.bond_posted = 0;
+
+    // Redemption limits (unlimited until set_redemption_limits)
+    session.max_permit_amount = 0;
+    session.rate_limit_amount = 0;
+    session.rate_limit_slots = 0;
+    session.rate_window_start_slot = 0;
+    session.rate_window_redeemed = 0;
+
+    // Chained delivery log (off until set_chained_receipts)
+    session.chained_receipts_required = false;
+    session.delivery_chain_hash = [0u8; 32];
+
+    // Disputable bucket failures
+    session.recent_failures = [RecentBucketFailure::default(); MAX_DISPUTABLE_FAILURES];
+    session.recent_failure_count = 0;
+
+    // Referral (none until set_referrer)
+    session.referrer = Pubkey::default();
+    session.referral_bps = 0;
+
+    // Late-start compensation
+    session.start_window_slot = slot;
+    session.acked_slot = 0;
+    session.late_start_claimed = false;
+
+    // Maximum duration (deadline set at ack)
+    session.max_duration_slots = terms.max_duration_slots;
+    session.duration_end_slot = 0;
+
+    // Sponsorship (none until a third party funds)
+    session.sponsor = Pubkey::default();
+    session.sponsored_amount = 0;
+
+    // Reservation fee (rate set at ack)
+    session.reservation_fee_bps = 0;
+    session.reservation_fee_paid = 0;
+
+    Ok(())
+}
+
+/// SessionOpened event for a freshly initialized session
+fn session_opened_event(session_key: Pubkey, session: &Session) -> SessionOpened {
+    SessionOpened {
+        session: session_key,
+        user: session.user,
+        provider: session.provider,
+        mode_id: session.mode_id,
+        max_spend: session.max_spend,
+        max_spend_usd: session.max_spend_usd,
+        base_coverage_p: session.base_coverage_p,
+        reserve_r: session.reserve_r,
+        cr_bps: session.cr_bps,
+        start_deadline_slot: session.start_deadline_slot,
+        is_bid: session.is_bid,
+        premium_bps: session.premium_bps,
+        fail_payout_bps: session.fail_payout_bps,
+        bid_coverage_p: session.bid_coverage_p,
+        reserve_base: session.reserve_base,
+        reserve_bid: session.reserve_bid,
+    }
+}
+
+/// Collateral ratio for a new session: the mode's cr_bps unless the user
+/// requested a higher one (up to MAX_SESSION_CR_BPS)
+fn effective_cr_bps(requested_cr_bps: u16, mode: &mode_registry::Mode) -> Result<u16> {
+    if requested_cr_bps == 0 {
+        return Ok(mode.cr_bps);
+    }
+    require!(
+        requested_cr_bps >= mode.cr_bps && requested_cr_bps <= MAX_SESSION_CR_BPS,
+        ErrorCode::InvalidCrOverride
+    );
+    Ok(requested_cr_bps)
+}
+
+/// Sponsor a funder would become, if any (funders other than the user)
+///
+/// A session has at most one sponsor; a second third-party funder is rejected.
+fn funding_sponsor(session: &Session, funder: Pubkey) -> Result<Option<Pubkey>> {
+    if funder == session.user {
+        return Ok(None);
+    }
+    require!(
+        session.sponsor == Pubkey::default() || session.sponsor == funder,
+        ErrorCode::SponsorMismatch
+    );
+    Ok(Some(funder))
+}
+
+/// Record funds a sponsor added to the escrow
+fn credit_sponsor(session: &mut Session, sponsor: Pubkey, received: u64) -> Result<()> {
+    session.sponsor = sponsor;
+    session.sponsored_amount = session.sponsored_amount
+        .checked_add(received)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(())
+}
+
+/// Portion of a refund owed to the user once unspent sponsorship is held back
+///
+/// Sponsored funds are returned first: the sponsor's share (up to
+/// sponsored_amount) stays in escrow for withdraw_sponsor_refund.
+fn user_refund_amount(sponsored_amount: u64, escrow_balance: u64) -> u64 {
+    escrow_balance.saturating_sub(sponsored_amount)
+}
+
+/// Pay the provider the reservation fee a started session owes up to end_slot
+///
+/// Comes out of the user's share of escrow, net of what earlier calls
+/// already paid (recorded in reservation_fee_paid). Returns the amount paid.
+#[allow(clippy::too_many_arguments)]
+fn pay_reservation_fee<'info>(
+    session: &mut Account<'info, Session>,
+    end_slot: u64,
+    escrow_balance: u64,
+    escrow: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    provider_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: &AccountInfo<'info>,
+    decimals: u8,
+) -> Result<u64> {
+    if !session.acked || session.reservation_fee_bps == 0 {
+        return Ok(0);
+    }
+    let fee = reservation_fee_amount(session.reserve_r, session.reservation_fee_bps, session.acked_slot, end_slot)?
+        .saturating_sub(session.reservation_fee_paid)
+        .min(user_refund_amount(session.sponsored_amount, escrow_balance));
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    let provider_token_info = provider_token_account
+        .ok_or(ErrorCode::ProviderTokenAccountRequired)?
+        .to_account_info();
+    let user_key = session.user;
+    let nonce_bytes = session.session_nonce.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"sess", user_key.as_ref(), &nonce_bytes, &[session.bump]];
+    let signer_seeds = &[seeds];
+
+    let cpi_accounts = TransferChecked {
+        from: escrow.clone(),
+        mint: mint.clone(),
+        to: provider_token_info,
+        authority: session.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+    token_interface::transfer_checked(cpi_ctx, fee, decimals)?;
+
+    session.reservation_fee_paid = session.reservation_fee_paid
+        .checked_add(fee)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(fee)
+}
+
+/// Fee owed to the provider for keeping reserve_r locked from acked_slot to end_slot
+///
+/// fee_bps of reserve_r accrues per RESERVATION_FEE_PERIOD_SLOTS, pro rata
+/// per slot.
+fn reservation_fee_amount(reserve_r: u64, fee_bps: u16, acked_slot: u64, end_slot: u64) -> Result<u64> {
+    let locked_slots = end_slot.saturating_sub(acked_slot);
+    let fee = (reserve_r as u128)
+        .checked_mul(fee_bps as u128)
+        .and_then(|v| v.checked_mul(locked_slots as u128))
+        .ok_or(ErrorCode::Overflow)?
+        / (10_000u128 * mode_registry::RESERVATION_FEE_PERIOD_SLOTS as u128);
+    u64::try_from(fee).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Slot from which a session acked at `slot` may be force-closed (0 = never)
+fn duration_end_slot(max_duration_slots: u64, slot: u64) -> Result<u64> {
+    if max_duration_slots == 0 {
+        return Ok(0);
+    }
+    let end_slot = slot.checked_add(max_duration_slots).ok_or(ErrorCode::Overflow)?;
+    Ok(end_slot)
+}
+
+/// Move a session to a new state, returning the event to emit for it
+fn transition_state(
+    session: &mut Session,
+    session_key: Pubkey,
+    to: SessionState,
+    slot: u64,
+) -> SessionStateChanged {
+    let from = session.state;
+    session.state = to;
+    SessionStateChanged { session: session_key, from, to, slot }
+}
+
+/// Reject session terms that fall outside the mode's bounds or are degenerate
+fn validate_terms_for_mode(terms: &SessionTerms, mode: &mode_registry::Mode) -> Result<()> {
+    require!(
+        terms.chunk_size > 0 && terms.price_per_chunk > 0 && terms.max_spend > 0,
+        ErrorCode::InvalidSessionTerms
+    );
+    require!(terms.start_deadline_slots > 0, ErrorCode::InvalidSessionTerms);
+    require!(terms.stall_timeout_slots > 0, ErrorCode::InvalidSessionTerms);
+
+    if terms.is_bid {
+        require!(terms.premium_bps <= mode.max_premium_bps, ErrorCode::InvalidSessionTerms);
+        require!(
+            terms.fail_payout_bps <= 10_000 && terms.max_penalty_bps <= 10_000,
+            ErrorCode::InvalidSessionTerms
+        );
+        require!(terms.sla_window_slots > 0, ErrorCode::InvalidSessionTerms);
+        require!(
+            terms.bucket_slots >= mode.min_bucket_slots
+                && terms.bucket_slots <= mode.max_bucket_slots,
+            ErrorCode::InvalidBucketConfig
+        );
+    }
+
+    validate_timing_for_mode(terms, mode)
+}
+
+/// Check session timing terms against the mode's configured bounds
+///
+/// The SLA bounds only apply to bid sessions; ask sessions ignore them.
+fn validate_timing_for_mode(terms: &SessionTerms, mode: &mode_registry::Mode) -> Result<()> {
+    let bounds = &mode.timing_bounds;
+    require!(
+        bounds.start_deadline.contains(terms.start_deadline_slots)
+            && bounds.stall_timeout.contains(terms.stall_timeout_slots),
+        ErrorCode::TimingOutOfBounds
+    );
+    // A mode with a duration cap does not admit unlimited sessions
+    require!(
+        bounds.duration.contains(terms.max_duration_slots)
+            && (bounds.duration.max == 0 || terms.max_duration_slots > 0),
+        ErrorCode::TimingOutOfBounds
+    );
+
+    if terms.is_bid {
+        require!(
+            bounds.sla_warmup.contains(terms.sla_warmup_slots)
+                && bounds.sla_window.contains(terms.sla_window_slots)
+                && bounds.terminate_window.contains(terms.terminate_window_slots),
+            ErrorCode::TimingOutOfBounds
+        );
+    }
+
+    Ok(())
+}
+
+/// Reject payment mints whose Token-2022 extensions escrow cannot honor
+///
+/// Classic SPL Token mints always pass. Token-2022 mints may only carry
+/// extensions in ALLOWED_MINT_EXTENSIONS; e.g. a permanent delegate could
+/// drain escrow and a transfer hook or default-frozen state could block
+/// refunds.
+fn assert_supported_payment_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .map_err(|_| ErrorCode::UnsupportedMintExtension)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| ErrorCode::UnsupportedMintExtension)?;
+
+    for extension in extensions {
+        require!(
+            ALLOWED_MINT_EXTENSIONS.contains(&extension),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+
+    Ok(())
+}
+
+/// Check a delivered chunk's inclusion proof against the committed root
+///
+/// Sibling order at each level follows the bits of chunk_index (0 = node is
+/// the left child).
+fn verify_delivery_inclusion(root: &[u8; 32], delivery_proof: &DeliveryProof) -> bool {
+    let mut node = hashv(&[
+        &[0x00],
+        &delivery_proof.chunk_index.to_le_bytes(),
+        &delivery_proof.chunk_hash,
+    ])
+    .to_bytes();
+    let mut index = delivery_proof.chunk_index;
+
+    for sibling in delivery_proof.proof.iter() {
+        node = if index & 1 == 0 {
+            hashv(&[&[0x01], &node, sibling]).to_bytes()
+        } else {
+            hashv(&[&[0x01], sibling, &node]).to_bytes()
+        };
+        index >>= 1;
+    }
+
+    index == 0 && node == *root
+}
+
+/// Free a provider's concurrency slot when an acked session reaches a terminal state
+fn release_active_session(active_sessions: &mut Account<ActiveSessions>) {
+    active_sessions.count = active_sessions.count.saturating_sub(1);
+}
+
+/// Accounts for collateral_vault::slash_and_pay CPIs made by one claim
+struct SlashCpiAccounts<'info> {
+    collateral_vault_program: AccountInfo<'info>,
+    position: AccountInfo<'info>,
+    reservation: AccountInfo<'info>,    // The session's ReservationRecord
+    reservation_rent_receiver: AccountInfo<'info>,
+    vault_callers: AccountInfo<'info>,
+    mode_vault: AccountInfo<'info>,
+    vault_token_account: AccountInfo<'info>,
+    destination: AccountInfo<'info>,    // Payout destination token account (no splits)
+    session_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    event_authority: AccountInfo<'info>,
+    insurance_fund: Option<AccountInfo<'info>>,
+    insurance_authority: Option<AccountInfo<'info>>,    // Lets the fund backstop collateral shortfalls
+    insurance_bps: u16,
+    mint: AccountInfo<'info>,
+    decimals: u8,
+    bond: Option<(AccountInfo<'info>, u64)>,    // Session bond account and balance
+    leg_accounts: Vec<AccountInfo<'info>>,      // Forwarded for slashes that reach collateral legs
+}
+
+/// Session bond account and balance for slash_to_payees
+///
+/// Once the provider has posted a bond the account must be passed, so claims
+/// cannot skip the bond and go straight to pooled collateral.
+fn bond_source<'info>(
+    bond: Option<&InterfaceAccount<'info, TokenAccount>>,
+    bond_posted: u64,
+) -> Result<Option<(AccountInfo<'info>, u64)>> {
+    match bond {
+        Some(account) => Ok(Some((account.to_account_info(), account.amount))),
+        None => {
+            require!(bond_posted == 0, ErrorCode::SessionBondAccountRequired);
+            Ok(None)
+        }
+    }
+}
+
+/// Per-recipient shares of a split payout
+///
+/// Each share is floored; the rounding dust goes to the first recipient so
+/// the shares always sum to `amount`.
+fn split_payout(splits: &[PayoutSplit], amount: u64) -> Result<[u64; MAX_PAYOUT_SPLITS]> {
+    let mut shares = [0u64; MAX_PAYOUT_SPLITS];
+    let mut allocated: u64 = 0;
+    for (share, split) in shares.iter_mut().zip(splits.iter()) {
+        *share = ((amount as u128)
+            .checked_mul(split.bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            / 10_000) as u64;
+        allocated = allocated.checked_add(*share).ok_or(ErrorCode::Overflow)?;
+    }
+    if !splits.is_empty() {
+        let dust = amount.checked_sub(allocated).ok_or(ErrorCode::Overflow)?;
+        shares[0] = shares[0].checked_add(dust).ok_or(ErrorCode::Overflow)?;
+    }
+    Ok(shares)
+}
+
+/// Slash `amount` from the provider and pay it out
+///
+/// The registry's insurance_bps slice (floored) goes to the protocol
+/// insurance fund first. Without payout splits the rest goes to the
+/// destination account. With splits, `recipient_accounts` must hold each
+/// recipient's token account (owner = recipient, session mint) in split order.
+/// Every payment draws on the session bond first; what is left is slashed
+/// from the provider's reserved collateral in a single multi-recipient
+/// slash_and_pay (one per MAX_SLASH_RECIPIENTS payees). The instruction's
+/// remaining accounts are forwarded so a slash that exhausts primary
+/// collateral can reach the provider's collateral legs (see
+/// collateral_vault::slash_legs), and the insurance fund is passed along as
+/// a backstop for any collateral shortfall to claimants. Returns the amount
+/// paid from the bond, which the caller must not count as slashed collateral.
+fn slash_to_payees<'info>(
+    accounts: &SlashCpiAccounts<'info>,
+    recipient_accounts: &[AccountInfo<'info>],
+    splits: &[PayoutSplit],
+    mint: &Pubkey,
+    session_key: Pubkey,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64> {
+    let mut bond_paid: u64 = 0;
+    let mut payouts: Vec<(AccountInfo<'info>, u64)> = Vec::with_capacity(MAX_PAYOUT_SPLITS + 1);
+    let mut pay = |to: AccountInfo<'info>, payout: u64| -> Result<()> {
+        let from_bond = match &accounts.bond {
+            Some((bond, balance)) => {
+                let from_bond = payout.min(balance.saturating_sub(bond_paid));
+                if from_bond > 0 {
+                    let cpi_accounts = TransferChecked {
+                        from: bond.clone(),
+                        mint: accounts.mint.clone(),
+                        to: to.clone(),
+                        authority: accounts.session_authority.clone(),
+                    };
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        accounts.token_program.clone(),
+                        cpi_accounts,
+                        signer_seeds,
+                    );
+                    token_interface::transfer_checked(cpi_ctx, from_bond, accounts.decimals)?;
+                    bond_paid += from_bond;
+                }
+                from_bond
+            }
+            None => 0,
+        };
+        if payout > from_bond {
+            payouts.push((to, payout - from_bond));
+        }
+        Ok(())
+    };
+
+    let insurance_cut = ((amount as u128)
+        .checked_mul(accounts.insurance_bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10_000) as u64;
+    if insurance_cut > 0 {
+        let fund = accounts
+            .insurance_fund
+            .clone()
+            .ok_or(ErrorCode::InsuranceFundAccountRequired)?;
+        pay(fund, insurance_cut)?;
+    }
+    let amount = amount - insurance_cut;
+
+    if splits.is_empty() {
+        if amount > 0 {
+            pay(accounts.destination.clone(), amount)?;
+        }
+    } else if amount > 0 {
+        require!(
+            recipient_accounts.len() >= splits.len(),
+            ErrorCode::PayoutSplitAccountsRequired
+        );
+        let shares = split_payout(splits, amount)?;
+        for (i, split) in splits.iter().enumerate() {
+            if shares[i] == 0 {
+                continue;
+            }
+            let info = &recipient_accounts[i];
+            require!(
+                info.owner == accounts.token_program.key,
+                ErrorCode::InvalidPayoutSplitAccount
+            );
+            let token_account = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require!(
+                token_account.owner == split.recipient && token_account.mint == *mint,
+                ErrorCode::InvalidPayoutSplitAccount
+            );
+            pay(info.clone(), shares[i])?;
+        }
+    }
+
+    // One slash_and_pay per MAX_SLASH_RECIPIENTS payees; the vault skips the
+    // insurance backstop for the fund's own cut
+    for chunk in payouts.chunks(collateral_vault::MAX_SLASH_RECIPIENTS) {
+        let mut total: u64 = 0;
+        for (_, payout) in chunk {
+            total = total.checked_add(*payout).ok_or(ErrorCode::Overflow)?;
+        }
+        let recipient_amounts = if chunk.len() > 1 {
+            chunk.iter().map(|(_, payout)| *payout).collect()
+        } else {
+            Vec::new()
+        };
+        let mut remaining_accounts: Vec<AccountInfo<'info>> =
+            chunk[1..].iter().map(|(to, _)| to.clone()).collect();
+        remaining_accounts.extend(accounts.leg_accounts.iter().cloned());
+
+        let cpi_accounts = SlashAndPay {
+            position: accounts.position.clone(),
+            reservation: accounts.reservation.clone(),
+            rent_receiver: accounts.reservation_rent_receiver.clone(),
+            mode_vault: accounts.mode_vault.clone(),
+            vault_token_account: accounts.vault_token_account.clone(),
+            user_token_account: chunk[0].0.clone(),
+            collateral_mint: accounts.mint.clone(),
+            session_authority: accounts.session_authority.clone(),
+            vault_callers: accounts.vault_callers.clone(),
+            insurance_fund_token_account: accounts.insurance_fund.clone(),
+            insurance_authority: accounts.insurance_authority.clone(),
+            token_program: accounts.token_program.clone(),
+            event_authority: accounts.event_authority.clone(),
+            program: accounts.collateral_vault_program.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.collateral_vault_program.clone(),
+            cpi_accounts,
+            signer_seeds,
+        )
+        .with_remaining_accounts(remaining_accounts);
+        collateral_vault::cpi::slash_and_pay(cpi_ctx, session_key, total, recipient_amounts)?;
+    }
+
+    Ok(bond_paid)
+}
+
+/// Append a compact record to the event queue
+///
+/// Written alongside emit! so indexers without log subscriptions can poll
+/// the queue and detect gaps via the sequence number.
+fn record_event(
+    queue: &mut Account<EventQueue>,
+    kind: EventKind,
+    session: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let head = queue.head as usize;
+    queue.records[head] = EventRecord {
+        seq: queue.next_seq,
+        kind,
+        session,
+        slot: Clock::get()?.slot,
+        amount,
+    };
+    queue.next_seq = queue.next_seq.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    queue.head = ((head + 1) % EVENT_QUEUE_CAPACITY) as u16;
+
+    Ok(())
+}
+
+/// Amounts settled by one validated permit redemption
+struct PermitRedemption {
+    credits_used: u64,
+    referral_amount: u64,
+    provider_amount: u64,
+}
+
+/// Validate a permit and apply it to the session
+///
+/// Checks state, expiry, nonce, the delivery chain link and the user's
+/// signature, applies credits, spend and rate limits, and advances the
+/// session's nonce and receipt hashes. Token movements are left to the
+/// caller.
+#[allow(clippy::too_many_arguments)]
+fn apply_permit_redemption(
+    session: &mut Session,
+    instructions_sysvar: &AccountInfo,
+    session_key: &Pubkey,
+    permit_nonce: u64,
+    amount: u64,
+    expiry_slot: u64,
+    chain: Option<DeliveryChainLink>,
+    escrow_balance: u64,
+    slot: u64,
+) -> Result<PermitRedemption> {
+    // Outstanding work stays redeemable during a provider close cooldown
+    require!(
+        session.state == SessionState::Active
+            || (session.state == SessionState::Closing && session.provider_close_requested),
+        ErrorCode::SessionNotActive
+    );
+    require!(slot <= expiry_slot, ErrorCode::PermitExpired);
+    require!(permit_nonce == session.next_permit_nonce, ErrorCode::InvalidPermitNonce);
+
+    // === Chained delivery log ===
+    let chain_hash = if session.chained_receipts_required {
+        let link = chain.ok_or(ErrorCode::DeliveryChainRequired)?;
+        let expected = hashv(&[
+            &session.delivery_chain_hash,
+            &permit_nonce.to_le_bytes(),
+            &link.chunks_digest,
+        ])
+        .to_bytes();
+        require!(link.chain_hash == expected, ErrorCode::DeliveryChainMismatch);
+        Some(link.chain_hash)
+    } else {
+        None
+    };
+
+    verify_permit_signature(
+        instructions_sysvar,
+        session,
+        session_key,
+        permit_nonce,
+        amount,
+        expiry_slot,
+        chain_hash.as_ref(),
+    )?;
+
+    // For bid sessions, the amount should already include the premium
+    // effective_price = base_price * (1 + premium_bps/10_000)
+    // This is enforced client-side when creating permits
+
+    // Applied service credits cover the permit before escrow does
+    let credits_available = session.credits_applied.saturating_sub(session.credits_consumed);
+    let credits_used = amount.min(credits_available);
+    let cash_amount = amount - credits_used;
+
+    require!(cash_amount <= escrow_balance, ErrorCode::InsufficientEscrow);
+
+    let new_total_spent = session.total_spent.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+    require!(new_total_spent <= session.max_spend, ErrorCode::MaxSpendExceeded);
+
+    // === Redemption limits ===
+    require!(
+        session.max_permit_amount == 0 || amount <= session.max_permit_amount,
+        ErrorCode::PermitAmountCapExceeded
+    );
+    if session.rate_limit_slots > 0 {
+        if slot >= session.rate_window_start_slot.saturating_add(session.rate_limit_slots) {
+            session.rate_window_start_slot = slot;
+            session.rate_window_redeemed = 0;
+        }
+        let window_redeemed = session.rate_window_redeemed
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(window_redeemed <= session.rate_limit_amount, ErrorCode::RedemptionRateLimited);
+        session.rate_window_redeemed = window_redeemed;
+    }
+
+    // Referral share of the cash portion, out of the provider's payment
+    let referral_amount = ((cash_amount as u128)
+        .checked_mul(session.referral_bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10_000) as u64;
+    let provider_amount = cash_amount - referral_amount;
+
+    session.total_spent = new_total_spent;
+    session.credits_consumed = session.credits_consumed
+        .checked_add(credits_used)
+        .ok_or(ErrorCode::Overflow)?;
+    session.next_permit_nonce = permit_nonce.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    session.last_progress_slot = slot;
+    session.receipt_hash = compute_receipt_hash(
+        &session.receipt_hash,
+        permit_nonce,
+        amount,
+        slot,
+    );
+    if let Some(chain_hash) = chain_hash {
+        session.delivery_chain_hash = chain_hash;
+    }
+
+    Ok(PermitRedemption {
+        credits_used,
+        referral_amount,
+        provider_amount,
+    })
+}
+
+/// Verify the session's permit signature over a permit
+///
+/// The permit message is exactly:
+/// (program_id, session, provider, permit_nonce, amount, expiry_slot)
+/// with integers little-endian, followed by the new delivery chain hash for
+/// chained-receipt sessions, signed as in verify_user_signature.
+fn verify_permit_signature(
+    instructions_sysvar: &AccountInfo,
+    session: &Session,
+    session_key: &Pubkey,
+    permit_nonce: u64,
+    amount: u64,
+    expiry_slot: u64,
+    chain_hash: Option<&[u8; 32]>,
+) -> Result<()> {
+    let mut permit = permit_message(session_key, &session.provider, permit_nonce, amount, expiry_slot);
+    if let Some(chain_hash) = chain_hash {
+        permit.extend_from_slice(chain_hash);
+    }
+
+    verify_user_signature(instructions_sysvar, session, &permit)
+}
+
+/// Verify the session user's signature over `expected_message`
+///
+/// The signature precompile instruction must immediately precede this one
+/// and carry exactly one signature whose signer and message live in its own
+/// data. Offsets are parsed strictly.
+///
+/// Ed25519 sessions: the signer must be the session user and the signed
+/// message is `expected_message` itself.
+///
+/// Secp256k1 sessions: the signer must be permit_eth_address and the signed
+/// message is the EIP-191 personal_sign payload over
+/// keccak256(expected_message), so EVM wallets can sign directly.
+fn verify_user_signature(
+    instructions_sysvar: &AccountInfo,
+    session: &Session,
+    expected_message: &[u8],
+) -> Result<()> {
+    let current_ix_idx = instructions::load_current_index_checked(instructions_sysvar)
+        .map_err(|_| ErrorCode::InvalidSignatureInstruction)?;
+
+    // Signature instruction must be immediately before this one
+    require!(current_ix_idx > 0, ErrorCode::InvalidSignatureInstruction);
+    let sig_ix_idx = current_ix_idx - 1;
+
+    let ix = load_instruction_at_checked(sig_ix_idx as usize, instructions_sysvar)
+        .map_err(|_| ErrorCode::InvalidSignatureInstruction)?;
+
+    match session.permit_scheme {
+        PermitScheme::Ed25519 => {
+            require!(ix.program_id == ED25519_PROGRAM_ID, ErrorCode::InvalidSignatureInstruction);
+
+            let (signer, message) = parse_ed25519_single_signature(&ix.data)?;
+
+            require!(signer == session.user.as_ref(), ErrorCode::InvalidPermitSigner);
+            require!(message == expected_message, ErrorCode::SignatureMessageMismatch);
+        }
+        PermitScheme::Secp256k1 => {
+            require!(ix.program_id == SECP256K1_PROGRAM_ID, ErrorCode::InvalidSignatureInstruction);
+
+            let own_index = u8::try_from(sig_ix_idx)
+                .map_err(|_| ErrorCode::InvalidSignatureInstruction)?;
+            let (eth_address, message) = parse_secp256k1_single_signature(&ix.data, own_index)?;
+
+            require!(
+                eth_address == session.permit_eth_address.as_slice(),
+                ErrorCode::InvalidPermitSigner
+            );
+
+            let digest = keccak::hash(expected_message);
+            let mut personal_message = Vec::with_capacity(28 + 32);
+            personal_message.extend_from_slice(b"\x19Ethereum Signed Message:\n32");
+            personal_message.extend_from_slice(&digest.to_bytes());
+
+            require!(message == personal_message.as_slice(), ErrorCode::SignatureMessageMismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Permit message bytes signed by the session's permit signer
+fn permit_message(
+    session: &Pubkey,
+    provider: &Pubkey,
+    permit_nonce: u64,
+    amount: u64,
+    expiry_slot: u64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 32 + 32 + 8 + 8 + 8);
+    message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
+    message.extend_from_slice(&session.to_bytes());
+    message.extend_from_slice(&provider.to_bytes());
+    message.extend_from_slice(&permit_nonce.to_le_bytes());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&expiry_slot.to_le_bytes());
+    message
+}
+
+/// Parse a secp256k1 precompile instruction holding exactly one signature
+///
+/// Layout: [num_signatures: u8] followed by an offsets entry of
+/// signature_offset (u16), signature_ix_index (u8), eth_address_offset
+/// (u16), eth_address_ix_index (u8), message_offset (u16), message_size
+/// (u16), message_ix_index (u8). Unlike Ed25519 the precompile takes real
+/// instruction indexes, so all three must equal own_index. Returns
+/// (eth_address, message).
+fn parse_secp256k1_single_signature(data: &[u8], own_index: u8) -> Result<(&[u8], &[u8])> {
+    const HEADER_LEN: usize = 1;
+    const OFFSETS_LEN: usize = 11;
+    const SIGNATURE_LEN: usize = 65; // 64-byte signature + recovery id
+    const ETH_ADDRESS_LEN: usize = 20;
+
+    fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+        let end = offset.checked_add(len).ok_or(ErrorCode::InvalidSignatureData)?;
+        data.get(offset..end).ok_or_else(|| error!(ErrorCode::InvalidSignatureData))
+    }
+
+    require!(
+        data.len() >= HEADER_LEN + OFFSETS_LEN && data[0] == 1,
+        ErrorCode::InvalidSignatureData
+    );
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let offsets = HEADER_LEN;
+    let signature_offset = read_u16(offsets);
+    let signature_ix_index = data[offsets + 2];
+    let eth_address_offset = read_u16(offsets + 3);
+    let eth_address_ix_index = data[offsets + 5];
+    let message_offset = read_u16(offsets + 6);
+    let message_size = read_u16(offsets + 8);
+    let message_ix_index = data[offsets + 10];
+
+    require!(
+        signature_ix_index == own_index
+            && eth_address_ix_index == own_index
+            && message_ix_index == own_index,
+        ErrorCode::InvalidSignatureData
+    );
+
+    slice(data, signature_offset, SIGNATURE_LEN)?;
+    let eth_address = slice(data, eth_address_offset, ETH_ADDRESS_LEN)?;
+    let message = slice(data, message_offset, message_size)?;
+
+    Ok((eth_address, message))
+}
+
+/// Parse an Ed25519 precompile instruction holding exactly one signature
+///
+/// Returns (pubkey, message). See parse_ed25519_signatures.
+fn parse_ed25519_single_signature(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let signatures = parse_ed25519_signatures(data)?;
+    require!(signatures.len() == 1, ErrorCode::InvalidSignatureData);
+
+    Ok(signatures[0])
+}
+
+/// Parse every signature of an Ed25519 precompile instruction
+///
+/// Layout: [num_signatures: u8][padding: u8] followed by one offsets entry
+/// per signature of seven little-endian u16s: signature_offset,
+/// signature_ix_index, pubkey_offset, pubkey_ix_index, message_offset,
+/// message_size, message_ix_index. All instruction indexes must be u16::MAX
+/// (data in this instruction), otherwise the precompile may have verified
+/// bytes from another instruction. Returns (pubkey, message) per signature.
+fn parse_ed25519_signatures(data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const SIGNATURE_LEN: usize = 64;
+    const PUBKEY_LEN: usize = 32;
+    const THIS_INSTRUCTION: u16 = u16::MAX;
+
+    fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+        let end = offset.checked_add(len).ok_or(ErrorCode::InvalidSignatureData)?;
+        data.get(offset..end).ok_or_else(|| error!(ErrorCode::InvalidSignatureData))
+    }
+
+    require!(data.len() >= HEADER_LEN, ErrorCode::InvalidSignatureData);
+    let count = data[0] as usize;
+    require!(
+        count > 0 && data.len() >= HEADER_LEN + count * OFFSETS_LEN,
+        ErrorCode::InvalidSignatureData
+    );
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let mut signatures = Vec::with_capacity(count);
+    for i in 0..count {
+        let offsets = HEADER_LEN + i * OFFSETS_LEN;
+        let signature_offset = read_u16(offsets) as usize;
+        let signature_ix_index = read_u16(offsets + 2);
+        let pubkey_offset = read_u16(offsets + 4) as usize;
+        let pubkey_ix_index = read_u16(offsets + 6);
+        let message_offset = read_u16(offsets + 8) as usize;
+        let message_size = read_u16(offsets + 10) as usize;
+        let message_ix_index = read_u16(offsets + 12);
+
+        require!(
+            signature_ix_index == THIS_INSTRUCTION
+                && pubkey_ix_index == THIS_INSTRUCTION
+                && message_ix_index == THIS_INSTRUCTION,
+            ErrorCode::InvalidSignatureData
+        );
+
+        slice(data, signature_offset, SIGNATURE_LEN)?;
+        let pubkey = slice(data, pubkey_offset, PUBKEY_LEN)?;
+        let message = slice(data, message_offset, message_size)?;
+        signatures.push((pubkey, message));
+    }
+
+    Ok(signatures)
+}
+
+// ============================================================================
+// Accounts
+// ============================================================================
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(session_nonce: u64, mode_id: u32)]
+pub struct OpenSession<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::INIT_SPACE,
+        seeds = [b"sess", user.key().as_ref(), &session_nonce.to_le_bytes()],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = payment_mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry mode the session is opened under (supplies the cr_bps floor)
+    #[account(
+        seeds = [b"mode", &mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    #[account(address = mode.mint @ ErrorCode::PaymentMintMismatch)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Provider pubkey
+    pub provider: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+
+    /// CHECK: Client request lookup PDA ["client_req", user, client_request_id],
+    /// required when client_request_id is set; created and verified in the handler
+    #[account(mut)]
+    pub client_request: Option<UncheckedAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(session_nonce: u64, mode_id: u32)]
+pub struct OpenSessionFromMode<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::INIT_SPACE,
+        seeds = [b"sess", user.key().as_ref(), &session_nonce.to_le_bytes()],
+        bump
+    )]
+    pub session: Box<Account<'info, Session>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = payment_mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry mode supplying mint, collateral ratio and term bounds
+    #[account(
+        seeds = [b"mode", &mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    #[account(address = mode.mint @ ErrorCode::PaymentMintMismatch)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Provider pubkey
+    pub provider: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(session_nonce: u64, mode_id: u32)]
+pub struct OpenSessionUsd<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::INIT_SPACE,
+        seeds = [b"sess", user.key().as_ref(), &session_nonce.to_le_bytes()],
+        bump
+    )]
+    pub session: Box<Account<'info, Session>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = payment_mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry mode supplying mint, price feed, collateral ratio and term bounds
+    #[account(
+        seeds = [b"mode", &mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    #[account(address = mode.mint @ ErrorCode::PaymentMintMismatch)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Pyth price update for the mode's feed (mint/USD)
+    pub price_update: Account<'info, PriceUpdateV2>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Provider pubkey
+    pub provider: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(mode_id: u32)]
+pub struct OpenSessionsBatch<'info> {
+    /// Registry mode supplying mint, collateral ratio and term bounds
+    #[account(
+        seeds = [b"mode", &mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    #[account(address = mode.mint @ ErrorCode::PaymentMintMismatch)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Provider pubkey
+    pub provider: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundSession<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub funder_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Session user, or a sponsor funding on the user's behalf
+    pub funder: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundSessionSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        constraint = session.mint == native_mint::ID @ ErrorCode::NotNativeSolSession
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Session user, or a sponsor funding on the user's behalf
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetAutoTopUp<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRedemptionLimits<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundSessionDelegated<'info> {
+    #[account(
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// User's token account with the session PDA approved as delegate
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.user @ ErrorCode::WrongUser,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongUser
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    pub keeper: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawExcessEscrow<'info> {
+    #[account(
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct IncreaseMaxSpend<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); created by collateral_vault::reserve
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// Mode vault the position holds shares of
+    pub mode_vault: Account<'info, ModeVault>,
+
+    pub user: Signer<'info>,
+
+    /// Pays the reservation record's rent
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PostSessionBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Session-owned bond token account
+    #[account(
+        init_if_needed,
+        payer = provider,
+        seeds = [b"bond", session.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = session,
+        token::token_program = token_program
+    )]
+    pub session_bond: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawSessionBond<'info> {
+    #[account(
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [b"bond", session.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = session,
+        token::token_program = token_program
+    )]
+    pub session_bond: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AckStart<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (seeds checked by collateral_vault::reserve)
+    #[account(
+        mut,
+        constraint = position.provider == session.provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); created by collateral_vault::reserve
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// Mode vault the position holds shares of
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Provider or one of its position operators (pays the reservation record's rent)
+    #[account(mut, constraint = position.can_operate(&provider.key()) @ ErrorCode::WrongProvider)]
+    pub provider: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// Registry mode for the session (supplies the concurrency cap)
+    #[account(
+        seeds = [b"mode", &session.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SnapshotWindowStart<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SnapshotBandwidthCheckpoint<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RedeemPermit<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Referrer's token account; required when the session pays a referral share
+    #[account(
+        mut,
+        constraint = referrer_token_account.owner == session.referrer @ ErrorCode::InvalidReferrerAccount,
+        constraint = referrer_token_account.mint == session.mint @ ErrorCode::InvalidReferrerAccount
+    )]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    pub provider: Signer<'info>,
+
+    /// CHECK: Instructions sysvar
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RedeemPermitToCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Provider's wallet token account; the redeemed share passes through it into the vault
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == provider.key() @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
+    )]
+    pub provider_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Referrer's token account; required when the session pays a referral share
+    #[account(
+        mut,
+        constraint = referrer_token_account.owner == session.referrer @ ErrorCode::InvalidReferrerAccount,
+        constraint = referrer_token_account.mint == session.mint @ ErrorCode::InvalidReferrerAccount
+    )]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    /// CHECK: Instructions sysvar
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Provider position PDA ["pos", provider, mode_id]; validated (or created) by collateral_vault
+    #[account(mut)]
+    pub position: UncheckedAccount<'info>,
+
+    /// CHECK: Mode vault PDA; validated by collateral_vault
+    #[account(mut)]
+    pub mode_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Registry mode PDA supplying the deposit caps; validated by collateral_vault
+    pub mode: UncheckedAccount<'info>,
+
+    /// CHECK: Mode vault's collateral ATA; validated by collateral_vault
+    #[account(mut)]
+    pub vault_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Position NFT mint; validated by collateral_vault
+    #[account(mut)]
+    pub position_nft_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Provider's position NFT ATA; validated by collateral_vault
+    #[account(mut)]
+    pub provider_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Position NFT metadata PDA; only needed when this creates the position
+    #[account(mut)]
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Position NFT master edition PDA; only needed when this creates the position
+    #[account(mut)]
+    pub nft_master_edition: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex token metadata program; validated by collateral_vault
+    pub token_metadata_program: Option<UncheckedAccount<'info>>,
+
+    /// Token program for the position NFT (Token-2022 for soulbound modes)
+    pub position_nft_token_program: Interface<'info, TokenInterface>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetReferrer<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+
+    /// Registry bounding the referral share
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetChainedReceipts<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetDeliveryProofRequirement<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetComputeSla<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CommitDeliveryRoot<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (supplies the operator keys)
+    #[account(
+        seeds = [b"pos", position.seed_provider.as_ref(), &session.mode_id.to_le_bytes()],
+        bump = position.bump,
+        seeds::program = collateral_vault::ID,
+        constraint = position.provider == session.provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// Provider or one of its position operators
+    #[account(constraint = position.can_operate(&provider.key()) @ ErrorCode::WrongProvider)]
+    pub provider: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VerifyDeliveryProofs<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Session user or verifier
+    pub checker: Signer<'info>,
+
+    /// CHECK: Registry ["verifier_rotation", pinned key] PDA; empty if the key was never rotated
+    #[account(
+        seeds = [b"verifier_rotation", session.verifier_pubkey.as_ref()],
+        bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub verifier_rotation: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EvaluateBandwidthSla<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitLatencyAttestation<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Verifier must be in the allowlist (checked via registry)
+    pub verifier: Signer<'info>,
+
+    /// Registry for verifier allowlist check
+    #[account(
+        seeds = [b"registry"],
+        bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Account<'info, mode_registry::Registry>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeSla<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimSlaFailure<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (for slash CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
+    )]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub user: Signer<'info>,
+
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
+    #[account(mut)]
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseSession<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Registry mode for the session (supplies the dispute window)
+    #[account(
+        seeds = [b"mode", &session.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+
+    pub user: Signer<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProviderCloseRequest<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (supplies the operator keys)
+    #[account(
+        seeds = [b"pos", position.seed_provider.as_ref(), &session.mode_id.to_le_bytes()],
+        bump = position.bump,
+        seeds::program = collateral_vault::ID,
+        constraint = position.provider == session.provider @ ErrorCode::WrongProvider
+    )]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// Provider or one of its position operators
+    #[account(constraint = position.can_operate(&provider.key()) @ ErrorCode::WrongProvider)]
+    pub provider: Signer<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseAtMaxDuration<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Registry mode for the session (supplies the dispute window)
+    #[account(
+        seeds = [b"mode", &session.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Account<'info, mode_registry::Mode>,
+
+    /// Session user or provider
+    pub caller: Signer<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeClose<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (for release CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Provider's token account; required when a reservation fee is owed
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
+    )]
+    pub provider_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// Provider's concurrent session counter; required only if the session was acked
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Option<Account<'info, ActiveSessions>>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(new_session_nonce: u64)]
+pub struct RenewSession<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", old_session.user.as_ref(), &old_session.session_nonce.to_le_bytes()],
+        bump = old_session.bump,
+        has_one = user @ ErrorCode::WrongUser,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub old_session: Box<Account<'info, Session>>,
+
+    #[account(
+        mut,
+        associated_token::mint = payment_mint,
+        associated_token::authority = old_session,
+        associated_token::token_program = token_program
+    )]
+    pub old_escrow_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Provider's token account; required when the old session owes a reservation fee
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == old_session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == old_session.mint @ ErrorCode::PaymentMintMismatch
+    )]
+    pub provider_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::INIT_SPACE,
+        seeds = [b"sess", user.key().as_ref(), &new_session_nonce.to_le_bytes()],
+        bump
+    )]
+    pub new_session: Box<Account<'info, Session>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = payment_mint,
+        associated_token::authority = new_session,
+        associated_token::token_program = token_program
+    )]
+    pub new_escrow_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = old_session.mint)]
+    pub payment_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Registry mode supplying collateral ratio, term bounds and the concurrency cap
+    #[account(
+        seeds = [b"mode", &old_session.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    /// Provider's collateral position
+    #[account(mut)]
+    pub position: Box<Account<'info, ProviderPosition>>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); created by collateral_vault::reserve
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// Mode vault the position holds shares of
+    pub mode_vault: Box<Account<'info, ModeVault>>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", provider.key().as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Box<Account<'info, ActiveSessions>>,
+
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Pays the new session's reservation record rent
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimNoStart<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimStall<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (for slash CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
+    )]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimLateStart<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (for slash CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
+    )]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    /// CHECK: Provider's position PDA; may be closed or transferred, so
+    /// reservation_unreachable checks it manually
+    pub position: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawSponsorRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = sponsor @ ErrorCode::SponsorMismatch
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = sponsor_token_account.owner == session.sponsor @ ErrorCode::SponsorMismatch,
+        constraint = sponsor_token_account.mint == session.mint @ ErrorCode::SponsorMismatch
+    )]
+    pub sponsor_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub sponsor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseSessionAccounts<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: ["bond", session] PDA; must not exist (never posted or already withdrawn)
+    #[account(seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: UncheckedAccount<'info>,
+
+    /// Session metadata, closed alongside the session if it was created
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"session_meta", session.key().as_ref()],
+        bump = session_metadata.bump
+    )]
+    pub session_metadata: Option<Account<'info, SessionMetadata>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProviderCancelUnfunded<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session metadata, closed alongside the session if it was created
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"session_meta", session.key().as_ref()],
+        bump = session_metadata.bump
+    )]
+    pub session_metadata: Option<Account<'info, SessionMetadata>>,
+
+    /// CHECK: Session owner receiving the reclaimed rent; checked by has_one
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    /// Provider, or anyone once the start deadline has passed
+    pub caller: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PostReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    /// Session user, or the provider relaying a user-signed receipt
+    pub submitter: Signer<'info>,
+
+    /// CHECK: Instructions sysvar for signature introspection
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetSessionMetadata<'info> {
+    #[account(
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + SessionMetadata::INIT_SPACE,
+        seeds = [b"session_meta", session.key().as_ref()],
+        bump
+    )]
+    pub session_metadata: Account<'info, SessionMetadata>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitEventQueue<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EventQueue::INIT_SPACE,
+        seeds = [b"event_queue"],
+        bump
+    )]
+    pub event_queue: Box<Account<'info, EventQueue>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitActiveSessions<'info> {
+    #[account(
+        init,
+        payer = provider,
+        space = 8 + ActiveSessions::INIT_SPACE,
+        seeds = [b"active_sessions", provider.key().as_ref()],
+        bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPayoutDestination<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPayoutSplits<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    pub user: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EnableSharedSession<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-            emit!(SlaSettled {
-                session: session_key,
-                status: SlaStatus::Met,
-                buckets_failed: 0,
-                penalty_paid: 0,
-                premium_to_host: escrow_balance,
-                premium_refunded_to_user: 0,
-            });
-        } else {
-            // === SLA FAILED: Penalty slashed, premium split or refunded ===
-            session.sla_status = SlaStatus::Failed;
-            session.state = SessionState::Claimed;
+    pub user: Signer<'info>,
 
-            // Compute penalty
-            let computed_penalty = session.bucket_penalty
-                .checked_mul(buckets_failed)
-                .ok_or(ErrorCode::Overflow)?;
-            let actual_penalty = computed_penalty
-                .min(session.penalty_accrued)
-                .min(reserve_r);
+    pub token_program: Interface<'info, TokenInterface>,
+}
 
-            let _failure_reason = session.sla_failure_reason;
-            let _ = session;
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ContributeShared<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
 
-            // Slash penalty
-            if actual_penalty > 0 {
-                let cpi_accounts = SlashAndPay {
-                    position: ctx.accounts.position.to_account_info(),
-                    vault_token_account: ctx.accounts.vault_token_account.to_account_info(),
-                    user_token_account: ctx.accounts.user_token_account.to_account_info(),
-                    session_authority: session_info.clone(),
-                    token_program: token_program_info.clone(),
-                };
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.collateral_vault_program.to_account_info(),
-                    cpi_accounts,
-                    signer_seeds,
-                );
-                collateral_vault::cpi::slash_and_pay(cpi_ctx, session_key, actual_penalty)?;
-            }
+    #[account(
+        init_if_needed,
+        payer = contributor,
+        space = 8 + Contribution::INIT_SPACE,
+        seeds = [b"contrib", session.key().as_ref(), contributor.key().as_ref()],
+        bump
+    )]
+    pub contribution: Account<'info, Contribution>,
 
-            // Release remaining collateral
-            let remaining_reserve = reserve_r.saturating_sub(actual_penalty);
-            if remaining_reserve > 0 {
-                let release_accounts = Release {
-                    position: ctx.accounts.position.to_account_info(),
-                    session_authority: ctx.accounts.session.to_account_info(),
-                };
-                let release_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.collateral_vault_program.to_account_info(),
-                    release_accounts,
-                    signer_seeds,
-                );
-                collateral_vault::cpi::release(release_ctx, session_key, remaining_reserve)?;
-            }
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-            // Refund escrow to user (SLA failed = no premium for host)
-            if escrow_balance > 0 {
-                let cpi_accounts = Transfer {
-                    from: escrow_info,
-                    to: user_token_info,
-                    authority: ctx.accounts.session.to_account_info(),
-                };
-                let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-                token::transfer(cpi_ctx, escrow_balance)?;
-            }
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
 
-            emit!(SlaSettled {
-                session: session_key,
-                status: SlaStatus::Failed,
-                buckets_failed,
-                penalty_paid: actual_penalty,
-                premium_to_host: 0,
-                premium_refunded_to_user: escrow_balance,
-            });
-        }
+    #[account(mut)]
+    pub contributor_token_account: InterfaceAccount<'info, TokenAccount>,
 
-        Ok(())
-    }
-}
+    /// Registry holding the protocol-wide pause flag
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID,
+        constraint = !registry.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
 
-// ============================================================================
-// Helper Functions
-// ============================================================================
+    #[account(mut)]
+    pub contributor: Signer<'info>,
 
-// Bitmap helpers for bucket tracking (1024 buckets max)
-fn bit_is_set(bitmap: &[u8; 128], idx: u64) -> bool {
-    if idx >= 1024 {
-        return true; // Out of bounds treated as "already set" (reject)
-    }
-    let i = idx as usize;
-    let byte = i >> 3;
-    let bit = i & 7;
-    (bitmap[byte] & (1u8 << bit)) != 0
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
-fn set_bit(bitmap: &mut [u8; 128], idx: u64) {
-    if idx >= 1024 {
-        return; // Out of bounds no-op
-    }
-    let i = idx as usize;
-    let byte = i >> 3;
-    let bit = i & 7;
-    bitmap[byte] |= 1u8 << bit;
-}
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawSharedShare<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
 
-/// Compute bucket start slot with checked math
-fn checked_bucket_start(
-    sla_window_start: u64,
-    bucket_index: u64,
-    bucket_slots: u64,
-) -> Option<u64> {
-    let offset = bucket_index.checked_mul(bucket_slots)?;
-    sla_window_start.checked_add(offset)
-}
+    #[account(
+        mut,
+        seeds = [b"contrib", session.key().as_ref(), contributor.key().as_ref()],
+        bump = contribution.bump,
+        close = contributor
+    )]
+    pub contribution: Account<'info, Contribution>,
 
-/// Compute bucket end slot with checked math
-#[allow(dead_code)]
-fn checked_bucket_end(
-    sla_window_start: u64,
-    bucket_index: u64,
-    bucket_slots: u64,
-) -> Option<u64> {
-    let start = checked_bucket_start(sla_window_start, bucket_index, bucket_slots)?;
-    start.checked_add(bucket_slots)?.checked_sub(1)
-}
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-/// Compute buckets_total with validation
-fn compute_buckets_total(sla_window_slots: u64, bucket_slots: u64) -> Result<u64> {
-    require!(bucket_slots > 0, ErrorCode::InvalidBucketConfig);
-    require!(sla_window_slots % bucket_slots == 0, ErrorCode::InvalidBucketConfig);
-    let total = sla_window_slots
-        .checked_div(bucket_slots)
-        .ok_or(ErrorCode::Overflow)?;
-    require!(total > 0 && total <= 1024, ErrorCode::InvalidBucketConfig);
-    Ok(total)
-}
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
 
-/// Compute per-bucket penalty with checked math
-fn compute_bucket_penalty(
-    collateral: u64,
-    max_penalty_bps: u16,
-    buckets_total: u64,
-) -> Result<u64> {
-    // penalty_per_bucket = collateral * max_penalty_bps / (buckets_total * 10_000)
-    let numerator = (collateral as u128)
-        .checked_mul(max_penalty_bps as u128)
-        .ok_or(ErrorCode::Overflow)?;
-    let denominator = (buckets_total as u128)
-        .checked_mul(10_000)
-        .ok_or(ErrorCode::Overflow)?;
-    let result = numerator
-        .checked_div(denominator)
-        .ok_or(ErrorCode::Overflow)?;
-    u64::try_from(result).map_err(|_| ErrorCode::Overflow.into())
-}
+    #[account(
+        mut,
+        constraint = contributor_token_account.owner == contributor.key() @ ErrorCode::WrongUser,
+        constraint = contributor_token_account.mint == session.mint @ ErrorCode::WrongUser
+    )]
+    pub contributor_token_account: InterfaceAccount<'info, TokenAccount>,
 
-/// Combine failure reasons
-fn combine_failure_reason(current: SlaFailureReason, new: SlaFailureReason) -> SlaFailureReason {
-    match (current, new) {
-        (SlaFailureReason::None, x) => x,
-        (x, SlaFailureReason::None) => x,
-        (SlaFailureReason::Latency, SlaFailureReason::Bandwidth) => SlaFailureReason::Both,
-        (SlaFailureReason::Bandwidth, SlaFailureReason::Latency) => SlaFailureReason::Both,
-        (SlaFailureReason::Both, _) => SlaFailureReason::Both,
-        (_, SlaFailureReason::Both) => SlaFailureReason::Both,
-        (x, _) => x, // Keep existing if same type
-    }
-}
+    #[account(mut)]
+    pub contributor: Signer<'info>,
 
-/// Verify Ed25519 signature via Instructions sysvar introspection
-/// 
-/// The Ed25519 precompile instruction must be in the same transaction,
-/// immediately preceding this instruction. We verify:
-/// 1. The instruction targets the Ed25519 program
-/// 2. The pubkey matches expected verifier
-/// 3. The message matches our expected payload
-fn verify_bucket_failure_signature(
-    instructions_sysvar: &AccountInfo,
-    expected_verifier: &Pubkey,
-    session_key: &Pubkey,
-    bucket_index: u64,
-    bucket_start_slot: u64,
-    failure_reason: SlaFailureReason,
-) -> Result<()> {
-    // Get current instruction index
-    let current_ix_idx = instructions::load_current_index_checked(instructions_sysvar)
-        .map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
-    
-    // Ed25519 instruction must be immediately before this one
-    require!(current_ix_idx > 0, ErrorCode::InvalidEd25519Instruction);
-    
-    let ed25519_ix = load_instruction_at_checked(
-        (current_ix_idx - 1) as usize,
-        instructions_sysvar,
-    ).map_err(|_| ErrorCode::InvalidEd25519Instruction)?;
-    
-    // Verify it's the Ed25519 program
-    require!(
-        ed25519_ix.program_id == ED25519_PROGRAM_ID,
-        ErrorCode::InvalidEd25519Instruction
-    );
-    
-    // Ed25519 instruction data format:
-    // - 2 bytes: number of signatures
-    // - For each signature:
-    //   - 2 bytes: signature offset
-    //   - 2 bytes: signature instruction index (0xFF = same tx)
-    //   - 2 bytes: public key offset  
-    //   - 2 bytes: public key instruction index
-    //   - 2 bytes: message data offset
-    //   - 2 bytes: message data size
-    //   - 2 bytes: message instruction index
-    // Then the actual data (signatures, pubkeys, messages)
-    
-    require!(ed25519_ix.data.len() >= 16, ErrorCode::InvalidEd25519Instruction);
-    
-    // Build expected message: (program_id, session, bucket_index, bucket_start, failure_reason)
-    let mut expected_message = Vec::with_capacity(32 + 32 + 8 + 8 + 1);
-    expected_message.extend_from_slice(&crate::ID.to_bytes());  // Domain separator
-    expected_message.extend_from_slice(&session_key.to_bytes());
-    expected_message.extend_from_slice(&bucket_index.to_le_bytes());
-    expected_message.extend_from_slice(&bucket_start_slot.to_le_bytes());
-    expected_message.push(failure_reason as u8);
-    
-    // Parse Ed25519 instruction to verify pubkey and message
-    // Simplified check: verify the instruction contains our expected verifier pubkey
-    // and the message bytes match
-    let verifier_bytes = expected_verifier.to_bytes();
-    
-    // Check pubkey is present in instruction data
-    let pubkey_found = ed25519_ix.data
-        .windows(32)
-        .any(|w| w == verifier_bytes);
-    require!(pubkey_found, ErrorCode::InvalidAttester);
-    
-    // Check message is present in instruction data
-    let message_found = ed25519_ix.data
-        .windows(expected_message.len())
-        .any(|w| w == expected_message.as_slice());
-    require!(message_found, ErrorCode::SignatureMessageMismatch);
-    
-    Ok(())
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-fn compute_insurance_coverage(max_spend: u64, price_per_chunk: u64) -> u64 {
-    use session_escrow::{INSURANCE_A, INSURANCE_B, INSURANCE_MIN_BPS, INSURANCE_CAP_BPS};
-
-    let term_a = max_spend.saturating_mul(INSURANCE_A).saturating_div(10000);
-    let term_b = price_per_chunk.saturating_mul(INSURANCE_B).saturating_div(10000);
-    let raw_coverage = term_a.saturating_add(term_b);
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReassignProvider<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
+    )]
+    pub session: Account<'info, Session>,
 
-    let p_min = max_spend.saturating_mul(INSURANCE_MIN_BPS).saturating_div(10000);
-    let p_cap = max_spend.saturating_mul(INSURANCE_CAP_BPS).saturating_div(10000);
+    /// Registry mode supplying the start deadline bounds
+    #[account(
+        seeds = [b"mode", &session.mode_id.to_le_bytes()],
+        bump = mode.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
 
-    raw_coverage.max(p_min).min(p_cap)
+    pub user: Signer<'info>,
 }
 
-/// Compute bid coverage based on premium and SLA strictness
-///
-/// bid_coverage_p = base_factor * (premium_weight * premium_bps + sla_weight * sla_strictness)
-/// where sla_strictness is derived from latency and bandwidth targets
-fn compute_bid_coverage(
-    max_spend: u64,
-    premium_bps: u16,
-    latency_target_ms: u16,
-    bandwidth_min_chunks: u32,
-) -> u64 {
-    use session_escrow::{BID_PREMIUM_WEIGHT, BID_SLA_WEIGHT};
+// ============================================================================
+// Bucketed SLA Account Structs
+// ============================================================================
 
-    // Premium contribution (higher premium = more coverage)
-    let premium_factor = (premium_bps as u64)
-        .saturating_mul(BID_PREMIUM_WEIGHT)
-        .saturating_div(10000);
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReportBucketFailure<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
 
-    // SLA strictness contribution
-    // Lower latency target = stricter = more coverage
-    // Higher bandwidth target = stricter = more coverage
-    let latency_strictness = if latency_target_ms > 0 {
-        // Normalize: 100ms target = 100, 50ms = 200, 200ms = 50
-        10000u64.saturating_div(latency_target_ms as u64)
-    } else {
-        100
-    };
+    /// Authorized verifier (session.verifier_pubkey or its rotated successor, or a quorum member)
+    pub verifier: Signer<'info>,
+
+    /// CHECK: Instructions sysvar for Ed25519 signature introspection
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    /// CHECK: Registry ["verifier_rotation", pinned key] PDA; empty if the key was never rotated
+    #[account(
+        seeds = [b"verifier_rotation", session.verifier_pubkey.as_ref()],
+        bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub verifier_rotation: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DisputeBucketFailure<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = provider @ ErrorCode::WrongProvider
+    )]
+    pub session: Account<'info, Session>,
 
-    let bandwidth_strictness = (bandwidth_min_chunks as u64).min(1000); // Cap at 1000 for normalization
+    pub provider: Signer<'info>,
 
-    let sla_factor = latency_strictness
-        .saturating_add(bandwidth_strictness)
-        .saturating_mul(BID_SLA_WEIGHT)
-        .saturating_div(10000);
+    /// CHECK: Registry verifier whose counter-attestation precedes this instruction
+    pub counter_verifier: UncheckedAccount<'info>,
 
-    // Combined factor applied to max_spend
-    let total_factor = premium_factor.saturating_add(sla_factor);
+    /// Registry holding the verifier allowlist
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
 
-    max_spend
-        .saturating_mul(total_factor)
-        .saturating_div(10000)
+    /// CHECK: Instructions sysvar for Ed25519 signature introspection
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
 }
 
-fn verify_permit_signature(
-    instructions_sysvar: &AccountInfo,
-    _user: &Pubkey,
-    _session: &Pubkey,
-    _provider: &Pubkey,
-    _permit_nonce: u64,
-    _amount: u64,
-    _expiry_slot: u64,
-) -> Result<()> {
-    let ix = load_instruction_at_checked(0, instructions_sysvar)
-        .map_err(|_| ErrorCode::InvalidSignatureInstruction)?;
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitComputeAttestation<'info> {
+    #[account(
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
 
-    require!(ix.program_id == ED25519_PROGRAM_ID, ErrorCode::InvalidSignatureInstruction);
-    require!(ix.data.len() >= 16, ErrorCode::InvalidSignatureData);
+    /// Authorized verifier (session.verifier_pubkey or its rotated successor, or a quorum member)
+    pub verifier: Signer<'info>,
 
-    Ok(())
-}
+    /// CHECK: Instructions sysvar for Ed25519 signature introspection
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
 
-// ============================================================================
-// Accounts
-// ============================================================================
+    /// CHECK: Registry ["verifier_rotation", pinned key] PDA; empty if the key was never rotated
+    #[account(
+        seeds = [b"verifier_rotation", session.verifier_pubkey.as_ref()],
+        bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub verifier_rotation: UncheckedAccount<'info>,
+}
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(session_nonce: u64, mode_id: u32)]
-pub struct OpenSession<'info> {
+pub struct CheckpointBucketBandwidth<'info> {
     #[account(
-        init,
-        payer = user,
-        space = 8 + Session::INIT_SPACE,
-        seeds = [b"sess", user.key().as_ref(), &session_nonce.to_le_bytes()],
-        bump
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
     )]
     pub session: Account<'info, Session>,
+}
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimPenaltyClawback<'info> {
     #[account(
-        init,
-        payer = user,
-        associated_token::mint = payment_mint,
-        associated_token::authority = session
+        mut,
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-
-    pub payment_mint: Account<'info, Mint>,
+    pub session: Account<'info, Session>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// Authorized verifier (session.verifier_pubkey or its rotated successor, or a quorum member)
+    pub verifier: Signer<'info>,
 
-    /// CHECK: Provider pubkey
-    pub provider: AccountInfo<'info>,
+    /// CHECK: Instructions sysvar for Ed25519 signature introspection
+    #[account(address = instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
-    pub system_program: Program<'info, System>,
+    /// CHECK: Registry ["verifier_rotation", pinned key] PDA; empty if the key was never rotated
+    #[account(
+        seeds = [b"verifier_rotation", session.verifier_pubkey.as_ref()],
+        bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub verifier_rotation: UncheckedAccount<'info>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct FundSession<'info> {
+pub struct TerminateForCause<'info> {
     #[account(
+        mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
         bump = session.bump,
         has_one = user @ ErrorCode::WrongUser
     )]
     pub session: Account<'info, Session>,
 
+    /// Provider's collateral position (for slash CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
     #[account(
         mut,
         associated_token::mint = session.mint,
-        associated_token::authority = session
+        associated_token::authority = session,
+        associated_token::token_program = token_program
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
+    )]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     pub user: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
+    #[account(mut)]
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct AckStart<'info> {
+pub struct SettleSla<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = provider @ ErrorCode::WrongProvider
+        bump = session.bump
     )]
     pub session: Account<'info, Session>,
 
-    /// Provider's collateral position
+    /// Provider's collateral position (for slash/release CPI)
     #[account(mut)]
     pub position: Account<'info, ProviderPosition>,
 
-    pub provider: Signer<'info>,
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
 
-    pub collateral_vault_program: Program<'info, CollateralVault>,
-}
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
 
-#[derive(Accounts)]
-pub struct SnapshotWindowStart<'info> {
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
     )]
-    pub session: Account<'info, Session>,
-}
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
 
-#[derive(Accounts)]
-pub struct RedeemPermit<'info> {
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Provider token account (premium if SLA met, reservation fee otherwise)
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = provider @ ErrorCode::WrongProvider
+        constraint = provider_token_account.owner == session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
     )]
-    pub session: Account<'info, Session>,
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
 
+    /// User token account (for escrow refund if SLA failed)
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
 
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
     #[account(mut)]
-    pub provider_token_account: Account<'info, TokenAccount>,
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
 
-    pub provider: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
 
-    /// CHECK: Instructions sysvar
-    #[account(address = instructions::ID)]
-    pub instructions_sysvar: AccountInfo<'info>,
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
 
-    pub token_program: Program<'info, Token>,
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct EvaluateBandwidthSla<'info> {
+pub struct SettleExpiredViolation<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
         bump = session.bump
     )]
     pub session: Account<'info, Session>,
+
+    /// Provider's collateral position (for slash/release CPI)
+    #[account(mut)]
+    pub position: Account<'info, ProviderPosition>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Account<'info, ModeVault>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    /// Anyone cranking the settlement; receives the tip
+    pub cranker: Signer<'info>,
+
+    /// Cranker's token account for the tip (session mint)
+    #[account(
+        mut,
+        constraint = cranker_token_account.mint == session.mint @ ErrorCode::InvalidCrankerTokenAccount
+    )]
+    pub cranker_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// User token account (escrow refund and penalty payout)
+    #[account(
+        mut,
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Provider's token account; required when a reservation fee is owed
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
+    )]
+    pub provider_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
+
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
+    #[account(
+        mut,
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
+    )]
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
+
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
+    #[account(mut)]
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
+
+    /// Provider's concurrent session counter
+    #[account(
+        mut,
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
+    )]
+    pub active_sessions: Account<'info, ActiveSessions>,
+
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct SubmitLatencyAttestation<'info> {
+pub struct ExpireSession<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
         bump = session.bump
     )]
-    pub session: Account<'info, Session>,
-
-    /// Verifier must be in the allowlist (checked via registry)
-    pub verifier: Signer<'info>,
+    pub session: Box<Account<'info, Session>>,
 
-    /// Registry for verifier allowlist check
+    /// Registry mode for the session (supplies max_session_slots)
     #[account(
-        seeds = [b"registry"],
-        bump,
+        seeds = [b"mode", &session.mode_id.to_le_bytes()],
+        bump = mode.bump,
         seeds::program = mode_registry::ID
     )]
-    pub registry: Account<'info, mode_registry::Registry>,
-}
+    pub mode: Box<Account<'info, mode_registry::Mode>>,
+
+    /// Provider's collateral position (for slash/release CPI)
+    #[account(mut)]
+    pub position: Box<Account<'info, ProviderPosition>>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Box<Account<'info, ModeVault>>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = session.mint,
+        associated_token::authority = session,
+        associated_token::token_program = token_program
+    )]
+    pub escrow_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: Box<InterfaceAccount<'info, Mint>>,
 
-#[derive(Accounts)]
-pub struct FinalizeSla<'info> {
+    /// User token account (escrow refund and penalty payout)
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
     )]
-    pub session: Account<'info, Session>,
-}
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
-#[derive(Accounts)]
-pub struct ClaimSlaFailure<'info> {
+    /// Provider's token account; required when a reservation fee is owed
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = user @ ErrorCode::WrongUser
+        constraint = provider_token_account.owner == session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
     )]
-    pub session: Account<'info, Session>,
-
-    /// Provider's collateral position (for slash CPI)
-    #[account(mut)]
-    pub position: Account<'info, ProviderPosition>,
+    pub provider_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    /// Provider's collateral vault token account
-    #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
 
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
 
-    pub user: Signer<'info>,
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    pub token_program: Program<'info, Token>,
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
+    #[account(mut)]
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub collateral_vault_program: Program<'info, CollateralVault>,
-}
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
 
-#[derive(Accounts)]
-pub struct CloseSession<'info> {
+    /// Provider's concurrent session counter
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = user @ ErrorCode::WrongUser
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
     )]
-    pub session: Account<'info, Session>,
+    pub active_sessions: Account<'info, ActiveSessions>,
 
-    pub user: Signer<'info>,
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct FinalizeClose<'info> {
+pub struct TerminateMutual<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser,
+        has_one = provider @ ErrorCode::WrongProvider
     )]
-    pub session: Account<'info, Session>,
+    pub session: Box<Account<'info, Session>>,
 
-    /// Provider's collateral position (for release CPI)
+    pub user: Signer<'info>,
+
+    pub provider: Signer<'info>,
+
+    /// Provider's collateral position (for slash/release CPI)
     #[account(mut)]
-    pub position: Account<'info, ProviderPosition>,
+    pub position: Box<Account<'info, ProviderPosition>>,
+
+    /// CHECK: ReservationRecord PDA for (position, session); validated by collateral_vault
+    #[account(mut)]
+    pub reservation: UncheckedAccount<'info>,
+
+    /// CHECK: Reservation record payer, refunded its rent when the record closes; validated by collateral_vault
+    #[account(mut)]
+    pub reservation_rent_receiver: UncheckedAccount<'info>,
+
+    /// CHECK: collateral_vault caller allowlist (mode_registry); validated by collateral_vault
+    pub vault_callers: UncheckedAccount<'info>,
+
+    /// Mode vault the position holds shares of (for slash CPI)
+    #[account(mut)]
+    pub mode_vault: Box<Account<'info, ModeVault>>,
+
+    /// Mode vault's collateral token account
+    #[account(mut)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
         associated_token::mint = session.mint,
-        associated_token::authority = session
+        associated_token::authority = session,
+        associated_token::token_program = token_program
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub escrow_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
-    pub collateral_vault_program: Program<'info, CollateralVault>,
-}
+    /// Session payment mint (SPL Token or Token-2022)
+    #[account(address = session.mint)]
+    pub payment_mint: Box<InterfaceAccount<'info, Mint>>,
 
-#[derive(Accounts)]
-pub struct ClaimNoStart<'info> {
+    /// User token account (escrow refund and penalty payout)
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = user @ ErrorCode::WrongUser
+        constraint = user_token_account.owner == session.payout_destination @ ErrorCode::WrongPayoutDestination,
+        constraint = user_token_account.mint == session.mint @ ErrorCode::WrongPayoutDestination
     )]
-    pub session: Account<'info, Session>,
+    pub user_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// Provider's token account; required when a reservation fee is owed
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        constraint = provider_token_account.owner == session.provider @ ErrorCode::WrongProvider,
+        constraint = provider_token_account.mint == session.mint @ ErrorCode::PaymentMintMismatch
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub provider_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    pub user: Signer<'info>,
-
-    pub token_program: Program<'info, Token>,
-}
+    /// Registry holding the insurance fund configuration
+    #[account(
+        seeds = [b"registry"],
+        bump = registry.bump,
+        seeds::program = mode_registry::ID
+    )]
+    pub registry: Box<Account<'info, mode_registry::Registry>>,
 
-#[derive(Accounts)]
-pub struct ClaimStall<'info> {
+    /// Protocol insurance fund token account; required when registry.insurance_bps > 0
     #[account(
         mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump,
-        has_one = user @ ErrorCode::WrongUser
+        constraint = insurance_fund_token_account.owner == registry.insurance_fund @ ErrorCode::InvalidInsuranceFundAccount,
+        constraint = insurance_fund_token_account.mint == session.mint @ ErrorCode::InvalidInsuranceFundAccount
     )]
-    pub session: Account<'info, Session>,
+    pub insurance_fund_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    /// Provider's collateral position (for slash CPI)
-    #[account(mut)]
-    pub position: Account<'info, ProviderPosition>,
+    /// CHECK: collateral_vault insurance authority PDA (the fund's backstop delegate); validated by collateral_vault
+    pub insurance_authority: Option<UncheckedAccount<'info>>,
 
-    /// Provider's collateral vault token account
+    /// Provider's per-session bond, slashed before pooled collateral; required once posted
+    #[account(mut, seeds = [b"bond", session.key().as_ref()], bump)]
+    pub session_bond: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// User's service-credit ledger (required when settlement_mode == ServiceCredit)
     #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// CHECK: collateral_vault's event authority PDA; validated by collateral_vault
+    pub collateral_vault_event_authority: UncheckedAccount<'info>,
 
+    /// Provider's concurrent session counter
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        seeds = [b"active_sessions", session.provider.as_ref()],
+        bump = active_sessions.bump
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub active_sessions: Account<'info, ActiveSessions>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-
-    pub user: Signer<'info>,
-
-    pub token_program: Program<'info, Token>,
-    pub collateral_vault_program: Program<'info, CollateralVault>,
+    /// On-chain event queue for log-less indexers
+    #[account(mut, seeds = [b"event_queue"], bump = event_queue.bump)]
+    pub event_queue: Box<Account<'info, EventQueue>>,
 }
 
 // ============================================================================
-// Bucketed SLA Account Structs
+// Service Credit Account Structs
 // ============================================================================
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct ReportBucketFailure<'info> {
+pub struct InitServiceCredit<'info> {
     #[account(
-        mut,
-        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump
+        init,
+        payer = user,
+        space = 8 + ServiceCredit::INIT_SPACE,
+        seeds = [b"credit", user.key().as_ref(), provider.key().as_ref(), mint.key().as_ref()],
+        bump
     )]
-    pub session: Account<'info, Session>,
+    pub service_credit: Account<'info, ServiceCredit>,
 
-    /// Authorized verifier (must match session.verifier_pubkey)
-    pub verifier: Signer<'info>,
+    /// CHECK: Provider pubkey
+    pub provider: AccountInfo<'info>,
 
-    /// CHECK: Instructions sysvar for Ed25519 signature introspection
-    #[account(address = instructions::ID)]
-    pub instructions_sysvar: AccountInfo<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct TerminateForCause<'info> {
+pub struct SetVerifierQuorum<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
@@ -1765,64 +9132,46 @@ pub struct TerminateForCause<'info> {
     )]
     pub session: Account<'info, Session>,
 
-    /// Provider's collateral position (for slash CPI)
-    #[account(mut)]
-    pub position: Account<'info, ProviderPosition>,
-
-    /// Provider's collateral vault token account
-    #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub user: Signer<'info>,
+}
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetSettlementMode<'info> {
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+    pub session: Account<'info, Session>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    /// Required when switching to ServiceCredit settlement
+    pub service_credit: Option<Account<'info, ServiceCredit>>,
 
     pub user: Signer<'info>,
-
-    pub token_program: Program<'info, Token>,
-    pub collateral_vault_program: Program<'info, CollateralVault>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct SettleSla<'info> {
+pub struct ApplyServiceCredits<'info> {
     #[account(
         mut,
         seeds = [b"sess", session.user.as_ref(), &session.session_nonce.to_le_bytes()],
-        bump = session.bump
+        bump = session.bump,
+        has_one = user @ ErrorCode::WrongUser
     )]
     pub session: Account<'info, Session>,
 
-    /// Provider's collateral position (for slash/release CPI)
-    #[account(mut)]
-    pub position: Account<'info, ProviderPosition>,
-
-    /// Provider's collateral vault token account
-    #[account(mut)]
-    pub vault_token_account: Account<'info, TokenAccount>,
-
     #[account(
         mut,
-        associated_token::mint = session.mint,
-        associated_token::authority = session
+        seeds = [b"credit", user.key().as_ref(), service_credit.provider.as_ref(), service_credit.mint.as_ref()],
+        bump = service_credit.bump,
+        has_one = user @ ErrorCode::WrongUser
     )]
-    pub escrow_token_account: Account<'info, TokenAccount>,
-
-    /// Provider token account (for premium payment if SLA met)
-    #[account(mut)]
-    pub provider_token_account: Account<'info, TokenAccount>,
+    pub service_credit: Account<'info, ServiceCredit>,
 
-    /// User token account (for escrow refund if SLA failed)
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, Token>,
-    pub collateral_vault_program: Program<'info, CollateralVault>,
+    pub user: Signer<'info>,
 }
 
 // ============================================================================
@@ -1841,8 +9190,10 @@ pub struct Session {
     pub chunk_size: u64,
     pub price_per_chunk: u64,
     pub max_spend: u64,
+    pub max_spend_usd: u64,                 // USD target at open (PEG_DECIMALS; 0 = token-denominated)
     pub total_spent: u64,
     pub reserve_r: u64,
+    pub cr_bps: u16,                        // Effective collateral ratio (mode floor or user override)
     pub start_deadline_slot: u64,
     pub stall_timeout_slots: u64,
     pub last_progress_slot: u64,
@@ -1857,6 +9208,7 @@ pub struct Session {
     pub fail_payout_bps: u16,
     pub latency_target_ms: u16,
     pub bandwidth_min_chunks: u32,
+    pub bandwidth_min_bytes: u64,           // 0 = use bandwidth_min_chunks
     pub sla_warmup_slots: u64,
     pub sla_window_slots: u64,
     pub sla_window_start_slot: u64,
@@ -1870,17 +9222,28 @@ pub struct Session {
 
     // SLA state
     pub sla_status: SlaStatus,
-    pub sla_failure_reason: SlaFailureReason,
+    pub sla_failure_reasons: u8,            // Bitmask of SlaFailureReason::mask()
     pub latency_attested: bool,
 
     // Nonce tracking for bandwidth SLA (legacy window-level)
     pub nonce_at_window_start: u64,
     pub nonce_at_window_end: u64,
 
+    // Bandwidth checkpoints (ring of MAX_BANDWIDTH_CHECKPOINTS sub-window boundaries)
+    pub bandwidth_checkpoints: [BandwidthCheckpoint; MAX_BANDWIDTH_CHECKPOINTS],
+    pub bandwidth_checkpoint_count: u32,    // Total recorded; ring index = count % MAX
+    pub bandwidth_subwindows_failed: u32,   // Sub-windows below the prorated target
+
+    // Bucketed bandwidth accounting (checkpoint_bucket_bandwidth crank)
+    pub bucket_bandwidth_tracking: bool,    // A bucket's start counter has been recorded
+    pub bucket_bandwidth_index: u64,        // Bucket currently being measured
+    pub bucket_bandwidth_counter: u64,      // Delivered counter at that bucket's checkpoint
+
     // Bucketed SLA configuration
     pub bucket_slots: u64,                  // Slots per bucket (e.g. 750 ≈ 5 min at 400ms)
     pub buckets_total: u64,                 // sla_window_slots / bucket_slots (max 1024)
     pub bucket_penalty: u64,                // Precomputed penalty per bucket
+    pub grace_buckets: u32,                 // Failed buckets tolerated before penalties accrue
 
     // Bucketed downtime tracking
     pub buckets_failed: u64,                // Counter for fast penalty calc
@@ -1893,12 +9256,250 @@ pub struct Session {
 
     // Penalty accounting
     pub penalty_accrued: u64,               // Running total (tokens)
+    pub penalty_weights: PenaltyWeights,    // bucket_penalty multiplier per failure reason
+    pub penalty_units: u64,                 // Sum of weights of penalized failures
 
     // Attester configuration
     pub verifier_pubkey: Pubkey,            // Authorized attester for bucket reports
+    pub quorum_verifiers: [Pubkey; MAX_QUORUM_VERIFIERS], // M-of-N attesters (replaces verifier_pubkey when set)
+    pub quorum_verifier_count: u8,
+    pub quorum_threshold: u8,               // 0 = single verifier_pubkey
 
     // Convenience flags
     pub terminated_for_cause: bool,
+
+    // Settlement
+    pub settlement_mode: SettlementMode,
+    pub credits_applied: u64,               // Service credits burned against this session
+    pub credits_consumed: u64,              // Portion of credits_applied used by permits
+
+    // Receipt accumulator
+    pub receipt_hash: [u8; 32],             // Rolling hash over all redemptions
+
+    // Settlement destination
+    pub payout_destination: Pubkey,         // Owner of refund/payout token accounts
+    pub payout_destination_set_slot: u64,   // Last change (cooldown anchor)
+
+    // Close cooldown
+    pub provider_close_requested: bool,     // Closing was initiated by the provider
+    pub close_unlock_slot: u64,             // finalize_close allowed from this slot (cooldown or dispute window)
+
+    // Delivery proofs (bandwidth SLA)
+    pub delivery_proofs_required: bool,     // Count only Merkle-proven chunks
+    pub delivery_root: [u8; 32],            // Provider-committed root of delivered chunks
+    pub delivery_chunk_count: u64,          // Leaves under delivery_root
+    pub chunks_proven: u64,                 // Leaves verified by inclusion proof
+    pub next_proof_index: u64,              // Lowest chunk index still provable
+
+    // Idempotency key from open_session (zero = none)
+    pub client_request_id: [u8; 16],
+
+    // Delivery receipts (user acknowledgements)
+    pub receipts_posted: u64,
+    pub next_receipt_index: u64,            // Lowest chunk index a receipt may cover
+    pub delivery_receipt_hash: [u8; 32],    // sha256(prev || chunk_index || chunk_hash)
+
+    // Compute SLA (0 = target not enforced)
+    pub compute_min_utilization_pct: u8,
+    pub compute_min_tokens_per_sec: u32,
+
+    // Penalty clawback
+    pub clawback_next_bucket: u64,          // First bucket a new clean streak may start at
+    pub penalty_clawed_back: u64,           // Total penalty forgiven by clean streaks
+
+    // Permit signing
+    pub permit_scheme: PermitScheme,
+    pub permit_eth_address: [u8; 20],       // Secp256k1 permit signer (EVM address)
+
+    // Claim payout splits (count = 0: whole payout to payout_destination)
+    pub payout_split_count: u8,
+    pub payout_splits: [PayoutSplit; MAX_PAYOUT_SPLITS],
+
+    // Shared sessions (co-funded escrow, pro-rata settlement)
+    pub is_shared: bool,
+    pub shared_total_contributed: u64,
+    pub shared_contributor_count: u32,
+    pub shared_settled_count: u32,          // Contributors that have withdrawn their share
+    pub shared_pool: u64,                   // Escrow balance at the first withdrawal
+    pub shared_pool_snapshotted: bool,
+
+    // Keeper top-ups via SPL delegation (amount = 0: disabled)
+    pub auto_top_up_threshold: u64,         // Top up while escrow balance is below this
+    pub auto_top_up_amount: u64,            // Tokens pulled per top-up
+
+    // Provider performance bond (["bond", session] token account)
+    pub bond_posted: u64,                   // Total bond received, net of transfer fees
+
+    // Redemption limits against leaked permit batches (0: unlimited)
+    pub max_permit_amount: u64,             // Cap on a single permit's amount
+    pub rate_limit_amount: u64,             // Max redeemed per rate window
+    pub rate_limit_slots: u64,              // Rate window length in slots
+    pub rate_window_start_slot: u64,
+    pub rate_window_redeemed: u64,
+
+    // Hash-chained delivery log enforced at redemption
+    pub chained_receipts_required: bool,    // Each permit must extend delivery_chain_hash
+    pub delivery_chain_hash: [u8; 32],      // sha256(prev || permit_nonce || chunks_digest)
+
+    // Recent bucket failures open to provider dispute
+    pub recent_failures: [RecentBucketFailure; MAX_DISPUTABLE_FAILURES],
+    pub recent_failure_count: u32,          // Total recorded; ring index = count % MAX
+
+    // Marketplace referral (referral_bps = 0: none)
+    pub referrer: Pubkey,                   // Owner of the referral token account
+    pub referral_bps: u16,                  // Share of each cash redemption
+
+    // Late-start compensation (claim_late_start)
+    pub start_window_slot: u64,             // Slot the current start deadline was measured from
+    pub acked_slot: u64,                    // Slot the provider acked
+    pub late_start_claimed: bool,
+
+    // Maximum duration (0 = unlimited)
+    pub max_duration_slots: u64,            // Slots after ack before either party can force a close
+    pub duration_end_slot: u64,             // acked_slot + max_duration_slots
+
+    // Third-party sponsorship (sponsor = default: none)
+    pub sponsor: Pubkey,                    // Funder other than the user
+    pub sponsored_amount: u64,              // Sponsored funds refundable to the sponsor first
+
+    // Provider reservation fee (rate snapshotted from the mode at ack)
+    pub reservation_fee_bps: u16,           // bps of reserve_r per RESERVATION_FEE_PERIOD_SLOTS
+    pub reservation_fee_paid: u64,          // Fee paid to the provider at finalize_close
+}
+
+/// Idempotency record: a client request id maps to exactly one session
+#[account]
+#[derive(InitSpace)]
+pub struct ClientRequest {
+    pub user: Pubkey,
+    pub request_id: [u8; 16],
+    pub session: Pubkey,
+    pub bump: u8,
+}
+
+/// One contributor's stake in a shared session's escrow
+#[account]
+#[derive(InitSpace)]
+pub struct Contribution {
+    pub session: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,                        // Tokens received into escrow (net of transfer fees)
+    pub bump: u8,
+}
+
+/// Off-chain job mapping for a session, written by the user
+#[account]
+#[derive(InitSpace)]
+pub struct SessionMetadata {
+    pub session: Pubkey,
+    #[max_len(MAX_METADATA_URI_LEN)]
+    pub uri: String,
+    #[max_len(MAX_METADATA_ENTRIES)]
+    pub entries: Vec<MetadataEntry>,
+    pub bump: u8,
+}
+
+/// Per (user, provider, mint) ledger of non-transferable service credits
+#[account]
+#[derive(InitSpace)]
+pub struct ServiceCredit {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+    pub balance: u64,
+    pub total_issued: u64,
+    pub total_burned: u64,
+    pub bump: u8,
+}
+
+/// Number of acked, not yet terminal sessions for a provider
+#[account]
+#[derive(InitSpace)]
+pub struct ActiveSessions {
+    pub provider: Pubkey,
+    pub count: u32,
+    pub bump: u8,
+}
+
+/// Ring buffer of compact event records (oldest overwritten first)
+#[account]
+#[derive(InitSpace)]
+pub struct EventQueue {
+    pub next_seq: u64,                      // Sequence number of the next record
+    pub head: u16,                          // Index the next record is written to
+    pub records: [EventRecord; EVENT_QUEUE_CAPACITY],
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub kind: EventKind,
+    pub session: Pubkey,
+    pub slot: u64,
+    pub amount: u64,                        // Event-specific headline amount
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum EventKind {
+    #[default]
+    None,
+    SessionOpened,
+    SessionStarted,
+    PermitRedeemed,
+    SlaFailureClaimed,
+    SessionClosed,
+    ClaimPaid,
+    TerminatedForCause,
+    SlaSettled,
+    SessionExpired,
+    TerminatedMutually,
+    ExpiredViolationSettled,
+    CloseRequested,
+    SessionCancelled,
+}
+
+/// Client-chosen terms of a session, validated against the mode in
+/// open_session_from_mode
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SessionTerms {
+    pub chunk_size: u64,
+    pub price_per_chunk: u64,
+    pub max_spend: u64,
+    pub start_deadline_slots: u64,
+    pub stall_timeout_slots: u64,
+    // Bid mode parameters
+    pub is_bid: bool,
+    pub premium_bps: u16,
+    pub fail_payout_bps: u16,
+    pub latency_target_ms: u16,
+    pub bandwidth_min_chunks: u32,
+    pub bandwidth_min_bytes: u64,
+    pub sla_warmup_slots: u64,
+    pub sla_window_slots: u64,
+    // Bucketed SLA parameters (only used if is_bid)
+    pub bucket_slots: u64,
+    pub terminate_window_slots: u64,
+    pub max_penalty_bps: u16,
+    pub grace_buckets: u32,
+    pub penalty_weights: PenaltyWeights,
+    pub verifier_pubkey: Pubkey,
+    // Wallet receiving refunds and claim payouts (defaults to user)
+    pub payout_destination: Option<Pubkey>,
+    // EVM address signing permits via secp256k1 (None = Ed25519 by user)
+    pub permit_eth_address: Option<[u8; 20]>,
+    // Requested collateral ratio (0 = mode floor); provider accepts at ack
+    pub cr_bps: u16,
+    // Slots after ack before either party can force a close (0 = unlimited)
+    pub max_duration_slots: u64,
+}
+
+/// Inclusion proof for one delivered chunk
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliveryProof {
+    pub chunk_index: u64,
+    pub chunk_hash: [u8; 32],
+    pub proof: Vec<[u8; 32]>,               // Sibling hashes, leaf to root
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
@@ -1911,22 +9512,133 @@ pub enum SessionState {
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
-pub enum SlaStatus {
-    None,               // Not a bid session or SLA not yet active
-    Pending,            // SLA active, no violations yet
-    Violated,           // First miss occurred, termination window open
-    Met,                // Window ended, SLA passed
-    Failed,             // Window ended, violations settled (no termination)
-    TerminatedForCause, // Client exercised termination right
+pub enum SlaStatus {
+    None,               // Not a bid session or SLA not yet active
+    Pending,            // SLA active, no violations yet
+    Violated,           // First miss occurred, termination window open
+    Met,                // Window ended, SLA passed
+    Failed,             // Window ended, violations settled (no termination)
+    TerminatedForCause, // Client exercised termination right
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SlaFailureReason {
+    None,
+    Latency,
+    Bandwidth,
+    Both,
+    PrivacyMode,  // Future: privacy/confidentiality violations
+    Compute,      // GPU utilization or throughput below target
+    Jitter,       // Latency variance above target
+    PacketLoss,   // Loss rate above target
+}
+
+impl SlaFailureReason {
+    /// Bit recorded in Session.sla_failure_reasons (Both = Latency | Bandwidth)
+    pub fn mask(self) -> u8 {
+        match self {
+            SlaFailureReason::None => 0,
+            SlaFailureReason::Latency => 1 << 0,
+            SlaFailureReason::Bandwidth => 1 << 1,
+            SlaFailureReason::Both => (1 << 0) | (1 << 1),
+            SlaFailureReason::PrivacyMode => 1 << 2,
+            SlaFailureReason::Compute => 1 << 3,
+            SlaFailureReason::Jitter => 1 << 4,
+            SlaFailureReason::PacketLoss => 1 << 5,
+        }
+    }
+}
+
+/// Key-value pair stored in SessionMetadata (e.g. "region" => "eu-west")
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct MetadataEntry {
+    #[max_len(MAX_METADATA_KEY_LEN)]
+    pub key: String,
+    #[max_len(MAX_METADATA_VALUE_LEN)]
+    pub value: String,
+}
+
+/// bucket_penalty multipliers per failure reason (e.g. privacy 3x, latency 1x)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct PenaltyWeights {
+    pub latency: u8,
+    pub bandwidth: u8,
+    pub privacy_mode: u8,
+    pub compute: u8,
+    pub jitter: u8,
+    pub packet_loss: u8,
+}
+
+impl PenaltyWeights {
+    /// Each weight must be in 1..=MAX_PENALTY_WEIGHT
+    fn is_valid(&self) -> bool {
+        [
+            self.latency,
+            self.bandwidth,
+            self.privacy_mode,
+            self.compute,
+            self.jitter,
+            self.packet_loss,
+        ]
+            .iter()
+            .all(|w| (1..=MAX_PENALTY_WEIGHT).contains(w))
+    }
+
+    /// Weight of one failed bucket; Both counts latency and bandwidth
+    fn weight(&self, reason: SlaFailureReason) -> u64 {
+        match reason {
+            SlaFailureReason::None => 0,
+            SlaFailureReason::Latency => self.latency as u64,
+            SlaFailureReason::Bandwidth => self.bandwidth as u64,
+            SlaFailureReason::Both => self.latency as u64 + self.bandwidth as u64,
+            SlaFailureReason::PrivacyMode => self.privacy_mode as u64,
+            SlaFailureReason::Compute => self.compute as u64,
+            SlaFailureReason::Jitter => self.jitter as u64,
+            SlaFailureReason::PacketLoss => self.packet_loss as u64,
+        }
+    }
+}
+
+/// A reported bucket failure and what it accrued, kept for disputes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct RecentBucketFailure {
+    pub bucket_index: u64,
+    pub reported_slot: u64,     // 0 = empty or already disputed
+    pub penalty: u64,           // penalty_accrued added by this failure
+    pub penalty_units: u64,     // penalty_units added by this failure
+}
+
+/// Link appended to the delivery log by a chained redemption
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryChainLink {
+    pub chunks_digest: [u8; 32],    // Digest of the chunks this permit pays for
+    pub chain_hash: [u8; 32],       // New delivery_chain_hash (signed by the user)
+}
+
+/// Delivered-chunk counter sampled at a slot inside the SLA window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct BandwidthCheckpoint {
+    pub slot: u64,
+    pub delivered: u64,     // Permit nonce, or chunks_proven under delivery proofs
+}
+
+/// One recipient of a split claim payout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct PayoutSplit {
+    pub recipient: Pubkey,  // Token account owner
+    pub bps: u16,           // Share of the payout (all shares sum to 10_000)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SettlementMode {
+    Cash,           // Penalties slashed from collateral and paid in tokens
+    ServiceCredit,  // Penalties allocated as credits against future fees
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
-pub enum SlaFailureReason {
-    None,
-    Latency,
-    Bandwidth,
-    Both,
-    PrivacyMode,  // Future: privacy/confidentiality violations
+pub enum PermitScheme {
+    Ed25519,        // Signed by the user's Solana key
+    Secp256k1,      // Signed by permit_eth_address (EIP-191 personal_sign)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -1934,6 +9646,8 @@ pub enum ClaimType {
     NoStart,
     Stall,
     SlaFailure,
+    EmergencyRefund,    // Provider position unreachable, escrow refunded only
+    LateStart,          // Acked but no delivery within the grace period; session continues
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -1953,8 +9667,10 @@ pub struct SessionOpened {
     pub provider: Pubkey,
     pub mode_id: u32,
     pub max_spend: u64,
+    pub max_spend_usd: u64,
     pub base_coverage_p: u64,
     pub reserve_r: u64,
+    pub cr_bps: u16,
     pub start_deadline_slot: u64,
     // Bid mode fields
     pub is_bid: bool,
@@ -1965,10 +9681,112 @@ pub struct SessionOpened {
     pub reserve_bid: u64,
 }
 
+#[event]
+pub struct PayoutDestinationUpdated {
+    pub session: Pubkey,
+    pub old_destination: Pubkey,
+    pub new_destination: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PayoutSplitsSet {
+    pub session: Pubkey,
+    pub splits: Vec<PayoutSplit>,
+}
+
+#[event]
+pub struct SharedSessionEnabled {
+    pub session: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+pub struct SharedContributionAdded {
+    pub session: Pubkey,
+    pub contributor: Pubkey,
+    pub received: u64,
+    pub contributor_total: u64,
+    pub total_contributed: u64,
+}
+
+#[event]
+pub struct SharedShareWithdrawn {
+    pub session: Pubkey,
+    pub contributor: Pubkey,
+    pub contributed: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ExcessEscrowWithdrawn {
+    pub session: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+}
+
+#[event]
+pub struct MaxSpendIncreased {
+    pub session: Pubkey,
+    pub old_max_spend: u64,
+    pub new_max_spend: u64,
+    pub base_coverage_p: u64,
+    pub bid_coverage_p: u64,
+    pub reserve_r: u64,
+}
+
 #[event]
 pub struct SessionFunded {
     pub session: Pubkey,
     pub amount: u64,
+    pub received: u64,                      // amount net of Token-2022 transfer fees
+    pub new_balance: u64,
+    pub sponsor: Option<Pubkey>,            // Set when a third party funded on the user's behalf
+}
+
+#[event]
+pub struct SponsorRefunded {
+    pub session: Pubkey,
+    pub sponsor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SessionBondPosted {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub received: u64,
+    pub bond_balance: u64,
+}
+
+#[event]
+pub struct SessionBondWithdrawn {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AutoTopUpSet {
+    pub session: Pubkey,
+    pub threshold: u64,
+    pub top_up_amount: u64,
+}
+
+#[event]
+pub struct RedemptionLimitsSet {
+    pub session: Pubkey,
+    pub max_permit_amount: u64,
+    pub rate_limit_amount: u64,
+    pub rate_limit_slots: u64,
+}
+
+#[event]
+pub struct SessionAutoToppedUp {
+    pub session: Pubkey,
+    pub keeper: Pubkey,
+    pub amount: u64,
+    pub received: u64,                      // amount net of Token-2022 transfer fees
     pub new_balance: u64,
 }
 
@@ -1978,6 +9796,15 @@ pub struct SessionStarted {
     pub started_at_slot: u64,
 }
 
+/// Emitted on every session state transition
+#[event]
+pub struct SessionStateChanged {
+    pub session: Pubkey,
+    pub from: SessionState,
+    pub to: SessionState,
+    pub slot: u64,
+}
+
 #[event]
 pub struct SlaWindowStartSnapshotted {
     pub session: Pubkey,
@@ -1985,12 +9812,101 @@ pub struct SlaWindowStartSnapshotted {
     pub slot: u64,
 }
 
+#[event]
+pub struct BandwidthCheckpointed {
+    pub session: Pubkey,
+    pub index: u8,
+    pub slot: u64,
+    pub delivered: u64,
+    pub subwindow_passed: bool,
+}
+
 #[event]
 pub struct PermitRedeemed {
     pub session: Pubkey,
     pub permit_nonce: u64,
     pub amount: u64,
     pub total_spent: u64,
+    pub credits_used: u64,
+    pub referral_amount: u64,
+    pub receipt_hash: [u8; 32],
+    pub delivery_chain_hash: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReceiptPosted {
+    pub session: Pubkey,
+    pub chunk_index: u64,
+    pub chunk_hash: [u8; 32],
+    pub receipts_posted: u64,
+    pub delivery_receipt_hash: [u8; 32],
+    pub user_signed: bool,
+}
+
+#[event]
+pub struct SessionMetadataSet {
+    pub session: Pubkey,
+    pub uri: String,
+    pub entry_count: u8,
+}
+
+#[event]
+pub struct ComputeSlaSet {
+    pub session: Pubkey,
+    pub min_utilization_pct: u8,
+    pub min_tokens_per_sec: u32,
+}
+
+#[event]
+pub struct ComputeAttestationSubmitted {
+    pub session: Pubkey,
+    pub verifier: Pubkey,
+    pub bucket_index: u64,
+    pub utilization_pct: u8,
+    pub tokens_per_sec: u32,
+    pub passed: bool,
+}
+
+#[event]
+pub struct PermitRedeemedToCollateral {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub mode_id: u32,
+    pub deposited: u64,
+}
+
+#[event]
+pub struct ReferrerSet {
+    pub session: Pubkey,
+    pub referrer: Pubkey,
+    pub referral_bps: u16,
+}
+
+#[event]
+pub struct ChainedReceiptsSet {
+    pub session: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct DeliveryProofRequirementSet {
+    pub session: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct DeliveryRootCommitted {
+    pub session: Pubkey,
+    pub delivery_root: [u8; 32],
+    pub chunk_count: u64,
+}
+
+#[event]
+pub struct DeliveryProofsVerified {
+    pub session: Pubkey,
+    pub verified: u64,
+    pub chunks_proven: u64,
 }
 
 #[event]
@@ -2000,6 +9916,9 @@ pub struct SlaEvaluated {
     pub passed: bool,
     pub actual_value: u64,
     pub target_value: u64,
+    // Bandwidth only (zero for latency)
+    pub actual_bytes: u64,
+    pub target_bytes: u64,
 }
 
 #[event]
@@ -2023,7 +9942,7 @@ pub struct SlaFailureClaimed {
     pub payout: u64,
     pub escrow_refunded: u64,
     pub remaining_reserve_released: u64,
-    pub failure_reason: SlaFailureReason,
+    pub failure_reasons: u8,
 }
 
 // Bucketed SLA Events
@@ -2038,13 +9957,93 @@ pub struct BucketFailureReported {
     pub is_first_violation: bool,
 }
 
+#[event]
+pub struct BucketFailureDisputed {
+    pub session: Pubkey,
+    pub bucket_index: u64,
+    pub counter_verifier: Pubkey,
+    pub penalty_reversed: u64,
+    pub buckets_failed: u64,
+    pub penalty_accrued: u64,
+}
+
+#[event]
+pub struct BucketFailuresBatchReported {
+    pub session: Pubkey,
+    pub bucket_indices: Vec<u64>,
+    pub failure_reason: SlaFailureReason,
+    pub buckets_failed: u64,
+    pub penalty_accrued: u64,
+    pub is_first_violation: bool,
+}
+
+#[event]
+pub struct BucketBandwidthMeasured {
+    pub session: Pubkey,
+    pub bucket_index: u64,
+    pub buckets_spanned: u64,
+    pub chunks_delivered: u64,
+    pub passed: bool,
+}
+
+#[event]
+pub struct SessionExpired {
+    pub session: Pubkey,
+    pub penalty_paid: u64,
+    pub collateral_released: u64,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct ExpiredViolationSettled {
+    pub session: Pubkey,
+    pub cranker: Pubkey,
+    pub buckets_failed: u64,
+    pub penalty_paid: u64,
+    pub crank_tip: u64,
+    pub premium_refunded_to_user: u64,
+}
+
+#[event]
+pub struct SessionTerminatedMutually {
+    pub session: Pubkey,
+    pub penalty_paid: u64,
+    pub collateral_released: u64,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct SessionRenewed {
+    pub old_session: Pubkey,
+    pub new_session: Pubkey,
+    pub escrow_rolled: u64,
+    pub reserve_r: u64,
+}
+
+#[event]
+pub struct ProviderReassigned {
+    pub session: Pubkey,
+    pub old_provider: Pubkey,
+    pub new_provider: Pubkey,
+    pub start_deadline_slot: u64,
+}
+
+#[event]
+pub struct PenaltyClawedBack {
+    pub session: Pubkey,
+    pub start_bucket_index: u64,
+    pub bucket_count: u64,
+    pub clawback: u64,
+    pub penalty_accrued: u64,
+}
+
 #[event]
 pub struct SessionTerminatedForCause {
     pub session: Pubkey,
     pub penalty_paid: u64,
     pub escrow_refunded: u64,
     pub buckets_failed: u64,
-    pub failure_reason: SlaFailureReason,
+    pub failure_reasons: u8,
     pub remaining_collateral_released: u64,
 }
 
@@ -2058,17 +10057,91 @@ pub struct SlaSettled {
     pub premium_refunded_to_user: u64,
 }
 
+// Service Credit Events
+#[event]
+pub struct ServiceCreditInitialized {
+    pub service_credit: Pubkey,
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct VerifierQuorumSet {
+    pub session: Pubkey,
+    pub verifiers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct SettlementModeSet {
+    pub session: Pubkey,
+    pub settlement_mode: SettlementMode,
+}
+
+#[event]
+pub struct ServiceCreditsIssued {
+    pub session: Pubkey,
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+}
+
+#[event]
+pub struct ServiceCreditsBurned {
+    pub session: Pubkey,
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub remaining_balance: u64,
+}
+
 #[event]
 pub struct SessionClosing {
     pub session: Pubkey,
 }
 
+#[event]
+pub struct SessionMaxDurationReached {
+    pub session: Pubkey,
+    pub closed_by: Pubkey,
+    pub duration_end_slot: u64,
+}
+
+#[event]
+pub struct ProviderCloseRequested {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub close_unlock_slot: u64,
+}
+
 #[event]
 pub struct SessionClosed {
     pub session: Pubkey,
     pub refunded: u64,
 }
 
+#[event]
+pub struct ReservationFeePaid {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SessionAccountsClosed {
+    pub session: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+pub struct UnfundedSessionCancelled {
+    pub session: Pubkey,
+    pub user: Pubkey,
+    pub cancelled_by: Pubkey,
+}
+
 #[event]
 pub struct ClaimPaid {
     pub session: Pubkey,
@@ -2171,4 +10244,208 @@ pub enum ErrorCode {
     ReportOutsideSlaWindow,
     #[msg("Report after termination deadline")]
     ReportAfterDeadline,
+    // Service credit errors
+    #[msg("Service credit account required")]
+    ServiceCreditAccountRequired,
+    #[msg("Service credit account does not match session")]
+    ServiceCreditMismatch,
+    #[msg("Insufficient service credits")]
+    InsufficientServiceCredits,
+    // Settlement destination errors
+    #[msg("Token account not owned by payout destination")]
+    WrongPayoutDestination,
+    #[msg("Payout destination change cooldown active")]
+    PayoutDestinationCooldown,
+    #[msg("Provider concurrent session cap reached")]
+    ConcurrentSessionCapReached,
+    #[msg("Verifier rotation not yet effective")]
+    VerifierRotationNotEffective,
+    #[msg("Provider position can still honor the reservation")]
+    PositionReachable,
+    #[msg("Permit not signed by session user")]
+    InvalidPermitSigner,
+    #[msg("New max spend must exceed the current one")]
+    MaxSpendNotIncreased,
+    #[msg("Amount exceeds escrow above remaining spend")]
+    ExceedsExcessEscrow,
+    #[msg("Close cooldown has not elapsed")]
+    CloseCooldownActive,
+    #[msg("Invalid verifier quorum configuration")]
+    InvalidQuorumConfig,
+    #[msg("Not enough distinct verifier attestations")]
+    QuorumNotReached,
+    #[msg("Payment mint has an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Escrow token account is not empty")]
+    EscrowNotEmpty,
+    #[msg("Delivery root already committed")]
+    DeliveryRootAlreadyCommitted,
+    #[msg("Delivery root not committed")]
+    DeliveryRootNotCommitted,
+    #[msg("Invalid delivery root")]
+    InvalidDeliveryRoot,
+    #[msg("Invalid delivery proof")]
+    InvalidDeliveryProof,
+    #[msg("Mode is not active")]
+    ModeNotActive,
+    #[msg("Payment mint does not match mode mint")]
+    PaymentMintMismatch,
+    #[msg("Session terms outside mode bounds")]
+    InvalidSessionTerms,
+    #[msg("Invalid clean bucket streak")]
+    InvalidCleanStreak,
+    #[msg("Batch must open between 1 and MAX_BATCH_SESSIONS sessions")]
+    InvalidBatchSize,
+    #[msg("Invalid or already initialized batch account")]
+    InvalidBatchAccount,
+    #[msg("New provider must differ from the current provider")]
+    SameProvider,
+    #[msg("Session has not reached its expiry slot")]
+    SessionNotExpired,
+    #[msg("Penalty weights must be between 1 and MAX_PENALTY_WEIGHT")]
+    InvalidPenaltyWeights,
+    #[msg("Compute utilization target must be <= 100% and some target must be set")]
+    InvalidComputeTarget,
+    #[msg("Compute SLA not configured for this session")]
+    ComputeSlaNotConfigured,
+    #[msg("Session metadata exceeds size limits")]
+    InvalidMetadata,
+    #[msg("Receipt chunk index must increase")]
+    InvalidReceiptIndex,
+    #[msg("client_request account required when client_request_id is set")]
+    ClientRequestAccountRequired,
+    #[msg("Client request account does not match the request id")]
+    InvalidClientRequestAccount,
+    #[msg("Client request id already used for another session")]
+    DuplicateClientRequest,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Payout splits must be up to 4 distinct recipients summing to 10000 bps")]
+    InvalidPayoutSplits,
+    #[msg("Payout split recipient token accounts required")]
+    PayoutSplitAccountsRequired,
+    #[msg("Payout split token account does not match recipient or mint")]
+    InvalidPayoutSplitAccount,
+    #[msg("Not a shared session")]
+    NotSharedSession,
+    #[msg("Operation not supported on shared sessions")]
+    SharedSessionUnsupported,
+    #[msg("Shared sessions require an empty escrow, cash settlement and no payout splits")]
+    InvalidSharedSessionSetup,
+    #[msg("Shared session contributors have not all withdrawn")]
+    SharedSharesOutstanding,
+    #[msg("Auto top-up needs a non-zero threshold")]
+    InvalidAutoTopUp,
+    #[msg("Auto top-up not configured")]
+    AutoTopUpNotConfigured,
+    #[msg("Escrow balance is not below the top-up threshold")]
+    TopUpThresholdNotReached,
+    #[msg("Session PDA is not the delegate of the user token account")]
+    SessionNotDelegate,
+    #[msg("Collateral ratio override must be between the mode floor and 500%")]
+    InvalidCrOverride,
+    #[msg("Provider did not accept the session's collateral ratio")]
+    CrNotAccepted,
+    #[msg("Insurance fund token account required")]
+    InsuranceFundAccountRequired,
+    #[msg("Insurance fund token account does not match the registry fund or mint")]
+    InvalidInsuranceFundAccount,
+    #[msg("Session bond account required once a bond is posted")]
+    SessionBondAccountRequired,
+
+    #[msg("User's termination window is still open")]
+    TerminationWindowOpen,
+
+    #[msg("Cranker token account must hold the session mint")]
+    InvalidCrankerTokenAccount,
+
+    #[msg("Rate limit amount and window must both be set or both be zero")]
+    InvalidRedemptionLimits,
+
+    #[msg("Permit amount exceeds the session's per-permit cap")]
+    PermitAmountCapExceeded,
+
+    #[msg("Redemption exceeds the session's rate limit for this window")]
+    RedemptionRateLimited,
+
+    #[msg("Mode has no USD price feed")]
+    UsdPricingUnavailable,
+
+    #[msg("Bandwidth checkpoint too soon after the previous one")]
+    CheckpointTooSoon,
+
+    #[msg("Session requires a delivery chain link on every redemption")]
+    DeliveryChainRequired,
+
+    #[msg("Delivery chain hash does not extend the stored chain")]
+    DeliveryChainMismatch,
+
+    #[msg("Bucket being measured has not ended")]
+    BucketNotEnded,
+
+    #[msg("Bucket batch must hold 1..=32 strictly increasing indices")]
+    InvalidBucketBatch,
+
+    #[msg("Counter-attester must be a different verifier than the session's attester")]
+    InvalidCounterVerifier,
+
+    #[msg("Bucket failure is not open to dispute")]
+    FailureNotDisputable,
+
+    #[msg("Referral share exceeds the registry bound or has no referrer")]
+    InvalidReferral,
+
+    #[msg("Referrer token account required for this session")]
+    ReferrerAccountRequired,
+
+    #[msg("Referrer token account must be owned by the referrer and hold the session mint")]
+    InvalidReferrerAccount,
+
+    #[msg("Permit leaves no provider cash to deposit")]
+    NothingToDeposit,
+
+    #[msg("Session timing parameter outside the mode's bounds")]
+    TimingOutOfBounds,
+
+    #[msg("Late-start compensation already claimed for this session")]
+    LateStartAlreadyClaimed,
+
+    #[msg("Delivery has already started")]
+    DeliveryAlreadyStarted,
+
+    #[msg("Late-start grace period has not elapsed")]
+    LateStartGraceNotElapsed,
+
+    #[msg("Provider acked early enough that no late-start compensation is owed")]
+    NoLateStartCompensation,
+
+    #[msg("Session has a posted provider bond")]
+    SessionBondOutstanding,
+
+    #[msg("Only the session user or provider can do this")]
+    NotSessionParty,
+
+    #[msg("Session has not reached its maximum duration")]
+    MaxDurationNotReached,
+
+    #[msg("Session is sponsored by a different account")]
+    SponsorMismatch,
+
+    #[msg("Session is not denominated in wrapped SOL")]
+    NotNativeSolSession,
+
+    #[msg("Account is not this session's provider position")]
+    InvalidPosition,
+
+    #[msg("Provider token account required to pay the reservation fee")]
+    ProviderTokenAccountRequired,
+
+    #[msg("Session has a pending stall or SLA claim")]
+    ClaimPending,
+
+    #[msg("Invalid verifier rotation record")]
+    InvalidVerifierRotation,
+
+    #[msg("Provider's active session counter required for a started session")]
+    ActiveSessionsAccountRequired,
 }
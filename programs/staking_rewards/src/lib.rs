@@ -46,7 +46,7 @@ pub mod staking_rewards {
         controller.paused = false;
         controller.bump = ctx.bumps.emission_controller;
 
-        emit!(EmissionControllerInitialized {
+        emit_cpi!(EmissionControllerInitialized {
             authority: controller.authority,
             reward_mint: controller.reward_mint,
             global_rate_per_slot,
@@ -75,7 +75,7 @@ pub mod staking_rewards {
         controller.native_pool_weight_bps = native_pool_weight_bps;
         controller.last_rate_change_slot = clock.slot;
 
-        emit!(EmissionWeightsUpdated {
+        emit_cpi!(EmissionWeightsUpdated {
             nft_pool_weight_bps,
             native_pool_weight_bps,
         });
@@ -89,7 +89,7 @@ pub mod staking_rewards {
         paused: bool,
     ) -> Result<()> {
         ctx.accounts.emission_controller.paused = paused;
-        emit!(EmissionPausedUpdated { paused });
+        emit_cpi!(EmissionPausedUpdated { paused });
         Ok(())
     }
 
@@ -127,7 +127,7 @@ pub mod staking_rewards {
         pool.paused = false;
         pool.bump = ctx.bumps.native_pool;
 
-        emit!(NativePoolInitialized {
+        emit_cpi!(NativePoolInitialized {
             authority: pool.authority,
             native_mint: pool.native_mint,
             pyth_feed: pool.pyth_feed,
@@ -143,7 +143,7 @@ pub mod staking_rewards {
         paused: bool,
     ) -> Result<()> {
         ctx.accounts.native_pool.paused = paused;
-        emit!(NativePoolPausedUpdated { paused });
+        emit_cpi!(NativePoolPausedUpdated { paused });
         Ok(())
     }
 
@@ -155,7 +155,7 @@ pub mod staking_rewards {
         require!(discount_bps <= MAX_BPS, ErrorCode::InvalidDiscount);
         let old_discount = ctx.accounts.native_pool.discount_bps;
         ctx.accounts.native_pool.discount_bps = discount_bps;
-        emit!(NativeDiscountUpdated {
+        emit_cpi!(NativeDiscountUpdated {
             old_discount_bps: old_discount,
             new_discount_bps: discount_bps,
         });
@@ -163,24 +163,57 @@ pub mod staking_rewards {
     }
 
     /// Initialize the staking pool (NFT-based)
+    ///
+    /// Without `reward_vault` the pool PDA must be the reward mint's mint
+    /// authority and rewards are minted on claim. With it, rewards are paid
+    /// from that pre-funded token account (owned by the pool PDA) and claims
+    /// fail once it runs dry instead of minting.
     pub fn initialize_pool(ctx: Context<InitializePool>) -> Result<()> {
+        let reward_vault = ctx.accounts.reward_vault.as_ref().map(|v| v.key()).unwrap_or_default();
         let pool = &mut ctx.accounts.pool;
         pool.authority = ctx.accounts.authority.key();
         pool.reward_mint = ctx.accounts.reward_mint.key();
+        pool.reward_vault = reward_vault;
         pool.total_staked_weight = 0;
         pool.reward_per_weight_accumulated = 0;
         pool.last_update_slot = Clock::get()?.slot;
         pool.total_rewards_distributed = 0;
         pool.bump = ctx.bumps.pool;
+        pool.min_claim_interval_slots = 0;
+        pool.unstake_epoch_slots = 0;
         
-        emit!(PoolInitialized {
+        emit_cpi!(PoolInitialized {
             authority: pool.authority,
             reward_mint: pool.reward_mint,
+            reward_vault,
         });
         
         Ok(())
     }
 
+    /// Set the minimum slots between reward claims per stake (authority only)
+    pub fn set_min_claim_interval(
+        ctx: Context<UpdateStakingPool>,
+        min_claim_interval_slots: u64,
+    ) -> Result<()> {
+        ctx.accounts.pool.min_claim_interval_slots = min_claim_interval_slots;
+        emit_cpi!(MinClaimIntervalUpdated { min_claim_interval_slots });
+        Ok(())
+    }
+
+    /// Set the unstake epoch length (authority only)
+    ///
+    /// When non-zero, unstaking goes through request_unstake/complete_unstake
+    /// and NFTs are released at the next epoch boundary. 0 = immediate unstake.
+    pub fn set_unstake_epoch_slots(
+        ctx: Context<UpdateStakingPool>,
+        unstake_epoch_slots: u64,
+    ) -> Result<()> {
+        ctx.accounts.pool.unstake_epoch_slots = unstake_epoch_slots;
+        emit_cpi!(UnstakeEpochUpdated { unstake_epoch_slots });
+        Ok(())
+    }
+
     /// Stake a provider position NFT
     pub fn stake_position(ctx: Context<StakePosition>) -> Result<()> {
         update_pool_rewards(&mut ctx.accounts.pool)?;
@@ -217,6 +250,8 @@ pub mod staking_rewards {
             .ok_or(ErrorCode::Overflow)?;
         stake_account.pending_rewards = 0;
         stake_account.bump = ctx.bumps.stake_account;
+        stake_account.last_claim_slot = clock.slot;
+        stake_account.unstake_release_slot = 0;
         
         // Update pool total
         let pool = &mut ctx.accounts.pool;
@@ -224,7 +259,7 @@ pub mod staking_rewards {
             .checked_add(stake_weight)
             .ok_or(ErrorCode::Overflow)?;
         
-        emit!(PositionStaked {
+        emit_cpi!(PositionStaked {
             owner: ctx.accounts.provider.key(),
             position: ctx.accounts.collateral_position.key(),
             stake_weight,
@@ -241,6 +276,8 @@ pub mod staking_rewards {
         let pool = &mut ctx.accounts.pool;
         let stake_account = &mut ctx.accounts.stake_account;
         
+        require!(stake_account.unstake_release_slot == 0, ErrorCode::UnstakeAlreadyRequested);
+        
         let pending = calculate_pending_rewards(pool, stake_account)?;
         stake_account.pending_rewards = stake_account.pending_rewards
             .checked_add(pending)
@@ -265,7 +302,7 @@ pub mod staking_rewards {
             .checked_div(PRECISION)
             .ok_or(ErrorCode::Overflow)?;
         
-        emit!(StakeWeightUpdated {
+        emit_cpi!(StakeWeightUpdated {
             owner: stake_account.owner,
             old_weight,
             new_weight,
@@ -287,6 +324,9 @@ pub mod staking_rewards {
         let pool = &mut ctx.accounts.pool;
         let stake_account = &mut ctx.accounts.stake_account;
         
+        // Queued stakes are paid out by complete_unstake
+        require!(stake_account.unstake_release_slot == 0, ErrorCode::UnstakeAlreadyRequested);
+        
         let pending = calculate_pending_rewards(pool, stake_account)?;
         let total_rewards = stake_account.pending_rewards
             .checked_add(pending)
@@ -294,11 +334,20 @@ pub mod staking_rewards {
         
         require!(total_rewards > 0, ErrorCode::NoRewardsToClaim);
         
+        // Rate-limit claims; rewards keep accruing to pending_rewards meanwhile
+        let current_slot = Clock::get()?.slot;
+        require!(
+            current_slot.saturating_sub(stake_account.last_claim_slot) >= pool.min_claim_interval_slots,
+            ErrorCode::ClaimTooSoon
+        );
+        
         let owner = stake_account.owner;
         let stake_weight = stake_account.stake_weight;
         let bump = pool.bump;
+        let reward_vault = pool.reward_vault;
         
         stake_account.pending_rewards = 0;
+        stake_account.last_claim_slot = current_slot;
         stake_account.reward_debt = stake_weight
             .checked_mul(pool.reward_per_weight_accumulated)
             .ok_or(ErrorCode::Overflow)?
@@ -312,20 +361,18 @@ pub mod staking_rewards {
         let _ = pool;
         drop(stake_account);
         
-        // Mint rewards
-        let bump_slice = [bump];
-        let seeds: &[&[u8]] = &[b"pool", &bump_slice];
-        let signer_seeds = &[seeds];
+        pay_rewards(
+            pool_info,
+            bump,
+            reward_vault,
+            reward_mint_info,
+            ctx.accounts.reward_vault.as_ref(),
+            provider_reward_info,
+            token_program_info,
+            total_rewards,
+        )?;
         
-        let cpi_accounts = MintTo {
-            mint: reward_mint_info,
-            to: provider_reward_info,
-            authority: pool_info,
-        };
-        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, signer_seeds);
-        token::mint_to(cpi_ctx, total_rewards)?;
-        
-        emit!(RewardsClaimed {
+        emit_cpi!(RewardsClaimed {
             owner,
             amount: total_rewards,
         });
@@ -333,8 +380,10 @@ pub mod staking_rewards {
         Ok(())
     }
 
-    /// Unstake position NFT
+    /// Unstake position NFT (only while the unstake queue is disabled)
     pub fn unstake_position(ctx: Context<UnstakePosition>) -> Result<()> {
+        require!(ctx.accounts.pool.unstake_epoch_slots == 0, ErrorCode::UnstakeQueueEnabled);
+        
         update_pool_rewards(&mut ctx.accounts.pool)?;
         
         // Capture account infos
@@ -358,6 +407,7 @@ pub mod staking_rewards {
         let position = stake_account.position;
         let stake_weight = stake_account.stake_weight;
         let pool_bump = pool.bump;
+        let reward_vault = pool.reward_vault;
         let stake_bump = stake_account.bump;
         
         pool.total_staked_weight = pool.total_staked_weight.saturating_sub(stake_weight);
@@ -370,19 +420,132 @@ pub mod staking_rewards {
         
         let _ = pool;
         
-        // Mint rewards if any
+        // Pay rewards if any
+        if total_rewards > 0 {
+            pay_rewards(
+                pool_info,
+                pool_bump,
+                reward_vault,
+                reward_mint_info,
+                ctx.accounts.reward_vault.as_ref(),
+                provider_reward_info,
+                token_program_info.clone(),
+                total_rewards,
+            )?;
+        }
+        
+        // Transfer NFT back
+        let stake_bump_slice = [stake_bump];
+        let stake_seeds: &[&[u8]] = &[
+            b"stake",
+            owner.as_ref(),
+            position.as_ref(),
+            &stake_bump_slice,
+        ];
+        let stake_signer = &[stake_seeds];
+        
+        let cpi_accounts = Transfer {
+            from: staking_nft_info,
+            to: provider_nft_info,
+            authority: stake_account_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, stake_signer);
+        token::transfer(cpi_ctx, 1)?;
+        
+        emit_cpi!(PositionUnstaked {
+            owner,
+            position,
+            rewards_claimed: total_rewards,
+        });
+        
+        Ok(())
+    }
+
+    /// Queue a position NFT for release at the next unstake epoch boundary
+    ///
+    /// Weight leaves the pool immediately and final rewards are fixed now, so
+    /// staking just before a distribution and exiting right after earns nothing
+    /// extra while the NFT stays locked until the boundary.
+    pub fn request_unstake(ctx: Context<RequestUnstake>) -> Result<()> {
+        update_pool_rewards(&mut ctx.accounts.pool)?;
+        
+        let current_slot = Clock::get()?.slot;
+        let pool = &mut ctx.accounts.pool;
+        let stake_account = &mut ctx.accounts.stake_account;
+        
+        require!(pool.unstake_epoch_slots > 0, ErrorCode::UnstakeQueueDisabled);
+        require!(stake_account.unstake_release_slot == 0, ErrorCode::UnstakeAlreadyRequested);
+        
+        let pending = calculate_pending_rewards(pool, stake_account)?;
+        stake_account.pending_rewards = stake_account.pending_rewards
+            .checked_add(pending)
+            .ok_or(ErrorCode::Overflow)?;
+        
+        let release_slot = next_epoch_boundary(current_slot, pool.unstake_epoch_slots)?;
+        let old_weight = stake_account.stake_weight;
+        
+        pool.total_staked_weight = pool.total_staked_weight.saturating_sub(old_weight);
+        stake_account.stake_weight = 0;
+        stake_account.reward_debt = 0;
+        stake_account.unstake_release_slot = release_slot;
+        
+        emit_cpi!(UnstakeRequested {
+            owner: stake_account.owner,
+            position: stake_account.position,
+            final_rewards: stake_account.pending_rewards,
+            release_slot,
+        });
+        
+        Ok(())
+    }
+
+    /// Release a queued position NFT and its final rewards after the epoch boundary
+    pub fn complete_unstake(ctx: Context<UnstakePosition>) -> Result<()> {
+        // Capture account infos
+        let pool_info = ctx.accounts.pool.to_account_info();
+        let stake_account_info = ctx.accounts.stake_account.to_account_info();
+        let reward_mint_info = ctx.accounts.reward_mint.to_account_info();
+        let provider_reward_info = ctx.accounts.provider_reward_account.to_account_info();
+        let staking_nft_info = ctx.accounts.staking_nft_custody.to_account_info();
+        let provider_nft_info = ctx.accounts.provider_nft_account.to_account_info();
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        
+        let pool = &mut ctx.accounts.pool;
+        let stake_account = &ctx.accounts.stake_account;
+        
+        require!(stake_account.unstake_release_slot > 0, ErrorCode::UnstakeNotRequested);
+        require!(
+            Clock::get()?.slot >= stake_account.unstake_release_slot,
+            ErrorCode::UnstakeEpochNotReached
+        );
+        
+        // Rewards were fixed at request time; weight is already zero
+        let total_rewards = stake_account.pending_rewards;
+        let owner = stake_account.owner;
+        let position = stake_account.position;
+        let pool_bump = pool.bump;
+        let reward_vault = pool.reward_vault;
+        let stake_bump = stake_account.bump;
+        
+        if total_rewards > 0 {
+            pool.total_rewards_distributed = pool.total_rewards_distributed
+                .checked_add(total_rewards)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        
+        let _ = pool;
+        
         if total_rewards > 0 {
-            let pool_bump_slice = [pool_bump];
-            let pool_seeds: &[&[u8]] = &[b"pool", &pool_bump_slice];
-            let pool_signer = &[pool_seeds];
-            
-            let cpi_accounts = MintTo {
-                mint: reward_mint_info,
-                to: provider_reward_info,
-                authority: pool_info,
-            };
-            let cpi_ctx = CpiContext::new_with_signer(token_program_info.clone(), cpi_accounts, pool_signer);
-            token::mint_to(cpi_ctx, total_rewards)?;
+            pay_rewards(
+                pool_info,
+                pool_bump,
+                reward_vault,
+                reward_mint_info,
+                ctx.accounts.reward_vault.as_ref(),
+                provider_reward_info,
+                token_program_info.clone(),
+                total_rewards,
+            )?;
         }
         
         // Transfer NFT back
@@ -403,7 +566,7 @@ pub mod staking_rewards {
         let cpi_ctx = CpiContext::new_with_signer(token_program_info, cpi_accounts, stake_signer);
         token::transfer(cpi_ctx, 1)?;
         
-        emit!(PositionUnstaked {
+        emit_cpi!(PositionUnstaked {
             owner,
             position,
             rewards_claimed: total_rewards,
@@ -431,6 +594,56 @@ fn compute_stake_weight(reserved: u64, free: u64) -> u64 {
     reserved_weighted.saturating_add(free_weighted)
 }
 
+/// First slot of the epoch after the one containing `slot`
+fn next_epoch_boundary(slot: u64, epoch_slots: u64) -> Result<u64> {
+    slot.checked_div(epoch_slots)
+        .and_then(|epoch| epoch.checked_add(1))
+        .and_then(|next| next.checked_mul(epoch_slots))
+        .ok_or(error!(ErrorCode::Overflow))
+}
+
+/// Pay `amount` of rewards to `to`
+///
+/// Pools without a reward vault mint them (the pool PDA is the mint
+/// authority). Reward-vault pools transfer them from the vault and reject a
+/// claim the vault cannot cover in full.
+#[allow(clippy::too_many_arguments)]
+fn pay_rewards<'info>(
+    pool: AccountInfo<'info>,
+    pool_bump: u8,
+    reward_vault_key: Pubkey,
+    reward_mint: AccountInfo<'info>,
+    reward_vault: Option<&Account<'info, TokenAccount>>,
+    to: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let bump_slice = [pool_bump];
+    let seeds: &[&[u8]] = &[b"pool", &bump_slice];
+    let signer_seeds = &[seeds];
+    
+    if reward_vault_key == Pubkey::default() {
+        let cpi_accounts = MintTo {
+            mint: reward_mint,
+            to,
+            authority: pool,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+        return token::mint_to(cpi_ctx, amount);
+    }
+    
+    let reward_vault = reward_vault.ok_or(ErrorCode::RewardVaultRequired)?;
+    require!(reward_vault.amount >= amount, ErrorCode::RewardVaultInsolvent);
+    
+    let cpi_accounts = Transfer {
+        from: reward_vault.to_account_info(),
+        to,
+        authority: pool,
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+    token::transfer(cpi_ctx, amount)
+}
+
 fn update_pool_rewards(pool: &mut Account<StakingPool>) -> Result<()> {
     let clock = Clock::get()?;
     let current_slot = clock.slot;
@@ -478,6 +691,7 @@ fn calculate_pending_rewards(pool: &StakingPool, stake: &StakeAccount) -> Result
 // Accounts
 // ============================================================================
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitEmissionController<'info> {
     #[account(
@@ -497,6 +711,7 @@ pub struct InitEmissionController<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct UpdateEmissionController<'info> {
     #[account(
@@ -514,6 +729,7 @@ pub struct UpdateEmissionController<'info> {
 // Native Staking Contexts
 // ============================================================================
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitNativePool<'info> {
     #[account(
@@ -555,6 +771,7 @@ pub struct InitNativePool<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct UpdateNativePool<'info> {
     #[account(
@@ -572,6 +789,7 @@ pub struct UpdateNativePool<'info> {
 // NFT Staking Contexts
 // ============================================================================
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitializePool<'info> {
     #[account(
@@ -586,12 +804,34 @@ pub struct InitializePool<'info> {
     #[account(mut)]
     pub reward_mint: Account<'info, Mint>,
     
+    /// Pre-funded reward token account (owner = pool PDA); omit to mint rewards
+    #[account(
+        token::mint = reward_mint,
+        token::authority = pool
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+    
     #[account(mut)]
     pub authority: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateStakingPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool"],
+        bump = pool.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub pool: Account<'info, StakingPool>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct StakePosition<'info> {
     #[account(
@@ -633,6 +873,7 @@ pub struct StakePosition<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct UpdateStakeWeight<'info> {
     #[account(
@@ -652,6 +893,7 @@ pub struct UpdateStakeWeight<'info> {
     pub collateral_position: Account<'info, CollateralPosition>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ClaimRewards<'info> {
     #[account(
@@ -672,6 +914,10 @@ pub struct ClaimRewards<'info> {
     #[account(mut)]
     pub reward_mint: Account<'info, Mint>,
     
+    /// Pool's reward vault (reward-vault pools only)
+    #[account(mut, address = pool.reward_vault @ ErrorCode::InvalidRewardVault)]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+    
     #[account(mut)]
     pub provider_reward_account: Account<'info, TokenAccount>,
     
@@ -680,6 +926,7 @@ pub struct ClaimRewards<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct UnstakePosition<'info> {
     #[account(
@@ -701,6 +948,10 @@ pub struct UnstakePosition<'info> {
     #[account(mut)]
     pub reward_mint: Account<'info, Mint>,
     
+    /// Pool's reward vault (reward-vault pools only)
+    #[account(mut, address = pool.reward_vault @ ErrorCode::InvalidRewardVault)]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+    
     #[account(mut)]
     pub provider_reward_account: Account<'info, TokenAccount>,
     
@@ -719,6 +970,27 @@ pub struct UnstakePosition<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool"],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, StakingPool>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", provider.key().as_ref(), stake_account.position.as_ref()],
+        bump = stake_account.bump,
+        constraint = stake_account.owner == provider.key() @ ErrorCode::WrongOwner
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+    
+    pub provider: Signer<'info>,
+}
+
 // ============================================================================
 // State
 // ============================================================================
@@ -750,6 +1022,9 @@ pub struct StakingPool {
     pub last_update_slot: u64,
     pub total_rewards_distributed: u64,
     pub bump: u8,
+    pub min_claim_interval_slots: u64,
+    pub unstake_epoch_slots: u64,       // 0 = immediate unstake
+    pub reward_vault: Pubkey,           // Pre-funded reward source (default = mint rewards)
 }
 
 #[account]
@@ -763,6 +1038,8 @@ pub struct StakeAccount {
     pub reward_debt: u64,
     pub pending_rewards: u64,
     pub bump: u8,
+    pub last_claim_slot: u64,
+    pub unstake_release_slot: u64,      // 0 = not queued for unstake
 }
 
 #[account]
@@ -775,6 +1052,12 @@ pub struct CollateralPosition {
     pub reserved: u64,
     pub position_nft_mint: Pubkey,
     pub bump: u8,
+    pub shares: u128,
+    pub withdrawal_allowlist: [Pubkey; 4],
+    pub withdrawal_allowlist_count: u8,
+    pub pending_withdrawal_allowlist: [Pubkey; 4],
+    pub pending_withdrawal_allowlist_count: u8,
+    pub withdrawal_allowlist_effective_slot: u64,
 }
 
 // ============================================================================
@@ -865,6 +1148,17 @@ pub struct NativeDiscountUpdated {
 pub struct PoolInitialized {
     pub authority: Pubkey,
     pub reward_mint: Pubkey,
+    pub reward_vault: Pubkey,
+}
+
+#[event]
+pub struct MinClaimIntervalUpdated {
+    pub min_claim_interval_slots: u64,
+}
+
+#[event]
+pub struct UnstakeEpochUpdated {
+    pub unstake_epoch_slots: u64,
 }
 
 #[event]
@@ -888,6 +1182,14 @@ pub struct RewardsClaimed {
     pub amount: u64,
 }
 
+#[event]
+pub struct UnstakeRequested {
+    pub owner: Pubkey,
+    pub position: Pubkey,
+    pub final_rewards: u64,
+    pub release_slot: u64,
+}
+
 #[event]
 pub struct PositionUnstaked {
     pub owner: Pubkey,
@@ -917,4 +1219,22 @@ pub enum ErrorCode {
     InvalidDiscount,
     #[msg("Pool is paused")]
     PoolPaused,
+    #[msg("Minimum claim interval not reached")]
+    ClaimTooSoon,
+    #[msg("Unstake queue is enabled; use request_unstake")]
+    UnstakeQueueEnabled,
+    #[msg("Unstake queue is disabled")]
+    UnstakeQueueDisabled,
+    #[msg("Unstake already requested")]
+    UnstakeAlreadyRequested,
+    #[msg("Unstake not requested")]
+    UnstakeNotRequested,
+    #[msg("Unstake epoch boundary not reached")]
+    UnstakeEpochNotReached,
+    #[msg("Reward vault account required")]
+    RewardVaultRequired,
+    #[msg("Invalid reward vault")]
+    InvalidRewardVault,
+    #[msg("Reward vault cannot cover the claim")]
+    RewardVaultInsolvent,
 }